@@ -0,0 +1,17 @@
+#[derive(Clone, Debug)]
+pub struct Foo {
+    a: u32,
+}
+
+#[derive(Default)]
+struct Bar;
+
+impl Bar {
+    fn bar(&self) -> Foo {
+        Foo { a: 0 }
+    }
+}
+
+fn main() {
+    println!("{:?}", Bar::default().bar().clone());
+}
@@ -0,0 +1,28 @@
+#![feature(rustc_private, test)]
+
+extern crate test;
+
+use rustc_tools::{with_tyctxt_from_str, Analyzer};
+use test::Bencher;
+
+const SNIPPET: &str = "pub fn f() -> Vec<u32> { vec![1, 2, 3] }";
+
+fn args() -> Vec<String> {
+    vec!["--crate-type".to_owned(), "lib".to_owned()]
+}
+
+#[bench]
+fn bench_with_tyctxt_from_str(b: &mut Bencher) {
+    let args = args();
+    b.iter(|| with_tyctxt_from_str(SNIPPET, &args, |tcx| tcx.hir().items().count()).unwrap());
+}
+
+#[bench]
+fn bench_analyzer_analyze_str(b: &mut Bencher) {
+    let analyzer = Analyzer::new(&args()).unwrap();
+    b.iter(|| {
+        analyzer
+            .analyze_str(SNIPPET, |tcx| tcx.hir().items().count())
+            .unwrap()
+    });
+}
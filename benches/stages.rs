@@ -0,0 +1,64 @@
+#![feature(rustc_private, test)]
+
+extern crate test;
+
+use rustc_tools::{AnalysisLevel, Config, Stage};
+use test::Bencher;
+
+/// A crate with a few thousand lines of code.
+fn source() -> String {
+    (0..1000)
+        .map(|i| {
+            format!(
+                "#[derive(Debug, Clone)]\npub struct S{i} {{ pub a: u32 }}\n\
+                 pub fn f{i}(s: &S{i}) -> u32 {{ s.a.wrapping_mul({i}) + 1 }}\n"
+            )
+        })
+        .collect()
+}
+
+fn config(source: &str) -> Config {
+    Config::new(&["--crate-type".to_owned(), "lib".to_owned()]).source_code(source)
+}
+
+#[bench]
+fn bench_parse(b: &mut Bencher) {
+    let source = source();
+    b.iter(|| {
+        config(&source)
+            .with_ast(Stage::Parse, |krate, _| krate.items.len())
+            .unwrap()
+    });
+}
+
+#[bench]
+fn bench_expand(b: &mut Bencher) {
+    let source = source();
+    b.iter(|| {
+        config(&source)
+            .with_ast(Stage::Expand, |krate, _| krate.items.len())
+            .unwrap()
+    });
+}
+
+#[bench]
+fn bench_hir(b: &mut Bencher) {
+    let source = source();
+    b.iter(|| {
+        config(&source)
+            .analysis_level(AnalysisLevel::HirOnly)
+            .with_tyctxt(|tcx| tcx.hir().items().count())
+            .unwrap()
+    });
+}
+
+#[bench]
+fn bench_full(b: &mut Bencher) {
+    let source = source();
+    b.iter(|| {
+        config(&source)
+            .analysis_level(AnalysisLevel::Full)
+            .with_tyctxt(|tcx| tcx.hir().items().count())
+            .unwrap()
+    });
+}
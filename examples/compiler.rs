@@ -0,0 +1,32 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+use rustc_tools::rustc_session::config::OutputType;
+use rustc_tools::rustc_session::output::find_crate_name;
+use rustc_tools::with_compiler;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Missing file operand");
+        return;
+    }
+    println!("Running compiler example with arguments `{args:?}`");
+    with_compiler(&args, |compiler, queries| {
+        let sess = compiler.session();
+        // Nothing else is run: the crate is only parsed.
+        let krate = match queries.parse() {
+            Ok(krate) => krate,
+            Err(_) => return,
+        };
+        let krate = krate.borrow();
+        // The name given with `--crate-name` or `#![crate_name]`, or the one of the file.
+        println!("Crate name: {}", find_crate_name(sess, &krate.attrs));
+
+        let outputs = compiler.build_output_filenames(sess, &krate.attrs);
+        println!("The compiler would write:");
+        for output_type in [OutputType::Exe, OutputType::Metadata, OutputType::Mir] {
+            println!("  * {}", outputs.path(output_type).display());
+        }
+    })
+    .unwrap();
+}
@@ -0,0 +1,48 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+use rustc_tools::rustc_ast::ast::{Item, ItemKind, TyKind};
+use rustc_tools::rustc_ast::visit::{walk_crate, walk_item, Visitor};
+use rustc_tools::rustc_ast_pretty::pprust;
+use rustc_tools::with_expanded_ast;
+
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct ImplVisitor {
+    // For each type, the number of impls written by the user and generated by macros.
+    impls: BTreeMap<String, (usize, usize)>,
+}
+
+impl<'ast> Visitor<'ast> for ImplVisitor {
+    fn visit_item(&mut self, i: &'ast Item) {
+        if let ItemKind::Impl(ref impl_) = i.kind {
+            if let TyKind::Path(_, ref path) = impl_.self_ty.kind {
+                let entry = self.impls.entry(pprust::path_to_string(path)).or_default();
+                // The code generated by a macro (like `#[derive]`) has a span coming from it.
+                if i.span.from_expansion() {
+                    entry.1 += 1;
+                } else {
+                    entry.0 += 1;
+                }
+            }
+        }
+        walk_item(self, i);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Missing file operand");
+        return;
+    }
+    println!("Running expanded AST example with arguments `{args:?}`");
+    with_expanded_ast(&args, |krate, _sess| {
+        let mut visitor = ImplVisitor::default();
+        walk_crate(&mut visitor, krate);
+        for (ty, (written, generated)) in visitor.impls {
+            println!("=> {ty}: {written} impl(s) written, {generated} impl(s) generated");
+        }
+    })
+    .unwrap();
+}
@@ -0,0 +1,47 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+use rustc_tools::rustc_hir::{Item, ItemKind};
+use rustc_tools::rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_tools::rustc_session::{declare_lint_pass, declare_tool_lint};
+use rustc_tools::with_lints;
+
+declare_tool_lint! {
+    /// Warns about functions named `foo`.
+    pub example::FOO_FUNCTIONS,
+    Warn,
+    "functions named `foo`"
+}
+
+declare_lint_pass!(FooFunctions => [FOO_FUNCTIONS]);
+
+impl<'tcx> LateLintPass<'tcx> for FooFunctions {
+    fn check_item(&mut self, cx: &LateContext<'tcx>, item: &'tcx Item<'tcx>) {
+        if matches!(item.kind, ItemKind::Fn(..)) && item.ident.name.as_str() == "foo" {
+            cx.struct_span_lint(
+                FOO_FUNCTIONS,
+                item.ident.span,
+                "please find a better name than `foo`",
+                |diag| diag,
+            );
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Missing file operand");
+        return;
+    }
+    println!("Running lint example with arguments `{:?}`", args);
+    with_lints(
+        &args,
+        |_sess, lint_store| {
+            // We need to register the lint itself and its lint pass.
+            lint_store.register_lints(&[FOO_FUNCTIONS]);
+            lint_store.register_late_pass(|_| Box::new(FooFunctions));
+        },
+        |_tcx| {},
+    )
+    .unwrap();
+}
@@ -0,0 +1,40 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::{AnalysisLevel, Config};
+
+/// Returns the number of basic blocks of the functions when the MIR is optimized at
+/// `mir_opt_level`.
+fn basic_blocks(args: &[String], mir_opt_level: u8) -> Vec<(String, usize)> {
+    // Same as `with_mir`, with another optimization level than the default one (1).
+    Config::new(args)
+        .analysis_level(AnalysisLevel::Full)
+        .mir_opt_level(mir_opt_level)
+        .with_tyctxt(|tcx| {
+            // `body_owners` returns all the items with a body: functions, closures, constants,
+            // etc.
+            tcx.hir()
+                .body_owners()
+                .filter(|&def_id| matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn))
+                .map(|def_id| {
+                    let mir = tcx.optimized_mir(def_id);
+                    (tcx.def_path_str(def_id.to_def_id()), mir.basic_blocks.len())
+                })
+                .collect()
+        })
+        .unwrap()
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Missing file operand");
+        return;
+    }
+    println!("Running MIR example with arguments `{args:?}`");
+    let unoptimized = basic_blocks(&args, 0);
+    let optimized = basic_blocks(&args, 3);
+    for ((name, before), (_, after)) in unoptimized.into_iter().zip(optimized) {
+        println!("=> {name} has {after} basic blocks ({before} at mir-opt-level 0)");
+    }
+}
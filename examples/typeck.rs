@@ -0,0 +1,51 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+use rustc_tools::rustc_hir::intravisit::{walk_local, Visitor};
+use rustc_tools::rustc_hir::Local;
+use rustc_tools::rustc_middle::hir::nested_filter;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::{AnalysisLevel, Config};
+
+struct LetVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+}
+
+impl<'tcx> Visitor<'tcx> for LetVisitor<'tcx> {
+    // We want to visit the bodies as well, so we need to go through nested items.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_local(&mut self, local: &'tcx Local<'tcx>) {
+        // The type-check results are stored per body owner (function, closure, constant...).
+        let owner = self.tcx.hir().enclosing_body_owner(local.hir_id);
+        let ty = self.tcx.typeck(owner).node_type(local.pat.hir_id);
+        let source_map = self.tcx.sess.source_map();
+        println!(
+            "=> `{}` has type `{}`",
+            source_map
+                .span_to_snippet(local.pat.span)
+                .unwrap_or_else(|_| "??".to_owned()),
+            ty
+        );
+        walk_local(self, local);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Missing file operand");
+        return;
+    }
+    println!("Running typeck example with arguments `{:?}`", args);
+    Config::new(&args)
+        .analysis_level(AnalysisLevel::TypeckBodies)
+        .with_tyctxt(|tcx| {
+            tcx.hir()
+                .visit_all_item_likes_in_crate(&mut LetVisitor { tcx });
+        })
+        .unwrap();
+}
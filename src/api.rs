@@ -0,0 +1,459 @@
+//! Helpers to get the public API of the local crate, for example to compare two versions of it.
+
+use rustc_attr::StabilityLevel;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
+use rustc_hir::{ItemKind, UseKind};
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{DefIdTree, GenericParamDefKind, TyCtxt};
+use rustc_span::symbol::{kw, sym, Symbol};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::paths::qualified_name;
+use crate::spans::location_string;
+
+/// Returned by [`public_api`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ApiSummary {
+    pub crate_name: String,
+    /// Sorted by path.
+    pub items: Vec<ApiItem>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiItem {
+    /// The path through which the item can be used from other crates (like
+    /// `my_crate::module::Struct::field`). An item reachable through several paths (because of
+    /// re-exports) is listed once for each of them.
+    pub path: String,
+    /// The path of the item where it's defined, as returned by [`qualified_name`]. It's different
+    /// from `path` for the re-exported items and their children.
+    pub definition: String,
+    /// The kind of item as returned by `DefKind::descr`, like `"function"` or `"struct"`.
+    pub kind: String,
+    /// The type of the functions (like `unsafe fn(u32) -> std::string::String`), of the
+    /// constants, statics and fields, and the aliased type of the type aliases. It's empty for the
+    /// other items.
+    pub signature: String,
+    /// The generic parameters declared by the item itself (not by its parent), like `'a`, `T` or
+    /// `const N: usize`. The `impl Trait` arguments are part of the signature and not included.
+    pub generics: Vec<String>,
+    /// The bounds of the generic parameters and the where clauses, like `T: std::clone::Clone`.
+    /// For the associated types of the traits, the bounds of the type are included as well (like
+    /// `<Self as Trait>::Item: std::fmt::Debug`).
+    pub where_clauses: Vec<String>,
+    /// `true` for the structs, enums and variants with `#[non_exhaustive]`.
+    pub non_exhaustive: bool,
+    /// `true` for the structs and unions with fields which aren't public, so they can't be built
+    /// with a struct expression by other crates.
+    pub private_fields: bool,
+    /// `true` for the associated items of the traits which have a default: the functions with a
+    /// body and the types and constants with a value. The impls of the trait don't need to define
+    /// them.
+    pub has_default: bool,
+    /// `true` if the item or one of the items in its path has `#[doc(hidden)]`.
+    pub doc_hidden: bool,
+    pub deprecation: Option<ApiDeprecation>,
+    /// Only available for the crates using `#![feature(staged_api)]`.
+    pub stability: Option<ApiStability>,
+    /// Where the item is defined, like `src/lib.rs:12:1`.
+    pub location: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiDeprecation {
+    pub since: Option<String>,
+    pub note: Option<String>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApiStability {
+    Stable { since: String },
+    Unstable { feature: String },
+}
+
+/// Returns all the public items of the local crate which can be reached from its root (so the
+/// public items of private modules are only included if they are re-exported), along with the
+/// public fields, the variants and the public associated items of the types and traits. The
+/// `#[macro_export]` macros are included at the root of the crate.
+///
+/// The items behind `pub use` (including glob re-exports and re-exports of items from other
+/// crates) are listed under the path of the re-export. The trait impls are not included.
+pub fn public_api(tcx: TyCtxt<'_>) -> ApiSummary {
+    let mut items = public_items(tcx)
+        .into_iter()
+        .map(|item| api_item(tcx, item.def_id, item.path, item.doc_hidden))
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| (&a.path, &a.definition).cmp(&(&b.path, &b.definition)));
+    items.dedup();
+    ApiSummary {
+        crate_name: tcx.crate_name(LOCAL_CRATE).to_string(),
+        items,
+    }
+}
+
+/// An item of the public API with the path through which it can be used, see [`public_api`].
+pub(crate) struct PublicItem {
+    pub(crate) path: String,
+    pub(crate) def_id: DefId,
+    /// `true` if the item or one of the items in its path has `#[doc(hidden)]`.
+    pub(crate) doc_hidden: bool,
+}
+
+/// Returns the items of [`public_api`], unsorted. An item is listed once for each path.
+pub(crate) fn public_items(tcx: TyCtxt<'_>) -> Vec<PublicItem> {
+    let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+    let mut collector = ApiCollector {
+        tcx,
+        items: Vec::new(),
+        module_stack: Vec::new(),
+    };
+    collector.add_children(LOCAL_CRATE.as_def_id(), &crate_name, false);
+
+    let hir = tcx.hir();
+    for item_id in hir.items() {
+        let item = hir.item(item_id);
+        if let ItemKind::Macro(macro_def, _) = &item.kind {
+            let is_exported = hir
+                .attrs(item.hir_id())
+                .iter()
+                .any(|attr| attr.has_name(sym::macro_export));
+            if macro_def.macro_rules && is_exported {
+                let path = format!("{}::{}", crate_name, item.ident);
+                collector.add(item.owner_id.to_def_id(), path, false);
+            }
+        }
+    }
+    collector.items
+}
+
+struct ApiCollector<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    items: Vec<PublicItem>,
+    /// The modules being visited, to prevent infinite recursion with re-exports like
+    /// `pub use crate as alias;`.
+    module_stack: Vec<DefId>,
+}
+
+impl<'tcx> ApiCollector<'tcx> {
+    fn add(&mut self, def_id: DefId, path: String, parent_hidden: bool) {
+        let doc_hidden = parent_hidden || self.tcx.is_doc_hidden(def_id);
+        self.items.push(PublicItem {
+            path: path.clone(),
+            def_id,
+            doc_hidden,
+        });
+        self.add_children(def_id, &path, doc_hidden);
+    }
+
+    fn add_children(&mut self, def_id: DefId, path: &str, doc_hidden: bool) {
+        let tcx = self.tcx;
+        let kind = tcx.def_kind(def_id);
+        let mut children = Vec::new();
+        match kind {
+            DefKind::Mod => {
+                if self.module_stack.contains(&def_id) {
+                    return;
+                }
+                self.module_stack.push(def_id);
+                for (name, child_id) in
+                    module_children(tcx, def_id, true, &mut FxHashSet::default())
+                {
+                    self.add(child_id, format!("{path}::{name}"), doc_hidden);
+                }
+                self.module_stack.pop();
+                return;
+            }
+            DefKind::Struct | DefKind::Union | DefKind::Variant => {
+                let variant = match kind {
+                    DefKind::Variant => tcx.adt_def(tcx.parent(def_id)).variant_with_id(def_id),
+                    _ => tcx.adt_def(def_id).non_enum_variant(),
+                };
+                for field in &variant.fields {
+                    // The fields of the variants are public.
+                    if tcx.visibility(field.did).is_public() || kind == DefKind::Variant {
+                        children.push((field.name, field.did));
+                    }
+                }
+            }
+            DefKind::Enum => {
+                for variant in tcx.adt_def(def_id).variants() {
+                    children.push((variant.name, variant.def_id));
+                }
+            }
+            DefKind::Trait => {
+                for &item_id in tcx.associated_item_def_ids(def_id) {
+                    children.push((tcx.item_name(item_id), item_id));
+                }
+            }
+            _ => {}
+        }
+        if matches!(
+            kind,
+            DefKind::Struct | DefKind::Union | DefKind::Enum | DefKind::ForeignTy
+        ) {
+            for &impl_id in tcx.inherent_impls(def_id) {
+                for &item_id in tcx.associated_item_def_ids(impl_id) {
+                    if tcx.visibility(item_id).is_public() {
+                        children.push((tcx.item_name(item_id), item_id));
+                    }
+                }
+            }
+        }
+        for (name, child_id) in children {
+            self.add(child_id, format!("{path}::{name}"), doc_hidden);
+        }
+    }
+}
+
+fn api_item(tcx: TyCtxt<'_>, def_id: DefId, path: String, doc_hidden: bool) -> ApiItem {
+    let kind = tcx.def_kind(def_id);
+    let (generics, where_clauses) = generics(tcx, def_id, kind);
+
+    let non_exhaustive = match kind {
+        DefKind::Enum => tcx.adt_def(def_id).is_variant_list_non_exhaustive(),
+        DefKind::Struct | DefKind::Union => tcx
+            .adt_def(def_id)
+            .non_enum_variant()
+            .is_field_list_non_exhaustive(),
+        DefKind::Variant => tcx
+            .adt_def(tcx.parent(def_id))
+            .variant_with_id(def_id)
+            .is_field_list_non_exhaustive(),
+        _ => false,
+    };
+    let private_fields = matches!(kind, DefKind::Struct | DefKind::Union)
+        && tcx
+            .adt_def(def_id)
+            .all_fields()
+            .any(|field| !tcx.visibility(field.did).is_public());
+    let has_default = matches!(
+        kind,
+        DefKind::AssocFn | DefKind::AssocTy | DefKind::AssocConst
+    ) && tcx.trait_of_item(def_id).is_some()
+        && tcx.associated_item(def_id).defaultness(tcx).has_value();
+
+    ApiItem {
+        path,
+        definition: qualified_name(tcx, def_id),
+        kind: kind.descr(def_id).to_owned(),
+        signature: signature(tcx, def_id, kind),
+        generics,
+        where_clauses,
+        non_exhaustive,
+        private_fields,
+        has_default,
+        doc_hidden,
+        deprecation: deprecation(tcx, def_id),
+        stability: stability(tcx, def_id),
+        location: location_string(tcx, tcx.def_span(def_id)),
+    }
+}
+
+/// Returns the generic parameters declared by `def_id` itself and its where clauses, see
+/// [`ApiItem::generics`] and [`ApiItem::where_clauses`].
+pub(crate) fn generics(
+    tcx: TyCtxt<'_>,
+    def_id: DefId,
+    kind: DefKind,
+) -> (Vec<String>, Vec<String>) {
+    let has_generics = matches!(
+        kind,
+        DefKind::Fn
+            | DefKind::AssocFn
+            | DefKind::Struct
+            | DefKind::Enum
+            | DefKind::Union
+            | DefKind::Trait
+            | DefKind::TraitAlias
+            | DefKind::TyAlias
+            | DefKind::AssocTy
+            | DefKind::AssocConst
+    );
+    if !has_generics {
+        return (Vec::new(), Vec::new());
+    }
+
+    let mut generics = Vec::new();
+    for param in &tcx.generics_of(def_id).params {
+        match param.kind {
+            // The `Self` parameter of the traits.
+            _ if param.name == kw::SelfUpper => {}
+            GenericParamDefKind::Type {
+                synthetic: true, ..
+            } => {}
+            GenericParamDefKind::Lifetime | GenericParamDefKind::Type { .. } => {
+                generics.push(param.name.to_string());
+            }
+            GenericParamDefKind::Const { .. } => generics.push(with_no_trimmed_paths!(format!(
+                "const {}: {}",
+                param.name,
+                tcx.type_of(param.def_id)
+            ))),
+        }
+    }
+    let mut predicates = tcx.explicit_predicates_of(def_id).predicates.to_vec();
+    // Only the associated types of the traits have bounds, the ones of the impls are aliases.
+    if kind == DefKind::AssocTy && tcx.def_kind(tcx.parent(def_id)) == DefKind::Trait {
+        predicates.extend_from_slice(tcx.explicit_item_bounds(def_id));
+    }
+    let where_clauses = predicates
+        .iter()
+        .map(|(predicate, _)| with_no_trimmed_paths!(predicate.to_string()))
+        .collect();
+    (generics, where_clauses)
+}
+
+pub(crate) fn deprecation(tcx: TyCtxt<'_>, def_id: DefId) -> Option<ApiDeprecation> {
+    tcx.lookup_deprecation(def_id)
+        .map(|deprecation| ApiDeprecation {
+            since: deprecation.since.map(|since| since.to_string()),
+            note: deprecation.note.map(|note| note.to_string()),
+        })
+}
+
+pub(crate) fn stability(tcx: TyCtxt<'_>, def_id: DefId) -> Option<ApiStability> {
+    tcx.lookup_stability(def_id)
+        .map(|stability| match stability.level {
+            StabilityLevel::Stable { since, .. } => ApiStability::Stable {
+                since: since.to_string(),
+            },
+            StabilityLevel::Unstable { .. } => ApiStability::Unstable {
+                feature: stability.feature.to_string(),
+            },
+        })
+}
+
+/// Returns the signature of `def_id`, see [`ApiItem::signature`].
+pub(crate) fn signature(tcx: TyCtxt<'_>, def_id: DefId, kind: DefKind) -> String {
+    with_no_trimmed_paths!(match kind {
+        DefKind::Fn | DefKind::AssocFn => {
+            let constness = if tcx.is_const_fn_raw(def_id) {
+                "const "
+            } else {
+                ""
+            };
+            format!("{}{}", constness, tcx.fn_sig(def_id))
+        }
+        DefKind::Static(mutability) =>
+            format!("{}{}", mutability.prefix_str(), tcx.type_of(def_id)),
+        DefKind::Const | DefKind::AssocConst | DefKind::Field | DefKind::TyAlias => {
+            tcx.type_of(def_id).to_string()
+        }
+        DefKind::AssocTy if tcx.associated_item(def_id).defaultness(tcx).has_value() => {
+            tcx.type_of(def_id).to_string()
+        }
+        _ => String::new(),
+    })
+}
+
+/// Returns the public children of the module `def_id` with the name under which they're
+/// available, or all the names in scope in the module (except the ones of the prelude and of the
+/// extern crates) if `public_only` is `false`. `visited_modules` prevents infinite recursion with
+/// glob re-exports.
+pub(crate) fn module_children(
+    tcx: TyCtxt<'_>,
+    def_id: DefId,
+    public_only: bool,
+    visited_modules: &mut FxHashSet<DefId>,
+) -> Vec<(Symbol, DefId)> {
+    if !visited_modules.insert(def_id) {
+        return Vec::new();
+    }
+    // The constructors share the name of their struct or variant.
+    let is_listed = |res: Res| match res {
+        Res::Def(DefKind::Ctor(..), _) => None,
+        Res::Def(_, def_id) => Some(def_id),
+        _ => None,
+    };
+    let Some(local_id) = def_id.as_local() else {
+        return tcx
+            .module_children(def_id)
+            .iter()
+            .filter(|child| child.vis.is_public())
+            .filter_map(|child| Some((child.ident.name, is_listed(child.res.expect_non_local())?)))
+            .collect();
+    };
+
+    let hir = tcx.hir();
+    let mut children = Vec::new();
+    let mut glob_children = Vec::new();
+    for &item_id in hir.get_module(local_id).0.item_ids {
+        let item = hir.item(item_id);
+        let item_def_id = item.owner_id.to_def_id();
+        if public_only && !tcx.visibility(item_def_id).is_public() {
+            continue;
+        }
+        match item.kind {
+            ItemKind::Use(path, UseKind::Glob) => {
+                for &res in &path.res {
+                    match res {
+                        Res::Def(DefKind::Mod, module_id) => {
+                            // The private items of the parent modules are visible too.
+                            let public_only =
+                                public_only || !tcx.is_descendant_of(def_id, module_id);
+                            glob_children.extend(module_children(
+                                tcx,
+                                module_id,
+                                public_only,
+                                visited_modules,
+                            ));
+                        }
+                        Res::Def(DefKind::Enum, enum_id) => glob_children.extend(
+                            tcx.adt_def(enum_id)
+                                .variants()
+                                .iter()
+                                .map(|variant| (variant.name, variant.def_id)),
+                        ),
+                        _ => {}
+                    }
+                }
+            }
+            ItemKind::Use(path, UseKind::Single) if item.ident.name != kw::Underscore => {
+                children.extend(
+                    path.res
+                        .iter()
+                        .filter_map(|&res| Some((item.ident.name, is_listed(res)?))),
+                );
+            }
+            ItemKind::Use(..)
+            | ItemKind::Impl(_)
+            | ItemKind::GlobalAsm(_)
+            | ItemKind::OpaqueTy(_) => {}
+            ItemKind::ExternCrate(_) => {
+                if let Some(krate) = tcx.extern_mod_stmt_cnum(item.owner_id.def_id) {
+                    children.push((item.ident.name, krate.as_def_id()));
+                }
+            }
+            ItemKind::ForeignMod { items, .. } => {
+                for foreign_item in items {
+                    let foreign_id = foreign_item.id.owner_id.to_def_id();
+                    if !public_only || tcx.visibility(foreign_id).is_public() {
+                        children.push((foreign_item.ident.name, foreign_id));
+                    }
+                }
+            }
+            // The `#[macro_export]` macros are at the root of the crate.
+            ItemKind::Macro(ref macro_def, _) if macro_def.macro_rules && public_only => {}
+            _ => children.push((item.ident.name, item_def_id)),
+        }
+    }
+    // The items defined in the module shadow the ones imported with a glob.
+    let names = children
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<FxHashSet<_>>();
+    children.extend(
+        glob_children
+            .into_iter()
+            .filter(|(name, _)| !names.contains(name)),
+    );
+    children
+}
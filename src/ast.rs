@@ -1,8 +1,12 @@
+use std::marker;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use rustc_ast::ast::Crate;
+use rustc_ast::ast::{AttrVec, Crate, Item};
+use rustc_ast::ptr::P;
+use rustc_ast::token::TokenKind;
+use rustc_ast_pretty::pprust::{self, state::NoAnn};
 use rustc_data_structures::sync::{Lrc, Send};
 use rustc_errors::emitter::{Emitter, EmitterWriter};
 use rustc_errors::translation::Translate;
@@ -10,9 +14,18 @@ use rustc_errors::{ColorConfig, Diagnostic, Handler, Level as DiagnosticLevel};
 use rustc_parse::new_parser_from_file;
 use rustc_parse::parser::Parser;
 use rustc_session::parse::ParseSess;
+use rustc_session::Session;
 use rustc_span::edition::Edition;
 use rustc_span::source_map::{FilePathMapping, SourceMap};
-use rustc_span::FileName;
+use rustc_span::symbol::sym;
+use rustc_span::{FileName, RealFileName};
+
+use crate::hir::{
+    apply_limits, compilation_failed, enter_compiler, infer_crate_type, inject_env_overlay,
+    inject_include_overlay,
+};
+use crate::sysroot::{check_toolchain, rustc_path};
+use crate::{Config, Error, Stage};
 
 /// You can check `ParseSess` documentation [here](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_session/parse/struct.ParseSess.html)
 /// and `Crate` documentation [here](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_ast/ast/struct.Crate.html).
@@ -20,11 +33,16 @@ use rustc_span::FileName;
 /// And to make things much simpler, I strongly recommend to use
 /// the [AST visitor](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_ast/visit/trait.Visitor.html).
 /// (You can take a look at how to use it with `examples/ast.rs`.)
+///
+/// Like the other functions running the compiler, the toolchain of `rustc` is checked first (see
+/// [`Config::check_toolchain`]). To skip this check, use [`Config::with_ast`] with
+/// [`Stage::Parse`] instead.
 pub fn with_ast_parser<T, F: Fn(&ParseSess, &Crate) -> T>(
     path: &Path,
     edition: Edition,
     callback: F,
-) -> Result<T, String> {
+) -> Result<T, Error> {
+    check_toolchain(&rustc_path())?;
     let path = PathBuf::from(&path);
 
     rustc_span::create_session_if_not_set_then(edition, move |_| {
@@ -36,6 +54,105 @@ pub fn with_ast_parser<T, F: Fn(&ParseSess, &Crate) -> T>(
     })
 }
 
+/// Unlike [`with_ast_parser`] which only parses the file, this function runs the compiler with
+/// `rustc_args` (take a look at [`with_tyctxt`](crate::with_tyctxt) for more information about
+/// them) and calls `callback` with the crate once its macros are expanded and its
+/// `#[cfg]`s are evaluated, right before it's lowered to HIR.
+///
+/// The spans of the generated code are kept, so you can use `span.from_expansion()` to know if
+/// a node was written by the user or generated by a macro (like `#[derive]`).
+pub fn with_expanded_ast<T: marker::Send, F: FnOnce(&Crate, &Session) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args).with_ast(Stage::Expand, callback)
+}
+
+/// Runs the compiler with `rustc_args` and returns the source code of the crate once its macros
+/// are expanded, like `-Zunpretty=expanded` does. The items removed by `#[cfg]` are not in it.
+///
+/// If the crate uses proc-macros, don't forget to give the `--extern` arguments for them.
+pub fn expand_to_string(rustc_args: &[String]) -> Result<String, Error> {
+    with_expanded_ast(rustc_args, |krate, sess| {
+        // The source is needed to print the comments.
+        let src_name = sess.io.input.source_name();
+        let src = sess
+            .source_map()
+            .get_source_file(&src_name)
+            .and_then(|file| file.src.as_deref().cloned())
+            .unwrap_or_default();
+        pprust::print_crate(
+            sess.source_map(),
+            krate,
+            src_name,
+            src,
+            &NoAnn,
+            true,
+            sess.edition(),
+            &sess.parse_sess.attr_id_generator,
+        )
+    })
+}
+
+/// Same as [`with_expanded_ast`] except that the compiler stops right after parsing the crate,
+/// so `callback` is called with the crate as it's written: the macros are not expanded, the
+/// `#[cfg]`s are not evaluated and the out-of-line modules (`mod foo;`) are not loaded yet. It's
+/// much faster since the dependencies (including `std`) are not loaded.
+pub fn with_parsed_crate<T: marker::Send, F: FnOnce(&Crate, &Session) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args).with_ast(Stage::Parse, callback)
+}
+
+pub(crate) fn run_until<T: marker::Send, F: FnOnce(&Crate, &Session) -> T + marker::Send>(
+    config: Config,
+    stage: Stage,
+    callback: F,
+) -> Result<T, Error> {
+    let allow_errors = config.allow_errors;
+    let env_overlay = config.injected_env();
+    let include_overlay = config.has_file_loader();
+    let (recursion_limit, type_length_limit) = (config.recursion_limit, config.type_length_limit);
+
+    enter_compiler(config, |compiler, queries| {
+        let sess = compiler.session();
+        let check_errors = || {
+            if !allow_errors && sess.diagnostic().has_errors_or_lint_errors().is_some() {
+                return Err(compilation_failed(sess));
+            }
+            Ok(())
+        };
+        match stage {
+            Stage::Parse => {
+                let krate = queries.parse().map_err(|_| compilation_failed(sess))?;
+                check_errors()?;
+                let krate = krate.borrow();
+                Ok(callback(&krate, sess))
+            }
+            Stage::Expand => {
+                queries
+                    .parse()
+                    .map(|mut krate| {
+                        infer_crate_type(sess, krate.get_mut());
+                        apply_limits(sess, krate.get_mut(), recursion_limit, type_length_limit);
+                        if let Some((vars, isolated)) = &env_overlay {
+                            inject_env_overlay(sess, krate.get_mut(), vars, *isolated);
+                        }
+                        if include_overlay {
+                            inject_include_overlay(sess, krate.get_mut());
+                        }
+                    })
+                    .map_err(|_| compilation_failed(sess))?;
+                let expansion = queries.expansion().map_err(|_| compilation_failed(sess))?;
+                check_errors()?;
+                let krate = Lrc::clone(&expansion.borrow().0);
+                Ok(callback(&krate, sess))
+            }
+        }
+    })
+}
+
 /// Emit errors against every files.
 struct SilentOnIgnoredFilesEmitter {
     source_map: Lrc<SourceMap>,
@@ -159,22 +276,69 @@ fn create_parser_session() -> ParseSess {
     ParseSess::with_span_handler(handler, source_map)
 }
 
-fn create_parser<'a>(file: &Path, sess: &'a ParseSess) -> Result<Parser<'a>, String> {
+fn create_parser<'a>(file: &Path, sess: &'a ParseSess) -> Result<Parser<'a>, Error> {
     catch_unwind(AssertUnwindSafe(move || {
         new_parser_from_file(sess, file, None)
     }))
-    .map_err(|e| format!("failed to create parser: {:?}", e))
+    .map_err(|e| Error::Parser(format!("failed to create parser: {e:?}")))
 }
 
-fn parse_crate(parser: &mut Parser) -> Result<Crate, String> {
+fn parse_crate(parser: &mut Parser) -> Result<Crate, Error> {
     let mut parser = AssertUnwindSafe(parser);
 
     match catch_unwind(move || parser.parse_crate_mod()) {
         Ok(Ok(k)) => Ok(k),
         Ok(Err(mut db)) => {
             db.emit();
-            Err(String::new())
+            Err(Error::Parser("failed to parse crate".to_owned()))
         }
-        Err(e) => Err(format!("parser panicked: {:?}", e)),
+        Err(e) => Err(Error::Parser(format!("parser panicked: {e:?}"))),
     }
 }
+
+/// Loads and parses the out-of-line module `item` (`mod name;`), whose parent module has its
+/// submodules in `dir`, like the compiler does during the expansion. Returns the inner attributes
+/// and the items of the module with the directory of its own submodules, or `None` if it can't be
+/// loaded or parsed.
+pub(crate) fn parse_out_of_line_module(
+    sess: &Session,
+    item: &Item,
+    dir: &Path,
+) -> Option<(AttrVec, Vec<P<Item>>, PathBuf)> {
+    let source_map = sess.source_map();
+    let path_attr = item
+        .attrs
+        .iter()
+        .find(|attr| attr.has_name(sym::path))
+        .and_then(|attr| attr.value_str());
+    let file = match path_attr {
+        // It's relative to the directory of the current file.
+        Some(path) => match source_map.span_to_filename(item.span) {
+            FileName::Real(RealFileName::LocalPath(file)) => {
+                file.parent().map(|dir| dir.join(path.as_str()))
+            }
+            _ => None,
+        },
+        None => Some(dir.join(format!("{}.rs", item.ident)))
+            .filter(|file| source_map.file_exists(file))
+            .or_else(|| Some(dir.join(item.ident.as_str()).join("mod.rs"))),
+    };
+    let file = file.filter(|file| source_map.file_exists(file))?;
+
+    let mut parser = new_parser_from_file(&sess.parse_sess, &file, None);
+    let (attrs, items) = match parser.parse_mod(&TokenKind::Eof) {
+        Ok((attrs, items, _)) => (attrs, items),
+        Err(err) => {
+            err.cancel();
+            return None;
+        }
+    };
+    // The submodules of `name/mod.rs` (or of a file given by `#[path]`) are in its directory and
+    // the ones of `name.rs` in `name/`.
+    let module_dir = if path_attr.is_some() || file.ends_with("mod.rs") {
+        file.parent().map(Path::to_owned).unwrap_or_default()
+    } else {
+        dir.join(item.ident.as_str())
+    };
+    Some((attrs, items, module_dir))
+}
@@ -0,0 +1,93 @@
+//! Helpers to know what the `async fn`s of the local crate are lowered to: the generator which
+//! implements their future, what it keeps across the `.await`s and its size.
+//!
+//! The generators are only laid out in the optimized MIR, which requires the crate to be fully
+//! checked, so these functions should be used with [`AnalysisLevel::Full`](crate::AnalysisLevel).
+
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::{AsyncGeneratorKind, ExprKind, GeneratorKind};
+use rustc_middle::ty::{GeneratorSubsts, ParamEnv, TyCtxt};
+use rustc_span::Span;
+
+use std::ops::ControlFlow;
+
+use crate::visit::for_each_expr_in_body;
+
+/// Returned by [`async_fn_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsyncFnInfo {
+    /// The generator the body of the function is lowered to. Its type (given by `tcx.type_of`)
+    /// is the one of the future returned by the function.
+    pub generator: LocalDefId,
+    /// The points where the future can be suspended, in the order in which the compiler found
+    /// them. There's one for each `.await` which can be reached.
+    pub await_points: Vec<AwaitPoint>,
+    /// The size in bytes of the future, or `Err` with the reason if it couldn't be computed, like
+    /// for generic functions.
+    pub future_size: Result<u64, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AwaitPoint {
+    /// The span of the `.await` in its desugaring, use `span.source_callsite()` to get the span
+    /// of the `.await` as written (without the awaited expression).
+    pub span: Span,
+    /// The types of the values held across the `.await`, which are stored in the future (like a
+    /// `MutexGuard` which is still alive). The lifetimes are erased, so they're written `'_`.
+    pub held_types: Vec<String>,
+}
+
+/// Returns what the `async fn` `def_id` is lowered to, or `None` if it isn't an `async fn` (or a
+/// method). `def_id` can also be the one of an `async` block or closure.
+pub fn async_fn_info(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Option<AsyncFnInfo> {
+    let generator = if tcx.generator_kind(def_id).is_some() {
+        def_id
+    } else {
+        let body_id = tcx.hir().maybe_body_owned_by(def_id)?;
+        // The body of an `async fn` is an `async` block wrapped in a call to `identity_future`.
+        for_each_expr_in_body(tcx, body_id, |expr| match expr.kind {
+            ExprKind::Closure(closure)
+                if tcx.generator_kind(closure.def_id)
+                    == Some(GeneratorKind::Async(AsyncGeneratorKind::Fn)) =>
+            {
+                ControlFlow::Break(closure.def_id)
+            }
+            _ => ControlFlow::Continue(()),
+        })?
+    };
+
+    let body = tcx.optimized_mir(generator);
+    let layout = body.generator_layout()?;
+    let await_points = layout
+        .variant_fields
+        .iter_enumerated()
+        // The first variants are the unresumed, returned and poisoned states.
+        .skip(GeneratorSubsts::POISONED + 1)
+        .map(|(variant, fields)| AwaitPoint {
+            span: layout.variant_source_info[variant].span,
+            held_types: fields
+                .iter()
+                .map(|local| layout.field_tys[*local].to_string())
+                .collect(),
+        })
+        .collect();
+
+    // The generics of the generator include the ones of the function it's defined in.
+    let parent = tcx.typeck_root_def_id(generator.to_def_id());
+    let future_size = if tcx.generics_of(parent).requires_monomorphization(tcx) {
+        Err("the function is generic".to_owned())
+    } else {
+        // `type_of` would give the generator with generic upvars, the type checking gives the
+        // actual ones.
+        let hir_id = tcx.hir().local_def_id_to_hir_id(generator);
+        let ty = tcx.erase_regions(tcx.typeck(generator).node_type(hir_id));
+        tcx.layout_of(ParamEnv::reveal_all().and(ty))
+            .map(|layout| layout.size.bytes())
+            .map_err(|e| e.to_string())
+    };
+    Some(AsyncFnInfo {
+        generator,
+        await_points,
+        future_size,
+    })
+}
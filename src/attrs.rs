@@ -0,0 +1,204 @@
+//! Helpers to read the attributes of the items (local or not): their documentation, the presence
+//! of an attribute (like a tool attribute registered with `#![register_tool]`) and the values
+//! given to an attribute.
+//!
+//! The attributes are read after the expansion, so the `#[cfg_attr]` have been replaced by the
+//! attributes they contain if their condition holds, and removed otherwise.
+
+use rustc_ast::util::comments::beautify_doc_string;
+use rustc_ast::{
+    AttrKind, Attribute, LitKind, MetaItem, MetaItemKind, MetaItemLit, NestedMetaItem,
+};
+use rustc_ast_pretty::pprust;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A value given to an attribute, returned by [`attr_meta_items`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetaItemValue {
+    Str(String),
+    Int(u128),
+    Bool(bool),
+    /// A path alone (like `Debug` or `my_tool::name`).
+    Path(String),
+    /// A path with a value (like `name = "value"`).
+    NameValue {
+        name: String,
+        value: Box<MetaItemValue>,
+    },
+    /// A path with a list of values (like `name(a, "b")`).
+    List {
+        name: String,
+        items: Vec<MetaItemValue>,
+    },
+    /// Any other literal (like a float or a character), as written in the source code.
+    Other(String),
+}
+
+/// Returns the documentation of `def_id`, both from the doc comments (`///`, `/** */`, etc) and
+/// from the `#[doc = "..."]` attributes, or `None` if it doesn't have any. The parts are joined
+/// with newlines in the order in which they're written, without the blank lines at the beginning
+/// and at the end.
+///
+/// Like with rustdoc, the leading whitespace common to all the lines of the doc comments is
+/// removed (so `/// text` gives `text`), whereas it's computed separately for each
+/// `#[doc = "..."]` attribute.
+pub fn doc_comment(tcx: TyCtxt<'_>, def_id: DefId) -> Option<String> {
+    let lines = doc_lines(tcx, def_id)?;
+    let first = lines.iter().position(|(line, _)| !line.trim().is_empty());
+    let last = lines.iter().rposition(|(line, _)| !line.trim().is_empty());
+    Some(match (first, last) {
+        (Some(first), Some(last)) => lines[first..=last]
+            .iter()
+            .map(|(line, _)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    })
+}
+
+/// Returns the lines of the documentation of `def_id` (unindented like with [`doc_comment`], but
+/// with the blank lines at the beginning and at the end) along with the span of the attribute
+/// they come from, or `None` if it doesn't have any documentation.
+pub(crate) fn doc_lines(tcx: TyCtxt<'_>, def_id: DefId) -> Option<Vec<(String, Span)>> {
+    // The lines with whether they come from a doc comment, the attributes are unindented here.
+    let mut lines = Vec::new();
+    let mut has_doc = false;
+    for attr in tcx.get_attrs_unchecked(def_id) {
+        let Some((data, kind)) = attr.doc_str_and_comment_kind() else { continue };
+        has_doc = true;
+        // Removes the stars of the block comments.
+        let doc = beautify_doc_string(data, kind);
+        let doc_lines = doc.as_str().split('\n');
+        if attr.is_doc_comment() {
+            lines.extend(doc_lines.map(|line| (line.to_owned(), attr.span, true)));
+        } else {
+            let doc_lines = doc_lines.collect::<Vec<_>>();
+            let indent = common_indent(&doc_lines);
+            lines.extend(
+                doc_lines
+                    .iter()
+                    .map(|line| (unindent(line, indent).to_owned(), attr.span, false)),
+            );
+        }
+    }
+    if !has_doc {
+        return None;
+    }
+
+    let comment_lines = lines
+        .iter()
+        .filter(|(_, _, is_comment)| *is_comment)
+        .map(|(line, _, _)| line.as_str())
+        .collect::<Vec<_>>();
+    let indent = common_indent(&comment_lines);
+    Some(
+        lines
+            .iter()
+            .map(|(line, span, is_comment)| {
+                let line = if *is_comment {
+                    unindent(line, indent)
+                } else {
+                    line
+                };
+                (line.to_owned(), *span)
+            })
+            .collect(),
+    )
+}
+
+/// Returns the number of spaces and tabs at the beginning of all the non-blank lines.
+fn common_indent(lines: &[&str]) -> usize {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0)
+}
+
+fn unindent(line: &str, indent: usize) -> &str {
+    if line.trim().is_empty() {
+        ""
+    } else {
+        &line[indent..]
+    }
+}
+
+/// Returns `true` if `def_id` has an attribute whose path is `path`, like `&["inline"]` for
+/// `#[inline]` or `&["my_tool", "skip"]` for `#[my_tool::skip]`.
+pub fn has_attr(tcx: TyCtxt<'_>, def_id: DefId, path: &[&str]) -> bool {
+    tcx.get_attrs_unchecked(def_id)
+        .iter()
+        .any(|attr| path_matches(attr, path))
+}
+
+/// Returns the values given to the attributes of `def_id` whose path is `path` (see
+/// [`has_attr`]), in the order in which they're written. For example
+/// `#[my_tool::config(name = "x", 2, flag)]` gives a [`MetaItemValue::NameValue`], a
+/// [`MetaItemValue::Int`] and a [`MetaItemValue::Path`], and `#[my_tool::config = "x"]` gives a
+/// [`MetaItemValue::Str`]. The attributes without a value (like `#[my_tool::config]`) and the
+/// ones which don't follow the meta item syntax (like `#[my_tool::config(+ -)]`) don't give any
+/// value.
+pub fn attr_meta_items(tcx: TyCtxt<'_>, def_id: DefId, path: &[&str]) -> Vec<MetaItemValue> {
+    let mut values = Vec::new();
+    for attr in tcx.get_attrs_unchecked(def_id) {
+        if !path_matches(attr, path) {
+            continue;
+        }
+        match attr.meta().map(|meta| meta.kind) {
+            Some(MetaItemKind::List(items)) => values.extend(items.iter().map(nested_value)),
+            Some(MetaItemKind::NameValue(lit)) => values.push(lit_value(&lit)),
+            Some(MetaItemKind::Word) | None => {}
+        }
+    }
+    values
+}
+
+fn path_matches(attr: &Attribute, path: &[&str]) -> bool {
+    let AttrKind::Normal(normal) = &attr.kind else { return false };
+    let segments = &normal.item.path.segments;
+    segments.len() == path.len()
+        && segments
+            .iter()
+            .zip(path)
+            .all(|(segment, name)| segment.ident.name.as_str() == *name)
+}
+
+fn nested_value(item: &NestedMetaItem) -> MetaItemValue {
+    match item {
+        NestedMetaItem::MetaItem(meta) => meta_value(meta),
+        NestedMetaItem::Lit(lit) => lit_value(lit),
+    }
+}
+
+fn meta_value(meta: &MetaItem) -> MetaItemValue {
+    let name = pprust::path_to_string(&meta.path);
+    match &meta.kind {
+        MetaItemKind::Word => MetaItemValue::Path(name),
+        MetaItemKind::NameValue(lit) => MetaItemValue::NameValue {
+            name,
+            value: Box::new(lit_value(lit)),
+        },
+        MetaItemKind::List(items) => MetaItemValue::List {
+            name,
+            items: items.iter().map(nested_value).collect(),
+        },
+    }
+}
+
+fn lit_value(lit: &MetaItemLit) -> MetaItemValue {
+    match lit.kind {
+        LitKind::Str(value, _) => MetaItemValue::Str(value.to_string()),
+        LitKind::Int(value, _) => MetaItemValue::Int(value),
+        LitKind::Bool(value) => MetaItemValue::Bool(value),
+        _ => MetaItemValue::Other(pprust::meta_list_item_to_string(&NestedMetaItem::Lit(
+            lit.clone(),
+        ))),
+    }
+}
@@ -0,0 +1,386 @@
+//! Helpers to check which of the public types of the local crate implement the auto traits (`Send`,
+//! `Sync` and `Unpin`), and why they don't, for example to make sure a handle type stays `Send`.
+//!
+//! The types are not checked with [`AnalysisLevel::HirOnly`](crate::AnalysisLevel) and solving the
+//! traits of an ill-formed type can make the compiler panic, so these functions should be used
+//! with [`AnalysisLevel::Full`](crate::AnalysisLevel).
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{
+    self, GenericParamDefKind, ImplPolarity, ParamEnv, ToPredicate, Ty, TyCtxt, TypeVisitable,
+};
+use rustc_span::symbol::sym;
+use rustc_span::DUMMY_SP;
+use rustc_trait_selection::infer::{InferCtxt, TyCtxtInferExt};
+use rustc_trait_selection::traits::query::evaluate_obligation::InferCtxtExt;
+use rustc_trait_selection::traits::{Obligation, ObligationCause};
+
+use std::collections::HashSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::paths::qualified_name;
+use crate::spans::location_string;
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AutoTrait {
+    Send,
+    Sync,
+    Unpin,
+}
+
+impl AutoTrait {
+    pub fn name(self) -> &'static str {
+        match self {
+            AutoTrait::Send => "Send",
+            AutoTrait::Sync => "Sync",
+            AutoTrait::Unpin => "Unpin",
+        }
+    }
+
+    fn def_id(self, tcx: TyCtxt<'_>) -> Option<DefId> {
+        match self {
+            AutoTrait::Send => tcx.get_diagnostic_item(sym::Send),
+            AutoTrait::Sync => tcx.lang_items().sync_trait(),
+            AutoTrait::Unpin => tcx.lang_items().unpin_trait(),
+        }
+    }
+}
+
+/// Returned by [`auto_trait_audit`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeVerdict {
+    /// See [`qualified_name`].
+    pub name: String,
+    /// Where the type is defined, like `src/lib.rs:12:1`.
+    pub location: String,
+    /// What was assumed about the type parameters, like `T: Send`. It's empty for the types which
+    /// aren't generic.
+    pub assumptions: Vec<String>,
+    /// One for each of the audited traits, in the same order.
+    pub traits: Vec<AutoTraitVerdict>,
+}
+
+impl TypeVerdict {
+    /// Returns why the type doesn't implement `auto_trait`, like
+    /// ``Foo -> inner: `std::rc::Rc<Bar>` isn't Send``, or `None` if it implements it or if it
+    /// wasn't audited.
+    pub fn explanation(&self, auto_trait: AutoTrait) -> Option<String> {
+        let verdict = self
+            .traits
+            .iter()
+            .find(|verdict| verdict.auto_trait == auto_trait && !verdict.implemented)?;
+        let mut explanation = self.name.clone();
+        for link in &verdict.chain {
+            explanation.push_str(&format!(" -> {}: `{}`", link.step, link.ty));
+        }
+        let last = verdict.chain.last();
+        explanation.push_str(&format!(
+            " isn't {}",
+            last.map_or(auto_trait, |link| link.auto_trait).name()
+        ));
+        if last.map_or(false, |link| link.negative_impl) {
+            explanation.push_str(" (explicitly)");
+        }
+        Some(explanation)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutoTraitVerdict {
+    pub auto_trait: AutoTrait,
+    pub implemented: bool,
+    /// If it isn't implemented, the components of the type leading to the one which is the cause,
+    /// each one being part of the previous one. It's empty if the cause is the type itself, like
+    /// for a type with `impl !Send`.
+    pub chain: Vec<ChainLink>,
+}
+
+/// A component of a type which doesn't implement an auto trait, see [`AutoTraitVerdict::chain`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainLink {
+    /// How the component is reached from the previous one:
+    /// - the name of a field (like `inner` or `0`), prefixed by the name of the variant for the
+    ///   enums (like `Connected::socket`),
+    /// - `.0`, `.1`, etc for the elements of a tuple, `[]` for the elements of an array or a slice
+    ///   and `*` for the type a reference points to,
+    /// - the name of the type parameter of the impl of the auto trait whose where clause isn't
+    ///   satisfied, like `T` for `std::sync::Arc<T>` (which is `Send` if `T: Send + Sync`) or for
+    ///   `&T` (which is `Send` if `T: Sync`),
+    /// - `T` for the type argument of `std::marker::PhantomData<T>`.
+    pub step: String,
+    pub ty: String,
+    /// The trait the component doesn't implement, which may not be the audited one: a reference
+    /// isn't `Send` because the referenced type isn't `Sync`.
+    pub auto_trait: AutoTrait,
+    /// `true` if the component has an impl like `impl !Send`, which is the case of
+    /// `std::rc::Rc<T>`, `std::cell::Cell<T>` or the raw pointers for example.
+    pub negative_impl: bool,
+}
+
+/// Returns whether the public structs, enums and unions of the local crate (the ones which can be
+/// used from other crates) implement the auto traits `traits`, in the order in which they're
+/// defined.
+///
+/// The type parameters of the generic types are assumed to implement the audited trait (for
+/// example `Wrapper<T>` is `Send` if `T: Send`), in addition to the bounds of the type. The
+/// assumptions are in [`TypeVerdict::assumptions`]. The lifetimes are ignored.
+///
+/// When a type doesn't implement a trait, the first field which doesn't implement it is followed
+/// until a component which is the cause is found: a type with a negative impl, a type whose impl
+/// of the trait has a where clause which isn't satisfied and none of whose type parameters is the
+/// cause, a trait object without the trait (like `dyn Fn()`), an opaque type, a closure, etc.
+pub fn auto_trait_audit(tcx: TyCtxt<'_>, traits: &[AutoTrait]) -> Vec<TypeVerdict> {
+    let effective_visibilities = tcx.effective_visibilities(());
+    tcx.hir_crate_items(())
+        .definitions()
+        .filter(|&def_id| {
+            matches!(
+                tcx.def_kind(def_id),
+                DefKind::Struct | DefKind::Enum | DefKind::Union
+            ) && effective_visibilities.is_exported(def_id)
+        })
+        .map(|def_id| {
+            let def_id = def_id.to_def_id();
+            let ty = tcx.erase_regions(tcx.type_of(def_id));
+            let type_params = tcx
+                .generics_of(def_id)
+                .params
+                .iter()
+                .filter(|param| matches!(param.kind, GenericParamDefKind::Type { .. }))
+                .map(|param| tcx.mk_ty_param(param.index, param.name))
+                .collect::<Vec<_>>();
+            let mut assumptions = Vec::new();
+            let traits = traits
+                .iter()
+                .map(|&auto_trait| {
+                    let Some(trait_def_id) = auto_trait.def_id(tcx) else {
+                        return AutoTraitVerdict { auto_trait, implemented: true, chain: Vec::new() };
+                    };
+                    let assumed = type_params.iter().map(|&param| {
+                        ty::Binder::dummy(tcx.mk_trait_ref(trait_def_id, [param]))
+                            .without_const()
+                            .to_predicate(tcx)
+                    });
+                    let param_env = tcx.param_env(def_id);
+                    let param_env = ParamEnv::new(
+                        tcx.mk_predicates(param_env.caller_bounds().iter().chain(assumed)),
+                        param_env.reveal(),
+                        param_env.constness(),
+                    );
+                    for param in &type_params {
+                        assumptions.push(format!("{}: {}", param, auto_trait.name()));
+                    }
+                    let mut auditor = Auditor {
+                        tcx,
+                        param_env,
+                        infcx: tcx.infer_ctxt().build(),
+                        visited: HashSet::new(),
+                    };
+                    let mut chain = Vec::new();
+                    let implemented = auditor.implements(ty, auto_trait);
+                    if !implemented {
+                        auditor.find_cause(ty, auto_trait, &mut chain);
+                    }
+                    AutoTraitVerdict {
+                        auto_trait,
+                        implemented,
+                        chain,
+                    }
+                })
+                .collect();
+            TypeVerdict {
+                name: qualified_name(tcx, def_id),
+                location: location_string(tcx, tcx.def_span(def_id)),
+                assumptions,
+                traits,
+            }
+        })
+        .collect()
+}
+
+struct Auditor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    infcx: InferCtxt<'tcx>,
+    /// The components already looked at by [`Auditor::find_cause`].
+    visited: HashSet<(Ty<'tcx>, AutoTrait)>,
+}
+
+impl<'tcx> Auditor<'tcx> {
+    fn implements(&self, ty: Ty<'tcx>, auto_trait: AutoTrait) -> bool {
+        let Some(trait_def_id) = auto_trait.def_id(self.tcx) else { return true };
+        let trait_ref = ty::Binder::dummy(self.tcx.mk_trait_ref(trait_def_id, [ty]));
+        self.holds(trait_ref.without_const().to_predicate(self.tcx))
+    }
+
+    fn holds(&self, predicate: ty::Predicate<'tcx>) -> bool {
+        self.infcx
+            .predicate_must_hold_modulo_regions(&Obligation::new(
+                self.tcx,
+                ObligationCause::dummy(),
+                self.param_env,
+                predicate,
+            ))
+    }
+
+    /// Adds to `chain` the components of `ty` (which doesn't implement `auto_trait`) leading to
+    /// the cause. Returns `false` if `ty` doesn't implement it only because of components already
+    /// visited, like a recursive type (a list for example) which doesn't implement it because of
+    /// itself.
+    fn find_cause(
+        &mut self,
+        ty: Ty<'tcx>,
+        auto_trait: AutoTrait,
+        chain: &mut Vec<ChainLink>,
+    ) -> bool {
+        let tcx = self.tcx;
+        if !self.visited.insert((ty, auto_trait)) {
+            return false;
+        }
+        let explicit_impls = auto_trait.def_id(tcx).map_or(Vec::new(), |trait_def_id| {
+            tcx.non_blanket_impls_for_ty(trait_def_id, ty).collect()
+        });
+        let candidates = if !explicit_impls.is_empty() {
+            let mut candidates = Vec::new();
+            for impl_def_id in explicit_impls {
+                match tcx.impl_polarity(impl_def_id) {
+                    ImplPolarity::Negative if self.impl_applies(impl_def_id, ty) => {
+                        if let Some(link) = chain.last_mut() {
+                            link.negative_impl = true;
+                        }
+                        return true;
+                    }
+                    ImplPolarity::Positive => {
+                        candidates.extend(self.unsatisfied_where_clauses(impl_def_id, ty));
+                    }
+                    _ => {}
+                }
+            }
+            candidates
+        } else {
+            let components = match *ty.kind() {
+                ty::Adt(adt, substs) if adt.is_phantom_data() => tcx
+                    .generics_of(adt.did())
+                    .params
+                    .iter()
+                    .zip(substs.types())
+                    .map(|(param, ty)| (param.name.to_string(), ty))
+                    .collect(),
+                ty::Adt(adt, substs) => adt
+                    .variants()
+                    .iter()
+                    .flat_map(|variant| {
+                        variant.fields.iter().map(move |field| {
+                            let step = if adt.is_enum() {
+                                format!("{}::{}", variant.name, field.name)
+                            } else {
+                                field.name.to_string()
+                            };
+                            (step, field.ty(tcx, substs))
+                        })
+                    })
+                    .collect(),
+                ty::Tuple(tys) => tys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| (format!(".{i}"), ty))
+                    .collect(),
+                ty::Array(ty, _) | ty::Slice(ty) => vec![("[]".to_owned(), ty)],
+                ty::Ref(_, ty, _) => vec![("*".to_owned(), ty)],
+                _ => Vec::new(),
+            };
+            components
+                .into_iter()
+                .filter(|&(_, ty)| !self.implements(ty, auto_trait))
+                .map(|(step, ty)| (step, ty, auto_trait))
+                .collect()
+        };
+        if candidates.is_empty() {
+            // `ty` is the cause.
+            return true;
+        }
+
+        for (step, ty, auto_trait) in candidates {
+            chain.push(ChainLink {
+                step,
+                ty: with_no_trimmed_paths!(ty.to_string()),
+                auto_trait,
+                negative_impl: false,
+            });
+            if self.find_cause(ty, auto_trait, chain) {
+                return true;
+            }
+            chain.pop();
+        }
+        false
+    }
+
+    /// Returns `true` if the impl `impl_def_id` is an impl for `ty`.
+    fn impl_applies(&self, impl_def_id: DefId, ty: Ty<'tcx>) -> bool {
+        self.infcx
+            .probe(|_| self.match_impl(impl_def_id, ty).is_some())
+    }
+
+    /// Returns the type parameters of the impl `impl_def_id` (an impl of an auto trait for `ty`)
+    /// which don't implement an auto trait required by the where clauses, with this trait.
+    fn unsatisfied_where_clauses(
+        &self,
+        impl_def_id: DefId,
+        ty: Ty<'tcx>,
+    ) -> Vec<(String, Ty<'tcx>, AutoTrait)> {
+        let tcx = self.tcx;
+        self.infcx.probe(|_| {
+            let Some(substs) = self.match_impl(impl_def_id, ty) else { return Vec::new() };
+            let generics = tcx.generics_of(impl_def_id);
+            tcx.predicates_of(impl_def_id)
+                .instantiate(tcx, substs)
+                .predicates
+                .into_iter()
+                .filter_map(|predicate| {
+                    let predicate = self.infcx.resolve_vars_if_possible(predicate);
+                    let trait_predicate = predicate.to_opt_poly_trait_pred()?.skip_binder();
+                    let auto_trait = [AutoTrait::Send, AutoTrait::Sync, AutoTrait::Unpin]
+                        .into_iter()
+                        .find(|auto_trait| {
+                            auto_trait.def_id(tcx) == Some(trait_predicate.def_id())
+                        })?;
+                    if self.holds(predicate) {
+                        return None;
+                    }
+                    let self_ty = trait_predicate.self_ty();
+                    if self_ty.needs_infer() {
+                        return None;
+                    }
+                    let index = substs.iter().position(|arg| arg == self_ty.into())?;
+                    let step = generics.param_at(index, tcx).name.to_string();
+                    Some((step, self_ty, auto_trait))
+                })
+                .collect()
+        })
+    }
+
+    /// Unifies the self type of the impl `impl_def_id` with `ty`, returning the generic arguments
+    /// of the impl.
+    fn match_impl(&self, impl_def_id: DefId, ty: Ty<'tcx>) -> Option<ty::SubstsRef<'tcx>> {
+        let substs = self.infcx.fresh_substs_for_item(DUMMY_SP, impl_def_id);
+        let impl_ty = ty::EarlyBinder(self.tcx.type_of(impl_def_id)).subst(self.tcx, substs);
+        let cause = ObligationCause::dummy();
+        if self
+            .infcx
+            .at(&cause, self.param_env)
+            .eq(impl_ty, ty)
+            .is_err()
+        {
+            return None;
+        }
+        Some(self.infcx.resolve_vars_if_possible(substs))
+    }
+}
@@ -0,0 +1,209 @@
+//! Helpers to know what the bodies of the local crate borrow and where these borrows are live,
+//! as computed by the borrow checker.
+//!
+//! The facts are computed with [Polonius](https://github.com/rust-lang/polonius) while the
+//! bodies are borrow-checked, which is slow, so it must be enabled with
+//! [`Config::borrowck_facts`](crate::Config::borrowck_facts).
+
+use polonius_engine::{Algorithm, Output};
+use rustc_ast::Mutability;
+use rustc_borrowck::consumers::{get_body_with_borrowck_facts, RichLocation};
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::mir::{
+    Body, BorrowKind, Local, Location, Place, ProjectionElem, Rvalue, StatementKind,
+    VarDebugInfoContents,
+};
+use rustc_middle::ty::query::Providers;
+use rustc_middle::ty::{self, TyCtxt, WithOptConstParam};
+use rustc_span::Span;
+use rustc_target::abi::VariantIdx;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    /// The borrows of the bodies, if [`Config::borrowck_facts`] is set.
+    ///
+    /// [`Config::borrowck_facts`]: crate::Config::borrowck_facts
+    static BORROWS: RefCell<Option<HashMap<LocalDefId, Vec<BorrowInfo>>>> = RefCell::new(None);
+}
+
+/// A borrow (`&place` or `&mut place`, written or not), returned by [`borrowck_facts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BorrowInfo {
+    /// The span of the borrow expression. It can also be an expression which borrows implicitly,
+    /// like the receiver of a method call taking `&self`, or a pattern binding by reference.
+    pub span: Span,
+    /// The borrowed place, like `x`, `(*self).field` or `v[i]`. The variables are given by their
+    /// name and the temporaries like in the MIR, like `_5`.
+    pub place: String,
+    /// `Mutability::Not` for the shared borrows and the unique immutable ones of the closures.
+    pub mutability: Mutability,
+    /// Where the borrow is created in the MIR (the one given by
+    /// [`TyCtxt::mir_promoted`](rustc_middle::ty::TyCtxt::mir_promoted), on which the borrow
+    /// checker runs).
+    pub location: Location,
+    /// The statements and the terminators where the borrow is live, sorted: the reference (or a
+    /// reference derived from it) may still be used after them.
+    pub live_at: Vec<Location>,
+    /// The span going from the first to the last expression of the function where the borrow is
+    /// live, `None` if it's never live (if the reference isn't used).
+    pub live_span: Option<Span>,
+}
+
+/// Returns the borrows of the body of `def_id` (a function, a closure, a constant, etc) in the
+/// order in which they appear in its MIR, or `None` if it doesn't have a body or if
+/// [`Config::borrowck_facts`](crate::Config::borrowck_facts) isn't set.
+///
+/// The body is borrow-checked if it wasn't already. The errors of the borrow checker are emitted
+/// as diagnostics, so you might want to use [`Config::allow_errors`](crate::Config::allow_errors)
+/// to get the borrows of the bodies which don't compile. The borrows still live where the borrow
+/// checker found them conflicting.
+pub fn borrowck_facts(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Option<Vec<BorrowInfo>> {
+    if !tcx.mir_keys(()).contains(&def_id) {
+        return None;
+    }
+    tcx.ensure().mir_borrowck(def_id);
+    BORROWS.with(|borrows| borrows.borrow().as_ref()?.get(&def_id).cloned())
+}
+
+fn compute_borrows(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Vec<BorrowInfo> {
+    let facts = get_body_with_borrowck_facts(tcx, WithOptConstParam::unknown(def_id));
+    let body = &facts.body;
+    let location = |point| match facts.location_table.to_location(point) {
+        RichLocation::Start(location) | RichLocation::Mid(location) => location,
+    };
+
+    // The output of the borrow checker only has the points where a loan is live if it was
+    // computed with this algorithm (and dumped).
+    let output = Output::compute(&facts.input_facts, Algorithm::Naive, true);
+    let mut live_at = HashMap::<_, Vec<_>>::new();
+    for (&point, loans) in &output.loan_live_at {
+        for &loan in loans {
+            live_at.entry(loan).or_default().push(location(point));
+        }
+    }
+
+    let mut loans = facts.input_facts.loan_issued_at.clone();
+    loans.sort_by_key(|&(_, loan, _)| loan);
+    loans
+        .into_iter()
+        .filter_map(|(_, loan, point)| {
+            let location = location(point);
+            let StatementKind::Assign(assign) = &body.stmt_at(location).left()?.kind else {
+                return None;
+            };
+            let Rvalue::Ref(_, kind, place) = assign.1 else { return None };
+            let mut live_at = live_at.remove(&loan).unwrap_or_default();
+            live_at.sort();
+            live_at.dedup();
+            let live_span = live_at
+                .iter()
+                .map(|&location| body.source_info(location).span.source_callsite())
+                // Like the drops at the end of the scope of a variable declared in a macro.
+                .filter(|span| body.span.contains(*span))
+                .reduce(|first, last| first.to(last));
+            Some(BorrowInfo {
+                span: body.source_info(location).span,
+                place: place_to_string(tcx, body, place),
+                mutability: match kind {
+                    BorrowKind::Mut { .. } => Mutability::Mut,
+                    BorrowKind::Shared | BorrowKind::Shallow | BorrowKind::Unique => {
+                        Mutability::Not
+                    }
+                },
+                location,
+                live_at,
+                live_span,
+            })
+        })
+        .collect()
+}
+
+/// Returns `place` as it would be written in Rust, with the dereferences.
+fn place_to_string<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>, place: Place<'tcx>) -> String {
+    let local_name = |local: Local| {
+        body.var_debug_info
+            .iter()
+            .find(|var_debug_info| {
+                matches!(var_debug_info.value, VarDebugInfoContents::Place(place)
+                    if place.as_local() == Some(local))
+            })
+            .map_or_else(
+                || format!("{local:?}"),
+                |var_debug_info| var_debug_info.name.to_string(),
+            )
+    };
+    let mut text = local_name(place.local);
+    for (base, elem) in place.iter_projections() {
+        match elem {
+            ProjectionElem::Deref => text = format!("*{text}"),
+            ProjectionElem::Field(field, _) => {
+                let base_ty = base.ty(body, tcx);
+                let name = match base_ty.ty.kind() {
+                    ty::Adt(adt_def, _) => {
+                        let variant = base_ty.variant_index.unwrap_or(VariantIdx::from_u32(0));
+                        adt_def.variant(variant).fields[field.index()]
+                            .name
+                            .to_string()
+                    }
+                    _ => field.index().to_string(),
+                };
+                // The dereferences apply to the whole place.
+                if text.starts_with('*') {
+                    text = format!("({text})");
+                }
+                text = format!("{text}.{name}");
+            }
+            ProjectionElem::Index(index) => text = format!("{}[{}]", text, local_name(index)),
+            ProjectionElem::ConstantIndex {
+                offset,
+                from_end: false,
+                ..
+            } => text = format!("{text}[{offset}]"),
+            ProjectionElem::ConstantIndex {
+                offset,
+                from_end: true,
+                ..
+            } => text = format!("{text}[-{offset}]"),
+            ProjectionElem::Subslice {
+                from,
+                to,
+                from_end: true,
+            } => text = format!("{text}[{from}..-{to}]"),
+            ProjectionElem::Subslice {
+                from,
+                to,
+                from_end: false,
+            } => text = format!("{text}[{from}..{to}]"),
+            ProjectionElem::Downcast(name, variant) => {
+                let name = name.map_or_else(|| variant.index().to_string(), |n| n.to_string());
+                text = format!("({text} as {name})");
+            }
+            ProjectionElem::OpaqueCast(_) => {}
+        }
+    }
+    text
+}
+
+pub(crate) fn keep_borrowck_facts(keep: bool) {
+    BORROWS.with(|borrows| *borrows.borrow_mut() = keep.then(HashMap::new));
+}
+
+/// Computes the borrows of the bodies as they're borrow-checked if [`keep_borrowck_facts`] was
+/// called with `true`.
+pub(crate) fn provide(providers: &mut Providers) {
+    providers.mir_borrowck = |tcx, def_id| {
+        let enabled = BORROWS.with(|borrows| borrows.borrow().is_some());
+        // The MIR the facts are computed from is stolen once the body is borrow-checked.
+        if enabled {
+            let body_borrows = compute_borrows(tcx, def_id);
+            BORROWS.with(|borrows| {
+                if let Some(borrows) = borrows.borrow_mut().as_mut() {
+                    borrows.insert(def_id, body_borrows);
+                }
+            });
+        }
+        (rustc_interface::DEFAULT_QUERY_PROVIDERS.mir_borrowck)(tcx, def_id)
+    };
+}
@@ -0,0 +1,201 @@
+//! Helpers to reuse the result of an analysis while the crate doesn't change, for example in a CI
+//! job analyzing the same crates again and again. Only available with the `serde` feature.
+//!
+//! ```ignore (needs-rustc-private)
+//! let cache = CachedAnalyzer::new("target/analysis-cache");
+//! // The compiler only runs if the crate changed since the previous call.
+//! let items = cache.run_or_load(&rustc_args, |tcx| tcx.hir().items().count())?;
+//! ```
+
+use rustc_data_structures::stable_hasher::StableHasher;
+use rustc_middle::ty::TyCtxt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::any;
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::hash::Hash;
+use std::marker;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::file_loader::RecordingFileLoader;
+use crate::hir::expand_args;
+use crate::source_files::{source_files, SourceFileOrigin};
+use crate::sysroot::required_nightly;
+use crate::{Config, Error};
+
+/// A hash of the inputs of a compilation, returned by [`fingerprint`]. It's the same between
+/// runs (and processes) as long as the inputs don't change. It's displayed as 32 hexadecimal
+/// digits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint(u128);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// Returns the fingerprint of the crate compiled with `rustc_args`. It's computed from:
+/// - the version of the compiler (see [`required_nightly`]),
+/// - the arguments (after expanding the `@file` ones), so the `--cfg`s, the edition and the paths
+///   of the `--extern` crates are included,
+/// - the size and the modification time of the `--extern` crates,
+/// - the content of the files of the crate: the ones given by [`source_files`] and the ones the
+///   compiler looked for without finding them (like the other possible paths of a module), so
+///   creating one of them changes the fingerprint.
+///
+/// The crate is compiled (without type-checking) to find its files. The environment variables
+/// read with `env!` and the crates found in the `-L` directories without an `--extern` path
+/// aren't part of the fingerprint.
+pub fn fingerprint(rustc_args: &[String]) -> Result<Fingerprint, Error> {
+    let rustc_args = expand_args(rustc_args)?;
+    let ((), files, _) = analyze(&rustc_args, |_| ())?;
+    Ok(compute_fingerprint(&rustc_args, &files))
+}
+
+/// Runs analyses and keeps their results in a directory, to skip the compiler entirely when the
+/// [`fingerprint`] of the crate is the one of the previous run.
+#[derive(Clone, Debug)]
+pub struct CachedAnalyzer {
+    dir: PathBuf,
+}
+
+impl CachedAnalyzer {
+    /// The results are written into `dir`, which is created if needed. A file is written for each
+    /// set of arguments (and working directory) and each type of result, so a directory should
+    /// only be used by one analysis: two callbacks returning the same type would share the
+    /// results.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the result of the previous call if the crate didn't change since then, without
+    /// running the compiler. Otherwise, calls `callback` like [`with_tyctxt`](crate::with_tyctxt)
+    /// and keeps its result for the next call.
+    ///
+    /// To check the fingerprint without compiling the crate, the files of the previous run are
+    /// kept with the result. A file of the cache which can't be read or deserialized (like one
+    /// written by an older version of the analysis) is ignored and replaced. The result isn't kept
+    /// if a file of the crate changed while it was compiled. [`Error::Cache`] is returned if the
+    /// result can't be serialized or written.
+    pub fn run_or_load<T, F>(&self, rustc_args: &[String], callback: F) -> Result<T, Error>
+    where
+        T: Serialize + DeserializeOwned + marker::Send,
+        F: FnOnce(TyCtxt<'_>) -> T + marker::Send,
+    {
+        let rustc_args = expand_args(rustc_args)?;
+        let path = self.entry_path::<T>(&rustc_args);
+        if let Some(value) = load_entry(&path, &rustc_args) {
+            return Ok(value);
+        }
+
+        let (value, files, changed) = analyze(&rustc_args, callback)?;
+        if changed {
+            return Ok(value);
+        }
+        let fingerprint = compute_fingerprint(&rustc_args, &files);
+        let entry = (
+            fingerprint.to_string(),
+            files.into_iter().collect::<Vec<_>>(),
+            &value,
+        );
+        let content = serde_json::to_string(&entry)
+            .map_err(|e| Error::Cache(format!("cannot serialize the result: {e}")))?;
+        // The file is renamed once written so another process never reads a partial file.
+        let tmp_path = path.with_extension("json.tmp");
+        fs::create_dir_all(&self.dir)
+            .and_then(|()| fs::write(&tmp_path, content))
+            .and_then(|()| fs::rename(&tmp_path, &path))
+            .map_err(|e| Error::Cache(format!("cannot write `{}`: {}", path.display(), e)))?;
+        Ok(value)
+    }
+
+    /// The file containing the results of the analyses returning `T` with `rustc_args`.
+    fn entry_path<T>(&self, rustc_args: &[String]) -> PathBuf {
+        let mut hasher = StableHasher::new();
+        any::type_name::<T>().hash(&mut hasher);
+        rustc_args.hash(&mut hasher);
+        env::current_dir().ok().hash(&mut hasher);
+        self.dir
+            .join(format!("{:016x}.json", hasher.finish::<u64>()))
+    }
+}
+
+/// Returns the result kept in `path` if the fingerprint of the files kept with it didn't change.
+fn load_entry<T: DeserializeOwned>(path: &Path, rustc_args: &[String]) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    let (fingerprint, files, value): (String, Vec<PathBuf>, T) =
+        serde_json::from_str(&content).ok()?;
+    let files = files.into_iter().collect();
+    (compute_fingerprint(rustc_args, &files).to_string() == fingerprint).then_some(value)
+}
+
+/// Calls `callback` and returns the files of the crate, and whether one of them changed while the
+/// crate was compiled.
+fn analyze<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<(T, BTreeSet<PathBuf>, bool), Error> {
+    let loaded = Arc::new(Mutex::new(BTreeSet::new()));
+    let (value, files, changed) = Config::new(rustc_args)
+        .file_loader(Box::new(RecordingFileLoader(Arc::clone(&loaded))))
+        .with_tyctxt(|tcx| {
+            let files = source_files(tcx);
+            let changed = files
+                .iter()
+                .any(|file| file.origin == SourceFileOrigin::Overlay);
+            let files = files
+                .into_iter()
+                .filter(|file| file.origin != SourceFileOrigin::Anonymous)
+                .filter_map(|file| file.path)
+                .collect::<Vec<_>>();
+            (callback(tcx), files, changed)
+        })?;
+    let mut loaded = mem::take(&mut *loaded.lock().unwrap());
+    loaded.extend(files);
+    Ok((value, loaded, changed))
+}
+
+/// Computes the fingerprint described in [`fingerprint`] from the files of the crate.
+fn compute_fingerprint(rustc_args: &[String], files: &BTreeSet<PathBuf>) -> Fingerprint {
+    let mut hasher = StableHasher::new();
+    required_nightly().hash(&mut hasher);
+    rustc_args.hash(&mut hasher);
+    for path in extern_paths(rustc_args) {
+        let metadata = fs::metadata(path).ok();
+        metadata.as_ref().map(fs::Metadata::len).hash(&mut hasher);
+        metadata
+            .and_then(|metadata| metadata.modified().ok())
+            .hash(&mut hasher);
+    }
+    for path in files {
+        path.hash(&mut hasher);
+        // The files which don't exist are hashed as well, so creating one of them changes the
+        // fingerprint.
+        fs::read(path).ok().hash(&mut hasher);
+    }
+    Fingerprint(hasher.finish())
+}
+
+/// The paths given to `--extern`, like `path/to/libfoo.rlib` in `--extern foo=path/to/libfoo.rlib`
+/// or `--extern priv:foo=path/to/libfoo.rlib`.
+fn extern_paths(rustc_args: &[String]) -> impl Iterator<Item = &str> {
+    rustc_args
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, arg)| {
+            if arg == "--extern" {
+                rustc_args.get(pos + 1).map(String::as_str)
+            } else {
+                arg.strip_prefix("--extern=")
+            }
+        })
+        .filter_map(|value| Some(value.split_once('=')?.1))
+}
@@ -0,0 +1,276 @@
+//! Helpers to get the call graph of the local crate.
+//!
+//! The method calls are resolved with the results of the type checking, so the bodies are
+//! type-checked if they weren't already. You might want to use
+//! [`AnalysisLevel::Full`](crate::AnalysisLevel) (or at least
+//! [`AnalysisLevel::TypeckBodies`](crate::AnalysisLevel)) to do it beforehand.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{BodyId, Expr, ExprKind};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{self, DefIdTree, Instance, InstanceDef, SubstsRef, TyCtxt, TypeckResults};
+use rustc_span::Span;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::paths::qualified_name;
+
+/// Returned by [`call_graph`]. The nodes are identified by their [`qualified_name`].
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    /// The functions of the local crate, as well as the other items with a body (like `const` or
+    /// `static` items). The closures are part of the function they're defined in.
+    pub nodes: BTreeMap<String, CallNode>,
+    /// All the calls, in the order in which they're found.
+    pub edges: Vec<CallEdge>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallNode {
+    pub def_id: LocalDefId,
+    /// The [`qualified_name`] of the module the item is in.
+    pub module: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallEdge {
+    /// The name of the node the call is in.
+    pub caller: String,
+    /// The node the call is in. Unlike [`CallEdge::caller`], it distinguishes the items with the
+    /// same name, like the methods of two impls of a generic trait for the same type.
+    pub caller_def_id: LocalDefId,
+    pub callee: Callee,
+    pub kind: CallKind,
+    /// The span of the call (or of the path for [`CallKind::Reference`]).
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Callee {
+    /// The function which is called is known. It can be in another crate, in which case it isn't
+    /// in the nodes of the graph.
+    Resolved { name: String, def_id: DefId },
+    /// The function depends on a value or on a type which is only known at runtime or at
+    /// monomorphization time: a method of a trait called on a type parameter or on a trait object,
+    /// a closure or a function pointer. `name` is the name of the trait method if it's one.
+    Dynamic { name: Option<String> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    /// A call like `function()`, `Type::method(value)` or `closure()`.
+    Call,
+    /// A call like `value.method()`.
+    MethodCall,
+    /// A path to a function which isn't called directly, like in `iter.map(function)`.
+    Reference,
+}
+
+impl CallGraph {
+    /// Returns the calls made by `caller`.
+    pub fn callees<'a>(&'a self, caller: &'a str) -> impl Iterator<Item = &'a CallEdge> + 'a {
+        self.edges.iter().filter(move |edge| edge.caller == caller)
+    }
+
+    /// Returns the nodes which can be reached from `root` (including itself) by following the
+    /// [`Callee::Resolved`] edges. The dynamic calls can't be followed, so the trait methods only
+    /// called through generics or trait objects are not included.
+    pub fn reachable_from<'a>(&'a self, root: &'a str) -> BTreeSet<&'a str> {
+        let mut reachable = BTreeSet::new();
+        let mut to_visit = vec![root];
+        while let Some(name) = to_visit.pop() {
+            if !self.nodes.contains_key(name) || !reachable.insert(name) {
+                continue;
+            }
+            for edge in self.callees(name) {
+                if let Callee::Resolved { name, .. } = &edge.callee {
+                    to_visit.push(name);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns the functions which can't be reached from any of `roots` (like `crate::main`). See
+    /// [`CallGraph::reachable_from`] for its limits.
+    pub fn unreachable_fns<'a>(&'a self, tcx: TyCtxt<'_>, roots: &[&'a str]) -> Vec<&'a str> {
+        let reachable = roots
+            .iter()
+            .flat_map(|root| self.reachable_from(root))
+            .collect::<BTreeSet<_>>();
+        self.nodes
+            .iter()
+            .filter(|(name, node)| {
+                matches!(tcx.def_kind(node.def_id), DefKind::Fn | DefKind::AssocFn)
+                    && !reachable.contains(name.as_str())
+            })
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// Returns the call graph of the local crate: the functions (and methods) called in every body,
+/// as well as the ones which are referenced without being called.
+///
+/// The calls to trait methods are resolved to the method of the impl when the type is known. The
+/// calls of operators, `Deref` and `Drop` are not included, nor the constructors of tuple structs
+/// and enum variants.
+pub fn call_graph(tcx: TyCtxt<'_>) -> CallGraph {
+    let mut visitor = CallVisitor {
+        tcx,
+        caller: None,
+        typeck_results: None,
+        graph: CallGraph::default(),
+    };
+    for body_owner in tcx.hir().body_owners() {
+        let def_id = node_of(tcx, body_owner);
+        let module = tcx.parent_module_from_def_id(def_id);
+        visitor.graph.nodes.insert(
+            qualified_name(tcx, def_id.to_def_id()),
+            CallNode {
+                def_id,
+                module: qualified_name(tcx, module.to_def_id()),
+            },
+        );
+    }
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.graph
+}
+
+/// Returns the node `body_owner` is part of: the closures and the anonymous constants (like the
+/// length of an array) belong to the item they're in, if it has a body.
+fn node_of(tcx: TyCtxt<'_>, mut body_owner: LocalDefId) -> LocalDefId {
+    loop {
+        let root = tcx
+            .typeck_root_def_id(body_owner.to_def_id())
+            .expect_local();
+        if tcx.def_kind(root) != DefKind::AnonConst {
+            return root;
+        }
+        let parent = tcx.local_parent(root);
+        if tcx.hir().maybe_body_owned_by(parent).is_none() {
+            return root;
+        }
+        body_owner = parent;
+    }
+}
+
+struct CallVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// The node of the body being visited and its typeck results.
+    caller: Option<LocalDefId>,
+    typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    graph: CallGraph,
+}
+
+impl<'tcx> CallVisitor<'tcx> {
+    fn add(&mut self, callee: Callee, kind: CallKind, span: Span) {
+        let Some(caller) = self.caller else { return };
+        self.graph.edges.push(CallEdge {
+            caller: qualified_name(self.tcx, caller.to_def_id()),
+            caller_def_id: caller,
+            callee,
+            kind,
+            span,
+        });
+    }
+
+    fn callee(&self, def_id: DefId, substs: SubstsRef<'tcx>) -> Callee {
+        let tcx = self.tcx;
+        let resolved = |def_id| Callee::Resolved {
+            name: qualified_name(tcx, def_id),
+            def_id,
+        };
+        if tcx.trait_of_item(def_id).is_none() {
+            return resolved(def_id);
+        }
+        let param_env = self
+            .caller
+            .map_or(ty::ParamEnv::empty(), |caller| tcx.param_env(caller));
+        match Instance::resolve(tcx, param_env, def_id, substs) {
+            Ok(Some(instance)) if !matches!(instance.def, InstanceDef::Virtual(..)) => {
+                resolved(instance.def_id())
+            }
+            _ => Callee::Dynamic {
+                name: Some(qualified_name(tcx, def_id)),
+            },
+        }
+    }
+
+    /// Returns the function `expr` points to if it's a path to one (or to a constructor).
+    fn fn_def(&self, expr: &Expr<'tcx>) -> Option<(DefId, SubstsRef<'tcx>)> {
+        match *self.typeck_results?.expr_ty(expr).kind() {
+            ty::FnDef(def_id, substs) => Some((def_id, substs)),
+            _ => None,
+        }
+    }
+
+    fn is_ctor(&self, def_id: DefId) -> bool {
+        matches!(self.tcx.def_kind(def_id), DefKind::Ctor(..))
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for CallVisitor<'tcx> {
+    // `visit_all_item_likes_in_crate` already goes through all the items.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let owner = self.tcx.hir().body_owner_def_id(body_id);
+        let old_caller = self.caller.replace(node_of(self.tcx, owner));
+        let old_typeck_results = self.typeck_results.replace(self.tcx.typeck_body(body_id));
+        self.visit_body(self.tcx.hir().body(body_id));
+        self.caller = old_caller;
+        self.typeck_results = old_typeck_results;
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        match expr.kind {
+            ExprKind::Call(func, args) => {
+                match self.fn_def(func) {
+                    // The constructors of tuple structs and enum variants are not calls.
+                    Some((def_id, _)) if self.is_ctor(def_id) => {}
+                    Some((def_id, substs)) => {
+                        let callee = self.callee(def_id, substs);
+                        self.add(callee, CallKind::Call, expr.span);
+                    }
+                    None => self.add(Callee::Dynamic { name: None }, CallKind::Call, expr.span),
+                }
+                // The path of the function mustn't be recorded again as a reference.
+                if !matches!(func.kind, ExprKind::Path(_)) {
+                    self.visit_expr(func);
+                }
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+            ExprKind::MethodCall(..) => {
+                let method = self.typeck_results.and_then(|typeck_results| {
+                    typeck_results
+                        .type_dependent_def_id(expr.hir_id)
+                        .map(|def_id| (def_id, typeck_results.node_substs(expr.hir_id)))
+                });
+                if let Some((def_id, substs)) = method {
+                    let callee = self.callee(def_id, substs);
+                    self.add(callee, CallKind::MethodCall, expr.span);
+                }
+            }
+            ExprKind::Path(_) => match self.fn_def(expr) {
+                Some((def_id, _)) if self.is_ctor(def_id) => {}
+                Some((def_id, substs)) => {
+                    let callee = self.callee(def_id, substs);
+                    self.add(callee, CallKind::Reference, expr.span);
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,284 @@
+//! Helpers to get the `rustc` arguments of a cargo package.
+
+use serde_json::Value;
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::Error;
+
+/// Returns the `rustc` arguments needed to compile the crate root of `package` (or of the package
+/// `manifest_path` belongs to if `None`), so you can give them to
+/// [`with_tyctxt`](crate::with_tyctxt).
+///
+/// To do so, `cargo check` is run on the package and the arguments are computed from its output
+/// and the one of `cargo metadata`. If the package has a library, its crate root is used,
+/// otherwise its first binary is. The dependencies are built with the `rustc` from the `RUSTC`
+/// environment variable if it's set, it must be the same version as the one this crate is built
+/// with.
+///
+/// The environment variables set by cargo (like `OUT_DIR`) are not returned, so if the package
+/// uses them with `env!`, you'll need to set them yourself.
+pub fn args_for_package(manifest_path: &Path, package: Option<&str>) -> Result<Vec<String>, Error> {
+    let metadata = run_cargo(
+        Command::new(cargo_path())
+            .args(["metadata", "--format-version", "1", "--manifest-path"])
+            .arg(manifest_path),
+    )?;
+    let metadata: Value = serde_json::from_str(&metadata)
+        .map_err(|e| Error::Cargo(format!("invalid `cargo metadata` output: {e}")))?;
+
+    let pkg = find_package(&metadata, manifest_path, package)?;
+    let pkg_id = str_field(pkg, "id")?;
+    let pkg_name = str_field(pkg, "name")?;
+    let targets = pkg["targets"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let lib = targets.iter().find(|target| is_lib(target));
+    let target = lib
+        .or_else(|| targets.iter().find(|target| has_kind(target, "bin")))
+        .ok_or_else(|| {
+            Error::Cargo(format!(
+                "package `{pkg_name}` has no library nor binary target"
+            ))
+        })?;
+    let target_name = str_field(target, "name")?;
+
+    let mut check = Command::new(cargo_path());
+    check
+        .args(["check", "--message-format=json", "--manifest-path"])
+        .arg(manifest_path)
+        .args(["--package", pkg_name]);
+    if lib.is_some() {
+        check.arg("--lib");
+    } else {
+        check.args(["--bin", target_name]);
+    }
+    // The package itself may not compile, only its dependencies are needed.
+    let output = check
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| Error::Cargo(format!("failed to run `cargo check`: {e}")))?;
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    let mut artifacts = HashMap::new();
+    // The directories of the artifacts depend on the target directory, the target and the
+    // profile, so they're taken from the artifacts themselves.
+    let mut dependency_dirs = BTreeSet::new();
+    let mut cfgs = Vec::new();
+    let mut linked_paths = Vec::new();
+    for message in output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+    {
+        let package_id = message["package_id"].as_str().unwrap_or_default();
+        match message["reason"].as_str() {
+            Some("compiler-artifact") if is_lib(&message["target"]) => {
+                if let Some(artifact) = pick_artifact(&message["filenames"]) {
+                    if let Some(dir) = artifact.parent() {
+                        dependency_dirs.insert(dir.to_owned());
+                    }
+                    let name = message["target"]["name"].as_str().unwrap_or_default();
+                    artifacts.insert(package_id.to_owned(), (name.replace('-', "_"), artifact));
+                }
+            }
+            Some("build-script-executed") => {
+                for path in message["linked_paths"].as_array().into_iter().flatten() {
+                    if let Some(path) = path.as_str() {
+                        linked_paths.push(path.to_owned());
+                    }
+                }
+                if package_id == pkg_id {
+                    for cfg in message["cfgs"].as_array().into_iter().flatten() {
+                        if let Some(cfg) = cfg.as_str() {
+                            cfgs.push(cfg.to_owned());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let node = metadata["resolve"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|node| node["id"].as_str() == Some(pkg_id))
+        .ok_or_else(|| {
+            Error::Cargo(format!(
+                "package `{pkg_name}` isn't in the dependencies graph"
+            ))
+        })?;
+
+    let mut args = vec![
+        str_field(target, "src_path")?.to_owned(),
+        "--crate-name".to_owned(),
+        target_name.replace('-', "_"),
+        "--crate-type".to_owned(),
+        if lib.is_some() {
+            crate_type(target)
+        } else {
+            "bin"
+        }
+        .to_owned(),
+        "--edition".to_owned(),
+        str_field(target, "edition")?.to_owned(),
+    ];
+    for dir in dependency_dirs {
+        args.extend(["-L".to_owned(), format!("dependency={}", dir.display())]);
+    }
+    for path in linked_paths {
+        args.extend(["-L".to_owned(), path]);
+    }
+    for feature in node["features"].as_array().into_iter().flatten() {
+        if let Some(feature) = feature.as_str() {
+            args.extend(["--cfg".to_owned(), format!("feature=\"{feature}\"")]);
+        }
+    }
+    for cfg in cfgs {
+        args.extend(["--cfg".to_owned(), cfg]);
+    }
+
+    for dep in node["deps"].as_array().into_iter().flatten() {
+        let kinds = dep["dep_kinds"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        // Build and dev dependencies are not needed to compile the crate.
+        if !kinds.iter().any(|kind| kind["kind"].is_null()) {
+            continue;
+        }
+        let dep_id = str_field(dep, "pkg")?;
+        match artifacts.get(dep_id) {
+            // `name` is the name used in the code, so it takes the renaming into account.
+            Some((_, artifact)) => args.extend([
+                "--extern".to_owned(),
+                format!("{}={}", str_field(dep, "name")?, artifact.display()),
+            ]),
+            // Platform-specific dependencies are not built if they're not needed.
+            None if kinds.iter().all(|kind| !kind["target"].is_null()) => {}
+            None => {
+                return Err(Error::Cargo(format!(
+                    "dependency `{dep_id}` of `{pkg_name}` couldn't be built"
+                )))
+            }
+        }
+    }
+    // Binaries can use the package's library.
+    if lib.is_none() {
+        if let Some((name, artifact)) = artifacts.get(pkg_id) {
+            args.extend([
+                "--extern".to_owned(),
+                format!("{}={}", name, artifact.display()),
+            ]);
+        }
+    }
+    Ok(args)
+}
+
+fn cargo_path() -> String {
+    env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned())
+}
+
+fn run_cargo(command: &mut Command) -> Result<String, Error> {
+    let output = command
+        .output()
+        .map_err(|e| Error::Cargo(format!("failed to run cargo: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::Cargo(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| Error::Cargo("invalid UTF-8 output".to_owned()))
+}
+
+fn find_package<'a>(
+    metadata: &'a Value,
+    manifest_path: &Path,
+    package: Option<&str>,
+) -> Result<&'a Value, Error> {
+    let packages = metadata["packages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let members = metadata["workspace_members"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let is_member = |pkg: &&Value| members.iter().any(|member| *member == pkg["id"]);
+    match package {
+        Some(name) => packages
+            .iter()
+            .filter(is_member)
+            .find(|pkg| pkg["name"].as_str() == Some(name))
+            .ok_or_else(|| Error::Cargo(format!("package `{name}` not found in the workspace"))),
+        None => {
+            let manifest_path = manifest_path
+                .canonicalize()
+                .unwrap_or_else(|_| manifest_path.into());
+            packages
+                .iter()
+                .filter(is_member)
+                .find(|pkg| {
+                    pkg["manifest_path"].as_str().map(PathBuf::from).as_deref()
+                        == Some(manifest_path.as_path())
+                })
+                .ok_or_else(|| {
+                    Error::Cargo(format!(
+                        "`{}` is a virtual manifest, a package needs to be specified",
+                        manifest_path.display()
+                    ))
+                })
+        }
+    }
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> Result<&'a str, Error> {
+    value[field]
+        .as_str()
+        .ok_or_else(|| Error::Cargo(format!("missing `{field}` field in cargo output")))
+}
+
+fn has_kind(target: &Value, kind: &str) -> bool {
+    target["kind"].as_array().map_or(false, |kinds| {
+        kinds.iter().any(|k| k.as_str() == Some(kind))
+    })
+}
+
+fn is_lib(target: &Value) -> bool {
+    ["lib", "rlib", "dylib", "proc-macro"]
+        .iter()
+        .any(|kind| has_kind(target, kind))
+}
+
+fn crate_type(target: &Value) -> &'static str {
+    if has_kind(target, "proc-macro") {
+        "proc-macro"
+    } else {
+        "lib"
+    }
+}
+
+/// `cargo check` only generates metadata files for libraries but proc-macro crates are always
+/// compiled.
+fn pick_artifact(filenames: &Value) -> Option<PathBuf> {
+    let filenames = filenames.as_array()?;
+    let with_extension = |extensions: &[&str]| {
+        filenames
+            .iter()
+            .filter_map(Value::as_str)
+            .map(PathBuf::from)
+            .find(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map_or(false, |ext| extensions.contains(&ext))
+            })
+    };
+    with_extension(&["rmeta"])
+        .or_else(|| with_extension(&["rlib"]))
+        .or_else(|| with_extension(&["so", "dylib", "dll"]))
+}
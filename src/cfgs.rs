@@ -0,0 +1,346 @@
+//! Helpers to find the items which only exist in some configurations (with some `#[cfg]`s), like
+//! the platform-specific code. The items removed by `#[cfg]` are gone before the HIR is built, so
+//! they can't be found with a single run of the compiler.
+
+use rustc_ast::ast::{
+    AssocItem, AssocItemKind, Attribute, Crate, ForeignItemKind, Inline, Item, ItemKind, ModKind,
+};
+use rustc_ast::ptr::P;
+use rustc_ast_pretty::pprust;
+use rustc_session::output::find_crate_name;
+use rustc_session::Session;
+use rustc_span::source_map::SourceMap;
+use rustc_span::symbol::sym;
+use rustc_span::{FileName, RealFileName, Span};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::ast::parse_out_of_line_module;
+use crate::spans::ast_location_string;
+use crate::{Config, DiagnosticsBuffer, Error, Stage};
+
+/// A configuration given to [`cfg_inventory`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CfgSet {
+    /// The name of the configuration in the [`CfgReport`], like `"windows"`.
+    pub name: String,
+    /// The configuration options which are set, like with [`Config::cfg_flag`] (`("name", None)`)
+    /// and [`Config::cfg_kv`] (`("name", Some("value"))`).
+    pub set: Vec<(String, Option<String>)>,
+    /// The configuration options set by default (for the target) which are unset: `("name",
+    /// None)` unsets `name` and all its values (like `target_os`), `("name", Some("value"))` only
+    /// this value.
+    ///
+    /// Along with [`CfgSet::set`], it allows evaluating the `#[cfg]`s like for another target
+    /// (for example by unsetting `unix` and `target_os` and setting `windows` and
+    /// `target_os = "windows"`) without having the standard library of this target. The standard
+    /// library is still the one of the current target though.
+    pub unset: Vec<(String, Option<String>)>,
+}
+
+/// Returned by [`cfg_inventory`] and [`cfg_inventory_approximate`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CfgReport {
+    /// The names of the configurations, in the order in which they were given. It's empty with
+    /// [`cfg_inventory_approximate`].
+    pub configs: Vec<String>,
+    /// Sorted by name, then by location.
+    pub items: Vec<CfgItem>,
+    /// `true` if the report comes from [`cfg_inventory_approximate`].
+    pub approximate: bool,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CfgItem {
+    /// The path of the item in the crate, like `my_crate::module::function`. The items of the
+    /// impls are like `my_crate::module::impl Trait for Type::method`.
+    pub name: String,
+    /// Like `"function"`, `"struct"` or `"associated function"`.
+    pub kind: String,
+    /// Where the item is defined, like `src/lib.rs:12:1`. With [`cfg_inventory`], it comes from
+    /// the first run in which the item exists.
+    pub location: String,
+    /// The names of the configurations in which the item exists. It's empty with
+    /// [`cfg_inventory_approximate`].
+    pub configs: Vec<String>,
+    /// The predicates of the `#[cfg]`s of the item and of the items containing it, outermost first,
+    /// like `unix` or `all(feature = "std", not(windows))`.
+    pub cfgs: Vec<String>,
+}
+
+/// Runs the compiler with `rustc_args` (see [`with_tyctxt`](crate::with_tyctxt)) once for each
+/// configuration of `configs`, up to the expansion, and returns the items which don't exist in all
+/// of them. The same item is recognized by its name, its kind and its location, so two functions
+/// with the same name defined under `#[cfg(unix)]` and `#[cfg(windows)]` are both reported.
+///
+/// The items are the ones of the modules, the traits, the impls and the `extern` blocks, including
+/// the items generated by macros (like the impls of `#[derive]`) but not the imports.
+///
+/// The compilation errors (like the imports of modules of the standard library which only exist
+/// on other targets, see [`CfgSet::unset`]) are ignored, unless the crate can't be parsed.
+pub fn cfg_inventory(rustc_args: &[String], configs: &[CfgSet]) -> Result<CfgReport, Error> {
+    // The index of the item in `items` by name, kind and location.
+    let mut index = HashMap::new();
+    let mut items = Vec::<CfgItem>::new();
+    for cfg_set in configs {
+        let mut config = Config::new(rustc_args)
+            .allow_errors(true)
+            .diagnostics_buffer(DiagnosticsBuffer::new());
+        for (name, value) in &cfg_set.set {
+            config = match value {
+                Some(value) => config.cfg_kv(name, value),
+                None => config.cfg_flag(name),
+            };
+        }
+        let unset = cfg_set.unset.clone();
+        if !unset.is_empty() {
+            config = config.configure(move |config| {
+                let parse_sess_created = config.parse_sess_created.take();
+                config.parse_sess_created = Some(Box::new(move |parse_sess| {
+                    if let Some(parse_sess_created) = parse_sess_created {
+                        parse_sess_created(parse_sess);
+                    }
+                    parse_sess.config.retain(|&(name, value)| {
+                        !unset.iter().any(|(unset_name, unset_value)| {
+                            name.as_str() == unset_name
+                                && unset_value.as_ref().map_or(true, |unset_value| {
+                                    value.map_or(false, |value| value.as_str() == unset_value)
+                                })
+                        })
+                    });
+                }));
+            });
+        }
+        let run_items = config.with_ast(Stage::Expand, |krate, sess| {
+            let mut collector = ItemCollector::new(sess, krate, None);
+            collector.collect(&krate.attrs, &krate.items);
+            collector.items
+        })?;
+        for item in run_items {
+            let key = (item.name.clone(), item.kind.clone(), item.location.clone());
+            let i = *index.entry(key).or_insert_with(|| {
+                items.push(item);
+                items.len() - 1
+            });
+            items[i].configs.push(cfg_set.name.clone());
+        }
+    }
+
+    items.retain(|item| item.configs.len() < configs.len());
+    items.sort_by(|a, b| (&a.name, &a.location).cmp(&(&b.name, &b.location)));
+    Ok(CfgReport {
+        configs: configs.iter().map(|config| config.name.clone()).collect(),
+        items,
+        approximate: false,
+    })
+}
+
+/// A cheaper version of [`cfg_inventory`] which parses the crate once (see
+/// [`with_parsed_crate`](crate::with_parsed_crate)) and returns the items with a `#[cfg]` or in an
+/// item with a `#[cfg]`, with their predicates in [`CfgItem::cfgs`], without evaluating them.
+///
+/// It's approximate since the macros aren't expanded: the items generated by macros are missing
+/// (and the `#[cfg]`s in `#[cfg_attr]` are ignored). The out-of-line modules (`mod name;`) are
+/// loaded from `name.rs` or `name/mod.rs` (or the `#[path]` attribute) next to the file of their
+/// parent module, the ones which can't be loaded or parsed are skipped.
+pub fn cfg_inventory_approximate(rustc_args: &[String]) -> Result<CfgReport, Error> {
+    Config::new(rustc_args).with_ast(Stage::Parse, |krate, sess| {
+        let root_file = match sess.source_map().span_to_filename(krate.spans.inner_span) {
+            FileName::Real(RealFileName::LocalPath(path)) => Some(path),
+            _ => None,
+        };
+        let mut collector = ItemCollector::new(sess, krate, root_file);
+        collector.collect(&krate.attrs, &krate.items);
+        let mut items = collector
+            .items
+            .into_iter()
+            .filter(|item| !item.cfgs.is_empty())
+            .collect::<Vec<_>>();
+        items.sort_by(|a, b| (&a.name, &a.location).cmp(&(&b.name, &b.location)));
+        CfgReport {
+            configs: Vec::new(),
+            items,
+            approximate: true,
+        }
+    })
+}
+
+struct ItemCollector<'a> {
+    sess: &'a Session,
+    /// The path of the current item, starting with the name of the crate.
+    path: Vec<String>,
+    /// The `#[cfg]` predicates of the current item and its parents.
+    cfgs: Vec<String>,
+    /// To load the out-of-line modules: the directory of the submodules of the current module,
+    /// and whether the current module is the root of its file (which isn't the case of the inline
+    /// modules). `None` if they're already loaded.
+    module_dir: Option<(PathBuf, bool)>,
+    items: Vec<CfgItem>,
+}
+
+impl<'a> ItemCollector<'a> {
+    /// `root_file` is the file of the crate root if the out-of-line modules must be loaded.
+    fn new(sess: &'a Session, krate: &Crate, root_file: Option<PathBuf>) -> Self {
+        ItemCollector {
+            sess,
+            path: vec![find_crate_name(sess, &krate.attrs).to_string()],
+            cfgs: Vec::new(),
+            module_dir: root_file
+                .map(|file| (file.parent().map(Path::to_owned).unwrap_or_default(), true)),
+            items: Vec::new(),
+        }
+    }
+
+    fn source_map(&self) -> &'a SourceMap {
+        self.sess.source_map()
+    }
+
+    /// Adds the items of a module whose attributes are `attrs`.
+    fn collect(&mut self, attrs: &[Attribute], items: &[P<Item>]) {
+        // The inner `#![cfg]` of the crate root.
+        let cfgs_len = self.cfgs.len();
+        self.cfgs.extend(cfgs(attrs));
+        for item in items {
+            self.item(item);
+        }
+        self.cfgs.truncate(cfgs_len);
+    }
+
+    fn item(&mut self, item: &Item) {
+        let (name, kind) = match &item.kind {
+            ItemKind::Use(_)
+            | ItemKind::ExternCrate(_)
+            | ItemKind::GlobalAsm(_)
+            | ItemKind::MacCall(_) => return,
+            ItemKind::ForeignMod(foreign_mod) => {
+                self.with_cfgs(&item.attrs, |this| {
+                    for foreign_item in &foreign_mod.items {
+                        let kind = match foreign_item.kind {
+                            ForeignItemKind::Static(..) => "foreign static",
+                            ForeignItemKind::Fn(_) => "foreign function",
+                            ForeignItemKind::TyAlias(_) => "foreign type",
+                            ForeignItemKind::MacCall(_) => continue,
+                        };
+                        this.add(
+                            foreign_item.ident.to_string(),
+                            kind,
+                            foreign_item.span,
+                            &foreign_item.attrs,
+                        );
+                    }
+                });
+                return;
+            }
+            ItemKind::Impl(impl_) => {
+                let name = match &impl_.of_trait {
+                    Some(trait_ref) => format!(
+                        "impl {} for {}",
+                        pprust::path_to_string(&trait_ref.path),
+                        pprust::ty_to_string(&impl_.self_ty)
+                    ),
+                    None => format!("impl {}", pprust::ty_to_string(&impl_.self_ty)),
+                };
+                (name, "impl")
+            }
+            ItemKind::Static(..) => (item.ident.to_string(), "static"),
+            ItemKind::Const(..) => (item.ident.to_string(), "constant"),
+            ItemKind::Fn(_) => (item.ident.to_string(), "function"),
+            ItemKind::Mod(..) => (item.ident.to_string(), "module"),
+            ItemKind::TyAlias(_) => (item.ident.to_string(), "type alias"),
+            ItemKind::Enum(..) => (item.ident.to_string(), "enum"),
+            ItemKind::Struct(..) => (item.ident.to_string(), "struct"),
+            ItemKind::Union(..) => (item.ident.to_string(), "union"),
+            ItemKind::Trait(_) => (item.ident.to_string(), "trait"),
+            ItemKind::TraitAlias(..) => (item.ident.to_string(), "trait alias"),
+            ItemKind::MacroDef(_) => (item.ident.to_string(), "macro"),
+        };
+        self.add(name.clone(), kind, item.span, &item.attrs);
+
+        self.with_cfgs(&item.attrs, |this| {
+            this.path.push(name);
+            match &item.kind {
+                ItemKind::Mod(_, ModKind::Loaded(items, inline, _)) => {
+                    let module_dir = this.module_dir.clone();
+                    if let Some((dir, _)) = &mut this.module_dir {
+                        dir.push(item.ident.as_str());
+                        this.module_dir = Some((dir.clone(), *inline == Inline::No));
+                    }
+                    this.collect(&[], items);
+                    this.module_dir = module_dir;
+                }
+                ItemKind::Mod(_, ModKind::Unloaded) => this.load_module(item),
+                ItemKind::Trait(trait_) => this.assoc_items(&trait_.items),
+                ItemKind::Impl(impl_) => this.assoc_items(&impl_.items),
+                _ => {}
+            }
+            this.path.pop();
+        });
+    }
+
+    fn assoc_items(&mut self, items: &[P<AssocItem>]) {
+        for item in items {
+            let kind = match item.kind {
+                AssocItemKind::Const(..) => "associated constant",
+                AssocItemKind::Fn(_) => "associated function",
+                AssocItemKind::Type(_) => "associated type",
+                AssocItemKind::MacCall(_) => continue,
+            };
+            self.add(item.ident.to_string(), kind, item.span, &item.attrs);
+        }
+    }
+
+    /// Loads the out-of-line module `item` (`mod name;`) and adds its items.
+    fn load_module(&mut self, item: &Item) {
+        let Some((dir, _)) = self.module_dir.clone() else { return };
+        let Some((attrs, items, module_dir)) = parse_out_of_line_module(self.sess, item, &dir) else {
+            return;
+        };
+        let previous = self.module_dir.replace((module_dir, true));
+        self.collect(&attrs, &items);
+        self.module_dir = previous;
+    }
+
+    fn with_cfgs<F: FnOnce(&mut Self)>(&mut self, attrs: &[Attribute], f: F) {
+        let cfgs_len = self.cfgs.len();
+        self.cfgs.extend(cfgs(attrs));
+        f(self);
+        self.cfgs.truncate(cfgs_len);
+    }
+
+    fn add(&mut self, name: String, kind: &str, span: Span, attrs: &[Attribute]) {
+        self.items.push(CfgItem {
+            name: self
+                .path
+                .iter()
+                .chain(std::iter::once(&name))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("::"),
+            kind: kind.to_owned(),
+            location: ast_location_string(self.source_map(), span),
+            configs: Vec::new(),
+            cfgs: self.cfgs.iter().cloned().chain(cfgs(attrs)).collect(),
+        });
+    }
+}
+
+/// Returns the predicates of the `#[cfg]` attributes of `attrs`.
+fn cfgs(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.has_name(sym::cfg))
+        .filter_map(|attr| {
+            let list = attr.meta_item_list()?;
+            match &list[..] {
+                [predicate] => Some(pprust::meta_list_item_to_string(predicate)),
+                _ => None,
+            }
+        })
+        .collect()
+}
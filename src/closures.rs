@@ -0,0 +1,96 @@
+//! Helpers to know what the closures of the local crate capture and how.
+//!
+//! The captures are computed by the type checking, so the bodies are type-checked if they weren't
+//! already. You might want to use [`AnalysisLevel::Full`](crate::AnalysisLevel) to do it
+//! beforehand.
+
+use rustc_ast::CaptureBy;
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::{Expr, ExprKind, Node};
+use rustc_middle::hir::place::{Place, PlaceBase, ProjectionKind};
+use rustc_middle::ty::{self, BorrowKind, TyCtxt, UpvarCapture};
+use rustc_span::Span;
+
+/// A variable (or a part of a variable) captured by a closure, returned by [`closure_captures`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureInfo {
+    /// The name of the captured variable, like `self`.
+    pub name: String,
+    /// The captured place, like `self.field.0`. Since the 2021 edition, the closures only capture
+    /// the fields they use (which is `self.field.0` here) instead of the whole variable. The
+    /// dereferences aren't written, whether they're explicit (like `*r`) or not (like when `self`
+    /// is a reference).
+    pub place: String,
+    pub kind: CaptureKind,
+    /// The span of the use of the place in the closure which requires this kind of capture.
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureKind {
+    /// The place is moved (or copied) into the closure, which is always the case for the `move`
+    /// closures.
+    ByValue,
+    ByRef,
+    ByMutRef,
+    /// A unique immutable borrow, which can't be written in Rust: before the 2021 edition, it is
+    /// used when the closure modifies the value behind a `&mut` reference it captures, like
+    /// `|| *r += 1`. Since, `*r` is captured with [`ByMutRef`](Self::ByMutRef) instead.
+    ByUniqueRef,
+}
+
+/// Returns the places captured by the closure `closure_def_id`, in the order in which they're
+/// used in the closure. The `async` blocks are closures as well and their captures are returned
+/// the same way. The list is empty if `closure_def_id` isn't a closure.
+///
+/// Use [`for_each_fn`](crate::visit::for_each_fn) to find the closures of the crate.
+pub fn closure_captures(tcx: TyCtxt<'_>, closure_def_id: LocalDefId) -> Vec<CaptureInfo> {
+    // The results of the function the closure is defined in.
+    tcx.typeck(closure_def_id)
+        .closure_min_captures_flattened(closure_def_id)
+        .map(|captured_place| CaptureInfo {
+            name: tcx
+                .hir()
+                .name(captured_place.get_root_variable())
+                .to_string(),
+            place: place_to_string(tcx, &captured_place.place),
+            kind: match captured_place.info.capture_kind {
+                UpvarCapture::ByValue => CaptureKind::ByValue,
+                UpvarCapture::ByRef(BorrowKind::ImmBorrow) => CaptureKind::ByRef,
+                UpvarCapture::ByRef(BorrowKind::MutBorrow) => CaptureKind::ByMutRef,
+                UpvarCapture::ByRef(BorrowKind::UniqueImmBorrow) => CaptureKind::ByUniqueRef,
+            },
+            span: captured_place.get_capture_kind_span(tcx),
+        })
+        .collect()
+}
+
+/// Returns `true` if `closure_def_id` is a `move` closure (or an `async move` block).
+pub fn is_move_closure(tcx: TyCtxt<'_>, closure_def_id: LocalDefId) -> bool {
+    matches!(
+        tcx.hir().get_by_def_id(closure_def_id),
+        Node::Expr(Expr {
+            kind: ExprKind::Closure(closure),
+            ..
+        }) if closure.capture_clause == CaptureBy::Value
+    )
+}
+
+/// Returns `place` as a path of fields, without the dereferences.
+fn place_to_string(tcx: TyCtxt<'_>, place: &Place<'_>) -> String {
+    let PlaceBase::Upvar(upvar_id) = place.base else { return String::new() };
+    let mut text = tcx.hir().name(upvar_id.var_path.hir_id).to_string();
+    for (i, projection) in place.projections.iter().enumerate() {
+        let ProjectionKind::Field(index, variant) = projection.kind else { continue };
+        // The fields of the tuples are only named by their index.
+        let field = match place.ty_before_projection(i).kind() {
+            ty::Adt(adt_def, _) => adt_def.variant(variant).fields[index as usize]
+                .name
+                .to_string(),
+            _ => index.to_string(),
+        };
+        text.push('.');
+        text.push_str(&field);
+    }
+    text
+}
@@ -0,0 +1,847 @@
+use rustc_ast::ast;
+use rustc_interface::{interface, Queries};
+use rustc_lint::LintStore;
+use rustc_middle::ty::TyCtxt;
+use rustc_session::config::CrateType;
+use rustc_session::lint::Level;
+use rustc_session::Session;
+use rustc_span::edition::Edition;
+use rustc_span::source_map::FileLoader;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::marker;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::diagnostics::DiagnosticsCollector;
+use crate::file_loader::{absolute_path, OverlayFileLoader};
+use crate::profiling::PassTimings;
+use crate::Error;
+
+type RegisterLints = Box<dyn Fn(&Session, &mut LintStore) + marker::Send + Sync>;
+type Configure = Box<dyn FnOnce(&mut interface::Config)>;
+
+/// How much analysis the compiler runs before calling the callback.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnalysisLevel {
+    /// Only what's needed to get the HIR and the items' information: the bodies are not
+    /// type-checked and the lints are not run. This is the fastest level.
+    #[default]
+    HirOnly,
+    /// Same as [`AnalysisLevel::HirOnly`] except that all bodies are type-checked, so you can use
+    /// [`TyCtxt::typeck`] to get the type of expressions. The lints are not run.
+    TypeckBodies,
+    /// The whole analysis is run (type checking, borrow checking, lints, etc), which is needed if
+    /// you want to use the MIR.
+    Full,
+}
+
+/// How far the compiler goes before calling the callback of [`Config::with_ast`]. To go up to the
+/// HIR, use [`Config::with_tyctxt`] with [`AnalysisLevel::HirOnly`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// The crate is parsed, see [`with_parsed_crate`](crate::with_parsed_crate).
+    Parse,
+    /// The macros of the crate are expanded, see [`with_expanded_ast`](crate::with_expanded_ast).
+    Expand,
+}
+
+/// A modifier of an extern crate given to [`Config::extern_crate_with_modifiers`], like `priv` in
+/// `--extern priv:name=path`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternModifier {
+    /// The crate is a private dependency, whose items must not be exported by the public API of
+    /// the crate (see the `exported_private_dependencies` lint).
+    Priv,
+    /// The crate isn't added to the extern prelude, so it must be imported with `extern crate`
+    /// to be used.
+    NoPrelude,
+}
+
+/// A limit of the compiler, see [`Config::recursion_limit`], [`Config::type_length_limit`] and
+/// [`Error::LimitExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitKind {
+    /// The recursion limit (`#![recursion_limit]`): how deep the macro expansions, the
+    /// auto-dereferencing and the trait resolution go.
+    Recursion,
+    /// The maximum length of the types (`#![type_length_limit]`) when the generic functions are
+    /// instantiated.
+    TypeLength,
+}
+
+/// A kind of file written by the compiler, given to [`Config::emit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmitKind {
+    /// The metadata of the crate (a `.rmeta`, like with `--emit=metadata`), which can be given to
+    /// the crates depending on it. It's empty if the crate isn't a library.
+    Metadata,
+    /// The MIR of the crate, as text (a `.mir`, like with `--emit=mir`).
+    Mir,
+    /// The LLVM IR of the crate, as text (a `.ll`, like with `--emit=llvm-ir`). The code
+    /// generation is run to get it, which is slow.
+    LlvmIr,
+}
+
+/// Allows to customize how the compiler is run. If the default behaviour is enough for you, you
+/// can use [`with_tyctxt`](crate::with_tyctxt) directly instead.
+pub struct Config {
+    pub(crate) rustc_args: Vec<String>,
+    pub(crate) source_code: Option<String>,
+    pub(crate) diagnostics_buffer: Option<DiagnosticsBuffer>,
+    pub(crate) diagnostics_collector: Option<DiagnosticsCollector>,
+    pub(crate) allow_errors: bool,
+    pub(crate) analysis_level: AnalysisLevel,
+    pub(crate) register_lints: Option<RegisterLints>,
+    pub(crate) configure: Option<Configure>,
+    pub(crate) file_loader: Option<Box<dyn FileLoader + marker::Send + Sync>>,
+    pub(crate) detect_sysroot: bool,
+    pub(crate) test: bool,
+    /// Lint levels applied after the ones of the `rustc` arguments.
+    pub(crate) lint_opts: Vec<(String, Level)>,
+    /// Overrides the `--cap-lints` of the `rustc` arguments.
+    pub(crate) lint_cap: Option<Level>,
+    pub(crate) stack_size: Option<usize>,
+    pub(crate) threads: Option<usize>,
+    pub(crate) pass_timings: Option<PassTimings>,
+    pub(crate) self_profile: Option<PathBuf>,
+    pub(crate) no_std: bool,
+    pub(crate) no_core: bool,
+    /// The attributes given to [`Config::inject_crate_attrs`].
+    pub(crate) crate_attrs: Vec<String>,
+    pub(crate) target: Option<String>,
+    /// The `--cfg` given with the builder methods, added to the ones of the `rustc` arguments.
+    pub(crate) cfgs: Vec<(String, Option<String>)>,
+    /// The features given to [`Config::check_cfg_features`].
+    pub(crate) check_cfg_features: Option<Vec<String>>,
+    pub(crate) edition: Option<Edition>,
+    /// The extern crates given with the builder methods, added to the `--extern` of the `rustc`
+    /// arguments.
+    pub(crate) externs: Vec<(String, Option<PathBuf>, Vec<ExternModifier>)>,
+    /// The proc-macro crates given to [`Config::extern_proc_macro`].
+    pub(crate) proc_macros: Vec<(String, PathBuf)>,
+    pub(crate) crate_types: Vec<CrateType>,
+    /// The directory given to [`Config::allow_output`].
+    pub(crate) output_dir: Option<PathBuf>,
+    /// The files given to [`Config::emit`] with the directory in which they're written.
+    pub(crate) emits: Vec<(EmitKind, PathBuf)>,
+    pub(crate) check_toolchain: bool,
+    pub(crate) keep_built_mir: bool,
+    pub(crate) borrowck_facts: bool,
+    pub(crate) mir_opt_level: Option<u8>,
+    pub(crate) inline_mir: Option<bool>,
+    /// The directory given to [`Config::working_dir`].
+    pub(crate) working_dir: Option<PathBuf>,
+    /// The variables given to [`Config::env_overlay`].
+    pub(crate) env_overlay: Option<HashMap<String, String>>,
+    /// The `OUT_DIR` and the files given to [`Config::generated_files`].
+    pub(crate) generated_files: Option<(PathBuf, HashMap<PathBuf, String>)>,
+    pub(crate) virtual_crate: Option<VirtualCrate>,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    pub(crate) recursion_limit: Option<usize>,
+    pub(crate) type_length_limit: Option<usize>,
+}
+
+impl Config {
+    /// `rustc_args` are the arguments you would pass to `rustc`, without the program name.
+    pub fn new(rustc_args: &[String]) -> Self {
+        Self {
+            rustc_args: rustc_args.to_vec(),
+            source_code: None,
+            diagnostics_buffer: None,
+            diagnostics_collector: None,
+            allow_errors: false,
+            analysis_level: AnalysisLevel::default(),
+            register_lints: None,
+            configure: None,
+            file_loader: None,
+            detect_sysroot: true,
+            test: false,
+            lint_opts: Vec::new(),
+            lint_cap: None,
+            stack_size: None,
+            threads: None,
+            pass_timings: None,
+            self_profile: None,
+            no_std: false,
+            no_core: false,
+            crate_attrs: Vec::new(),
+            target: None,
+            cfgs: Vec::new(),
+            check_cfg_features: None,
+            edition: None,
+            externs: Vec::new(),
+            proc_macros: Vec::new(),
+            crate_types: Vec::new(),
+            output_dir: None,
+            emits: Vec::new(),
+            check_toolchain: true,
+            keep_built_mir: false,
+            borrowck_facts: false,
+            mir_opt_level: None,
+            inline_mir: None,
+            working_dir: None,
+            env_overlay: None,
+            generated_files: None,
+            virtual_crate: None,
+            cancellation_token: None,
+            recursion_limit: None,
+            type_length_limit: None,
+        }
+    }
+
+    /// Use `source` as the crate root source code instead of reading it from a file. The
+    /// `rustc` arguments must not contain a file operand in this case.
+    pub fn source_code(mut self, source: &str) -> Self {
+        self.source_code = Some(source.to_owned());
+        self
+    }
+
+    /// Compiles `virtual_crate`, whose files are never read from the file system, see
+    /// [`with_tyctxt_virtual`](crate::with_tyctxt_virtual). The `rustc` arguments must not
+    /// contain a file operand in this case.
+    pub fn virtual_crate(mut self, virtual_crate: VirtualCrate) -> Self {
+        self.virtual_crate = Some(virtual_crate);
+        self
+    }
+
+    /// All diagnostics (including the errors about invalid arguments, which are emitted before the
+    /// `SourceMap` is created) will be written into `buffer` instead of stderr. Only the fatal
+    /// errors the compiler emits while creating its session (like a target specification which
+    /// can't be loaded) are still written to stderr.
+    pub fn diagnostics_buffer(mut self, buffer: DiagnosticsBuffer) -> Self {
+        self.diagnostics_buffer = Some(buffer);
+        self
+    }
+
+    /// All diagnostics will be converted into [`Diagnostic`](crate::diagnostics::Diagnostic) and
+    /// stored into `collector` instead of being written to stderr. If a
+    /// [`diagnostics_buffer`](Self::diagnostics_buffer) is set as well, the rendered diagnostics
+    /// will still be written into it.
+    pub fn collect_diagnostics(mut self, collector: DiagnosticsCollector) -> Self {
+        self.diagnostics_collector = Some(collector);
+        self
+    }
+
+    /// If `true`, the callback will be called even if the compiler emitted errors (only the
+    /// parsing errors the compiler cannot recover from still make the compilation fail). Take a
+    /// look at
+    /// [`with_tyctxt_allow_errors`](crate::with_tyctxt_allow_errors) for more information.
+    pub fn allow_errors(mut self, allow_errors: bool) -> Self {
+        self.allow_errors = allow_errors;
+        self
+    }
+
+    /// Sets how much analysis the compiler runs before calling the callback. The default is
+    /// [`AnalysisLevel::HirOnly`].
+    pub fn analysis_level(mut self, analysis_level: AnalysisLevel) -> Self {
+        self.analysis_level = analysis_level;
+        self
+    }
+
+    /// If `true` (the default) and `--sysroot` isn't passed in the `rustc` arguments, the sysroot
+    /// is detected by running `rustc --print sysroot` (using the `RUSTC` and `RUSTUP_TOOLCHAIN`
+    /// environment variables if they're set). If it's `false`, the compiler will guess the
+    /// sysroot from the location of its own libraries.
+    pub fn detect_sysroot(mut self, detect_sysroot: bool) -> Self {
+        self.detect_sysroot = detect_sysroot;
+        self
+    }
+
+    /// If `true`, the MIR of the functions is rendered as soon as it's built, so
+    /// [`mir_pretty`](crate::mir::mir_pretty) can return it with
+    /// [`MirPhase::Built`](crate::mir::MirPhase::Built) once the next passes have consumed it
+    /// (which the full analysis does). It is `false` by default since it renders the MIR of all
+    /// the functions.
+    pub fn keep_built_mir(mut self, keep_built_mir: bool) -> Self {
+        self.keep_built_mir = keep_built_mir;
+        self
+    }
+
+    /// If `true`, the borrows of the bodies and where they're live are computed as the bodies
+    /// are borrow-checked, so [`borrowck_facts`](crate::borrowck::borrowck_facts) can return
+    /// them. It is `false` by default since it's done with Polonius, which is much slower than
+    /// the borrow checker.
+    pub fn borrowck_facts(mut self, borrowck_facts: bool) -> Self {
+        self.borrowck_facts = borrowck_facts;
+        self
+    }
+
+    /// Sets how much the MIR is optimized, like `-Z mir-opt-level` does (it overrides it): from 0
+    /// (only the passes needed for the code generation) to 4 (the unsound ones too).
+    ///
+    /// If it isn't given in the `rustc` arguments either, it is 1 (like `rustc` without `-O`), so
+    /// the analyses of the optimized MIR (see the [`mir`](crate::mir) module) give the same
+    /// results whatever the other options.
+    pub fn mir_opt_level(mut self, mir_opt_level: u8) -> Self {
+        self.mir_opt_level = Some(mir_opt_level);
+        self
+    }
+
+    /// Enables or disables the inlining of the MIR, like `-Z inline-mir` does (it overrides it).
+    /// If it isn't given in the `rustc` arguments either, the MIR isn't inlined whatever the
+    /// level given to [`mir_opt_level`](Self::mir_opt_level), so the optimized MIR of a function
+    /// only contains its own code.
+    pub fn inline_mir(mut self, inline_mir: bool) -> Self {
+        self.inline_mir = Some(inline_mir);
+        self
+    }
+
+    /// If `true` (the default), the compiler isn't run if the toolchain of the sysroot isn't the
+    /// one this crate was built with (see [`required_nightly`](crate::required_nightly)), and
+    /// [`Error::ToolchainMismatch`] is returned instead: the compiler can't load the libraries of
+    /// another toolchain, and usually crashes when trying to. The version is checked with the
+    /// `rustc` of the detected sysroot or with the `bin/rustc` of the one given with `--sysroot`
+    /// (if it exists).
+    pub fn check_toolchain(mut self, check_toolchain: bool) -> Self {
+        self.check_toolchain = check_toolchain;
+        self
+    }
+
+    /// If `true`, the crate is compiled as if it had `#![no_std]`, so it only depends on `core`
+    /// (and `alloc` if it uses it).
+    pub fn no_std(mut self, no_std: bool) -> Self {
+        self.no_std = no_std;
+        self
+    }
+
+    /// If `true`, the crate is compiled as if it had `#![feature(no_core)]` and `#![no_core]`, so
+    /// it doesn't depend on any crate of the sysroot, which isn't detected then (see
+    /// [`detect_sysroot`](Self::detect_sysroot)). The crate must define the lang items it uses
+    /// (like `#[lang = "sized"]`), which requires `#![feature(lang_items)]`.
+    pub fn no_core(mut self, no_core: bool) -> Self {
+        self.no_core = no_core;
+        self
+    }
+
+    /// Compiles the crate as if its root started with `#![attr]` for each `attr` of `attrs`, like
+    /// `-Z crate-attr` does: `inject_crate_attrs(&["allow(dead_code)", "feature(never_type)"])`
+    /// for example. The attributes are added to the ones given by the previous calls.
+    ///
+    /// The unstable features enabled with `feature(...)` can be used even if the compiler only
+    /// allows the stable ones (on a stable toolchain without `RUSTC_BOOTSTRAP`), and so can the
+    /// ones of the crate.
+    pub fn inject_crate_attrs(mut self, attrs: &[&str]) -> Self {
+        self.crate_attrs
+            .extend(attrs.iter().map(|attr| (*attr).to_owned()));
+        self
+    }
+
+    /// Analyzes the crate for `target`, like `--target` does (it overrides it): either a target
+    /// triple (like `wasm32-unknown-unknown`) or the path of a target specification file ending
+    /// with `.json`.
+    ///
+    /// Before running the compiler, `core` is looked for in the sysroot and in the `-L` search
+    /// paths. If it isn't found (or if the target is unknown), [`Error::MissingTargetLibs`] is
+    /// returned. It's skipped with [`no_core`](Self::no_core).
+    pub fn target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_owned());
+        self
+    }
+
+    /// Compiles the crate with `edition`, like `--edition` does (it overrides it). The unstable
+    /// editions can be used without `-Z unstable-options`.
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.edition = Some(edition);
+        self
+    }
+
+    /// Enables the `name` feature, like `--cfg 'feature="name"'` does.
+    pub fn feature(self, name: &str) -> Self {
+        self.cfg_kv("feature", name)
+    }
+
+    /// Sets the `name` configuration option, like `--cfg name` does, so `#[cfg(name)]` holds.
+    ///
+    /// `name` must be an identifier, otherwise [`Error::InvalidCfg`] is returned before running
+    /// the compiler.
+    pub fn cfg_flag(mut self, name: &str) -> Self {
+        self.cfgs.push((name.to_owned(), None));
+        self
+    }
+
+    /// Sets the `name` configuration option to `value`, like `--cfg 'name="value"'` does, so
+    /// `#[cfg(name = "value")]` holds. `value` can be any string, it doesn't need to be quoted
+    /// nor escaped.
+    ///
+    /// `name` must be an identifier, otherwise [`Error::InvalidCfg`] is returned before running
+    /// the compiler.
+    pub fn cfg_kv(mut self, name: &str, value: &str) -> Self {
+        self.cfgs.push((name.to_owned(), Some(value.to_owned())));
+        self
+    }
+
+    /// Enables the checking of the features, like `--check-cfg 'values(feature, "a", "b")'` does:
+    /// the features used in `#[cfg]` which aren't in `features` are reported by the
+    /// `unexpected_cfgs` lint.
+    pub fn check_cfg_features(mut self, features: &[&str]) -> Self {
+        self.check_cfg_features = Some(features.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// Makes the crate `name` available from the library at `path`, like `--extern name=path`
+    /// does. `path` can be an `.rlib` or an `.rmeta` (like the ones produced by `cargo check`).
+    /// Since only the metadata of the dependencies is read, the `.rmeta` next to an `.rlib` is
+    /// used instead of it if it exists (which is also the case for the `--extern` of the `rustc`
+    /// arguments).
+    ///
+    /// `name` must be an ASCII identifier, otherwise [`Error::InvalidExtern`] is returned before
+    /// running the compiler.
+    pub fn extern_crate<P: Into<PathBuf>>(self, name: &str, path: P) -> Self {
+        self.extern_crate_with_modifiers(name, Some(path.into()), &[])
+    }
+
+    /// Makes the crate `name` available from the library search paths (given with `-L`), like
+    /// `--extern name` does.
+    pub fn extern_crate_unlocated(self, name: &str) -> Self {
+        self.extern_crate_with_modifiers(name, None, &[])
+    }
+
+    /// Same as [`extern_crate`](Self::extern_crate) (or
+    /// [`extern_crate_unlocated`](Self::extern_crate_unlocated) if `path` is `None`) with
+    /// `modifiers`, like `--extern priv,noprelude:name=path` does. `-Z unstable-options` isn't
+    /// needed. Like with `--extern`, the crate is added to the extern prelude if one of the times
+    /// it's given doesn't have [`ExternModifier::NoPrelude`].
+    pub fn extern_crate_with_modifiers(
+        mut self,
+        name: &str,
+        path: Option<PathBuf>,
+        modifiers: &[ExternModifier],
+    ) -> Self {
+        self.externs
+            .push((name.to_owned(), path, modifiers.to_vec()));
+        self
+    }
+
+    /// Makes the proc-macro crate `name` available from the dynamic library at `path` (a `.so`, a
+    /// `.dylib` or a `.dll`), like `--extern name=path` does. The crates using derive macros (like
+    /// the ones of `serde_derive` or `thiserror`) need them.
+    ///
+    /// The proc macros are loaded and run by the compiler, so they must be built for the host
+    /// (the platform of the current process) even if another [`target`](Self::target) is set,
+    /// and with the same version of the compiler as this crate. `path` is checked before running
+    /// the compiler: if it isn't a dynamic library of the host or if it doesn't export the
+    /// symbols of a proc-macro crate, [`Error::InvalidExtern`] is returned.
+    pub fn extern_proc_macro<P: Into<PathBuf>>(mut self, name: &str, path: P) -> Self {
+        self.proc_macros.push((name.to_owned(), path.into()));
+        self
+    }
+
+    /// Compiles the crate as a `crate_type`, like `--crate-type` does. It can be called several
+    /// times to give several types, which override the `--crate-type` of the `rustc` arguments.
+    ///
+    /// If the type isn't given (here, with `--crate-type` or with `#![crate_type]`), the crate is
+    /// compiled as a [`CrateType::ProcMacro`] if it defines proc macros at its root (with
+    /// `#[proc_macro]`, `#[proc_macro_derive]` or `#[proc_macro_attribute]`), and as a binary
+    /// otherwise. Nothing is ever linked, so all the types can be analyzed.
+    pub fn crate_type(mut self, crate_type: CrateType) -> Self {
+        self.crate_types.push(crate_type);
+        self
+    }
+
+    /// If `true`, the crate is compiled like with `rustc --test`: the `#[cfg(test)]` items are
+    /// kept and the test harness is generated. Take a look at
+    /// [`discover_tests`](crate::test_discovery::discover_tests) for more information.
+    pub fn test(mut self, test: bool) -> Self {
+        self.test = test;
+        self
+    }
+
+    /// Sets `lint` (a lint name like `dead_code` or a lint group like `unused`) to `allow`, like
+    /// `-A lint` does. The levels set with this method, [`warn`](Self::warn),
+    /// [`deny`](Self::deny) and [`forbid`](Self::forbid) are applied after the ones of the `rustc`
+    /// arguments and in the order of the calls, so the last level given for a lint is the one used.
+    ///
+    /// Unlike with `-A`, an unknown lint name isn't an error: a warning with the `unknown_lints`
+    /// code is emitted instead.
+    pub fn allow(self, lint: &str) -> Self {
+        self.lint_level(lint, Level::Allow)
+    }
+
+    /// Sets `lint` to `warn`, like `-W lint` does. See [`allow`](Self::allow).
+    pub fn warn(self, lint: &str) -> Self {
+        self.lint_level(lint, Level::Warn)
+    }
+
+    /// Sets `lint` to `deny`, like `-D lint` does. See [`allow`](Self::allow).
+    ///
+    /// The denied lints are errors, so the compilation fails when they're emitted unless
+    /// [`allow_errors`](Self::allow_errors) is used.
+    pub fn deny(self, lint: &str) -> Self {
+        self.lint_level(lint, Level::Deny)
+    }
+
+    /// Sets `lint` to `forbid`, like `-F lint` does. See [`allow`](Self::allow).
+    pub fn forbid(self, lint: &str) -> Self {
+        self.lint_level(lint, Level::Forbid)
+    }
+
+    fn lint_level(mut self, lint: &str, level: Level) -> Self {
+        self.lint_opts.push((lint.to_owned(), level));
+        self
+    }
+
+    /// Caps the level of all the lints to `level`, like `--cap-lints` does. It replaces the cap
+    /// given in the `rustc` arguments.
+    pub fn cap_lints(mut self, level: Level) -> Self {
+        self.lint_cap = Some(level);
+        self
+    }
+
+    /// Sets the recursion limit of the crate, like `#![recursion_limit = "..."]` does (it replaces
+    /// the attribute of the crate if there's one). The default is 128. When a limit is reached,
+    /// [`Error::LimitExceeded`] is returned.
+    pub fn recursion_limit(mut self, limit: usize) -> Self {
+        self.recursion_limit = Some(limit);
+        self
+    }
+
+    /// Sets the type-length limit of the crate, like `#![type_length_limit = "..."]` does (it
+    /// replaces the attribute of the crate if there's one). The default is 1048576. It's only
+    /// checked when the generic functions are instantiated, so with [`EmitKind::LlvmIr`].
+    pub fn type_length_limit(mut self, limit: usize) -> Self {
+        self.type_length_limit = Some(limit);
+        self
+    }
+
+    /// Sets the size (in bytes) of the stack on which the compiler analyzes the crate and calls the
+    /// callback. Deeply nested code (like long method chains) may need more than the default,
+    /// which is the one of `rustc`: 8 MiB, or the value of the `RUST_MIN_STACK` environment
+    /// variable if it's set.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Sets the number of threads the compiler uses to analyze the crate, like `-Z threads` does
+    /// (it overrides it). It must be positive.
+    ///
+    /// Only the compilers built with the parallel front-end can use several threads. With the
+    /// other ones, a warning is emitted and the analysis runs on a single thread.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// The analysis stops as soon as possible once `token` is cancelled, and
+    /// [`Error::Cancelled`] is returned instead of the result of the callback.
+    ///
+    /// The token is checked before the compiler starts, between its stages (after the parsing,
+    /// before the type context is created and before the callback is called) and each time a body is
+    /// type-checked, including by the queries of the callback. Otherwise, the callback can check
+    /// [`CancellationToken::is_cancelled`] itself.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// The timings of the stages of the analysis will be pushed into `timings`. Take a look at
+    /// the [`profiling`](crate::profiling) module for more information.
+    pub fn pass_timings(mut self, timings: PassTimings) -> Self {
+        self.pass_timings = Some(timings);
+        self
+    }
+
+    /// The compiler will write its self-profile (like with `-Z self-profile`) in the `directory`
+    /// directory, in a file named after the `--crate-name` argument and the process ID (like
+    /// `my_crate-0012345.mm_profdata`, or `unknown-crate-0012345.mm_profdata` without
+    /// `--crate-name`). The file is complete once the analysis is over.
+    pub fn self_profile<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.self_profile = Some(directory.into());
+        self
+    }
+
+    /// Allows the compiler to write files. By default, nothing is written: the crate is never
+    /// built nor linked, and the options of the `rustc` arguments which would write files
+    /// (`--emit`, `-o`, `--out-dir`, `-C incremental`, `-Z self-profile` and `-Z dump-mir`) are
+    /// ignored with a warning.
+    ///
+    /// With this option, `directory` is used as the output directory (instead of `--out-dir`),
+    /// so the outputs written by the callbacks go in it (like the metadata written by
+    /// [`compile_dependency`](crate::dependencies::compile_dependency)), and the options writing
+    /// files are kept. `--emit` and `-o` are still ignored since nothing is built.
+    pub fn allow_output<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.output_dir = Some(directory.into());
+        self
+    }
+
+    /// Makes the compiler write the `kind` file of the crate in `directory`, once the callback of
+    /// [`with_tyctxt`](Self::with_tyctxt) has returned. It can be called several times to write
+    /// several files. The files are named after the crate (like `libmy_crate.rmeta` or
+    /// `my_crate.mir`), and [`with_tyctxt_emit`](Self::with_tyctxt_emit) returns their paths.
+    ///
+    /// The compiler only writes these files once the whole analysis is done, so the analysis
+    /// level is [`AnalysisLevel::Full`] whatever the one given. For [`EmitKind::LlvmIr`], the code
+    /// generation is run as well (with a single codegen unit so there's only one file), but
+    /// nothing is linked.
+    pub fn emit<P: Into<PathBuf>>(mut self, kind: EmitKind, directory: P) -> Self {
+        self.emits.push((kind, directory.into()));
+        self
+    }
+
+    /// `register_lints` will be called with the `LintStore` so you can register your own lints and
+    /// lint passes. Lints are only run with [`AnalysisLevel::Full`], take a look at
+    /// [`with_lints`](crate::with_lints) for more information.
+    pub fn register_lints<F: Fn(&Session, &mut LintStore) + marker::Send + Sync + 'static>(
+        mut self,
+        register_lints: F,
+    ) -> Self {
+        self.register_lints = Some(Box::new(register_lints));
+        self
+    }
+
+    /// The compiler will use `file_loader` to read the source files (the crate root, the
+    /// modules and the files included with `include!`, `include_str!` or `include_bytes!`). The
+    /// files given to [`generated_files`](Self::generated_files) are looked up first.
+    ///
+    /// The compiler reads the files of `include_str!` and `include_bytes!` from the file system,
+    /// so these macros are defined at the beginning of the crate root with `macro_rules!` (like
+    /// `env!` with [`env_overlay`](Self::env_overlay)), to include the file with `include!`
+    /// instead. The macros called with a path (like `std::include_str!`) aren't affected, and a
+    /// file included with `include_bytes!` which isn't UTF-8 is read from the file system if
+    /// `file_loader` can't read it. It isn't done with [`with_compiler`](Self::with_compiler) and
+    /// [`Stage::Parse`].
+    pub fn file_loader(mut self, file_loader: Box<dyn FileLoader + marker::Send + Sync>) -> Self {
+        self.file_loader = Some(file_loader);
+        self
+    }
+
+    /// The compiler will read the source files from `files` first and will fall back to the file
+    /// system if they're not in it. It's useful if you have unsaved files in an editor for
+    /// example. Paths can be relative (to the current directory) or absolute.
+    ///
+    /// The files included with `include!`, `include_str!` and `include_bytes!` are read from
+    /// `files` too, see [`file_loader`](Self::file_loader).
+    pub fn file_overlay(self, files: HashMap<PathBuf, String>) -> Self {
+        self.file_loader(Box::new(OverlayFileLoader::new(files)))
+    }
+
+    /// The relative path of the input file is relative to `directory` instead of the current
+    /// directory, and so are the paths of the modules (`mod name;`) and of the files included with
+    /// `include!`, `include_str!` or `include_bytes!`, which are relative to it. It's also the
+    /// working directory of the session, which is used in the paths of the debug information.
+    ///
+    /// The other relative paths of the `rustc` arguments (like the ones of `--extern` or `-L`) and
+    /// the ones given to [`file_overlay`](Self::file_overlay) are still relative to the current
+    /// directory.
+    pub fn working_dir<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.working_dir = Some(directory.into());
+        self
+    }
+
+    /// `env!` and `option_env!` will only see the variables of `vars`, and not the ones of the
+    /// current process: `env!("CARGO_PKG_VERSION")` fails if it isn't in `vars` for example.
+    ///
+    /// The compiler reads the variables of the process, so it's done by defining `env!` and
+    /// `option_env!` at the beginning of the crate root with `macro_rules!`, shadowing the macros
+    /// of the standard library. The names must be string literals (not raw strings) to be found,
+    /// and the macros called with a path (like `std::env!`) aren't affected. It isn't done with
+    /// [`with_compiler`](Self::with_compiler) and [`Stage::Parse`].
+    pub fn env_overlay(mut self, vars: HashMap<String, String>) -> Self {
+        self.env_overlay = Some(vars);
+        self
+    }
+
+    /// Supplies the files a build script would generate: `env!("OUT_DIR")` and
+    /// `option_env!("OUT_DIR")` give `out_dir` (made absolute) and the files of `files`, whose paths
+    /// are relative to `out_dir`, are read before the ones of the
+    /// [`file_loader`](Self::file_loader) or of the [`file_overlay`](Self::file_overlay). So
+    /// `include!(concat!(env!("OUT_DIR"), "/generated.rs"))` works without building the crate,
+    /// and `out_dir` doesn't need to exist.
+    ///
+    /// `OUT_DIR` is defined like with [`env_overlay`](Self::env_overlay) (and is added to its
+    /// variables if it's used too), the other variables are still read from the current process
+    /// if it isn't used. `include_str!` and `include_bytes!` read `files` as well (see
+    /// [`file_loader`](Self::file_loader)). [`included_files`](crate::includes::included_files)
+    /// tells which included files are missing.
+    pub fn generated_files<P: Into<PathBuf>>(
+        mut self,
+        out_dir: P,
+        files: HashMap<PathBuf, String>,
+    ) -> Self {
+        self.generated_files = Some((absolute_path(&out_dir.into()), files));
+        self
+    }
+
+    /// `configure` will be called with the compiler configuration right before the compiler is
+    /// run, allowing you to change whatever you want in it (like setting a `parse_sess_created`
+    /// callback to customize the `ParseSess`).
+    ///
+    /// Be careful: some fields are already used by this crate. For example, if diagnostics are
+    /// captured, `parse_sess_created` is already set. If you want to keep this behaviour, you can
+    /// `take()` it and call it from your own callback.
+    pub fn configure<F: FnOnce(&mut interface::Config) + 'static>(mut self, configure: F) -> Self {
+        self.configure = Some(Box::new(configure));
+        self
+    }
+
+    /// Returns `true` if the files aren't read from the file system by the compiler, in which
+    /// case `include_str!` and `include_bytes!` are defined to read them with the file loader.
+    pub(crate) fn has_file_loader(&self) -> bool {
+        self.file_loader.is_some() || self.generated_files.is_some() || self.virtual_crate.is_some()
+    }
+
+    /// Returns the variables defined by [`env_overlay`](Self::env_overlay) and
+    /// [`generated_files`](Self::generated_files), and whether `env!` and `option_env!` only see
+    /// them.
+    pub(crate) fn injected_env(&self) -> Option<(HashMap<String, String>, bool)> {
+        let out_dir = self
+            .generated_files
+            .as_ref()
+            .map(|(out_dir, _)| ("OUT_DIR".to_owned(), out_dir.to_string_lossy().into_owned()));
+        match &self.env_overlay {
+            Some(vars) => Some((vars.clone().into_iter().chain(out_dir).collect(), true)),
+            None => out_dir.map(|var| (HashMap::from([var]), false)),
+        }
+    }
+
+    /// Runs the compiler with this configuration and calls `callback` with the expanded AST. Take
+    /// a look at [`with_expanded_ast`](crate::with_expanded_ast) for more information.
+    pub fn with_expanded_ast<
+        T: marker::Send,
+        F: FnOnce(&ast::Crate, &Session) -> T + marker::Send,
+    >(
+        self,
+        callback: F,
+    ) -> Result<T, Error> {
+        self.with_ast(Stage::Expand, callback)
+    }
+
+    /// Runs the compiler with this configuration up to `stage` and calls `callback` with the AST
+    /// at this point. Take a look at [`with_parsed_crate`](crate::with_parsed_crate) and
+    /// [`with_expanded_ast`](crate::with_expanded_ast) for more information.
+    pub fn with_ast<T: marker::Send, F: FnOnce(&ast::Crate, &Session) -> T + marker::Send>(
+        self,
+        stage: Stage,
+        callback: F,
+    ) -> Result<T, Error> {
+        crate::ast::run_until(self, stage, callback)
+    }
+
+    /// Runs the compiler with this configuration and calls `callback` with the compiler and its
+    /// queries. Take a look at [`with_compiler`](crate::with_compiler) for more information.
+    pub fn with_compiler<
+        T: marker::Send,
+        F: FnOnce(&interface::Compiler, &Queries<'_>) -> T + marker::Send,
+    >(
+        self,
+        callback: F,
+    ) -> Result<T, Error> {
+        crate::hir::enter_compiler(self, |compiler, queries| Ok(callback(compiler, queries)))
+    }
+
+    /// Runs the compiler with this configuration and calls `callback` with the `TyCtxt`. Take a
+    /// look at [`with_tyctxt`](crate::with_tyctxt) for more information.
+    pub fn with_tyctxt<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+        self,
+        callback: F,
+    ) -> Result<T, Error> {
+        crate::hir::run_compiler(self, callback)
+    }
+
+    /// Same as [`with_tyctxt`](Self::with_tyctxt) but also returns the paths of the files written
+    /// because of [`emit`](Self::emit), in the order in which they were given.
+    pub fn with_tyctxt_emit<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+        self,
+        callback: F,
+    ) -> Result<(T, Vec<PathBuf>), Error> {
+        crate::hir::run_compiler_emit(self, callback)
+    }
+
+    /// Same as [`with_tyctxt`](Self::with_tyctxt) without the `Send` bounds. Take a look at
+    /// [`with_tyctxt_local`](crate::with_tyctxt_local) for more information.
+    ///
+    /// # Safety
+    ///
+    /// See [`with_tyctxt_local`](crate::with_tyctxt_local).
+    pub unsafe fn with_tyctxt_local<T, F: FnOnce(TyCtxt<'_>) -> T>(
+        self,
+        callback: F,
+    ) -> Result<T, Error> {
+        crate::hir::run_compiler_local(self, callback)
+    }
+}
+
+/// Buffer in which the rendered diagnostics are written. It can be cloned so you can keep a
+/// handle on it while it's used by the compiler.
+#[derive(Clone, Default)]
+pub struct DiagnosticsBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl DiagnosticsBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the content of the buffer and empties it.
+    pub fn take(&self) -> String {
+        let content = std::mem::take(&mut *self.0.lock().unwrap());
+        String::from_utf8_lossy(&content).into_owned()
+    }
+}
+
+impl Write for DiagnosticsBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Handle to cancel the analyses it's given to with [`Config::cancellation_token`], from another
+/// thread. It can be cloned so you can keep a handle on it while it's used by the compiler.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the analyses using this token. The ones started afterwards with it are cancelled
+    /// right away.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A crate made of files which only exist in memory, given to
+/// [`with_tyctxt_virtual`](crate::with_tyctxt_virtual) or [`Config::virtual_crate`].
+///
+/// The paths are the names of the files in the diagnostics and in the spans, so they're usually
+/// relative (like `src/lib.rs`). The modules are looked up next to the crate root like on the file
+/// system: with `root("lib.rs", ..)`, `mod foo;` is `foo.rs` or `foo/mod.rs`, and `#[path]`
+/// attributes are relative to the file containing them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VirtualCrate {
+    pub(crate) root: Option<PathBuf>,
+    pub(crate) files: HashMap<PathBuf, String>,
+}
+
+impl VirtualCrate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the crate root, whose file name gives the name of the crate (like with a file given
+    /// to `rustc`) unless `--crate-name` is used.
+    pub fn root<P: Into<PathBuf>>(mut self, path: P, source: &str) -> Self {
+        let path = path.into();
+        self.files.insert(path.clone(), source.to_owned());
+        self.root = Some(path);
+        self
+    }
+
+    /// Adds a file, like a module or a file included with `include!`.
+    pub fn file<P: Into<PathBuf>>(mut self, path: P, source: &str) -> Self {
+        self.files.insert(path.into(), source.to_owned());
+        self
+    }
+}
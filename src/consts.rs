@@ -0,0 +1,144 @@
+//! Helpers to evaluate the constants of a crate to values which can be used once the compilation
+//! is over, like configuration tables written as constants.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::interpret::{ErrorHandled, GlobalId};
+use rustc_middle::ty::layout::IntegerExt;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{self, Instance, InternalSubsts, Ty, TyCtxt, ValTree};
+use rustc_target::abi::{Integer, Size};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::capture_errors;
+use crate::paths::qualified_name;
+use crate::Error;
+
+/// Value of a constant returned by [`eval_const`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstValueRepr {
+    Bool(bool),
+    /// A signed integer (`i8` to `i128` and `isize`).
+    Int(i128),
+    /// An unsigned integer (`u8` to `u128` and `usize`).
+    Uint(u128),
+    Char(char),
+    /// A `str` (usually behind a reference).
+    Str(String),
+    /// An array or a slice of `u8` (like a byte string).
+    Bytes(Vec<u8>),
+    /// An array or a slice of any other type.
+    Array(Vec<ConstValueRepr>),
+    Tuple(Vec<ConstValueRepr>),
+    /// A value which can't be represented by the other variants (like a struct, a float or a raw
+    /// pointer). It contains the type of the value.
+    Opaque(String),
+}
+
+/// Evaluates the constant `def_id` (a `const` item or an associated constant with a value, from
+/// the local crate or not) like [`TyCtxt::const_eval_poly`] does, and returns its value. The
+/// references are represented by the value they point to, so a `&str` is a
+/// [`ConstValueRepr::Str`].
+///
+/// If the evaluation fails, the errors are returned in [`Error::ConstEval`] as they're displayed
+/// by the compiler (they're also emitted, like the other errors of the crate). The compiler only
+/// evaluates a constant once, so the next calls for the same constant only give its name, as do
+/// the calls for the constants whose evaluation already failed during the analysis. The constants
+/// depending on generic parameters (like the associated constants of generic impls) can't be
+/// evaluated.
+pub fn eval_const(tcx: TyCtxt<'_>, def_id: DefId) -> Result<ConstValueRepr, Error> {
+    let name = qualified_name(tcx, def_id);
+    let has_value = match tcx.def_kind(def_id) {
+        DefKind::Const => true,
+        DefKind::AssocConst => tcx.associated_item(def_id).defaultness(tcx).has_value(),
+        _ => false,
+    };
+    if !has_value {
+        return Err(Error::ConstEval(format!(
+            "`{name}` is not a constant with a value"
+        )));
+    }
+
+    // Same as `TyCtxt::const_eval_poly` but to a valtree, which is easier to go through.
+    let cid = GlobalId {
+        instance: Instance::new(def_id, InternalSubsts::identity_for_item(tcx, def_id)),
+        promoted: None,
+    };
+    let param_env = tcx.param_env(def_id).with_reveal_all_normalized(tcx);
+    let (res, errors) =
+        capture_errors(|| tcx.const_eval_global_id_for_typeck(param_env, cid, None));
+    let ty = tcx.type_of(def_id);
+    match res {
+        _ if !errors.is_empty() => Err(Error::ConstEval(errors.concat())),
+        Ok(Some(valtree)) => Ok(convert(tcx, valtree, ty)),
+        // The value contains something which isn't supported by the valtrees (like a raw
+        // pointer).
+        Ok(None) => Ok(ConstValueRepr::Opaque(type_string(ty))),
+        Err(ErrorHandled::TooGeneric) => Err(Error::ConstEval(format!(
+            "`{name}` depends on generic parameters"
+        ))),
+        Err(ErrorHandled::Reported(_)) => Err(Error::ConstEval(format!(
+            "the evaluation of `{name}` failed"
+        ))),
+    }
+}
+
+fn convert<'tcx>(tcx: TyCtxt<'tcx>, valtree: ValTree<'tcx>, ty: Ty<'tcx>) -> ConstValueRepr {
+    match (ty.kind(), valtree) {
+        (ty::Bool, ValTree::Leaf(leaf)) => match leaf.try_to_bool() {
+            Ok(value) => ConstValueRepr::Bool(value),
+            Err(_) => ConstValueRepr::Opaque(type_string(ty)),
+        },
+        (ty::Char, ValTree::Leaf(leaf)) => match char::try_from(leaf) {
+            Ok(value) => ConstValueRepr::Char(value),
+            Err(_) => ConstValueRepr::Opaque(type_string(ty)),
+        },
+        (ty::Int(int_ty), ValTree::Leaf(leaf)) => {
+            let size = Integer::from_int_ty(&tcx, *int_ty).size();
+            ConstValueRepr::Int(size.sign_extend(leaf.assert_bits(size)) as i128)
+        }
+        (ty::Uint(uint_ty), ValTree::Leaf(leaf)) => {
+            let size = Integer::from_uint_ty(&tcx, *uint_ty).size();
+            ConstValueRepr::Uint(leaf.assert_bits(size))
+        }
+        (ty::Ref(_, pointee, _), _) => convert(tcx, valtree, *pointee),
+        (ty::Str, ValTree::Branch(bytes)) => {
+            ConstValueRepr::Str(String::from_utf8_lossy(&to_bytes(bytes)).into_owned())
+        }
+        (ty::Array(elem_ty, _) | ty::Slice(elem_ty), ValTree::Branch(elems)) => {
+            if *elem_ty == tcx.types.u8 {
+                ConstValueRepr::Bytes(to_bytes(elems))
+            } else {
+                ConstValueRepr::Array(
+                    elems
+                        .iter()
+                        .map(|elem| convert(tcx, *elem, *elem_ty))
+                        .collect(),
+                )
+            }
+        }
+        (ty::Tuple(field_tys), ValTree::Branch(fields)) => ConstValueRepr::Tuple(
+            fields
+                .iter()
+                .zip(field_tys.iter())
+                .map(|(field, field_ty)| convert(tcx, *field, field_ty))
+                .collect(),
+        ),
+        _ => ConstValueRepr::Opaque(type_string(ty)),
+    }
+}
+
+/// The `str` and the `[u8]` are branches of `u8` leaves.
+fn to_bytes(leaves: &[ValTree<'_>]) -> Vec<u8> {
+    leaves
+        .iter()
+        .map(|leaf| leaf.unwrap_leaf().assert_bits(Size::from_bytes(1)) as u8)
+        .collect()
+}
+
+fn type_string(ty: Ty<'_>) -> String {
+    with_no_trimmed_paths!(ty.to_string())
+}
@@ -0,0 +1,71 @@
+//! Helpers to list the crates loaded by the compiler along with the local crate, to know which
+//! versions of the dependencies are actually used.
+
+use rustc_hir::def_id::{CrateNum, StableCrateId, LOCAL_CRATE};
+use rustc_middle::ty::TyCtxt;
+use rustc_session::cstore::{CrateDepKind, ExternCrateSource};
+use rustc_span::hygiene::{AstPass, ExpnKind};
+
+use std::path::PathBuf;
+
+/// An extern crate, returned by [`crate_graph`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternCrateInfo {
+    pub name: String,
+    pub krate: CrateNum,
+    /// The library file the crate was loaded from: its `.rmeta` file if it has one, otherwise its
+    /// `.rlib` file or its dynamic library (always the case for the proc-macro crates).
+    pub path: PathBuf,
+    /// Identifies the crate across compilations, two versions of a crate have different ids.
+    pub stable_crate_id: StableCrateId,
+    /// `true` if it's a proc-macro crate, loaded for its macros only.
+    pub is_proc_macro: bool,
+    /// `true` if the crate is loaded by an `extern crate` item injected by the compiler, which
+    /// is `std` (or `core` and `compiler_builtins` with `#![no_std]`).
+    pub is_std_injected: bool,
+}
+
+/// Returns the crates loaded to compile the local crate, in the order in which they were loaded.
+/// They include the crates used by the local crate (given with `--extern` or found in the library
+/// search paths) and the dependencies of all of them (`core`, `alloc`, etc).
+pub fn crate_graph(tcx: TyCtxt<'_>) -> Vec<ExternCrateInfo> {
+    tcx.crates(())
+        .iter()
+        .map(|&krate| {
+            let source = tcx.used_crate_source(krate);
+            let is_std_injected = match tcx.extern_crate(krate.as_def_id()) {
+                Some(extern_crate) => {
+                    matches!(extern_crate.src, ExternCrateSource::Extern(_))
+                        && extern_crate.is_direct()
+                        && matches!(
+                            extern_crate.span.ctxt().outer_expn_data().kind,
+                            ExpnKind::AstPass(AstPass::StdImports)
+                        )
+                }
+                None => false,
+            };
+            ExternCrateInfo {
+                name: tcx.crate_name(krate).to_string(),
+                krate,
+                path: [&source.rmeta, &source.rlib, &source.dylib]
+                    .into_iter()
+                    .find_map(|path| path.as_ref())
+                    .map(|(path, _)| path.clone())
+                    .unwrap_or_default(),
+                stable_crate_id: tcx.stable_crate_id(krate),
+                is_proc_macro: is_proc_macro_crate(tcx, krate),
+                is_std_injected,
+            }
+        })
+        .collect()
+}
+
+/// Returns `true` if `krate` is an extern proc-macro crate. They are only loaded for their macros
+/// and from a dynamic library, whereas a `#[no_link]` crate still has its `.rlib` or `.rmeta`.
+pub(crate) fn is_proc_macro_crate(tcx: TyCtxt<'_>, krate: CrateNum) -> bool {
+    if krate == LOCAL_CRATE || tcx.dep_kind(krate) != CrateDepKind::MacrosOnly {
+        return false;
+    }
+    let source = tcx.used_crate_source(krate);
+    source.rlib.is_none() && source.rmeta.is_none()
+}
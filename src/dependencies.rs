@@ -0,0 +1,55 @@
+//! Helpers to compile the local dependencies of a crate to metadata, so the crate can be analyzed
+//! without running `rustc` or `cargo` beforehand.
+
+use rustc_session::config::CrateType;
+
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::DiagnosticsCollector;
+use crate::{Config, EmitKind, Error};
+
+/// Compiles the library whose root file is `dep_root` (like `a/src/lib.rs`) to metadata only,
+/// like `rustc --emit=metadata --crate-type=lib` does, and returns the path of the `.rmeta` file
+/// written in `out_dir`. It can then be given to the crates depending on it with
+/// [`Config::extern_crate`] (or with `--extern`).
+///
+/// `rustc_args` are the other arguments used to compile the library (its `--edition`, its
+/// `--cfg`, its own `--extern`, etc), without the file operand. `--crate-name`, `--crate-type`
+/// and `--emit` are overridden.
+///
+/// If the library doesn't compile, [`Error::DependencyCompilation`] is returned with the
+/// diagnostics emitted by the compiler.
+pub fn compile_dependency(
+    dep_root: &Path,
+    crate_name: &str,
+    out_dir: &Path,
+    rustc_args: &[String],
+) -> Result<PathBuf, Error> {
+    let mut args = rustc_args.to_vec();
+    args.extend([
+        "--crate-name".to_owned(),
+        crate_name.to_owned(),
+        dep_root.display().to_string(),
+    ]);
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&args)
+        // The diagnostics are taken in the callback so the final "aborting due to previous error"
+        // isn't included.
+        .allow_errors(true)
+        .collect_diagnostics(collector.clone())
+        .crate_type(CrateType::Rlib)
+        .emit(EmitKind::Metadata, out_dir)
+        .with_tyctxt_emit(|tcx| tcx.sess.has_errors().map(|_| collector.take()));
+    let diagnostics = match res {
+        // The metadata isn't written if the crate has errors.
+        Ok((Some(diagnostics), _)) => diagnostics,
+        Ok((None, mut paths)) => return Ok(paths.remove(0)),
+        // The errors the compiler can't recover from.
+        Err(Error::CompilationFailed { .. }) => collector.take(),
+        Err(e) => return Err(e),
+    };
+    Err(Error::DependencyCompilation {
+        crate_name: crate_name.to_owned(),
+        diagnostics,
+    })
+}
@@ -0,0 +1,168 @@
+//! Helpers to find the deprecated (and the stable or unstable) items of the local crate and the
+//! places where deprecated items are used.
+
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{BodyId, Expr, ExprKind, HirId, QPath};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{DefIdTree, TyCtxt, TypeckResults};
+use rustc_span::symbol::sym;
+use rustc_span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::api::{deprecation, stability, ApiDeprecation, ApiStability};
+use crate::paths::qualified_name;
+use crate::spans::location_string;
+
+/// Returned by [`deprecations`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecatedItem {
+    /// The [`qualified_name`] of the item.
+    pub name: String,
+    /// The kind of item as returned by `DefKind::descr`, like `"function"` or `"struct"`.
+    pub kind: String,
+    /// Set if the item has `#[deprecated]`.
+    pub deprecation: Option<ApiDeprecation>,
+    /// Set if the item has `#[stable]` or `#[unstable]`, which requires
+    /// `#![feature(staged_api)]`.
+    pub stability: Option<ApiStability>,
+    /// Where the item is defined, like `src/lib.rs:12:1`.
+    pub location: String,
+}
+
+/// Returned by [`uses_of_deprecated`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecatedUse {
+    /// The [`qualified_name`] of the deprecated item.
+    pub name: String,
+    pub deprecation: ApiDeprecation,
+    /// The [`qualified_name`] of the item (function, impl, struct, etc) in which it's used.
+    pub used_in: String,
+    /// Where it's used, like `src/lib.rs:12:5`.
+    pub location: String,
+}
+
+/// Returns the items of the local crate which have `#[deprecated]`, `#[stable]` or `#[unstable]`,
+/// in the order in which they're defined. The items which are deprecated because one of their
+/// parents is (like the items of a deprecated module) are not included.
+pub fn deprecations(tcx: TyCtxt<'_>) -> Vec<DeprecatedItem> {
+    let has_attr = |def_id, name| tcx.has_attr(def_id, name);
+    tcx.hir_crate_items(())
+        .definitions()
+        .map(|def_id| def_id.to_def_id())
+        .filter(|&def_id| {
+            has_attr(def_id, sym::deprecated)
+                || has_attr(def_id, sym::stable)
+                || has_attr(def_id, sym::unstable)
+        })
+        .map(|def_id| DeprecatedItem {
+            name: qualified_name(tcx, def_id),
+            kind: tcx.def_kind(def_id).descr(def_id).to_owned(),
+            deprecation: has_attr(def_id, sym::deprecated)
+                .then(|| deprecation(tcx, def_id))
+                .flatten(),
+            stability: (has_attr(def_id, sym::stable) || has_attr(def_id, sym::unstable))
+                .then(|| stability(tcx, def_id))
+                .flatten(),
+            location: location_string(tcx, tcx.def_span(def_id)),
+        })
+        .collect()
+}
+
+/// Returns the places where deprecated items (local or not) are used in the local crate: the
+/// paths in the items and in the bodies and the method calls. Unlike the `deprecated` lint, it
+/// doesn't depend on the lint levels. As with the lint, the uses of an item inside of the item
+/// which makes it deprecated (like in a deprecated module) are not included.
+///
+/// The method calls are resolved with the results of the type checking, so the bodies are
+/// type-checked if they weren't already. You might want to use
+/// [`AnalysisLevel::TypeckBodies`](crate::AnalysisLevel) to do it beforehand.
+pub fn uses_of_deprecated(tcx: TyCtxt<'_>) -> Vec<DeprecatedUse> {
+    let mut visitor = DeprecatedUseVisitor {
+        tcx,
+        typeck_results: None,
+        uses: Vec::new(),
+    };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.uses
+}
+
+struct DeprecatedUseVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// The typeck results of the body being visited.
+    typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    uses: Vec<DeprecatedUse>,
+}
+
+impl<'tcx> DeprecatedUseVisitor<'tcx> {
+    fn check(&mut self, def_id: DefId, hir_id: HirId, span: Span) {
+        let tcx = self.tcx;
+        // The constructors have the deprecation of their struct or variant.
+        let def_id = match tcx.def_kind(def_id) {
+            DefKind::Ctor(..) => tcx.parent(def_id),
+            _ => def_id,
+        };
+        let Some(entry) = tcx.lookup_deprecation_entry(def_id) else { return };
+        let used_in = hir_id.owner.def_id;
+        if tcx
+            .lookup_deprecation_entry(used_in.to_def_id())
+            .map_or(false, |used_in_entry| used_in_entry.same_origin(&entry))
+        {
+            return;
+        }
+        self.uses.push(DeprecatedUse {
+            name: qualified_name(tcx, def_id),
+            deprecation: ApiDeprecation {
+                since: entry.attr.since.map(|since| since.to_string()),
+                note: entry.attr.note.map(|note| note.to_string()),
+            },
+            used_in: qualified_name(tcx, used_in.to_def_id()),
+            location: location_string(tcx, span),
+        });
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for DeprecatedUseVisitor<'tcx> {
+    // `visit_all_item_likes_in_crate` already goes through all the items.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let old_typeck_results = self.typeck_results.replace(self.tcx.typeck_body(body_id));
+        self.visit_body(self.tcx.hir().body(body_id));
+        self.typeck_results = old_typeck_results;
+    }
+
+    fn visit_qpath(&mut self, qpath: &'tcx QPath<'tcx>, id: HirId, _span: Span) {
+        // The type-relative paths (like `Type::method`) can only be resolved in the bodies.
+        let res = match (qpath, self.typeck_results) {
+            (_, Some(typeck_results)) => typeck_results.qpath_res(qpath, id),
+            (QPath::Resolved(_, path), None) => path.res,
+            _ => Res::Err,
+        };
+        if let Res::Def(_, def_id) = res {
+            self.check(def_id, id, qpath.span());
+        }
+        intravisit::walk_qpath(self, qpath, id);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(segment, ..) = expr.kind {
+            let def_id = self
+                .typeck_results
+                .and_then(|typeck_results| typeck_results.type_dependent_def_id(expr.hir_id));
+            if let Some(def_id) = def_id {
+                self.check(def_id, expr.hir_id, segment.ident.span);
+            }
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,552 @@
+//! Structured diagnostics emitted by the compiler and helpers to emit your own.
+
+use rustc_data_structures::sync::Lrc;
+use rustc_error_messages::FluentArgs;
+use rustc_errors::emitter::{Emitter, EmitterWriter};
+use rustc_errors::translation::{to_fluent_args, Translate};
+use rustc_errors::{
+    Applicability as RustcApplicability, DiagnosticArgValue, DiagnosticId, DiagnosticMessage,
+    FluentBundle, LazyFallbackBundle, Level as RustcLevel, MultiSpan, TRACK_DIAGNOSTICS,
+};
+use rustc_middle::ty::TyCtxt;
+use rustc_span::edition::{Edition, ALL_EDITIONS};
+use rustc_span::source_map::SourceMap;
+use rustc_span::{Span, Symbol};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::cell::{Cell, RefCell};
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::{DiagnosticsBuffer, LimitKind};
+
+/// Level of a [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Level {
+    /// Also used for fatal errors and compiler bugs.
+    Error,
+    Warning,
+    Note,
+    Help,
+    FailureNote,
+}
+
+impl Level {
+    fn new(level: RustcLevel) -> Self {
+        match level {
+            RustcLevel::Bug
+            | RustcLevel::DelayedBug
+            | RustcLevel::Fatal
+            | RustcLevel::Error { .. } => Self::Error,
+            RustcLevel::Warning(_) | RustcLevel::Allow | RustcLevel::Expect(_) => Self::Warning,
+            RustcLevel::Note | RustcLevel::OnceNote => Self::Note,
+            RustcLevel::Help => Self::Help,
+            RustcLevel::FailureNote => Self::FailureNote,
+        }
+    }
+}
+
+/// Same as the compiler's [`Applicability`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_errors/enum.Applicability.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended and can be applied automatically.
+    MachineApplicable,
+    /// The suggestion may be what the user intended but it is uncertain.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders like `(...)` or `{ /* fields */ }`.
+    HasPlaceholders,
+    Unspecified,
+}
+
+impl From<RustcApplicability> for Applicability {
+    fn from(applicability: RustcApplicability) -> Self {
+        match applicability {
+            RustcApplicability::MachineApplicable => Self::MachineApplicable,
+            RustcApplicability::MaybeIncorrect => Self::MaybeIncorrect,
+            RustcApplicability::HasPlaceholders => Self::HasPlaceholders,
+            RustcApplicability::Unspecified => Self::Unspecified,
+        }
+    }
+}
+
+impl From<Applicability> for RustcApplicability {
+    fn from(applicability: Applicability) -> Self {
+        match applicability {
+            Applicability::MachineApplicable => Self::MachineApplicable,
+            Applicability::MaybeIncorrect => Self::MaybeIncorrect,
+            Applicability::HasPlaceholders => Self::HasPlaceholders,
+            Applicability::Unspecified => Self::Unspecified,
+        }
+    }
+}
+
+/// Location of a span in its source file. Lines and columns start at 1, columns are counted in
+/// characters and byte offsets are relative to the beginning of the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    pub is_primary: bool,
+    pub label: Option<String>,
+}
+
+impl DiagnosticSpan {
+    fn new(source_map: &SourceMap, span: Span, is_primary: bool, label: Option<String>) -> Self {
+        let start = source_map.lookup_char_pos(span.lo());
+        let end = source_map.lookup_char_pos(span.hi());
+        Self {
+            file_name: start.file.name.prefer_local().to_string(),
+            byte_start: (span.lo() - start.file.start_pos).0 as usize,
+            byte_end: (span.hi() - start.file.start_pos).0 as usize,
+            line_start: start.line,
+            line_end: end.line,
+            column_start: start.col.0 + 1,
+            column_end: end.col.0 + 1,
+            is_primary,
+            label,
+        }
+    }
+}
+
+/// A part of a [`Suggestion`]: the code behind `span` should be replaced with `replacement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SuggestionPart {
+    pub span: DiagnosticSpan,
+    pub replacement: String,
+}
+
+/// A code suggestion. All its `parts` must be applied together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Suggestion {
+    pub message: String,
+    pub applicability: Applicability,
+    pub parts: Vec<SuggestionPart>,
+}
+
+/// A note or a help attached to a [`Diagnostic`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SubDiagnostic {
+    pub level: Level,
+    pub message: String,
+    pub spans: Vec<DiagnosticSpan>,
+}
+
+/// A diagnostic emitted by the compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    /// Error code (like `E0425`) or lint name (like `dead_code`).
+    pub code: Option<String>,
+    pub spans: Vec<DiagnosticSpan>,
+    pub children: Vec<SubDiagnostic>,
+    pub suggestions: Vec<Suggestion>,
+    /// The diagnostic as it would have been displayed by the compiler.
+    pub rendered: String,
+}
+
+impl Diagnostic {
+    /// Returns the first primary span of the diagnostic.
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+}
+
+/// Collects the diagnostics emitted by the compiler. It can be cloned so you can keep a handle on
+/// it while it's used by the compiler.
+#[derive(Clone, Default)]
+pub struct DiagnosticsCollector(Arc<Mutex<Vec<Diagnostic>>>);
+
+impl DiagnosticsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the collected diagnostics and empties the collector.
+    pub fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// Emits a warning pointing to `span` with the handler of the compiler, so it's rendered like the
+/// compiler's own warnings and respects the `--error-format` and `--cap-lints` options, or is
+/// collected if the diagnostics are collected. `code` is displayed like an error code (for
+/// example `warning[W001]: ...`).
+pub fn emit_warning(tcx: TyCtxt<'_>, span: Span, code: Option<&str>, message: &str) {
+    let mut diag = tcx.sess.struct_span_warn(span, message);
+    if let Some(code) = code {
+        diag.code(DiagnosticId::Error(code.to_owned()));
+    }
+    diag.emit();
+}
+
+/// Same as [`emit_warning`] but for an error. Note that the errors emitted from the callback
+/// don't make it fail since the compilation is already over, you need to handle them yourself.
+pub fn emit_error(tcx: TyCtxt<'_>, span: Span, code: Option<&str>, message: &str) {
+    let mut diag = tcx.sess.struct_span_err(span, message);
+    if let Some(code) = code {
+        diag.code(DiagnosticId::Error(code.to_owned()));
+    }
+    diag.emit();
+}
+
+/// Same as [`emit_warning`] with a suggestion to replace the code behind `span` with
+/// `replacement`. Tools like `rustfix` (or [`Suggestion`] if the diagnostics are collected) will
+/// know if they can apply it automatically thanks to `applicability`.
+pub fn emit_with_suggestion(
+    tcx: TyCtxt<'_>,
+    span: Span,
+    message: &str,
+    replacement: &str,
+    applicability: Applicability,
+) {
+    tcx.sess
+        .struct_span_warn(span, message)
+        .span_suggestion(span, "try", replacement, applicability.into())
+        .emit();
+}
+
+/// Emitter converting the compiler diagnostics into [`Diagnostic`] and storing them into a
+/// [`DiagnosticsCollector`].
+pub(crate) struct CollectingEmitter {
+    pub(crate) source_map: Option<Lrc<SourceMap>>,
+    pub(crate) fallback_bundle: LazyFallbackBundle,
+    pub(crate) collector: DiagnosticsCollector,
+    /// If set, the rendered diagnostics are also written into it.
+    pub(crate) buffer: Option<DiagnosticsBuffer>,
+    pub(crate) short_message: bool,
+    pub(crate) diagnostic_width: Option<usize>,
+}
+
+impl CollectingEmitter {
+    fn convert_spans(&self, span: &MultiSpan, args: &FluentArgs<'_>) -> Vec<DiagnosticSpan> {
+        let Some(source_map) = &self.source_map else { return Vec::new() };
+        span.span_labels()
+            .into_iter()
+            .filter(|label| !label.span.is_dummy())
+            .map(|label| {
+                let text = label
+                    .label
+                    .map(|msg| self.translate_message(&msg, args).unwrap().into_owned());
+                DiagnosticSpan::new(source_map, label.span, label.is_primary, text)
+            })
+            .collect()
+    }
+
+    fn render(&self, diag: &rustc_errors::Diagnostic) -> String {
+        render(
+            diag,
+            self.source_map.clone(),
+            self.fallback_bundle.clone(),
+            self.short_message,
+            self.diagnostic_width,
+        )
+    }
+}
+
+impl Translate for CollectingEmitter {
+    fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
+        None
+    }
+
+    fn fallback_fluent_bundle(&self) -> &FluentBundle {
+        &self.fallback_bundle
+    }
+}
+
+impl Emitter for CollectingEmitter {
+    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
+        self.source_map.as_ref()
+    }
+
+    fn emit_diagnostic(&mut self, diag: &rustc_errors::Diagnostic) {
+        let args = to_fluent_args(diag.args());
+        let suggestions = diag
+            .suggestions
+            .iter()
+            .flatten()
+            .flat_map(|sugg| {
+                let message = self
+                    .translate_message(&sugg.msg, &args)
+                    .unwrap()
+                    .into_owned();
+                sugg.substitutions
+                    .iter()
+                    .map(move |sub| (message.clone(), sugg, sub))
+            })
+            .filter_map(|(message, sugg, sub)| {
+                let source_map = self.source_map.as_ref()?;
+                Some(Suggestion {
+                    message,
+                    applicability: sugg.applicability.into(),
+                    parts: sub
+                        .parts
+                        .iter()
+                        .map(|part| SuggestionPart {
+                            span: DiagnosticSpan::new(source_map, part.span, true, None),
+                            replacement: part.snippet.clone(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        let children = diag
+            .children
+            .iter()
+            .map(|child| SubDiagnostic {
+                level: Level::new(child.level),
+                message: self.translate_messages(&child.message, &args).into_owned(),
+                spans: self.convert_spans(&child.span, &args),
+            })
+            .collect();
+        let rendered = self.render(diag);
+        if let Some(buffer) = &mut self.buffer {
+            let _ = buffer.write_all(rendered.as_bytes());
+        }
+        let diagnostic = Diagnostic {
+            level: Level::new(diag.level()),
+            message: self.translate_messages(&diag.message, &args).into_owned(),
+            code: diag.code.as_ref().map(|code| match code {
+                DiagnosticId::Error(code) => code.clone(),
+                DiagnosticId::Lint { name, .. } => name.clone(),
+            }),
+            spans: self.convert_spans(&diag.span, &args),
+            children,
+            suggestions,
+            rendered,
+        };
+        self.collector.0.lock().unwrap().push(diagnostic);
+    }
+}
+
+/// Returns `diag` as it would have been displayed by the compiler.
+fn render(
+    diag: &rustc_errors::Diagnostic,
+    source_map: Option<Lrc<SourceMap>>,
+    fallback_bundle: LazyFallbackBundle,
+    short_message: bool,
+    diagnostic_width: Option<usize>,
+) -> String {
+    let buffer = DiagnosticsBuffer::new();
+    EmitterWriter::new(
+        Box::new(buffer.clone()),
+        source_map,
+        None,
+        fallback_bundle,
+        short_message,
+        false,
+        false,
+        diagnostic_width,
+        false,
+        false,
+    )
+    .emit_diagnostic(diag);
+    buffer.take()
+}
+
+thread_local! {
+    /// The errors captured by [`capture_errors`], `None` when they're not being captured.
+    static CAPTURED_ERRORS: RefCell<Option<Vec<String>>> = RefCell::new(None);
+}
+
+/// Calls `f` and returns the errors the compiler emitted in the meantime, rendered as they would
+/// have been displayed. They're still emitted and counted by the handler of the session, since the
+/// compiler expects the errors it reports to be counted.
+pub(crate) fn capture_errors<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let previous = CAPTURED_ERRORS.with(|captured| captured.replace(Some(Vec::new())));
+    let res = f();
+    let errors = CAPTURED_ERRORS.with(|captured| captured.replace(previous));
+    (res, errors.unwrap_or_default())
+}
+
+thread_local! {
+    /// The crate of the sysroot (`std` or `core`) the compiler couldn't find.
+    static MISSING_SYSROOT_CRATE: Cell<Option<&'static str>> = Cell::new(None);
+}
+
+/// Returns `std` or `core` if the compiler emitted an error because it couldn't find it (on the
+/// current thread).
+pub(crate) fn take_missing_sysroot_crate() -> Option<&'static str> {
+    MISSING_SYSROOT_CRATE.with(Cell::take)
+}
+
+thread_local! {
+    /// The first limit the compiler reached, with the span where it was reached.
+    static EXCEEDED_LIMIT: RefCell<Option<(LimitKind, Option<DiagnosticSpan>)>> =
+        RefCell::new(None);
+}
+
+/// Returns the limit the compiler reached (on the current thread) if it emitted an error because
+/// of it, with the span of the error.
+pub(crate) fn take_exceeded_limit() -> Option<(LimitKind, Option<DiagnosticSpan>)> {
+    EXCEEDED_LIMIT.with(RefCell::take)
+}
+
+/// Returns the limit `diag` is about, if it's the error emitted when a limit is reached.
+fn exceeded_limit(diag: &rustc_errors::Diagnostic) -> Option<LimitKind> {
+    if !diag.is_error() {
+        return None;
+    }
+    let code = match &diag.code {
+        Some(DiagnosticId::Error(code)) => code.as_str(),
+        _ => "",
+    };
+    let slug = match diag.message.first() {
+        Some((DiagnosticMessage::FluentIdentifier(slug, _), _)) => slug.as_ref(),
+        _ => "",
+    };
+    match (code, slug) {
+        // `E0055` is the auto-dereferencing and `E0275` the trait resolution.
+        ("E0055" | "E0275", _)
+        | (
+            _,
+            "expand_recursion_limit_reached"
+            | "query_system_query_overflow"
+            | "monomorphize_recursion_limit",
+        ) => Some(LimitKind::Recursion),
+        (_, "monomorphize_type_length_limit") => Some(LimitKind::TypeLength),
+        _ => None,
+    }
+}
+
+/// The hook called by the compiler before emitting a diagnostic, see [`track_diagnostics`].
+type TrackDiagnostic =
+    fn(&mut rustc_errors::Diagnostic, &mut dyn FnMut(&mut rustc_errors::Diagnostic));
+
+/// The hook set up by the compiler, which [`track_diagnostic`] calls once it's done.
+static RUSTC_TRACK_DIAGNOSTIC: OnceLock<TrackDiagnostic> = OnceLock::new();
+
+/// What [`track_diagnostic`] needs to know about the compilation running on its thread.
+struct Tracking {
+    source_map: Lrc<SourceMap>,
+    fallback_bundle: LazyFallbackBundle,
+}
+
+impl Translate for Tracking {
+    fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
+        None
+    }
+
+    fn fallback_fluent_bundle(&self) -> &FluentBundle {
+        &self.fallback_bundle
+    }
+}
+
+thread_local! {
+    static TRACKING: RefCell<Option<Tracking>> = RefCell::new(None);
+}
+
+/// Looks at the diagnostics of the compilation running on the current thread, so the errors can
+/// be captured with [`capture_errors`], the missing sysroot crates are recorded for
+/// [`take_missing_sysroot_crate`] and the limits reached for [`take_exceeded_limit`]. The
+/// handler of the session and its emitter are left as they are.
+///
+/// The compiler sets up its own hook each time it starts, so it must be called at the beginning
+/// of each compilation.
+pub(crate) fn track_diagnostics(source_map: Lrc<SourceMap>) {
+    TRACKING.with(|tracking| {
+        *tracking.borrow_mut() = Some(Tracking {
+            source_map,
+            fallback_bundle: rustc_errors::fallback_fluent_bundle(
+                rustc_errors::DEFAULT_LOCALE_RESOURCES,
+                false,
+            ),
+        })
+    });
+    let rustc_track_diagnostic = TRACK_DIAGNOSTICS.swap(&(track_diagnostic as TrackDiagnostic));
+    RUSTC_TRACK_DIAGNOSTIC.get_or_init(|| *rustc_track_diagnostic);
+}
+
+/// If `diag` is about a keyword of a newer edition used as an identifier (like `async` since the
+/// 2018 edition), returns a note giving the edition of the code, since it's often chosen with the
+/// API rather than written next to the code.
+fn edition_keyword_note(
+    translator: &impl Translate,
+    diag: &rustc_errors::Diagnostic,
+) -> Option<String> {
+    let message = translator.translate_messages(&diag.message, &to_fluent_args(diag.args()));
+    let (_, rest) = message.split_once("keyword `")?;
+    let (keyword, _) = rest.split_once('`')?;
+    let keyword = Symbol::intern(keyword);
+    let edition = diag.span.primary_span()?.edition();
+    let since = ALL_EDITIONS
+        .iter()
+        .copied()
+        .find(|edition| keyword.is_reserved(|| *edition))?;
+    if since == Edition::Edition2015 || edition < since {
+        return None;
+    }
+    Some(format!(
+        "`{keyword}` is a keyword since the {since} edition and this code is compiled with the \
+         {edition} edition"
+    ))
+}
+
+/// The hook installed by [`track_diagnostics`]. `emit` emits the diagnostic and counts it.
+fn track_diagnostic(
+    diag: &mut rustc_errors::Diagnostic,
+    emit: &mut dyn FnMut(&mut rustc_errors::Diagnostic),
+) {
+    TRACKING.with(|tracking| {
+        let tracking = tracking.borrow();
+        let Some(tracking) = &*tracking else { return };
+        if diag.code == Some(DiagnosticId::Error("E0463".to_owned())) {
+            let missing = diag.args().find_map(|(name, value)| match value {
+                DiagnosticArgValue::Str(krate) if name == "crate_name" => {
+                    ["std", "core"].into_iter().find(|name| krate == name)
+                }
+                _ => None,
+            });
+            if missing.is_some() {
+                MISSING_SYSROOT_CRATE.with(|krate| krate.set(missing));
+            }
+        }
+        if let Some(which) = exceeded_limit(diag) {
+            EXCEEDED_LIMIT.with(|exceeded| {
+                let mut exceeded = exceeded.borrow_mut();
+                if exceeded.is_none() {
+                    let span = diag
+                        .span
+                        .primary_span()
+                        .filter(|span| !span.is_dummy())
+                        .map(|span| DiagnosticSpan::new(&tracking.source_map, span, true, None));
+                    *exceeded = Some((which, span));
+                }
+            });
+        }
+        if let Some(note) = edition_keyword_note(tracking, diag) {
+            diag.note(&note);
+        }
+        if diag.is_error() {
+            CAPTURED_ERRORS.with(|captured| {
+                if let Some(errors) = &mut *captured.borrow_mut() {
+                    errors.push(render(
+                        diag,
+                        Some(Lrc::clone(&tracking.source_map)),
+                        tracking.fallback_bundle.clone(),
+                        false,
+                        None,
+                    ));
+                }
+            });
+        }
+    });
+    match RUSTC_TRACK_DIAGNOSTIC.get() {
+        Some(rustc_track_diagnostic) => rustc_track_diagnostic(diag, emit),
+        None => emit(diag),
+    }
+}
@@ -0,0 +1,718 @@
+//! Helpers to build an index of the documentation of the local crate, like the JSON output of
+//! rustdoc, with the intra-doc links (like ``[`Foo::bar`]``) resolved.
+
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::def::{DefKind, Namespace, Res};
+use rustc_hir::def_id::{DefId, CRATE_DEF_ID, LOCAL_CRATE};
+use rustc_hir::{ItemKind, PrimTy, UseKind};
+use rustc_middle::ty::{DefIdTree, TyCtxt};
+use rustc_span::hygiene::MacroKind;
+use rustc_span::symbol::{sym, Symbol};
+use rustc_span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::api::{module_children, public_items};
+use crate::attrs::{doc_comment, doc_lines};
+use crate::doctests::Fence;
+use crate::paths::qualified_name;
+use crate::spans::location_string;
+
+/// Returned by [`doc_index`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocIndex {
+    pub crate_name: String,
+    /// Sorted by path, the crate root being the first one.
+    pub items: Vec<DocItem>,
+    /// The intra-doc links which couldn't be resolved, in the order of the items.
+    pub broken_links: Vec<BrokenLink>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocItem {
+    /// The paths through which the item can be used from other crates (see
+    /// [`ApiItem::path`](crate::api::ApiItem::path)), sorted. The path of the crate root is the
+    /// name of the crate.
+    pub paths: Vec<String>,
+    /// The path of the item where it's defined, as returned by [`qualified_name`].
+    pub definition: String,
+    /// The kind of item as returned by `DefKind::descr`, like `"function"` or `"struct"`.
+    pub kind: String,
+    /// The documentation as returned by [`doc_comment`], `None` if the item isn't documented.
+    pub docs: Option<String>,
+    /// The intra-doc links of `docs` which were resolved, in the order in which they're written.
+    pub links: Vec<DocLink>,
+    /// Where the item is defined, like `src/lib.rs:12:1`.
+    pub location: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocLink {
+    /// The destination of the link as written in the documentation, like `` `Foo::bar` `` for
+    /// ``[`Foo::bar`]`` or `Vec` for `[vectors](Vec)`.
+    pub link: String,
+    pub target: LinkTarget,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// An item of the local crate, with the first of its [`DocItem::paths`] if it's public.
+    Local {
+        definition: String,
+        path: Option<String>,
+    },
+    /// An item of another crate.
+    External {
+        crate_name: String,
+        definition: String,
+    },
+    /// A primitive type (like `u32`) or one of its associated items (like `u32::MAX`), which
+    /// isn't checked.
+    Primitive { path: String },
+}
+
+/// An intra-doc link which couldn't be resolved, returned in [`DocIndex::broken_links`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// The [`DocItem::definition`] of the item whose documentation contains the link.
+    pub item: String,
+    /// See [`DocLink::link`].
+    pub link: String,
+    /// Why the link couldn't be resolved, like ``no item named `Foo` in scope``.
+    pub reason: String,
+    /// The location of the line of the documentation containing the link, like
+    /// `src/lib.rs:12:1`.
+    pub location: String,
+}
+
+/// Returns the documentation of the crate root and of the items of its public API (see
+/// [`public_api`](crate::api::public_api)), except the ones with `#[doc(hidden)]` and the items
+/// of other crates which are re-exported.
+///
+/// Like with rustdoc, the intra-doc links are resolved from the module of the item (or from the
+/// module itself for the modules), through the items of the module and its imports, the extern
+/// crates, the prelude and the primitive types. The paths can start with `crate`, `self`, `super`
+/// or `Self` (in the documentation of a type, a trait or their associated items), and go through
+/// the fields, the variants and the associated items of the types and the traits. The
+/// disambiguators (like `struct@Foo`, `foo()` or `foo!`) and the generic arguments (like
+/// `Vec<T>`) are supported.
+///
+/// The links which don't look like paths (like `[1, 2]`) aren't intra-doc links, and neither are
+/// the ones in code blocks. Unlike rustdoc, a link must be written on a single line.
+pub fn doc_index(tcx: TyCtxt<'_>) -> DocIndex {
+    let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+    let mut paths = FxHashMap::<_, Vec<_>>::default();
+    paths.insert(CRATE_DEF_ID.to_def_id(), vec![crate_name.clone()]);
+    for item in public_items(tcx) {
+        if item.def_id.is_local() && !item.doc_hidden {
+            paths.entry(item.def_id).or_default().push(item.path);
+        }
+    }
+    for item_paths in paths.values_mut() {
+        item_paths.sort();
+        item_paths.dedup();
+    }
+    let mut def_ids = paths.keys().copied().collect::<Vec<_>>();
+    def_ids.sort_by(|a, b| paths[a].cmp(&paths[b]));
+
+    let resolver = LinkResolver::new(tcx);
+    let mut items = Vec::new();
+    let mut broken_links = Vec::new();
+    for def_id in def_ids {
+        let definition = qualified_name(tcx, def_id);
+        let mut links = Vec::new();
+        for raw_link in intra_doc_links(&doc_lines(tcx, def_id).unwrap_or_default()) {
+            match resolver.resolve(def_id, &raw_link) {
+                Ok(target) => links.push(DocLink {
+                    link: raw_link.link,
+                    target: match target {
+                        Target::Def(target_id) if target_id.is_local() => LinkTarget::Local {
+                            definition: qualified_name(tcx, target_id),
+                            path: paths.get(&target_id).map(|paths| paths[0].clone()),
+                        },
+                        Target::Def(target_id) => LinkTarget::External {
+                            crate_name: tcx.crate_name(target_id.krate).to_string(),
+                            definition: qualified_name(tcx, target_id),
+                        },
+                        Target::Primitive => LinkTarget::Primitive {
+                            path: raw_link.path,
+                        },
+                    },
+                }),
+                Err(reason) => broken_links.push(BrokenLink {
+                    item: definition.clone(),
+                    link: raw_link.link,
+                    reason,
+                    location: location_string(tcx, raw_link.span),
+                }),
+            }
+        }
+        items.push(DocItem {
+            paths: paths[&def_id].clone(),
+            definition,
+            kind: tcx.def_kind(def_id).descr(def_id).to_owned(),
+            docs: doc_comment(tcx, def_id),
+            links,
+            location: location_string(tcx, tcx.def_span(def_id)),
+        });
+    }
+    DocIndex {
+        crate_name,
+        items,
+        broken_links,
+    }
+}
+
+/// An intra-doc link found by [`intra_doc_links`].
+struct RawLink {
+    /// See [`DocLink::link`].
+    link: String,
+    /// The path to resolve, without the backticks, the disambiguator, the generic arguments and
+    /// the URL fragment (like `#method.new`).
+    path: String,
+    /// The disambiguator written before the path (like `struct` for `struct@Foo`), or `fn` and
+    /// `macro` for the suffixes `()` and `!`.
+    disambiguator: Option<String>,
+    /// The span of the documentation attribute containing the link.
+    span: Span,
+}
+
+/// Returns the intra-doc links of the documentation `lines`, as returned by [`doc_lines`].
+fn intra_doc_links(lines: &[(String, Span)]) -> Vec<RawLink> {
+    // The reference definitions (`[label]: destination`) and the lines which aren't code.
+    let mut definitions = HashMap::new();
+    let mut text_lines = Vec::new();
+    let mut previous_blank = true;
+    let mut lines = lines.iter();
+    while let Some((line, span)) = lines.next() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if let Some(fence) = Fence::parse(line) {
+            for (line, _) in lines.by_ref() {
+                if fence.is_closed_by(line) {
+                    break;
+                }
+            }
+        } else if indent >= 4 && previous_blank {
+            // An indented code block, `previous_blank` stays `true` until its end.
+            continue;
+        } else if let Some((label, destination)) = reference_definition(line) {
+            definitions
+                .entry(normalize_label(label))
+                .or_insert(destination);
+        } else {
+            text_lines.push((line.as_str(), *span));
+        }
+        previous_blank = line.trim().is_empty();
+    }
+
+    let mut links = Vec::new();
+    for (line, span) in text_lines {
+        for (destination, is_explicit) in links_in_line(line, &definitions) {
+            let Some((path, disambiguator)) = link_path(destination, is_explicit) else {
+                continue;
+            };
+            links.push(RawLink {
+                link: destination.to_owned(),
+                path,
+                disambiguator,
+                span,
+            });
+        }
+    }
+    links
+}
+
+/// Parses a reference definition, like `[label]: destination "title"`.
+fn reference_definition(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(' ').unwrap_or(line);
+    let rest = rest.strip_prefix("  ").unwrap_or(rest).strip_prefix('[')?;
+    let (label, rest) = rest.split_once("]:")?;
+    let destination = rest.split_whitespace().next()?;
+    let destination = destination
+        .strip_prefix('<')
+        .and_then(|destination| destination.strip_suffix('>'))
+        .unwrap_or(destination);
+    (!label.is_empty()).then_some((label, destination))
+}
+
+/// The labels of the links are case-insensitive and their whitespace is collapsed.
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Returns the destinations of the links of `line` along with whether they're explicit (like
+/// `[text](destination)` or a reference defined in `definitions`) or not (like `[destination]`
+/// without a definition).
+fn links_in_line<'a>(
+    line: &'a str,
+    definitions: &HashMap<String, &'a str>,
+) -> Vec<(&'a str, bool)> {
+    let bytes = line.as_bytes();
+    let mut links = Vec::new();
+    let mut opening_brackets = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'`' => {
+                // The brackets of the code spans aren't links.
+                let len = line[i..].len() - line[i..].trim_start_matches('`').len();
+                let closing = "`".repeat(len);
+                let mut end = i + len;
+                let mut closed = false;
+                while let Some(offset) = line[end..].find(&closing) {
+                    let start = end + offset;
+                    end = start + line[start..].len() - line[start..].trim_start_matches('`').len();
+                    if end - start == len {
+                        closed = true;
+                        break;
+                    }
+                }
+                i = if closed { end } else { i + len };
+                continue;
+            }
+            b'[' => opening_brackets.push(i),
+            b']' => {
+                let Some(start) = opening_brackets.pop() else {
+                    i += 1;
+                    continue;
+                };
+                let text = &line[start + 1..i];
+                let rest = &line[i + 1..];
+                let definition = |label: &str| definitions.get(&normalize_label(label)).copied();
+                let mut link = None;
+                if let Some(inside) = rest.strip_prefix('(') {
+                    if let Some(end) = closing_parenthesis(inside) {
+                        let destination = inside[..end].split_whitespace().next().unwrap_or("");
+                        let destination = destination
+                            .strip_prefix('<')
+                            .and_then(|destination| destination.strip_suffix('>'))
+                            .unwrap_or(destination);
+                        link = Some((destination, true));
+                        i += end + 2;
+                    }
+                } else if let Some(inside) = rest.strip_prefix('[') {
+                    if let Some(end) = inside.find(']') {
+                        // `[text][]` is the same as `[text]`.
+                        let label = if end == 0 { text } else { &inside[..end] };
+                        link = Some(definition(label).map_or((label, false), |d| (d, true)));
+                        i += end + 2;
+                    }
+                }
+                let link = link.unwrap_or_else(|| {
+                    definition(text).map_or((text, false), |destination| (destination, true))
+                });
+                let is_image = start > 0 && bytes[start - 1] == b'!';
+                let is_footnote = text.starts_with('^');
+                if !is_image && !is_footnote {
+                    links.push(link);
+                }
+                // The links can't contain other links.
+                opening_brackets.clear();
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    links
+}
+
+/// Returns the position of the parenthesis closing the destination of an inline link.
+fn closing_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the path of the item a link points to with its disambiguator, or `None` if it isn't
+/// an intra-doc link (like a URL or some text between brackets).
+fn link_path(destination: &str, is_explicit: bool) -> Option<(String, Option<String>)> {
+    // The URLs, and the explicit links to a part of the current page (like `#examples`).
+    if is_explicit && (destination.contains("//") || destination.starts_with('#')) {
+        return None;
+    }
+    let mut path = destination.split('#').next()?.trim().trim_matches('`');
+    let mut disambiguator = None;
+    if let Some((prefix, rest)) = path.split_once('@') {
+        disambiguator = Some(prefix.to_owned());
+        path = rest;
+    }
+    for (suffix, kind) in [
+        ("()", "fn"),
+        ("!()", "macro"),
+        ("![]", "macro"),
+        ("!{}", "macro"),
+        ("!", "macro"),
+    ] {
+        if let Some(rest) = path.strip_suffix(suffix) {
+            disambiguator.get_or_insert_with(|| kind.to_owned());
+            path = rest;
+            break;
+        }
+    }
+
+    let mut stripped = String::new();
+    let mut depth = 0;
+    for c in path.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    let is_path = !stripped.is_empty()
+        && stripped
+            .strip_prefix("::")
+            .unwrap_or(&stripped)
+            .split("::")
+            .all(|segment| {
+                segment.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                    && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+            });
+    is_path.then_some((stripped, disambiguator))
+}
+
+/// What a link points to.
+enum Target {
+    Def(DefId),
+    Primitive,
+}
+
+struct LinkResolver<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// The module imported by the prelude, like `std::prelude::rust_2021`.
+    prelude: Option<DefId>,
+}
+
+impl<'tcx> LinkResolver<'tcx> {
+    fn new(tcx: TyCtxt<'tcx>) -> Self {
+        let hir = tcx.hir();
+        // The glob import of the prelude is generated at the root of the crate.
+        let prelude = hir
+            .get_module(CRATE_DEF_ID)
+            .0
+            .item_ids
+            .iter()
+            .map(|&item_id| hir.item(item_id))
+            .filter(|item| item.span.from_expansion())
+            .find_map(|item| match item.kind {
+                // The resolution of the glob import itself is `Res::Err`.
+                ItemKind::Use(path, UseKind::Glob) => match path.segments.last()?.res {
+                    Res::Def(DefKind::Mod, def_id) => Some(def_id),
+                    _ => None,
+                },
+                _ => None,
+            });
+        Self { tcx, prelude }
+    }
+
+    /// Resolves `link`, written in the documentation of `item`.
+    fn resolve(&self, item: DefId, link: &RawLink) -> Result<Target, String> {
+        let tcx = self.tcx;
+        let scope = if tcx.def_kind(item) == DefKind::Mod {
+            item
+        } else {
+            let hir_id = tcx.hir().local_def_id_to_hir_id(item.expect_local());
+            tcx.parent_module(hir_id).to_def_id()
+        };
+
+        if let Some(disambiguator) = &link.disambiguator {
+            if !DISAMBIGUATORS.contains(&disambiguator.as_str()) {
+                return Err(format!("unknown disambiguator `{disambiguator}`"));
+            }
+        }
+
+        let mut segments = link.path.split("::").map(Symbol::intern);
+        let first = segments.next().unwrap();
+        let mut candidates = match first.as_str() {
+            "crate" => vec![CRATE_DEF_ID.to_def_id()],
+            "self" => vec![scope],
+            "super" => vec![tcx
+                .opt_parent(scope)
+                .ok_or("`super` can't be used at the root of the crate")?],
+            "Self" => vec![self
+                .self_type(item)
+                .ok_or("`Self` can only be used in the documentation of a type or a trait")?],
+            // A global path like `::std::vec::Vec`.
+            "" => {
+                let name = segments.next().unwrap();
+                vec![self
+                    .extern_crate(name)
+                    .ok_or_else(|| format!("no crate named `{name}`"))?]
+            }
+            _ => {
+                let candidates = self.names_in_scope(scope, first);
+                if candidates.is_empty() {
+                    return if PrimTy::from_name(first).is_some()
+                        && matches!(
+                            link.disambiguator.as_deref(),
+                            None | Some("prim" | "primitive")
+                        ) {
+                        Ok(Target::Primitive)
+                    } else {
+                        Err(format!("no item named `{first}` in scope"))
+                    };
+                }
+                candidates
+            }
+        };
+
+        for name in segments {
+            let parent = candidates
+                .iter()
+                .copied()
+                .find(|&def_id| tcx.def_kind(def_id).ns() == Some(Namespace::TypeNS))
+                .ok_or_else(|| {
+                    format!(
+                        "`{}` doesn't have items",
+                        qualified_name(tcx, candidates[0])
+                    )
+                })?;
+            candidates = self.children(parent, name);
+            if candidates.is_empty() {
+                return Err(format!(
+                    "no item named `{}` in `{}`",
+                    name,
+                    qualified_name(tcx, parent)
+                ));
+            }
+        }
+
+        let mut found = FxHashSet::default();
+        candidates.retain(|&def_id| found.insert(def_id));
+        let described = |def_id: DefId| {
+            let kind = tcx.def_kind(def_id);
+            format!("{} {}", kind.article(), kind.descr(def_id))
+        };
+        if let Some(disambiguator) = &link.disambiguator {
+            let unfiltered = candidates.clone();
+            candidates.retain(|&def_id| matches_disambiguator(tcx.def_kind(def_id), disambiguator));
+            if candidates.is_empty() {
+                return Err(match unfiltered[..] {
+                    [def_id] => format!(
+                        "`{}` is {}, not a `{}`",
+                        link.path,
+                        described(def_id),
+                        disambiguator
+                    ),
+                    _ => format!("no `{}` named `{}`", disambiguator, link.path),
+                });
+            }
+        }
+        // Several items of the same namespace can be found if a type has an inherent method and a
+        // trait method with the same name, the inherent one being the first.
+        let first = candidates[0];
+        if let Some(&other) = candidates
+            .iter()
+            .find(|&&def_id| tcx.def_kind(def_id).ns() != tcx.def_kind(first).ns())
+        {
+            return Err(format!(
+                "`{}` is ambiguous: it's both {} and {}",
+                link.path,
+                described(first),
+                described(other)
+            ));
+        }
+        Ok(Target::Def(first))
+    }
+
+    /// Returns the items named `name` which can be used in the module `scope`.
+    fn names_in_scope(&self, scope: DefId, name: Symbol) -> Vec<DefId> {
+        let named = |children: Vec<(Symbol, DefId)>| {
+            children
+                .into_iter()
+                .filter(|(child_name, _)| *child_name == name)
+                .map(|(_, def_id)| def_id)
+                .collect::<Vec<_>>()
+        };
+        let candidates = named(module_children(
+            self.tcx,
+            scope,
+            false,
+            &mut FxHashSet::default(),
+        ));
+        if !candidates.is_empty() {
+            return candidates;
+        }
+        if let Some(krate) = self.extern_crate(name) {
+            return vec![krate];
+        }
+        self.prelude
+            .map(|prelude| {
+                named(module_children(
+                    self.tcx,
+                    prelude,
+                    true,
+                    &mut FxHashSet::default(),
+                ))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the root of the crate named `name` if it's a dependency of the local crate.
+    fn extern_crate(&self, name: Symbol) -> Option<DefId> {
+        let tcx = self.tcx;
+        tcx.crates(())
+            .iter()
+            .find(|&&krate| {
+                tcx.crate_name(krate) == name
+                    && (name == sym::core
+                        || name == sym::std
+                        || tcx
+                            .extern_crate(krate.as_def_id())
+                            .map_or(false, |extern_crate| extern_crate.is_direct()))
+            })
+            .map(|krate| krate.as_def_id())
+    }
+
+    /// Returns the items named `name` in the module, the type or the trait `parent`.
+    fn children(&self, parent: DefId, name: Symbol) -> Vec<DefId> {
+        let tcx = self.tcx;
+        let associated_items = |impl_or_trait: DefId| {
+            tcx.associated_items(impl_or_trait)
+                .filter_by_name_unhygienic(name)
+                .map(|item| item.def_id)
+                .collect::<Vec<_>>()
+        };
+        match tcx.def_kind(parent) {
+            DefKind::Mod => {
+                module_children(tcx, parent, !parent.is_local(), &mut FxHashSet::default())
+                    .into_iter()
+                    .filter(|(child_name, _)| *child_name == name)
+                    .map(|(_, def_id)| def_id)
+                    .collect()
+            }
+            kind @ (DefKind::Struct | DefKind::Union | DefKind::Enum) => {
+                let adt_def = tcx.adt_def(parent);
+                let mut children = if kind == DefKind::Enum {
+                    adt_def
+                        .variants()
+                        .iter()
+                        .filter(|variant| variant.name == name)
+                        .map(|variant| variant.def_id)
+                        .collect()
+                } else {
+                    adt_def
+                        .non_enum_variant()
+                        .fields
+                        .iter()
+                        .filter(|field| field.name == name)
+                        .map(|field| field.did)
+                        .collect::<Vec<_>>()
+                };
+                for &impl_id in tcx.inherent_impls(parent) {
+                    children.extend(associated_items(impl_id));
+                }
+                if children.is_empty() {
+                    // The methods of the traits implemented in the local crate.
+                    for &impl_id in tcx.all_local_trait_impls(()).values().flatten() {
+                        if tcx.type_of(impl_id).ty_adt_def() == Some(adt_def) {
+                            children.extend(associated_items(impl_id.to_def_id()));
+                        }
+                    }
+                }
+                children
+            }
+            DefKind::Variant => tcx
+                .adt_def(tcx.parent(parent))
+                .variant_with_id(parent)
+                .fields
+                .iter()
+                .filter(|field| field.name == name)
+                .map(|field| field.did)
+                .collect(),
+            DefKind::Trait => associated_items(parent),
+            DefKind::TyAlias => match tcx.type_of(parent).ty_adt_def() {
+                Some(adt_def) => self.children(adt_def.did(), name),
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the type or the trait `Self` refers to in the documentation of `def_id`.
+    fn self_type(&self, mut def_id: DefId) -> Option<DefId> {
+        let tcx = self.tcx;
+        loop {
+            match tcx.def_kind(def_id) {
+                DefKind::Struct | DefKind::Union | DefKind::Enum | DefKind::Trait => {
+                    return Some(def_id)
+                }
+                DefKind::Impl => return Some(tcx.type_of(def_id).ty_adt_def()?.did()),
+                DefKind::Mod => return None,
+                _ => def_id = tcx.opt_parent(def_id)?,
+            }
+        }
+    }
+}
+
+/// The disambiguators of [`matches_disambiguator`], and `prim` for the primitive types.
+const DISAMBIGUATORS: &[&str] = &[
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "mod",
+    "module",
+    "const",
+    "constant",
+    "static",
+    "fn",
+    "function",
+    "method",
+    "type",
+    "value",
+    "macro",
+    "derive",
+    "attr",
+    "field",
+    "variant",
+    "tyalias",
+    "typealias",
+    "prim",
+    "primitive",
+];
+
+/// Returns `true` if an item of the kind `kind` can be the target of a link with
+/// `disambiguator`.
+fn matches_disambiguator(kind: DefKind, disambiguator: &str) -> bool {
+    match disambiguator {
+        "struct" => kind == DefKind::Struct,
+        "enum" => kind == DefKind::Enum,
+        "union" => kind == DefKind::Union,
+        "trait" => kind == DefKind::Trait,
+        "mod" | "module" => kind == DefKind::Mod,
+        "const" | "constant" => matches!(kind, DefKind::Const | DefKind::AssocConst),
+        "static" => matches!(kind, DefKind::Static(_)),
+        "fn" | "function" | "method" => matches!(kind, DefKind::Fn | DefKind::AssocFn),
+        "type" => kind.ns() == Some(Namespace::TypeNS),
+        "value" => kind.ns() == Some(Namespace::ValueNS),
+        "macro" => kind == DefKind::Macro(MacroKind::Bang),
+        "derive" => kind == DefKind::Macro(MacroKind::Derive),
+        "attr" => kind == DefKind::Macro(MacroKind::Attr),
+        "field" => kind == DefKind::Field,
+        "variant" => kind == DefKind::Variant,
+        "tyalias" | "typealias" => kind == DefKind::TyAlias,
+        _ => false,
+    }
+}
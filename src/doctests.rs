@@ -0,0 +1,206 @@
+//! Helpers to extract the code examples of the documentation of the local crate, the way rustdoc
+//! does to run them as doctests.
+
+use rustc_hir::{HirId, OwnerId};
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+
+use std::borrow::Cow;
+
+use crate::attrs::doc_lines;
+use crate::paths::qualified_name;
+
+/// A code block returned by [`doctests`].
+#[derive(Clone, Debug)]
+pub struct Doctest {
+    /// The [`qualified_name`] of the documented item.
+    pub item: String,
+    /// The code of the block as rustdoc compiles it: the hidden lines (starting with `# `) are
+    /// included without the `#`, and `##` at the beginning of a line is replaced by `#`.
+    pub code: String,
+    pub attributes: DoctestAttributes,
+    /// The span of the documentation attributes the block comes from. With `///` comments, it
+    /// goes from the line opening the block to the one closing it.
+    pub span: Span,
+}
+
+/// The attributes given in the info string of a code block (like `` ```no_run,edition2018 ``).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DoctestAttributes {
+    /// `true` if the block has `ignore`.
+    pub ignore: bool,
+    /// The targets given with `ignore-<target>`, on which the test is ignored.
+    pub ignore_targets: Vec<String>,
+    pub no_run: bool,
+    pub should_panic: bool,
+    pub compile_fail: bool,
+    pub test_harness: bool,
+    /// The edition given with `edition<year>`, like `"2018"`.
+    pub edition: Option<String>,
+    /// The error codes the code must fail with, like `E0425`.
+    pub error_codes: Vec<String>,
+}
+
+/// Returns the Rust code blocks of the documentation of the local items, in the order in which
+/// the items are defined. Like with rustdoc, the fenced code blocks (written with `` ``` `` or
+/// `~~~`) are Rust code unless their info string contains another language (like `text` or
+/// `python`) and doesn't contain `rust`. The indented code blocks are not included, nor are the
+/// code blocks nested in lists or block quotes.
+pub fn doctests(tcx: TyCtxt<'_>) -> Vec<Doctest> {
+    let hir = tcx.hir();
+    // All the definitions with attributes, including the crate root, the fields and the variants.
+    let def_ids = hir
+        .krate()
+        .owners
+        .iter_enumerated()
+        .filter_map(|(def_id, owner)| Some((def_id, owner.as_owner()?)))
+        .flat_map(|(def_id, owner)| {
+            owner.attrs.map.iter().filter_map(move |(local_id, _)| {
+                hir.opt_local_def_id(HirId {
+                    owner: OwnerId { def_id },
+                    local_id: *local_id,
+                })
+            })
+        });
+    let mut doctests = Vec::new();
+    for def_id in def_ids {
+        let def_id = def_id.to_def_id();
+        let Some(lines) = doc_lines(tcx, def_id) else { continue };
+        let mut lines = lines.iter();
+        while let Some((line, start_span)) = lines.next() {
+            let Some(fence) = Fence::parse(line) else { continue };
+            let mut code = Vec::new();
+            let mut end_span = *start_span;
+            for (line, span) in lines.by_ref() {
+                end_span = *span;
+                if fence.is_closed_by(line) {
+                    break;
+                }
+                code.push(fence.strip_indent(line));
+            }
+            let Some(attributes) = parse_info_string(fence.info) else { continue };
+            doctests.push(Doctest {
+                item: qualified_name(tcx, def_id),
+                code: code
+                    .iter()
+                    .map(|line| unhide(line))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                attributes,
+                span: start_span.to(end_span),
+            });
+        }
+    }
+    doctests
+}
+
+/// The line opening a fenced code block.
+pub(crate) struct Fence<'a> {
+    /// Either `` '`' `` or `'~'`.
+    c: char,
+    len: usize,
+    indent: usize,
+    info: &'a str,
+}
+
+impl<'a> Fence<'a> {
+    pub(crate) fn parse(line: &'a str) -> Option<Self> {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent > 3 {
+            return None;
+        }
+        let rest = &line[indent..];
+        let c = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = rest.len() - rest.trim_start_matches(c).len();
+        let info = rest[len..].trim();
+        if len < 3 || (c == '`' && info.contains('`')) {
+            return None;
+        }
+        Some(Self {
+            c,
+            len,
+            indent,
+            info,
+        })
+    }
+
+    pub(crate) fn is_closed_by(&self, line: &str) -> bool {
+        let rest = line.trim_start_matches(' ');
+        let fence = rest.trim_end();
+        line.len() - rest.len() <= 3
+            && fence.len() >= self.len
+            && fence.chars().all(|c| c == self.c)
+    }
+
+    /// Removes up to the indentation of the opening line from `line`.
+    fn strip_indent<'b>(&self, line: &'b str) -> &'b str {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        &line[indent.min(self.indent)..]
+    }
+}
+
+/// Returns the attributes of a code block or `None` if it isn't Rust code.
+fn parse_info_string(info: &str) -> Option<DoctestAttributes> {
+    let mut attributes = DoctestAttributes::default();
+    let mut seen_rust = false;
+    let mut seen_other = false;
+    for token in info.split(|c: char| c == ',' || c.is_whitespace()) {
+        match token {
+            "" => {}
+            "rust" => seen_rust = true,
+            "ignore" => {
+                attributes.ignore = true;
+                seen_rust = true;
+            }
+            "no_run" => {
+                attributes.no_run = true;
+                seen_rust = true;
+            }
+            "should_panic" => {
+                attributes.should_panic = true;
+                seen_rust = true;
+            }
+            "compile_fail" => {
+                attributes.compile_fail = true;
+                seen_rust = true;
+            }
+            "test_harness" => {
+                attributes.test_harness = true;
+                seen_rust = true;
+            }
+            _ => {
+                if let Some(target) = token.strip_prefix("ignore-") {
+                    attributes.ignore_targets.push(target.to_owned());
+                    seen_rust = true;
+                } else if let Some(edition) = token.strip_prefix("edition") {
+                    attributes.edition = Some(edition.to_owned());
+                    seen_rust = true;
+                } else if token.len() == 5
+                    && token.starts_with('E')
+                    && token[1..].chars().all(|c| c.is_ascii_digit())
+                {
+                    attributes.error_codes.push(token.to_owned());
+                    seen_rust = true;
+                } else {
+                    seen_other = true;
+                }
+            }
+        }
+    }
+    (seen_rust || !seen_other).then_some(attributes)
+}
+
+/// Returns `line` as rustdoc compiles it, see [`Doctest::code`].
+fn unhide(line: &str) -> Cow<'_, str> {
+    let trimmed = line.trim();
+    if trimmed.starts_with("##") {
+        // `##` escapes a `#` which isn't a hidden line marker.
+        Cow::Owned(line.replacen("##", "#", 1))
+    } else if let Some(rest) = trimmed.strip_prefix("# ") {
+        Cow::Borrowed(rest)
+    } else if trimmed == "#" {
+        Cow::Borrowed("")
+    } else {
+        Cow::Borrowed(line)
+    }
+}
@@ -0,0 +1,143 @@
+//! Helpers to export graphs in the DOT format of [Graphviz](https://graphviz.org/), so they can
+//! be rendered with `dot -Tsvg`.
+
+use rustc_hir::def_id::{LocalDefId, CRATE_DEF_ID};
+use rustc_hir::ItemKind;
+use rustc_middle::ty::TyCtxt;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::call_graph::{CallGraph, Callee};
+use crate::paths::qualified_name;
+
+/// Options of [`to_dot_with_options`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DotOptions {
+    /// Draw the functions of each module in a box labelled with the name of the module.
+    pub cluster_by_module: bool,
+}
+
+/// Same as [`to_dot_with_options`] with the default options.
+pub fn to_dot(graph: &CallGraph) -> String {
+    to_dot_with_options(graph, DotOptions::default())
+}
+
+/// Returns `graph` in the DOT format. A call graph has an edge for each call, whereas there is at
+/// most one edge between two nodes in the DOT output.
+///
+/// The functions of other crates are drawn with a dashed border and the dynamic calls (see
+/// [`Callee::Dynamic`]) go to dotted nodes, named after the trait method when there is one.
+pub fn to_dot_with_options(graph: &CallGraph, options: DotOptions) -> String {
+    let mut edges = BTreeSet::new();
+    let mut external_nodes = BTreeSet::new();
+    let mut dynamic_nodes = BTreeSet::new();
+    for edge in &graph.edges {
+        let callee = match &edge.callee {
+            Callee::Resolved { name, .. } => {
+                if !graph.nodes.contains_key(name) {
+                    external_nodes.insert(name.as_str());
+                }
+                name.clone()
+            }
+            Callee::Dynamic { name } => {
+                let name = format!("<dyn {}>", name.as_deref().unwrap_or("?"));
+                dynamic_nodes.insert(name.clone());
+                name
+            }
+        };
+        edges.insert((edge.caller.as_str(), callee));
+    }
+
+    let mut out = String::from("digraph call_graph {\n");
+    if options.cluster_by_module {
+        let mut modules = BTreeMap::<_, Vec<_>>::new();
+        for (name, node) in &graph.nodes {
+            modules.entry(node.module.as_str()).or_default().push(name);
+        }
+        for (index, (module, names)) in modules.into_iter().enumerate() {
+            writeln!(out, "    subgraph cluster_{index} {{").unwrap();
+            writeln!(out, "        label={};", quote(module)).unwrap();
+            for name in names {
+                writeln!(out, "        {};", quote(name)).unwrap();
+            }
+            out.push_str("    }\n");
+        }
+    } else {
+        for name in graph.nodes.keys() {
+            writeln!(out, "    {};", quote(name)).unwrap();
+        }
+    }
+    for name in external_nodes {
+        writeln!(out, "    {} [style=dashed];", quote(name)).unwrap();
+    }
+    for name in &dynamic_nodes {
+        writeln!(out, "    {} [style=dotted];", quote(name)).unwrap();
+    }
+    for (caller, callee) in &edges {
+        writeln!(out, "    {} -> {};", quote(caller), quote(callee)).unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Returns the tree of the modules of the local crate in the DOT format, each module having an
+/// edge to its submodules. The modules are identified by their [`qualified_name`].
+///
+/// The nodes are labelled with the name of the module. The modules written inline
+/// (`mod name { ... }`) are drawn with a dashed border, the label of the other ones also contains
+/// the file they're in (which is the one given with `#[path]` if there is one). The modules
+/// declared inside of functions are not included.
+pub fn module_tree_dot(tcx: TyCtxt<'_>) -> String {
+    let mut out = String::from("digraph module_tree {\n");
+    writeln!(
+        out,
+        "    {};",
+        quote(&qualified_name(tcx, CRATE_DEF_ID.to_def_id()))
+    )
+    .unwrap();
+    write_submodules(tcx, CRATE_DEF_ID, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+/// The modules are written in the order in which they're declared.
+fn write_submodules(tcx: TyCtxt<'_>, module: LocalDefId, out: &mut String) {
+    let hir = tcx.hir();
+    let source_map = tcx.sess.source_map();
+    let parent_name = qualified_name(tcx, module.to_def_id());
+    for &item_id in hir.get_module(module).0.item_ids {
+        let item = hir.item(item_id);
+        let ItemKind::Mod(submodule) = item.kind else { continue };
+        let def_id = item.owner_id.def_id;
+        let name = qualified_name(tcx, def_id.to_def_id());
+        let file = source_map.span_to_filename(submodule.spans.inner_span);
+        let attributes = if file == source_map.span_to_filename(item.span) {
+            format!("label={}, style=dashed", quote(item.ident.as_str()))
+        } else {
+            format!(
+                "label={}",
+                quote(&format!("{}\n{}", item.ident, file.prefer_local()))
+            )
+        };
+        writeln!(out, "    {} [{}];", quote(&name), attributes).unwrap();
+        writeln!(out, "    {} -> {};", quote(&parent_name), quote(&name)).unwrap();
+        write_submodules(tcx, def_id, out);
+    }
+}
+
+/// Returns `s` as a DOT quoted string.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,188 @@
+//! Helpers to know whether an item was written by hand or generated by a macro (or by the
+//! compiler), to handle the generated code separately, and to go through the macro calls a span
+//! comes from.
+
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::Span;
+
+use crate::crate_graph::is_proc_macro_crate;
+
+/// Where an item comes from, returned by [`expansion_info`].
+///
+/// When a macro call is itself generated by a macro (like a `#[derive]` written in a
+/// `macro_rules!`), this is the macro which generated the item directly. The origin of the call
+/// can be found with [`span_expansion_info`] on `call_site_span`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpansionOrigin {
+    /// The item is written in the source code. This includes the items of the files included with
+    /// `include!` and the items given to a macro which outputs them as they are (like with an
+    /// `$item:item` fragment).
+    Written,
+    /// Generated by a declarative macro, which includes the builtin macros like `concat_idents!`.
+    MacroRules {
+        macro_name: String,
+        /// The span of the macro call, like `my_macro!(...)`.
+        call_site_span: Span,
+    },
+    /// Generated by a derive macro, builtin (like `Debug`) or not.
+    Derive {
+        /// The path of the derive macro as written in the attribute, like `Debug` or
+        /// `serde::Serialize`.
+        trait_name: String,
+        /// The span of the derive macro in the attribute, like `Debug` in `#[derive(Debug)]`.
+        call_site_span: Span,
+    },
+    /// Generated by an attribute macro, a proc macro or a builtin one (like `#[test]`).
+    ProcMacroAttr {
+        name: String,
+        /// The span of the attribute.
+        call_site_span: Span,
+    },
+    /// Generated by a function-like proc macro.
+    ProcMacroBang {
+        name: String,
+        /// The span of the macro call, like `my_macro!(...)`.
+        call_site_span: Span,
+    },
+    /// Injected by the compiler, like the `extern crate std;` item or the test harness.
+    Compiler {
+        /// What injected the item, like `"standard library imports"`.
+        description: String,
+    },
+}
+
+impl ExpansionOrigin {
+    /// Returns `true` if the item isn't [`ExpansionOrigin::Written`].
+    pub fn is_generated(&self) -> bool {
+        !matches!(self, ExpansionOrigin::Written)
+    }
+}
+
+/// Which items to give to [`for_each_item_filtered`](crate::visit::for_each_item_filtered).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpansionFilter {
+    All,
+    /// Only the items whose origin is [`ExpansionOrigin::Written`].
+    SkipGenerated,
+    /// Only the items generated by a macro or by the compiler.
+    OnlyGenerated,
+}
+
+impl ExpansionFilter {
+    /// Returns `true` if the items coming from `origin` are kept by the filter.
+    pub fn includes(self, origin: &ExpansionOrigin) -> bool {
+        match self {
+            ExpansionFilter::All => true,
+            ExpansionFilter::SkipGenerated => !origin.is_generated(),
+            ExpansionFilter::OnlyGenerated => origin.is_generated(),
+        }
+    }
+}
+
+/// A macro call (or a compiler transformation) in the chain returned by [`expansion_backtrace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpansionFrame {
+    pub kind: ExpansionKind,
+    /// The name of the macro as written in the call, like `my_macro` or `serde::Serialize`. For
+    /// the other kinds, a description of the transformation like ``operator `?` ``, `` `for`
+    /// loop `` or `standard library imports`.
+    pub name: String,
+    /// The span of the definition of the macro, `None` for the desugarings and the code injected
+    /// by the compiler.
+    pub def_site_span: Option<Span>,
+    /// The span of the macro call or of the desugared expression. It is itself in the expansion
+    /// of the next frame, if there is one.
+    pub call_site_span: Span,
+}
+
+/// The kind of an [`ExpansionFrame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpansionKind {
+    /// A function-like macro (`my_macro!(...)`), declarative or proc macro.
+    Bang,
+    /// An attribute macro, like `#[test]`.
+    Attr,
+    /// A derive macro, like `Debug` in `#[derive(Debug)]`.
+    Derive,
+    /// A desugaring done while lowering the AST to the HIR, like the one of `?` or `for` loops.
+    Desugaring,
+    /// Code injected by the compiler, like the `extern crate std;` item or the test harness.
+    Compiler,
+}
+
+/// Returns where the item `def_id` (local or not) comes from, computed from the hygiene data of
+/// its span.
+pub fn expansion_info(tcx: TyCtxt<'_>, def_id: DefId) -> ExpansionOrigin {
+    span_expansion_info(tcx, tcx.def_span(def_id))
+}
+
+/// Returns where the code of `span` comes from, see [`expansion_info`].
+pub fn span_expansion_info(tcx: TyCtxt<'_>, span: Span) -> ExpansionOrigin {
+    let expn_data = span.ctxt().outer_expn_data();
+    let call_site_span = expn_data.call_site;
+    match expn_data.kind {
+        ExpnKind::Root => ExpansionOrigin::Written,
+        ExpnKind::Macro(MacroKind::Bang, name) => {
+            let is_proc_macro = expn_data
+                .macro_def_id
+                .map_or(false, |def_id| is_proc_macro_crate(tcx, def_id.krate));
+            if is_proc_macro {
+                ExpansionOrigin::ProcMacroBang {
+                    name: name.to_string(),
+                    call_site_span,
+                }
+            } else {
+                ExpansionOrigin::MacroRules {
+                    macro_name: name.to_string(),
+                    call_site_span,
+                }
+            }
+        }
+        ExpnKind::Macro(MacroKind::Derive, name) => ExpansionOrigin::Derive {
+            trait_name: name.to_string(),
+            call_site_span,
+        },
+        ExpnKind::Macro(MacroKind::Attr, name) => ExpansionOrigin::ProcMacroAttr {
+            name: name.to_string(),
+            call_site_span,
+        },
+        kind @ (ExpnKind::AstPass(_) | ExpnKind::Desugaring(_) | ExpnKind::Inlined) => {
+            ExpansionOrigin::Compiler {
+                description: kind.descr(),
+            }
+        }
+    }
+}
+
+/// Returns the chain of expansions `span` comes from, starting with the macro (or desugaring)
+/// which produced it and ending with the outermost one, whose call site is written in the source
+/// code. The list is empty if `span` doesn't come from an expansion.
+///
+/// Like in the diagnostics of the compiler, the recursive calls of a macro (when its call site is
+/// the one of the previous frame) only give one frame.
+pub fn expansion_backtrace(span: Span) -> Vec<ExpansionFrame> {
+    span.macro_backtrace()
+        .map(|expn_data| {
+            let (kind, name) = match expn_data.kind {
+                ExpnKind::Macro(MacroKind::Bang, name) => (ExpansionKind::Bang, name.to_string()),
+                ExpnKind::Macro(MacroKind::Attr, name) => (ExpansionKind::Attr, name.to_string()),
+                ExpnKind::Macro(MacroKind::Derive, name) => {
+                    (ExpansionKind::Derive, name.to_string())
+                }
+                ExpnKind::Desugaring(kind) => (ExpansionKind::Desugaring, kind.descr().to_owned()),
+                ExpnKind::AstPass(pass) => (ExpansionKind::Compiler, pass.descr().to_owned()),
+                kind @ (ExpnKind::Root | ExpnKind::Inlined) => {
+                    (ExpansionKind::Compiler, kind.descr())
+                }
+            };
+            ExpansionFrame {
+                kind,
+                name,
+                def_site_span: (!expn_data.def_site.is_dummy()).then_some(expn_data.def_site),
+                call_site_span: expn_data.call_site,
+            }
+        })
+        .collect()
+}
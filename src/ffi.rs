@@ -0,0 +1,236 @@
+//! Helpers to get the FFI boundary of the local crate: the functions and statics shared with
+//! other languages and the types with a defined layout they use.
+//!
+//! The types are rendered as Rust code (like `extern "C" fn(i32) -> *const u8`).
+
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::{ForeignItemKind, ItemKind};
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{TyCtxt, VariantDef};
+use rustc_span::Span;
+use rustc_target::spec::abi::Abi;
+
+use crate::layout::{repr_of, ReprInfo};
+use crate::paths::qualified_name;
+
+/// Returned by [`ffi_surface`].
+#[derive(Clone, Debug, Default)]
+pub struct FfiSurface {
+    pub functions: Vec<FfiFunction>,
+    pub types: Vec<FfiType>,
+    pub statics: Vec<FfiStatic>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FfiFunction {
+    /// The [`qualified_name`] of the function.
+    pub name: String,
+    /// The name of the symbol if it's not mangled: the one given with `#[link_name]` or
+    /// `#[export_name]`, or the name of the function. It's `None` for the functions defined in
+    /// the crate without `#[no_mangle]` nor `#[export_name]`.
+    pub symbol: Option<String>,
+    /// Like `"C"` or `"system"`.
+    pub abi: String,
+    /// `true` if the function is declared in an `extern` block, `false` if it's defined in the
+    /// crate.
+    pub is_foreign: bool,
+    /// The names and the types of the parameters.
+    pub inputs: Vec<(String, String)>,
+    pub output: String,
+    /// `true` if the function takes a variable number of arguments (`...`).
+    pub c_variadic: bool,
+    pub span: Span,
+}
+
+/// A struct, an enum or an union with a `repr` attribute defining its layout.
+#[derive(Clone, Debug)]
+pub struct FfiType {
+    /// The [`qualified_name`] of the type.
+    pub name: String,
+    /// `"struct"`, `"enum"` or `"union"`.
+    pub kind: String,
+    /// The generic parameters, the field types containing them are rendered with their names.
+    pub generics: Vec<String>,
+    pub repr: ReprInfo,
+    /// The names and the types of the fields of the structs and unions, in the order in which
+    /// they're declared.
+    pub fields: Vec<(String, String)>,
+    /// The names and the fields of the variants of the enums.
+    pub variants: Vec<(String, Vec<(String, String)>)>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct FfiStatic {
+    /// The [`qualified_name`] of the static.
+    pub name: String,
+    /// The name of the symbol, see [`FfiFunction::symbol`].
+    pub symbol: String,
+    pub ty: String,
+    pub mutable: bool,
+    /// `true` if the static is declared in an `extern` block, `false` if it's defined in the
+    /// crate.
+    pub is_foreign: bool,
+    pub span: Span,
+}
+
+/// Returns the FFI boundary of the local crate, in the order in which the items are defined:
+///
+/// * The functions with another ABI than the Rust one (like `extern "C"`), both declared in
+///   `extern` blocks and defined in the crate. The methods are not included.
+/// * The structs, enums and unions with `#[repr(C)]`, `#[repr(transparent)]` or an integer
+///   `repr` (like `#[repr(u8)]`).
+/// * The statics declared in `extern` blocks and the ones defined with `#[no_mangle]` or
+///   `#[export_name]`.
+pub fn ffi_surface(tcx: TyCtxt<'_>) -> FfiSurface {
+    let hir = tcx.hir();
+    let mut surface = FfiSurface::default();
+    for item_id in hir.items() {
+        let item = hir.item(item_id);
+        let def_id = item.owner_id.def_id;
+        match item.kind {
+            ItemKind::Fn(ref sig, ..) if is_foreign_abi(sig.header.abi) => {
+                surface
+                    .functions
+                    .push(ffi_function(tcx, def_id, sig.header.abi, false, item.span));
+            }
+            ItemKind::ForeignMod { abi, items } => {
+                for foreign_item_ref in items {
+                    let foreign_item = hir.foreign_item(foreign_item_ref.id);
+                    let foreign_id = foreign_item.owner_id.def_id;
+                    match foreign_item.kind {
+                        ForeignItemKind::Fn(..) => surface.functions.push(ffi_function(
+                            tcx,
+                            foreign_id,
+                            abi,
+                            true,
+                            foreign_item.span,
+                        )),
+                        ForeignItemKind::Static(_, mutability) => {
+                            surface.statics.push(FfiStatic {
+                                name: qualified_name(tcx, foreign_id.to_def_id()),
+                                symbol: symbol(tcx, foreign_id, true)
+                                    .unwrap_or_else(|| foreign_item.ident.to_string()),
+                                ty: type_string(tcx, foreign_id.to_def_id()),
+                                mutable: mutability.is_mut(),
+                                is_foreign: true,
+                                span: foreign_item.span,
+                            });
+                        }
+                        ForeignItemKind::Type => {}
+                    }
+                }
+            }
+            ItemKind::Static(_, mutability, _) => {
+                if let Some(symbol) = symbol(tcx, def_id, false) {
+                    surface.statics.push(FfiStatic {
+                        name: qualified_name(tcx, def_id.to_def_id()),
+                        symbol,
+                        ty: type_string(tcx, def_id.to_def_id()),
+                        mutable: mutability.is_mut(),
+                        is_foreign: false,
+                        span: item.span,
+                    });
+                }
+            }
+            ItemKind::Struct(..) | ItemKind::Enum(..) | ItemKind::Union(..) => {
+                if let Some(ffi_type) = ffi_type(tcx, def_id.to_def_id(), item.span) {
+                    surface.types.push(ffi_type);
+                }
+            }
+            _ => {}
+        }
+    }
+    surface
+}
+
+/// The ABIs which are only used internally by the compiler and the standard library are not
+/// considered as foreign.
+fn is_foreign_abi(abi: Abi) -> bool {
+    !matches!(
+        abi,
+        Abi::Rust | Abi::RustIntrinsic | Abi::RustCall | Abi::PlatformIntrinsic
+    )
+}
+
+fn ffi_function(
+    tcx: TyCtxt<'_>,
+    def_id: LocalDefId,
+    abi: Abi,
+    is_foreign: bool,
+    span: Span,
+) -> FfiFunction {
+    let sig = tcx.fn_sig(def_id).skip_binder();
+    let inputs = tcx
+        .fn_arg_names(def_id)
+        .iter()
+        .zip(sig.inputs())
+        .map(|(name, ty)| (name.to_string(), with_no_trimmed_paths!(ty.to_string())))
+        .collect();
+    FfiFunction {
+        name: qualified_name(tcx, def_id.to_def_id()),
+        symbol: symbol(tcx, def_id, is_foreign),
+        abi: abi.name().to_owned(),
+        is_foreign,
+        inputs,
+        output: with_no_trimmed_paths!(sig.output().to_string()),
+        c_variadic: sig.c_variadic,
+        span,
+    }
+}
+
+/// The foreign items always have an unmangled symbol whereas the items defined in the crate need
+/// `#[no_mangle]` or `#[export_name]`.
+fn symbol(tcx: TyCtxt<'_>, def_id: LocalDefId, is_foreign: bool) -> Option<String> {
+    let attrs = tcx.codegen_fn_attrs(def_id);
+    if let Some(name) = attrs.link_name.or(attrs.export_name) {
+        return Some(name.to_string());
+    }
+    (is_foreign || attrs.flags.contains(CodegenFnAttrFlags::NO_MANGLE))
+        .then(|| tcx.item_name(def_id.to_def_id()).to_string())
+}
+
+fn ffi_type(tcx: TyCtxt<'_>, def_id: DefId, span: Span) -> Option<FfiType> {
+    let adt = tcx.adt_def(def_id);
+    let repr = repr_of(tcx, def_id);
+    if !repr.c && !repr.transparent && repr.int.is_none() {
+        return None;
+    }
+    let fields = |variant: &VariantDef| {
+        variant
+            .fields
+            .iter()
+            .map(|field| (field.name.to_string(), type_string(tcx, field.did)))
+            .collect::<Vec<_>>()
+    };
+    let (kind, type_fields, variants) = if adt.is_enum() {
+        let variants = adt
+            .variants()
+            .iter()
+            .map(|variant| (variant.name.to_string(), fields(variant)))
+            .collect();
+        ("enum", Vec::new(), variants)
+    } else {
+        let kind = if adt.is_union() { "union" } else { "struct" };
+        (kind, fields(adt.non_enum_variant()), Vec::new())
+    };
+    Some(FfiType {
+        name: qualified_name(tcx, def_id),
+        kind: kind.to_owned(),
+        generics: tcx
+            .generics_of(def_id)
+            .params
+            .iter()
+            .map(|param| param.name.to_string())
+            .collect(),
+        repr,
+        fields: type_fields,
+        variants,
+        span,
+    })
+}
+
+fn type_string(tcx: TyCtxt<'_>, def_id: DefId) -> String {
+    with_no_trimmed_paths!(tcx.type_of(def_id).to_string())
+}
@@ -0,0 +1,203 @@
+use rustc_span::source_map::{FileLoader, RealFileLoader};
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::marker;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// File loader which first looks into `files` and then falls back to another loader (the file
+/// system by default) if the file wasn't found.
+pub(crate) struct OverlayFileLoader {
+    files: HashMap<PathBuf, String>,
+    fallback: Box<dyn FileLoader + marker::Send + Sync>,
+}
+
+impl OverlayFileLoader {
+    pub(crate) fn new(files: HashMap<PathBuf, String>) -> Self {
+        Self::with_fallback(files, Box::new(RealFileLoader))
+    }
+
+    pub(crate) fn with_fallback(
+        files: HashMap<PathBuf, String>,
+        fallback: Box<dyn FileLoader + marker::Send + Sync>,
+    ) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|(path, content)| (absolute_path(&path), content))
+                .collect(),
+            fallback,
+        }
+    }
+}
+
+impl FileLoader for OverlayFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        self.files.contains_key(&absolute_path(path)) || self.fallback.file_exists(path)
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        match self.files.get(&absolute_path(path)) {
+            Some(content) => Ok(content.clone()),
+            None => self.fallback.read_file(path),
+        }
+    }
+}
+
+/// File loader of a [`VirtualCrate`](crate::VirtualCrate): its files are only looked up in
+/// `files`, the other files (like the sources of the standard library shown in the diagnostics)
+/// are read with `fallback` if their path is absolute.
+pub(crate) struct VirtualFileLoader {
+    files: HashMap<PathBuf, String>,
+    fallback: Box<dyn FileLoader + marker::Send + Sync>,
+}
+
+impl VirtualFileLoader {
+    pub(crate) fn new(
+        files: HashMap<PathBuf, String>,
+        fallback: Box<dyn FileLoader + marker::Send + Sync>,
+    ) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|(path, content)| (normalize_path(&path), content))
+                .collect(),
+            fallback,
+        }
+    }
+}
+
+impl FileLoader for VirtualFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        self.files.contains_key(&normalize_path(path))
+            || (path.is_absolute() && self.fallback.file_exists(path))
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        match self.files.get(&normalize_path(path)) {
+            Some(content) => Ok(content.clone()),
+            None if path.is_absolute() => self.fallback.read_file(path),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "not a file of the virtual crate",
+            )),
+        }
+    }
+}
+
+/// The suffix added to the paths given to `include!` by the `include_str!` defined by
+/// [`inject_include_overlay`](crate::hir::inject_include_overlay).
+pub(crate) const INCLUDE_STR_SUFFIX: &str = "?include_str";
+/// Same as [`INCLUDE_STR_SUFFIX`] for `include_bytes!`.
+pub(crate) const INCLUDE_BYTES_SUFFIX: &str = "?include_bytes";
+
+/// The compiler reads the files of `include_str!` and `include_bytes!` from the file system, so
+/// they're replaced by an `include!` of the path with a suffix (see
+/// [`inject_include_overlay`](crate::hir::inject_include_overlay)). This file loader reads these
+/// paths with the loader it wraps, and returns the content as a string or byte string literal.
+pub(crate) struct IncludeFileLoader(pub(crate) Box<dyn FileLoader + marker::Send + Sync>);
+
+impl FileLoader for IncludeFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        match included_file(path) {
+            Some((path, _)) => self.0.file_exists(&path),
+            None => self.0.file_exists(path),
+        }
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        let Some((path, bytes)) = included_file(path) else {
+            return self.0.read_file(path);
+        };
+        let content = match self.0.read_file(&path) {
+            Ok(content) => content.into_bytes(),
+            // The files included with `include_bytes!` don't need to be UTF-8.
+            Err(e) if bytes && e.kind() == io::ErrorKind::InvalidData => fs::read(&path)?,
+            // A file `include!` can't read is a fatal error, whereas it's a simple error for
+            // `include_str!` and `include_bytes!`.
+            Err(e) => {
+                let message = format!("couldn't read {}: {}", path.display(), e);
+                return Ok(format!("compile_error!({message:?})"));
+            }
+        };
+        Ok(include_literal(&content, bytes))
+    }
+}
+
+/// Returns the path of the file and whether it's included with `include_bytes!` if `path` is
+/// one given to `include!` by the `include_str!` or `include_bytes!` of
+/// [`inject_include_overlay`](crate::hir::inject_include_overlay).
+pub(crate) fn included_file(path: &Path) -> Option<(PathBuf, bool)> {
+    let path = path.to_str()?;
+    if let Some(path) = path.strip_suffix(INCLUDE_STR_SUFFIX) {
+        Some((PathBuf::from(path), false))
+    } else {
+        path.strip_suffix(INCLUDE_BYTES_SUFFIX)
+            .map(|path| (PathBuf::from(path), true))
+    }
+}
+
+/// The source code of the string literal (or byte string literal if `bytes` is `true`) whose
+/// value is `content`, which is UTF-8 if `bytes` is `false`.
+pub(crate) fn include_literal(content: &[u8], bytes: bool) -> String {
+    if !bytes {
+        return format!("{:?}", String::from_utf8_lossy(content));
+    }
+    let mut literal = String::from("b\"");
+    for &byte in content {
+        literal.extend(std::ascii::escape_default(byte).map(char::from));
+    }
+    literal.push('"');
+    literal
+}
+
+/// Reads the files from the file system and keeps the paths of the files read or looked for.
+pub(crate) struct RecordingFileLoader(pub(crate) Arc<Mutex<BTreeSet<PathBuf>>>);
+
+impl FileLoader for RecordingFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        self.0.lock().unwrap().insert(path.to_owned());
+        RealFileLoader.file_exists(path)
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        self.0.lock().unwrap().insert(path.to_owned());
+        RealFileLoader.read_file(path)
+    }
+}
+
+/// Paths used by the compiler are built from the path of the crate root, so they can be relative
+/// or absolute and contain `..` (with `#[path]` attributes for example). To be sure they match
+/// the overlay's, we make all of them absolute and normalize them.
+pub(crate) fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        normalize_path(path)
+    } else {
+        match std::env::current_dir() {
+            Ok(cwd) => normalize_path(&cwd.join(path)),
+            Err(_) => normalize_path(path),
+        }
+    }
+}
+
+/// Removes the `.` and `..` components of `path` without looking at the file system. The `..` at
+/// the beginning of a relative path are kept.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,390 @@
+//! Helpers to get the generic parameters and the where clauses of the items as data, for example
+//! to document them.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{
+    self, BoundVariableKind, Clause, GenericParamDefKind, PredicateKind, TyCtxt,
+};
+use rustc_span::symbol::kw;
+use rustc_span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Options of [`generics_of_item_with_options`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GenericsOptions {
+    /// Include the `Sized` bounds the compiler adds to the type parameters and to the associated
+    /// types which don't have a `?Sized` bound. They're marked as
+    /// [`WherePredicateKind::Trait::implicit`].
+    pub implicit_sized: bool,
+}
+
+/// Returned by [`generics_of_item`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenericsInfo {
+    /// The generic parameters declared by the item itself, not the ones of its parent (like the
+    /// impl of a method). The lifetimes come first, in the order in which they're declared.
+    pub params: Vec<GenericParam>,
+    /// The bounds of the parameters and the where clauses, in the order in which they're
+    /// written.
+    pub predicates: Vec<WherePredicate>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenericParam {
+    /// Like `'a`, `T` or `N`. The parameters of the `impl Trait` arguments are named after their
+    /// bounds, like `impl Iterator<Item = u32>`.
+    pub name: String,
+    pub kind: GenericParamKind,
+    /// `true` for the parameters of the `impl Trait` arguments of the functions.
+    pub synthetic: bool,
+    /// The parameter as written in the generics, without its bounds: `'a`, `T = u32` or
+    /// `const N: usize`.
+    pub display: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenericParamKind {
+    Lifetime,
+    Type {
+        default: Option<String>,
+        /// `true` if the parameter has a `?Sized` bound.
+        maybe_unsized: bool,
+    },
+    Const {
+        ty: String,
+        default: Option<String>,
+    },
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WherePredicate {
+    /// Like `T: std::iter::Iterator<Item = U>` or `T: 'a`.
+    pub display: String,
+    pub kind: WherePredicateKind,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WherePredicateKind {
+    /// `bounded: for<'a> trait_path<generic_args, assoc_bindings>`.
+    Trait {
+        bounded: String,
+        /// Like `std::iter::Iterator`.
+        trait_path: String,
+        /// The generic arguments of the trait, without `Self`.
+        generic_args: Vec<String>,
+        /// The associated types constrained by the bound, like `Item = U`.
+        assoc_bindings: Vec<AssocBinding>,
+        /// The lifetimes of a higher-ranked bound, like `'a` in `for<'a> Fn(&'a u8)`.
+        for_lifetimes: Vec<String>,
+        /// `true` for the `Sized` bounds added by the compiler, see
+        /// [`GenericsOptions::implicit_sized`].
+        implicit: bool,
+    },
+    /// A constraint on an associated type which isn't part of a trait bound of the item.
+    Projection {
+        /// Like `<T as std::iter::Iterator>::Item`.
+        bounded: String,
+        term: String,
+    },
+    /// `bounded: lifetime`.
+    TypeOutlives { bounded: String, lifetime: String },
+    /// `lifetime: bound`.
+    LifetimeOutlives { lifetime: String, bound: String },
+    /// The other predicates, like the ones of the const generics.
+    Other,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssocBinding {
+    pub name: String,
+    pub term: String,
+}
+
+/// Same as [`generics_of_item_with_options`] with the default options.
+pub fn generics_of_item(tcx: TyCtxt<'_>, def_id: DefId) -> GenericsInfo {
+    generics_of_item_with_options(tcx, def_id, GenericsOptions::default())
+}
+
+/// Returns the generic parameters of `def_id` (local or not) and their bounds. The bounds are the
+/// ones written by the user, the ones the compiler infers (like `T: 'a` for `struct S<'a, T>(&'a
+/// T)`) aren't included. For the associated types, the bounds of the type itself (like
+/// `type Item: Display`) are included too.
+///
+/// For the functions, the lifetimes which only appear in the arguments and in the return type
+/// (the late-bound lifetimes) are included even though they aren't generic parameters for the
+/// compiler. The paths are printed in full, like `std::iter::Iterator`.
+pub fn generics_of_item_with_options(
+    tcx: TyCtxt<'_>,
+    def_id: DefId,
+    options: GenericsOptions,
+) -> GenericsInfo {
+    let kind = tcx.def_kind(def_id);
+    let has_generics = matches!(
+        kind,
+        DefKind::Fn
+            | DefKind::AssocFn
+            | DefKind::Struct
+            | DefKind::Enum
+            | DefKind::Union
+            | DefKind::Trait
+            | DefKind::TraitAlias
+            | DefKind::TyAlias
+            | DefKind::AssocTy
+            | DefKind::AssocConst
+            | DefKind::Impl
+    );
+    if !has_generics {
+        return GenericsInfo::default();
+    }
+    let generics = tcx.generics_of(def_id);
+    let mut predicates = tcx.explicit_predicates_of(def_id).predicates.to_vec();
+    if kind == DefKind::AssocTy {
+        predicates.extend_from_slice(tcx.explicit_item_bounds(def_id));
+    }
+    let sized_trait = tcx.lang_items().sized_trait();
+
+    // The lifetimes, including the late-bound ones, ordered by their declaration.
+    let mut lifetimes = generics
+        .params
+        .iter()
+        .filter(|param| matches!(param.kind, GenericParamDefKind::Lifetime))
+        .map(|param| (param.def_id, param.name))
+        .collect::<Vec<_>>();
+    if matches!(kind, DefKind::Fn | DefKind::AssocFn) {
+        for bound_var in tcx.fn_sig(def_id).bound_vars() {
+            if let BoundVariableKind::Region(ty::BrNamed(def_id, name)) = bound_var {
+                if name != kw::UnderscoreLifetime {
+                    lifetimes.push((def_id, name));
+                }
+            }
+        }
+    }
+    lifetimes.sort_by_key(|&(def_id, _)| tcx.def_span(def_id).lo());
+    let mut params = lifetimes
+        .into_iter()
+        .map(|(_, name)| GenericParam {
+            name: name.to_string(),
+            kind: GenericParamKind::Lifetime,
+            synthetic: false,
+            display: name.to_string(),
+        })
+        .collect::<Vec<_>>();
+    for param in &generics.params {
+        let name = param.name.to_string();
+        let param = match param.kind {
+            // The `Self` parameter of the traits.
+            _ if param.name == kw::SelfUpper => continue,
+            GenericParamDefKind::Lifetime => continue,
+            GenericParamDefKind::Type {
+                has_default,
+                synthetic,
+            } => {
+                let default = has_default
+                    .then(|| with_no_trimmed_paths!(tcx.type_of(param.def_id).to_string()));
+                let maybe_unsized = !predicates.iter().any(|(predicate, _)| {
+                    matches!(
+                        predicate.kind().skip_binder(),
+                        PredicateKind::Clause(Clause::Trait(trait_predicate))
+                            if Some(trait_predicate.def_id()) == sized_trait
+                                && matches!(
+                                    trait_predicate.self_ty().kind(),
+                                    ty::Param(param_ty) if param_ty.index == param.index
+                                )
+                    )
+                });
+                GenericParam {
+                    display: match &default {
+                        Some(default) => format!("{name} = {default}"),
+                        None => name.clone(),
+                    },
+                    name,
+                    kind: GenericParamKind::Type {
+                        default,
+                        maybe_unsized,
+                    },
+                    synthetic,
+                }
+            }
+            GenericParamDefKind::Const { has_default } => {
+                let ty = with_no_trimmed_paths!(tcx.type_of(param.def_id).to_string());
+                let default = has_default.then(|| {
+                    with_no_trimmed_paths!(tcx
+                        .const_param_default(param.def_id)
+                        .skip_binder()
+                        .to_string())
+                });
+                GenericParam {
+                    display: match &default {
+                        Some(default) => format!("const {name}: {ty} = {default}"),
+                        None => format!("const {name}: {ty}"),
+                    },
+                    name,
+                    kind: GenericParamKind::Const { ty, default },
+                    synthetic: false,
+                }
+            }
+        };
+        params.push(param);
+    }
+
+    let is_implicit =
+        |predicate: ty::Predicate<'_>, span: Span| match predicate.kind().skip_binder() {
+            // The compiler gives them the span of the parameter, or of the whole associated type.
+            PredicateKind::Clause(Clause::Trait(trait_predicate)) => {
+                Some(trait_predicate.def_id()) == sized_trait
+                    && match trait_predicate.self_ty().kind() {
+                        ty::Param(param_ty) => {
+                            span == tcx.def_span(generics.type_param(param_ty, tcx).def_id)
+                        }
+                        ty::Alias(ty::Projection, _) => span.contains(tcx.def_span(def_id)),
+                        _ => false,
+                    }
+            }
+            _ => false,
+        };
+    let mut where_predicates = Vec::<(WherePredicate, Option<ty::PolyTraitRef<'_>>)>::new();
+    for &(predicate, span) in &predicates {
+        let implicit = is_implicit(predicate, span);
+        if implicit && !options.implicit_sized {
+            continue;
+        }
+        let for_lifetimes = predicate
+            .kind()
+            .bound_vars()
+            .iter()
+            .filter_map(|bound_var| match bound_var {
+                BoundVariableKind::Region(ty::BrNamed(_, name)) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let (kind, trait_ref) = match predicate.kind().skip_binder() {
+            PredicateKind::Clause(Clause::Trait(trait_predicate)) => (
+                WherePredicateKind::Trait {
+                    bounded: with_no_trimmed_paths!(trait_predicate.self_ty().to_string()),
+                    trait_path: with_no_trimmed_paths!(tcx.def_path_str(trait_predicate.def_id())),
+                    generic_args: trait_predicate.trait_ref.substs[1..]
+                        .iter()
+                        .map(|arg| with_no_trimmed_paths!(arg.to_string()))
+                        .collect(),
+                    assoc_bindings: Vec::new(),
+                    for_lifetimes,
+                    implicit,
+                },
+                Some(predicate.kind().rebind(trait_predicate.trait_ref)),
+            ),
+            PredicateKind::Clause(Clause::Projection(projection)) => {
+                // `T: Iterator<Item = U>` is `T: Iterator` and `<T as Iterator>::Item == U`.
+                let trait_ref = predicate
+                    .kind()
+                    .rebind(projection.projection_ty.trait_ref(tcx));
+                let binding = AssocBinding {
+                    name: tcx.item_name(projection.projection_ty.def_id).to_string(),
+                    term: with_no_trimmed_paths!(projection.term.to_string()),
+                };
+                let bound = where_predicates
+                    .iter_mut()
+                    .find(|(_, bound_trait_ref)| *bound_trait_ref == Some(trait_ref));
+                if let Some((
+                    WherePredicate {
+                        kind: WherePredicateKind::Trait { assoc_bindings, .. },
+                        ..
+                    },
+                    _,
+                )) = bound
+                {
+                    assoc_bindings.push(binding);
+                    continue;
+                }
+                (
+                    WherePredicateKind::Projection {
+                        bounded: with_no_trimmed_paths!(projection.projection_ty.to_string()),
+                        term: binding.term,
+                    },
+                    None,
+                )
+            }
+            PredicateKind::Clause(Clause::TypeOutlives(ty::OutlivesPredicate(ty, region))) => (
+                WherePredicateKind::TypeOutlives {
+                    bounded: with_no_trimmed_paths!(ty.to_string()),
+                    lifetime: region.to_string(),
+                },
+                None,
+            ),
+            PredicateKind::Clause(Clause::RegionOutlives(ty::OutlivesPredicate(a, b))) => (
+                WherePredicateKind::LifetimeOutlives {
+                    lifetime: a.to_string(),
+                    bound: b.to_string(),
+                },
+                None,
+            ),
+            _ => (WherePredicateKind::Other, None),
+        };
+        let predicate = WherePredicate {
+            display: with_no_trimmed_paths!(predicate.to_string()),
+            kind,
+        };
+        where_predicates.push((predicate, trait_ref));
+    }
+    let predicates = where_predicates
+        .into_iter()
+        .map(|(mut predicate, _)| {
+            if let WherePredicateKind::Trait {
+                bounded,
+                trait_path,
+                generic_args,
+                assoc_bindings,
+                for_lifetimes,
+                ..
+            } = &predicate.kind
+            {
+                predicate.display = trait_display(
+                    bounded,
+                    trait_path,
+                    generic_args,
+                    assoc_bindings,
+                    for_lifetimes,
+                );
+            }
+            predicate
+        })
+        .collect();
+    GenericsInfo { params, predicates }
+}
+
+/// Returns `bounded: for<'a> trait_path<generic_args, assoc_bindings>`.
+fn trait_display(
+    bounded: &str,
+    trait_path: &str,
+    generic_args: &[String],
+    assoc_bindings: &[AssocBinding],
+    for_lifetimes: &[String],
+) -> String {
+    let mut display = format!("{bounded}: ");
+    if !for_lifetimes.is_empty() {
+        display.push_str(&format!("for<{}> ", for_lifetimes.join(", ")));
+    }
+    display.push_str(trait_path);
+    let args = generic_args
+        .iter()
+        .cloned()
+        .chain(
+            assoc_bindings
+                .iter()
+                .map(|binding| format!("{} = {}", binding.name, binding.term)),
+        )
+        .collect::<Vec<_>>();
+    if !args.is_empty() {
+        display.push_str(&format!("<{}>", args.join(", ")));
+    }
+    display
+}
@@ -1,27 +1,66 @@
+use rustc_ast::{ast, attr, AttrStyle};
 use rustc_data_structures::sync::{Lrc, Send};
 use rustc_data_structures::unord::UnordSet;
-use rustc_driver::abort_on_err;
 use rustc_errors::emitter::{Emitter, EmitterWriter};
 use rustc_errors::json::JsonEmitter;
-use rustc_errors::ErrorGuaranteed;
+use rustc_errors::{DelayedBugPanic, DiagnosticId, ExplicitBug, FatalError};
 use rustc_feature::UnstableFeatures;
 use rustc_hir::def_id::LocalDefId;
-use rustc_interface::interface;
+use rustc_interface::{interface, Queries};
+use rustc_lint::{CheckLintNameResult, LintStore};
+use rustc_middle::ty::query::{ExternProviders, Providers};
 use rustc_middle::ty::TyCtxt;
 use rustc_session::config::{
-    parse_crate_types_from_list, parse_externs, rustc_optgroups, CodegenOptions, ErrorOutputType,
-    Input, Options, UnstableOptions,
+    nightly_options, parse_crate_types_from_list, parse_externs, rustc_optgroups, CodegenOptions,
+    ErrorOutputType, ExternEntry, ExternLocation, Externs, Input, Options, OutputType, OutputTypes,
+    SwitchWithOptPath, UnstableOptions, CG_OPTIONS, Z_OPTIONS,
 };
-use rustc_session::early_error_no_abort;
+use rustc_session::filesearch;
+use rustc_session::lint::Level;
+use rustc_session::output::filename_for_metadata;
+use rustc_session::parse::ParseSess;
 use rustc_session::search_paths::SearchPath;
-use rustc_session::{config, early_error, getopts};
-use rustc_span::source_map::{FilePathMapping, SourceMap};
-use rustc_span::FileName;
+use rustc_session::utils::CanonicalizedPath;
+use rustc_session::{config, getopts, Session};
+use rustc_span::def_id::LOCAL_CRATE;
+use rustc_span::edition::{Edition, EDITION_NAME_LIST};
+use rustc_span::source_map::{FileLoader, FilePathMapping, RealFileLoader, SourceMap};
+use rustc_span::symbol::{sym, Symbol};
+use rustc_span::{FileName, RealFileName, DUMMY_SP};
+use rustc_target::spec::{Target, TargetTriple};
 
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::fs;
 use std::io::{self, Read};
+use std::iter;
 use std::marker;
-use std::path::PathBuf;
-use std::sync::LazyLock;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, Once, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::diagnostics::{
+    take_exceeded_limit, take_missing_sysroot_crate, track_diagnostics, CollectingEmitter,
+    Diagnostic, DiagnosticsCollector,
+};
+use crate::file_loader::{
+    IncludeFileLoader, OverlayFileLoader, VirtualFileLoader, INCLUDE_BYTES_SUFFIX,
+    INCLUDE_STR_SUFFIX,
+};
+use crate::profiling::PassTimings;
+use crate::sysroot::{check_toolchain, detect_sysroot, rustc_path};
+use crate::{
+    AnalysisLevel, CancellationToken, Config, DiagnosticsBuffer, EmitKind, Error, ExternModifier,
+    LimitKind, VirtualCrate,
+};
 
 /// If you need more information than what is provided by
 /// [`with_ast_parser`](crate::with_ast_parser), this is the function you'll use.
@@ -29,7 +68,9 @@ use std::sync::LazyLock;
 /// **VERY IMPORTANT TO NOTE**: if you want to run this code on a crate with dependencies, you'll
 /// need to pass the according options so that `rustc` knows where to look for them. otherwise it
 /// will simply fail to compile and the `callback` won't be called. A good example of the list
-/// of the expected arguments can be seen when you run `cargo build -v`.
+/// of the expected arguments can be seen when you run `cargo build -v`. If you want to run it
+/// on a cargo package, [`cargo::args_for_package`](crate::cargo::args_for_package) returns them
+/// for you.
 ///
 /// Don't forget to take a look at the [`TyCtxt`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.TyCtxt.html)
 /// and at the [`Map`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/hir/map/struct.Map.html)
@@ -38,64 +79,1212 @@ use std::sync::LazyLock;
 /// And to make things much simpler, I strongly recommend to use
 /// the [HIR visitor](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_hir/intravisit/trait.Visitor.html).
 /// (You can take a look at how to use it with `examples/hir.rs`.)
+///
+/// It can be called as many times as needed in the same process. The calls made from different
+/// threads are serialized (the compiler runs one crate at a time), whereas a `callback` can call
+/// it again to analyze another crate in the meantime.
 pub fn with_tyctxt<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
     rustc_args: &[String],
     callback: F,
-) -> Result<T, String> {
-    // Most of this code comes from rustdoc.
-    rustc_driver::init_rustc_env_logger();
-    let args = rustc_driver::args::arg_expand_all(rustc_args);
+) -> Result<T, Error> {
+    Config::new(rustc_args).with_tyctxt(callback)
+}
 
-    let mut options = getopts::Options::new();
-    for option in rustc_optgroups() {
-        (option.apply)(&mut options);
+/// Same as [`with_tyctxt`] except that the source code is provided directly instead of being
+/// read from a file (or stdin). `rustc_args` must therefore not contain a file operand, but it can
+/// contain all the other arguments (`--extern`, `--edition`, `--cfg`, etc).
+///
+/// The source code will be named `<anon>` in the [`SourceMap`].
+pub fn with_tyctxt_from_str<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    source: &str,
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args)
+        .source_code(source)
+        .with_tyctxt(callback)
+}
+
+/// Same as [`with_tyctxt`] except that the crate is made of the files of `virtual_crate`, which
+/// are never read from the file system. `rustc_args` must not contain a file operand, like with
+/// [`with_tyctxt_from_str`].
+///
+/// A module whose file isn't in `virtual_crate` gives the usual "file not found for module"
+/// error.
+pub fn with_tyctxt_virtual<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    virtual_crate: VirtualCrate,
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args)
+        .virtual_crate(virtual_crate)
+        .with_tyctxt(callback)
+}
+
+/// Same as [`with_tyctxt`] but gives up once `timeout` has elapsed and returns
+/// [`Error::TimedOut`], for example to survive the inputs which make the type-checking hang.
+///
+/// The compiler runs on a new thread. When it times out, the analysis is cancelled (see
+/// [`Config::cancellation_token`]), but the compiler can't be interrupted in the middle of a
+/// query, so the thread is left running until it reaches the next check, or forever if the
+/// query never ends. In the meantime, the compiler can't be used by another analysis:
+/// [`with_tyctxt`] and the other functions wait for the thread to finish, whereas
+/// `with_tyctxt_timeout` waits for it until its own deadline and then returns
+/// [`Error::PreviousAnalysisRunning`] without running the compiler.
+pub fn with_tyctxt_timeout<T, F>(
+    rustc_args: &[String],
+    timeout: Duration,
+    callback: F,
+) -> Result<T, Error>
+where
+    T: marker::Send + 'static,
+    F: FnOnce(TyCtxt<'_>) -> T + marker::Send + 'static,
+{
+    let deadline = Instant::now() + timeout;
+    let lock_running = || {
+        TIMED_OUT_RUNNING
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    };
+    let mut running = lock_running();
+    while *running > 0 {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::PreviousAnalysisRunning);
+        }
+        running = TIMED_OUT_FINISHED
+            .wait_timeout(running, remaining)
+            .unwrap_or_else(PoisonError::into_inner)
+            .0;
+    }
+    drop(running);
+
+    let token = CancellationToken::new();
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    let rustc_args = rustc_args.to_vec();
+    thread::spawn({
+        let token = token.clone();
+        let timed_out = Arc::clone(&timed_out);
+        move || {
+            let res = Config::new(&rustc_args)
+                .cancellation_token(token)
+                .with_tyctxt(callback);
+            let mut running = lock_running();
+            if timed_out.load(Ordering::Relaxed) {
+                *running -= 1;
+                TIMED_OUT_FINISHED.notify_all();
+            } else {
+                let _ = sender.send(res);
+            }
+        }
+    });
+
+    let res = receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+    match res {
+        Ok(res) => res,
+        Err(RecvTimeoutError::Timeout) => {
+            // The thread may have finished in the meantime: it checks `timed_out` with the lock
+            // held.
+            let mut running = lock_running();
+            if let Ok(res) = receiver.try_recv() {
+                return res;
+            }
+            timed_out.store(true, Ordering::Relaxed);
+            *running += 1;
+            token.cancel();
+            Err(Error::TimedOut)
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(Error::InternalCompilerError {
+            message: "the thread of the analysis panicked".to_owned(),
+            backtrace: String::new(),
+        }),
+    }
+}
+
+/// The number of analyses started by [`with_tyctxt_timeout`] which timed out and are still
+/// running. [`TIMED_OUT_FINISHED`] is notified when one of them finishes.
+static TIMED_OUT_RUNNING: Mutex<usize> = Mutex::new(0);
+static TIMED_OUT_FINISHED: Condvar = Condvar::new();
+
+/// Same as [`with_tyctxt`] except that the diagnostics are not written to stderr but returned
+/// alongside the result instead.
+pub fn with_tyctxt_captured<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> (Result<T, Error>, String) {
+    let buffer = DiagnosticsBuffer::new();
+    let res = Config::new(rustc_args)
+        .diagnostics_buffer(buffer.clone())
+        .with_tyctxt(callback);
+    (res, buffer.take())
+}
+
+/// Same as [`with_tyctxt`] except that the diagnostics are not written to stderr but returned
+/// alongside the result as [`Diagnostic`].
+pub fn with_tyctxt_collect_diagnostics<
+    T: marker::Send,
+    F: FnOnce(TyCtxt<'_>) -> T + marker::Send,
+>(
+    rustc_args: &[String],
+    callback: F,
+) -> (Result<T, Error>, Vec<Diagnostic>) {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(rustc_args)
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(callback);
+    (res, collector.take())
+}
+
+/// Same as [`with_tyctxt_collect_diagnostics`] except that the crate is compiled with `edition`
+/// (whatever the `--edition` of `rustc_args`) and that the callback is called even if the crate
+/// contains errors (see [`with_tyctxt_allow_errors`]).
+///
+/// Running it with two editions and comparing the diagnostics shows what changes between them,
+/// like the identifiers which became keywords (the errors about them have a note giving the
+/// edition).
+pub fn analyze_under_edition<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc_args: &[String],
+    edition: Edition,
+    callback: F,
+) -> (Result<T, Error>, Vec<Diagnostic>) {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(rustc_args)
+        .edition(edition)
+        .allow_errors(true)
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(callback);
+    (res, collector.take())
+}
+
+/// Same as [`with_tyctxt`] except that the callback is called even if the crate contains errors
+/// (unresolved names, wrong types, etc). The diagnostics are not written to stderr but returned
+/// alongside the result as [`Diagnostic`] so you can see what went wrong.
+///
+/// Parsing errors the compiler cannot recover from will still return
+/// [`Error::CompilationFailed`].
+///
+/// Since the crate doesn't compile, the `TyCtxt` must be used with care: the item tree (items,
+/// their names, their attributes, their spans and their children) is reliable, as are the
+/// bodies' HIR. However, anything depending on name resolution or type information (for example
+/// `type_of`, `fn_sig` or the `Res` of a path) may contain `{type error}` or `Res::Err` for the
+/// erroneous parts, and some queries may even panic if they hit them, so try to stay at the HIR
+/// level as much as possible.
+pub fn with_tyctxt_allow_errors<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<(T, Vec<Diagnostic>), Error> {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(rustc_args)
+        .collect_diagnostics(collector.clone())
+        .allow_errors(true)
+        .with_tyctxt(callback)?;
+    Ok((res, collector.take()))
+}
+
+/// Same as [`with_tyctxt`] except that the whole analysis (type checking, borrow checking, lints,
+/// etc) is run before calling the callback, meaning that you can look at the
+/// [MIR](https://rustc-dev-guide.rust-lang.org/mir/index.html) of the local functions with
+/// [`TyCtxt::optimized_mir`] or [`TyCtxt::mir_built`]. It is of course slower than
+/// [`with_tyctxt`].
+///
+/// If the analysis emits errors, [`Error::CompilationFailed`] is returned.
+pub fn with_mir<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args)
+        .analysis_level(AnalysisLevel::Full)
+        .with_tyctxt(callback)
+}
+
+/// Same as [`with_mir`] except that `register_lints` is called with the `LintStore` so you can
+/// register your own lints and lint passes. They will be run alongside the compiler's lints and
+/// the diagnostics they emit go through the same emitter.
+///
+/// Take a look at `examples/lint.rs` to see how to write a lint.
+pub fn with_lints<
+    T: marker::Send,
+    R: Fn(&Session, &mut LintStore) + marker::Send + Sync + 'static,
+    F: FnOnce(TyCtxt<'_>) -> T + marker::Send,
+>(
+    rustc_args: &[String],
+    register_lints: R,
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args)
+        .analysis_level(AnalysisLevel::Full)
+        .register_lints(register_lints)
+        .with_tyctxt(callback)
+}
+
+/// Same as [`with_tyctxt`] except that `configure` is called with the compiler configuration
+/// before running it, allowing you to customize it. Take a look at [`Config::configure`] for more
+/// information.
+///
+/// For example, to mute all diagnostics (where `SilentEmitter` is an emitter discarding all
+/// diagnostics):
+///
+/// ```ignore (needs-rustc-private)
+/// use rustc_tools::rustc_errors::Handler;
+///
+/// rustc_tools::with_tyctxt_configured(&args, |config| {
+///     config.parse_sess_created = Some(Box::new(|parse_sess| {
+///         parse_sess.span_diagnostic = Handler::with_emitter(false, None, Box::new(SilentEmitter));
+///     }));
+/// }, |tcx| { /* ... */ });
+/// ```
+pub fn with_tyctxt_configured<
+    T: marker::Send,
+    C: FnOnce(&mut interface::Config) + 'static,
+    F: FnOnce(TyCtxt<'_>) -> T + marker::Send,
+>(
+    rustc_args: &[String],
+    configure: C,
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args)
+        .configure(configure)
+        .with_tyctxt(callback)
+}
+
+/// Same as [`with_tyctxt`] except that neither `callback` nor its result need to be `Send`, so
+/// `callback` can for example fill an `Rc<RefCell<_>>` owned by the caller.
+///
+/// The compiler always runs on its own thread, so `callback` is still called on another thread
+/// while the current one waits for it to return.
+///
+/// # Safety
+///
+/// `callback` and its result must not depend on the thread they're used on. In particular, they
+/// must not contain values which have to be dropped on the thread they were created on (like a
+/// `MutexGuard`) or which refer to the thread-local variables of the current thread (which are not
+/// the ones `callback` sees).
+pub unsafe fn with_tyctxt_local<T, F: FnOnce(TyCtxt<'_>) -> T>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args).with_tyctxt_local(callback)
+}
+
+/// Runs the compiler with `rustc_args` and calls `callback` with the compiler and its queries
+/// before anything is done, so it decides how far the compilation goes. It's meant for the tools
+/// which need what the compiler computes along the way (like the output file names or the
+/// dependency graph), the other functions (like [`with_tyctxt`]) are built on top of it.
+///
+/// The compiler is set up like with [`with_tyctxt`] (the sysroot detection, the diagnostics, the
+/// handling of the panics, etc), but the rest is up to `callback`:
+///
+/// * the queries must be called in order: once a step is done, its result can't be used anymore
+///   (calling `queries.parse()` after `queries.expansion()` panics for example);
+/// * nothing checks the errors emitted by the compiler, `callback` has to use
+///   `Session::has_errors` (the queries only return an error if the compiler can't go further);
+/// * the values of the compiler (like a `Symbol`, a `Span` or a `DefId`) only make sense while
+///   the compiler runs, so `callback` must convert what it returns into types which don't depend
+///   on it (like strings). It can't return references since the compiler is dropped when it
+///   returns.
+pub fn with_compiler<
+    T: marker::Send,
+    F: FnOnce(&interface::Compiler, &Queries<'_>) -> T + marker::Send,
+>(
+    rustc_args: &[String],
+    callback: F,
+) -> Result<T, Error> {
+    Config::new(rustc_args).with_compiler(callback)
+}
+
+pub(crate) fn run_compiler<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    config: Config,
+    callback: F,
+) -> Result<T, Error> {
+    run_compiler_emit(config, callback).map(|(value, _)| value)
+}
+
+/// Same as [`run_compiler`] but also returns the paths of the files given to [`Config::emit`].
+pub(crate) fn run_compiler_emit<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    mut config: Config,
+    callback: F,
+) -> Result<(T, Vec<PathBuf>), Error> {
+    if !config.emits.is_empty() {
+        // The compiler only writes the files once the analysis is done.
+        config.analysis_level = AnalysisLevel::Full;
     }
-    let matches = match options.parse(&args[..]) {
-        Ok(m) => m,
-        Err(err) => {
-            early_error(ErrorOutputType::default(), &err.to_string());
+    let allow_errors = config.allow_errors;
+    let analysis_level = config.analysis_level;
+    let timings = config.pass_timings.clone();
+    let timings = timings.as_ref();
+    let emits = config.emits.clone();
+    let codegen = emits.iter().any(|(kind, _)| *kind == EmitKind::LlvmIr);
+
+    let env_overlay = config.injected_env();
+    let include_overlay = config.has_file_loader();
+    let cancellation_token = config.cancellation_token.clone();
+    let (recursion_limit, type_length_limit) = (config.recursion_limit, config.type_length_limit);
+
+    let (value, paths) = enter_compiler(config, |compiler, queries| {
+        let sess = compiler.session();
+        PassTimings::time(timings, "parse", || {
+            queries.parse().map(|mut krate| {
+                infer_crate_type(sess, krate.get_mut());
+                apply_limits(sess, krate.get_mut(), recursion_limit, type_length_limit);
+                if let Some((vars, isolated)) = &env_overlay {
+                    inject_env_overlay(sess, krate.get_mut(), vars, *isolated);
+                }
+                if include_overlay {
+                    inject_include_overlay(sess, krate.get_mut());
+                }
+            })
+        })
+        .map_err(|_| compilation_failed(sess))?;
+        check_cancelled(&cancellation_token)?;
+        // FIXME: very likely unneeded.
+        PassTimings::time(timings, "expansion", || queries.expansion().map(|_| ()))
+            .map_err(|_| compilation_failed(sess))?;
+
+        if !allow_errors && sess.diagnostic().has_errors_or_lint_errors().is_some() {
+            return Err(compilation_failed(sess));
+        }
+
+        check_cancelled(&cancellation_token)?;
+        let global_ctxt = PassTimings::time(timings, "global_ctxt", || queries.global_ctxt())
+            .map_err(|_| compilation_failed(sess))?;
+
+        let (value, paths) = global_ctxt.enter(|tcx| {
+            // Errors are checked just below.
+            let _ = match analysis_level {
+                AnalysisLevel::HirOnly => Ok(()),
+                AnalysisLevel::TypeckBodies => PassTimings::time(timings, "typeck_bodies", || {
+                    rustc_hir_analysis::check_crate(tcx)
+                }),
+                AnalysisLevel::Full => PassTimings::time(timings, "analysis", || tcx.analysis(())),
+            };
+            if !allow_errors && sess.diagnostic().has_errors_or_lint_errors().is_some() {
+                return Err(compilation_failed(sess));
+            }
+            check_cancelled(&cancellation_token)?;
+            let value = PassTimings::time(timings, "callback", || callback(tcx));
+            // Like the compiler, nothing is written if the crate has errors.
+            if emits.is_empty() || sess.has_errors().is_some() {
+                return Ok((value, Vec::new()));
+            }
+            // Otherwise the code generation writes all the files.
+            if !codegen {
+                PassTimings::time(timings, "emit", || write_outputs(tcx, &emits))?;
+            }
+            let paths = emits
+                .iter()
+                .map(|(kind, _)| output_path(tcx, *kind))
+                .collect::<Vec<_>>();
+            Ok((value, paths))
+        })?;
+        if codegen && !paths.is_empty() {
+            PassTimings::time(timings, "codegen", || {
+                queries.linker().and_then(|linker| linker.link())
+            })
+            .map_err(|_| compilation_failed(sess))?;
         }
+        Ok((value, paths))
+    })?;
+
+    // The files are all written in the same directory, so they're moved to theirs.
+    let paths = paths
+        .into_iter()
+        .zip(&emits)
+        .map(|(path, (_, directory))| {
+            let Some(file_name) = path.file_name() else { return Ok(path) };
+            let destination = directory.join(file_name);
+            if destination != path {
+                fs::create_dir_all(directory).map_err(|e| {
+                    Error::Emit(format!("failed to create `{}`: {}", directory.display(), e))
+                })?;
+                fs::rename(&path, &destination).map_err(|e| {
+                    Error::Emit(format!("failed to move `{}`: {}", path.display(), e))
+                })?;
+            }
+            Ok(destination)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok((value, paths))
+}
+
+/// Writes the metadata and the MIR of the crate (if they're in `emits`) the way the compiler does
+/// before the code generation.
+fn write_outputs(tcx: TyCtxt<'_>, emits: &[(EmitKind, PathBuf)]) -> Result<(), Error> {
+    if emits.iter().any(|(kind, _)| *kind == EmitKind::Metadata) {
+        rustc_metadata::fs::encode_and_write_metadata(tcx);
+    }
+    if emits.iter().any(|(kind, _)| *kind == EmitKind::Mir) {
+        rustc_mir_transform::dump_mir::emit_mir(tcx)
+            .map_err(|e| Error::Emit(format!("failed to write the MIR: {e}")))?;
+    }
+    Ok(())
+}
+
+/// Returns the path of the `kind` file written by the compiler, in the output directory.
+fn output_path(tcx: TyCtxt<'_>, kind: EmitKind) -> PathBuf {
+    let outputs = tcx.output_filenames(());
+    match kind {
+        EmitKind::Metadata => filename_for_metadata(tcx.sess, tcx.crate_name(LOCAL_CRATE), outputs),
+        EmitKind::Mir => outputs.path(OutputType::Mir),
+        EmitKind::LlvmIr => outputs.path(OutputType::LlvmAssembly),
+    }
+}
+
+/// Returns the output type of the compiler writing the `kind` file.
+fn output_type(kind: EmitKind) -> OutputType {
+    match kind {
+        EmitKind::Metadata => OutputType::Metadata,
+        EmitKind::Mir => OutputType::Mir,
+        EmitKind::LlvmIr => OutputType::LlvmAssembly,
+    }
+}
+
+/// Adds `#![crate_type = "proc-macro"]` to the parsed crate if its type isn't given and if it
+/// defines proc macros, which the compiler only allows in proc-macro crates. It must be called
+/// before the expansion, which is when the compiler reads the crate types.
+pub(crate) fn infer_crate_type(sess: &Session, krate: &mut ast::Crate) {
+    if !sess.opts.crate_types.is_empty()
+        || sess.opts.test
+        || krate
+            .attrs
+            .iter()
+            .any(|attr| attr.has_name(sym::crate_type))
+    {
+        return;
+    }
+    let proc_macro_attrs = [
+        sym::proc_macro,
+        sym::proc_macro_derive,
+        sym::proc_macro_attribute,
+    ];
+    let defines_proc_macros = krate.items.iter().any(|item| {
+        item.attrs
+            .iter()
+            .any(|attr| proc_macro_attrs.iter().any(|name| attr.has_name(*name)))
+    });
+    if defines_proc_macros {
+        krate.attrs.push(attr::mk_attr_name_value_str(
+            &sess.parse_sess.attr_id_generator,
+            AttrStyle::Inner,
+            sym::crate_type,
+            Symbol::intern("proc-macro"),
+            DUMMY_SP,
+        ));
+    }
+}
+
+/// The limits of the compiler when the crate doesn't set them.
+const DEFAULT_RECURSION_LIMIT: usize = 128;
+const DEFAULT_TYPE_LENGTH_LIMIT: usize = 1048576;
+
+/// Replaces the `#![recursion_limit]` and `#![type_length_limit]` attributes of the parsed crate
+/// with the limits given to the [`Config`] (`None` keeps the ones of the crate), and records the
+/// limits in effect for [`Error::LimitExceeded`]. It must be called before the expansion, which
+/// uses the recursion limit.
+pub(crate) fn apply_limits(
+    sess: &Session,
+    krate: &mut ast::Crate,
+    recursion_limit: Option<usize>,
+    type_length_limit: Option<usize>,
+) {
+    let mut apply = |name: Symbol, limit: Option<usize>, default: usize| match limit {
+        Some(limit) => {
+            krate.attrs.retain(|attr| !attr.has_name(name));
+            krate.attrs.insert(
+                0,
+                attr::mk_attr_name_value_str(
+                    &sess.parse_sess.attr_id_generator,
+                    AttrStyle::Inner,
+                    name,
+                    Symbol::intern(&limit.to_string()),
+                    DUMMY_SP,
+                ),
+            );
+            limit
+        }
+        // Like the compiler, the first valid attribute is used.
+        None => krate
+            .attrs
+            .iter()
+            .filter(|attr| attr.has_name(name))
+            .find_map(|attr| attr.value_str()?.as_str().parse().ok())
+            .unwrap_or(default),
     };
+    let limits = (
+        apply(
+            sym::recursion_limit,
+            recursion_limit,
+            DEFAULT_RECURSION_LIMIT,
+        ),
+        apply(
+            sym::type_length_limit,
+            type_length_limit,
+            DEFAULT_TYPE_LENGTH_LIMIT,
+        ),
+    );
+    LIMITS.with(|cell| cell.set(limits));
+}
 
-    // Note that we discard any distinction between different non-zero exit
-    // codes from `from_matches` here.
-    let config = match create_config(&matches) {
-        Some(opts) => opts,
-        None => return Err("Failed to create_config".to_owned()),
+/// Defines `env!` and `option_env!` with `macro_rules!` at the beginning of the parsed crate, so
+/// they see the variables of `vars` (see [`Config::env_overlay`]). If `isolated` is `false`, the
+/// other variables are given by the macros of the standard library. It must be called before the
+/// expansion.
+pub(crate) fn inject_env_overlay(
+    sess: &Session,
+    krate: &mut ast::Crate,
+    vars: &HashMap<String, String>,
+    isolated: bool,
+) {
+    // Sorted so the expansion is always the same.
+    let vars = vars.iter().collect::<BTreeMap<_, _>>();
+    let core = core_path(sess, krate);
+    let option = if sess.edition() == Edition::Edition2015 {
+        "Option"
+    } else {
+        "::core::option::Option"
     };
+    let mut env_rules = String::new();
+    let mut option_env_rules = String::new();
+    for (name, value) in vars {
+        env_rules.push_str(&format!("({name:?} $(,)?) => {{ {value:?} }};\n"));
+        env_rules.push_str(&format!(
+            "({name:?}, $message:expr $(,)?) => {{ {value:?} }};\n"
+        ));
+        option_env_rules.push_str(&format!(
+            "({name:?} $(,)?) => {{ {option}::Some::<&'static str>({value:?}) }};\n"
+        ));
+    }
+    let (env_fallback, option_env_fallback) = if isolated {
+        (
+            "($name:expr $(,)?) => {
+    compile_error!(concat!(\"environment variable `\", $name, \"` not defined at compile time\"))
+};
+($name:expr, $message:expr $(,)?) => { compile_error!($message) };"
+                .to_owned(),
+            format!("($name:expr $(,)?) => {{ {option}::None::<&'static str> }};"),
+        )
+    } else {
+        (
+            format!("($($args:tt)*) => {{ {core}::env!($($args)*) }};"),
+            format!("($($args:tt)*) => {{ {core}::option_env!($($args)*) }};"),
+        )
+    };
+    let source = format!(
+        "#[allow(unused_macros)]
+macro_rules! env {{
+{env_rules}{env_fallback}
+}}
+#[allow(unused_macros)]
+macro_rules! option_env {{
+{option_env_rules}{option_env_fallback}
+}}"
+    );
+    inject_items(sess, krate, "env overlay", source);
+}
+
+/// Defines `include_str!` and `include_bytes!` with `macro_rules!` at the beginning of the parsed
+/// crate, so the files they include are read with the file loader of the [`Config`] (see
+/// [`Config::file_loader`]) instead of the file system. It must be called before the expansion.
+///
+/// They call `include!` with the path followed by a suffix, which the [`IncludeFileLoader`] reads
+/// with the file loader and turns into a literal. Like with `include!`, the relative paths are
+/// relative to the file of the outermost macro call.
+pub(crate) fn inject_include_overlay(sess: &Session, krate: &mut ast::Crate) {
+    let core = core_path(sess, krate);
+    let rules = |name: &str, suffix: &str| {
+        format!(
+            "#[allow(unused_macros)]
+macro_rules! {name} {{
+($file:expr $(,)?) => {{ {core}::include!({core}::concat!($file, {suffix:?})) }};
+($($args:tt)*) => {{ {core}::{name}!($($args)*) }};
+}}\n"
+        )
+    };
+    let source =
+        rules("include_str", INCLUDE_STR_SUFFIX) + &rules("include_bytes", INCLUDE_BYTES_SUFFIX);
+    inject_items(sess, krate, "include overlay", source);
+}
+
+/// The path of `core` from the crate root. The paths starting with `::` are relative to the crate
+/// root in the 2015 edition, where only `std` (or `core` with `#![no_std]`) is.
+fn core_path(sess: &Session, krate: &ast::Crate) -> &'static str {
+    let no_std = krate.attrs.iter().any(|attr| attr.has_name(sym::no_std));
+    if sess.edition() == Edition::Edition2015 && !no_std {
+        "::std"
+    } else {
+        "::core"
+    }
+}
+
+/// Parses `source` and adds its items at the beginning of the crate.
+fn inject_items(sess: &Session, krate: &mut ast::Crate, name: &str, source: String) {
+    match rustc_parse::parse_crate_from_source_str(
+        FileName::Custom(name.to_owned()),
+        source,
+        &sess.parse_sess,
+    ) {
+        Ok(overlay) => {
+            krate.items.splice(0..0, overlay.items);
+        }
+        Err(mut err) => {
+            err.emit();
+        }
+    }
+}
+
+thread_local! {
+    /// `true` on the threads running the compiler.
+    static IN_COMPILER: Cell<bool> = Cell::new(false);
+    /// The recursion and type-length limits of the crate, see [`apply_limits`].
+    static LIMITS: Cell<(usize, usize)> =
+        Cell::new((DEFAULT_RECURSION_LIMIT, DEFAULT_TYPE_LENGTH_LIMIT));
+    /// The token given to [`Config::cancellation_token`], set on the thread of the compiler for
+    /// the queries to check it.
+    static CANCELLATION_TOKEN: RefCell<Option<CancellationToken>> = RefCell::new(None);
+}
+
+fn check_cancelled(token: &Option<CancellationToken>) -> Result<(), Error> {
+    match token {
+        Some(token) if token.is_cancelled() => Err(Error::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// Stops the compilation (like a fatal error does) if the analysis was cancelled. It's called by
+/// the queries, see [`cancellation_providers`].
+fn abort_if_cancelled() {
+    let cancelled = CANCELLATION_TOKEN.with(|token| {
+        token
+            .borrow()
+            .as_ref()
+            .map_or(false, CancellationToken::is_cancelled)
+    });
+    if cancelled {
+        FatalError.raise();
+    }
+}
+
+/// Same as [`run_compiler`] without the `Send` bounds, see [`with_tyctxt_local`] for the safety
+/// requirements.
+pub(crate) unsafe fn run_compiler_local<T, F: FnOnce(TyCtxt<'_>) -> T>(
+    config: Config,
+    callback: F,
+) -> Result<T, Error> {
+    let callback = AssertSend(callback);
+    // `callback.into_inner()` makes the closure capture the whole wrapper and not only its field.
+    run_compiler(config, move |tcx| AssertSend(callback.into_inner()(tcx)))
+        .map(AssertSend::into_inner)
+}
+
+/// Moves a value to the compiler thread and back while the current thread is waiting.
+struct AssertSend<T>(T);
+
+// SAFETY: only used by `run_compiler_local`, whose callers ensure that the value doesn't depend on
+// the thread it's used on. The current thread doesn't use it while it's on the compiler thread.
+unsafe impl<T> marker::Send for AssertSend<T> {}
+
+impl<T> AssertSend<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Runs the compiler with `config` and calls `f` with its queries, so it decides how far the
+/// compilation goes.
+///
+/// The compilers started from different threads run one after the other.
+pub(crate) fn enter_compiler<
+    T: marker::Send,
+    F: for<'tcx> FnOnce(&interface::Compiler, &'tcx Queries<'tcx>) -> Result<T, Error> + marker::Send,
+>(
+    config: Config,
+    f: F,
+) -> Result<T, Error> {
+    static INIT_LOGGER: Once = Once::new();
+    static COMPILER_LOCK: Mutex<()> = Mutex::new(());
+
+    // The logger can only be initialized once per process.
+    INIT_LOGGER.call_once(rustc_driver::init_rustc_env_logger);
+
+    // The panic hook is process-wide so the compilers can't run concurrently (see `catch_ice`).
+    // A callback can still run the compiler again: it's not concurrent with the compiler the
+    // callback comes from, which is waiting for it.
+    let _guard = if IN_COMPILER.with(Cell::get) {
+        None
+    } else {
+        // The lock can't be poisoned since the panics are caught while it's held.
+        Some(COMPILER_LOCK.lock().unwrap_or_else(PoisonError::into_inner))
+    };
+    catch_ice(|| {
+        // Fatal errors unwind with a special marker value. We don't want them to reach the caller
+        // so we convert them into `Error::CompilationFailed`. The ones emitted once the session
+        // exists are caught below so we can get the actual number of errors.
+        rustc_driver::catch_fatal_errors(|| enter_compiler_inner(config, f))
+            .unwrap_or(Err(Error::CompilationFailed { error_count: 1 }))
+    })
+}
+
+/// Calls `f` and converts the panics of the compiler into `Error::InternalCompilerError`.
+///
+/// The panic hook is replaced while `f` runs so the panics of the current thread and of the
+/// compiler thread (named `rustc`) are neither printed nor reported as ICEs. The panics of the
+/// other threads go to the previous hook, which is restored once `f` returns.
+fn catch_ice<T>(f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    let caller = thread::current().id();
+    let captured = Arc::new(Mutex::new(None));
+    let previous = Arc::new(panic::take_hook());
+    panic::set_hook({
+        let captured = Arc::clone(&captured);
+        let previous = Arc::clone(&previous);
+        Box::new(move |info| {
+            let current = thread::current();
+            if current.id() != caller && current.name() != Some("rustc") {
+                return previous(info);
+            }
+            let mut captured = captured.lock().unwrap();
+            // The first panic is the interesting one, the next ones are usually caused by it.
+            if captured.is_none() {
+                let backtrace = Backtrace::force_capture().to_string();
+                *captured = Some((panic_message(info.payload()), backtrace));
+            }
+        })
+    });
+
+    let res = panic::catch_unwind(AssertUnwindSafe(f));
+
+    // Drops our hook so `previous` can be moved back.
+    drop(panic::take_hook());
+    match Arc::try_unwrap(previous) {
+        Ok(previous) => panic::set_hook(previous),
+        Err(previous) => panic::set_hook(Box::new(move |info| previous(info))),
+    }
+
+    res.unwrap_or_else(|payload| {
+        let (message, backtrace) = captured
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| (panic_message(&*payload), String::new()));
+        Err(Error::InternalCompilerError { message, backtrace })
+    })
+}
+
+fn panic_message(payload: &(dyn Any + marker::Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else if payload.is::<ExplicitBug>() || payload.is::<DelayedBugPanic>() {
+        // The message of the bug is emitted as a diagnostic before panicking.
+        "the compiler hit a bug, see the emitted diagnostics".to_owned()
+    } else {
+        "the compiler panicked".to_owned()
+    }
+}
+
+pub(crate) fn compilation_failed(sess: &Session) -> Error {
+    Error::CompilationFailed {
+        error_count: sess.diagnostic().err_count(),
+    }
+}
+
+fn enter_compiler_inner<
+    T: marker::Send,
+    F: for<'tcx> FnOnce(&interface::Compiler, &'tcx Queries<'tcx>) -> Result<T, Error> + marker::Send,
+>(
+    config: Config,
+    f: F,
+) -> Result<T, Error> {
+    let matches = parse_args(&expand_args(&config.rustc_args)?)?;
+    let stack_size = config.stack_size;
+    let keep_built_mir = config.keep_built_mir;
+    let borrowck_facts = config.borrowck_facts;
+    let cancellation_token = config.cancellation_token.clone();
+    check_cancelled(&cancellation_token)?;
+    // The arguments the compiler would reject with `early_error` are checked by `create_config`,
+    // so the fatal errors left here come from arguments we don't know about.
+    let config = rustc_driver::catch_fatal_errors(|| create_config(&matches, config))
+        .unwrap_or_else(|_| {
+            Err(Error::ArgParse(
+                "invalid arguments (see the error written to stderr)".to_owned(),
+            ))
+        })?;
 
-    interface::run_compiler(config, |compiler| {
+    let res = interface::run_compiler(config, |compiler| {
+        // This is the thread of the compiler, which is dropped once it returns.
+        IN_COMPILER.with(|in_compiler| in_compiler.set(true));
+        CANCELLATION_TOKEN.with(|token| *token.borrow_mut() = cancellation_token.clone());
+        crate::mir::keep_built_mir(keep_built_mir);
+        crate::borrowck::keep_borrowck_facts(borrowck_facts);
         let sess = compiler.session();
+        track_diagnostics(sess.parse_sess.clone_source_map());
 
         if sess.opts.describe_lints {
-            early_error(
-                ErrorOutputType::default(),
-                "`describe-lints` option is not allowed",
-            );
+            return Err(Error::ArgParse(
+                "`describe-lints` option is not allowed".to_owned(),
+            ));
         }
 
-        compiler.enter(|queries| {
-            {
-                // FIXME: very likely unneeded.
-                let _expansion = abort_on_err(queries.expansion(), sess);
+        let enter = || {
+            compiler.enter(|queries| {
+                rustc_driver::catch_fatal_errors(|| f(compiler, queries))
+                    .unwrap_or_else(|_| Err(compilation_failed(sess)))
+            })
+        };
+        let res = match stack_size {
+            // The compiler thread is already spawned, so the analysis switches to a new stack.
+            Some(stack_size) => stacker::grow(stack_size, enter),
+            None => enter(),
+        };
+        match (res, take_missing_sysroot_crate(), take_exceeded_limit()) {
+            (Err(Error::CompilationFailed { .. }), Some(krate), _) => {
+                Err(Error::MissingSysrootCrate(krate.to_owned()))
+            }
+            (Err(Error::CompilationFailed { .. }), _, Some((which, span))) => {
+                let (recursion_limit, type_length_limit) = LIMITS.with(Cell::get);
+                let limit = match which {
+                    LimitKind::Recursion => recursion_limit,
+                    LimitKind::TypeLength => type_length_limit,
+                };
+                Err(Error::LimitExceeded { which, limit, span })
             }
+            (res, ..) => res,
+        }
+    });
+    // The compilation may have been stopped by a query, see `abort_if_cancelled`.
+    match res {
+        Err(_) => check_cancelled(&cancellation_token).and(res),
+        Ok(_) => res,
+    }
+}
+
+/// Checks that `core` can be found for `target`, either in the sysroot or in the `-L` search
+/// paths, so a target which isn't installed gives a helpful error instead of the "can't find crate"
+/// one of the compiler.
+fn check_target_libs(
+    target: &TargetTriple,
+    sysroot: Option<&Path>,
+    search_paths: &[SearchPath],
+) -> Result<(), Error> {
+    let sysroot = match sysroot {
+        Some(sysroot) => sysroot.to_owned(),
+        // The compiler will fail to find it as well.
+        None => match filesearch::get_or_default_sysroot() {
+            Ok(sysroot) => sysroot,
+            Err(_) => return Ok(()),
+        },
+    };
+    let triple = target.triple().to_owned();
+    if let Err(e) = Target::search(target, &sysroot) {
+        return Err(Error::MissingTargetLibs {
+            triple,
+            hint: format!("{e} (`rustc --print target-list` lists the known targets)"),
+        });
+    }
 
-            if sess.diagnostic().has_errors_or_lint_errors().is_some() {
-                sess.fatal("Compilation failed, aborting");
+    let has_core = iter::once(filesearch::make_target_lib_path(&sysroot, &triple))
+        .chain(
+            search_paths
+                .iter()
+                .map(|search_path| search_path.dir.clone()),
+        )
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("libcore-") && (name.ends_with(".rlib") || name.ends_with(".rmeta"))
+        });
+    if has_core {
+        return Ok(());
+    }
+    let hint = match target {
+        TargetTriple::TargetTriple(_) => {
+            format!("install them with `rustup target add {triple}`")
+        }
+        TargetTriple::TargetJson { .. } => {
+            "build `core` for this target (with `cargo -Z build-std` for example) and pass its \
+             directory with `-L`"
+                .to_owned()
+        }
+    };
+    Err(Error::MissingTargetLibs { triple, hint })
+}
+
+/// Adds the extern crates given with the `Config` to the ones of the `--extern` arguments, the way
+/// `--extern` does. The `.rlib` files are then replaced by the `.rmeta` next to them if they exist:
+/// only the metadata of the dependencies is read, and the compiler would pick the `.rmeta` anyway
+/// if both were given.
+fn add_externs(
+    externs: Externs,
+    config_externs: impl Iterator<Item = (String, Option<PathBuf>, Vec<ExternModifier>)>,
+) -> Result<Externs, Error> {
+    let mut entries = externs
+        .iter()
+        .map(|(name, entry)| (name.clone(), entry.clone()))
+        .collect::<BTreeMap<_, _>>();
+    for (name, path, modifiers) in config_externs {
+        if !name.is_ascii() || !rustc_lexer::is_ident(&name) {
+            return Err(Error::InvalidExtern(format!(
+                "`{name}` isn't a valid crate name"
+            )));
+        }
+        let entry = entries.entry(name).or_insert_with(|| ExternEntry {
+            location: ExternLocation::FoundInLibrarySearchDirectories,
+            is_private_dep: false,
+            add_prelude: false,
+            nounused_dep: false,
+        });
+        if let Some(path) = path {
+            let path = CanonicalizedPath::new(&path);
+            match &mut entry.location {
+                ExternLocation::ExactPaths(paths) => {
+                    paths.insert(path);
+                }
+                location => *location = ExternLocation::ExactPaths(BTreeSet::from([path])),
             }
+        }
+        entry.is_private_dep |= modifiers.contains(&ExternModifier::Priv);
+        entry.add_prelude |= !modifiers.contains(&ExternModifier::NoPrelude);
+    }
 
-            let global_ctxt = abort_on_err(queries.global_ctxt(), sess);
+    for entry in entries.values_mut() {
+        if let ExternLocation::ExactPaths(paths) = &mut entry.location {
+            *paths = paths
+                .iter()
+                .map(|path| {
+                    let rmeta = path.original().with_extension("rmeta");
+                    if path.original().extension() == Some("rlib".as_ref()) && rmeta.is_file() {
+                        CanonicalizedPath::new(&rmeta)
+                    } else {
+                        path.clone()
+                    }
+                })
+                .collect();
+        }
+    }
+    Ok(Externs::new(entries))
+}
 
-            global_ctxt.enter(|tcx| Ok(callback(tcx)))
-        })
-    })
+/// Checks that `path` is a proc-macro crate which can be loaded by the compiler, so it doesn't fail
+/// (or crash) when loading it.
+fn check_proc_macro(path: &Path) -> Result<(), Error> {
+    if path.extension() != Some(env::consts::DLL_EXTENSION.as_ref()) {
+        return Err(Error::InvalidExtern(format!(
+            "`{}` isn't a `.{}`: proc macros are run by the compiler, so they must be built as \
+             dynamic libraries for the host (`{}`)",
+            path.display(),
+            env::consts::DLL_EXTENSION,
+            config::host_triple(),
+        )));
+    }
+    let content = fs::read(path)
+        .map_err(|e| Error::InvalidExtern(format!("failed to read `{}`: {}", path.display(), e)))?;
+    // The compiler finds the macros with this symbol (followed by the hash of the crate), which is
+    // exported by all the proc-macro crates.
+    let symbol = b"__rustc_proc_macro_decls_";
+    if content.windows(symbol.len()).any(|bytes| bytes == symbol) {
+        Ok(())
+    } else {
+        Err(Error::InvalidExtern(format!(
+            "`{}` isn't a proc-macro crate",
+            path.display()
+        )))
+    }
 }
 
-fn make_input(
-    error_format: ErrorOutputType,
-    free_matches: &[String],
-    diag: &rustc_errors::Handler,
-) -> Result<Option<Input>, ErrorGuaranteed> {
+/// Replaces the `@file` arguments of `args` with the content of the files.
+pub(crate) fn expand_args(args: &[String]) -> Result<Vec<String>, Error> {
+    rustc_driver::catch_fatal_errors(|| rustc_driver::args::arg_expand_all(args))
+        .map_err(|_| Error::ArgParse("failed to load an argument file".to_owned()))
+}
+
+/// Parses `args` (whose `@file` arguments are already expanded) like `rustc` does.
+pub(crate) fn parse_args(args: &[String]) -> Result<getopts::Matches, Error> {
+    // Most of this code comes from rustdoc.
+    let mut options = getopts::Options::new();
+    for option in rustc_optgroups() {
+        (option.apply)(&mut options);
+    }
+    options
+        .parse(args)
+        .map_err(|err| Error::ArgParse(err.to_string()))
+}
+
+/// Checks the arguments choosing how the diagnostics are displayed (`--color`, `--error-format`
+/// and `--json`), with the same messages as the compiler.
+fn check_diagnostic_args(matches: &getopts::Matches) -> Result<(), String> {
+    if let Some(arg) = matches.opt_str("color") {
+        if !["auto", "always", "never"].contains(&arg.as_str()) {
+            return Err(format!(
+                "argument for `--color` must be auto, always or never (instead was `{arg}`)"
+            ));
+        }
+    }
+    let json = matches.opt_strs("json");
+    if !json.is_empty() && matches.opt_present("color") {
+        return Err("cannot specify the `--color` option with `--json`".to_owned());
+    }
+    for option in json.iter().flat_map(|option| option.split(',')) {
+        if ![
+            "diagnostic-short",
+            "diagnostic-rendered-ansi",
+            "artifacts",
+            "unused-externs",
+            "unused-externs-silent",
+            "future-incompat",
+        ]
+        .contains(&option)
+        {
+            return Err(format!("unknown `--json` option `{option}`"));
+        }
+    }
+    let error_format = if matches.opts_present(&["error-format".to_owned()]) {
+        matches.opt_str("error-format")
+    } else {
+        None
+    };
+    match error_format.as_deref() {
+        None | Some("human" | "human-annotate-rs" | "short") if !json.is_empty() => {
+            Err("using `--json` requires also using `--error-format=json`".to_owned())
+        }
+        None | Some("human" | "human-annotate-rs" | "short" | "json" | "pretty-json") => Ok(()),
+        Some(arg) => Err(format!(
+            "argument for `--error-format` must be `human`, `json` or `short` (instead was \
+             `{arg}`)"
+        )),
+    }
+}
+
+/// Checks the arguments the compiler reports with `early_error` while building its options, with
+/// the same messages. `early_error` writes to stderr (before any handler exists) and stops the
+/// compilation, so they're checked first to be reported like the other errors.
+fn check_args(matches: &getopts::Matches) -> Result<(), String> {
+    if let Some(arg) = matches.opt_str("edition") {
+        let edition = arg.parse::<Edition>().map_err(|_| {
+            format!(
+                "argument for `--edition` must be one of: {EDITION_NAME_LIST}. (instead was \
+                 `{arg}`)"
+            )
+        })?;
+        if !edition.is_stable() && !nightly_options::is_unstable_enabled(matches) {
+            return Err(format!(
+                "edition {edition} is unstable and only available with -Z unstable-options"
+            ));
+        }
+    }
+    check_options(matches, CG_OPTIONS, "C", "codegen")?;
+    check_options(matches, Z_OPTIONS, "Z", "unstable")?;
+    if let Some(cap) = matches.opt_str("cap-lints") {
+        if Level::from_str(&cap).is_none() {
+            return Err(format!("unknown lint level: `{cap}`"));
+        }
+    }
+    for path in matches.opt_strs("L") {
+        let path = ["native=", "crate=", "dependency=", "framework=", "all="]
+            .iter()
+            .find_map(|kind| path.strip_prefix(kind))
+            .unwrap_or(&path);
+        if path.is_empty() {
+            return Err("empty search path given via `-L`".to_owned());
+        }
+    }
+    for arg in matches.opt_strs("extern") {
+        let (name, path) = match arg.split_once('=') {
+            Some((name, path)) => (name, Some(path)),
+            None => (arg.as_str(), None),
+        };
+        let Some((options, _)) = name.split_once(':') else { continue };
+        if !nightly_options::is_unstable_enabled(matches) {
+            return Err(
+                "the `-Z unstable-options` flag must also be passed to enable `--extern options"
+                    .to_owned(),
+            );
+        }
+        for option in options.split(',') {
+            match option {
+                "priv" | "nounused" => {}
+                "noprelude" if path.is_some() => {}
+                "noprelude" => {
+                    return Err("the `noprelude` --extern option requires a file path".to_owned())
+                }
+                _ => return Err(format!("unknown --extern option `{option}`")),
+            }
+        }
+    }
+    match matches.opt_str("target") {
+        Some(target)
+            if target.ends_with(".json")
+                && TargetTriple::from_path(Path::new(&target)).is_err() =>
+        {
+            Err(format!("target file {target:?} does not exist"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The `-C` or `-Z` options: their name, the function setting them, and the descriptions of their
+/// type and of what they do.
+type OptionDescrs<O> = &'static [(
+    &'static str,
+    fn(&mut O, Option<&str>) -> bool,
+    &'static str,
+    &'static str,
+)];
+
+/// Checks the `-C` or `-Z` options (depending on `prefix`) like `CodegenOptions::build` and
+/// `UnstableOptions::build` do.
+fn check_options<O: Default>(
+    matches: &getopts::Matches,
+    descrs: OptionDescrs<O>,
+    prefix: &str,
+    outputname: &str,
+) -> Result<(), String> {
+    let mut options = O::default();
+    for option in matches.opt_strs(prefix) {
+        let (key, value) = match option.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (option.as_str(), None),
+        };
+        let Some((_, setter, type_desc, _)) = descrs
+            .iter()
+            .find(|(name, ..)| *name == key.replace('-', "_"))
+        else {
+            return Err(format!("unknown {outputname} option: `{key}`"));
+        };
+        if !setter(&mut options, value) {
+            return Err(match value {
+                Some(value) => format!(
+                    "incorrect value `{value}` for {outputname} option `{key}` - {type_desc} was \
+                     expected"
+                ),
+                None => format!(
+                    "{outputname} option `{key}` requires {type_desc} ({prefix} {key}=<value>)"
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Reports the invalid argument described by `msg` and returns the error.
+fn invalid_arg(msg: String, error_format: ErrorOutputType, config: &Config) -> Error {
+    new_handler(
+        error_format,
+        None,
+        None,
+        &UnstableOptions::default(),
+        true,
+        config.diagnostics_buffer.as_ref(),
+        config.diagnostics_collector.as_ref(),
+    )
+    .struct_err(&msg)
+    .emit();
+    Error::ArgParse(msg)
+}
+
+fn make_input(free_matches: &[String], diag: &rustc_errors::Handler) -> Result<Input, Error> {
     if free_matches.len() == 1 {
         let ifile = &free_matches[0];
         if ifile == "-" {
@@ -103,25 +1292,23 @@ fn make_input(
             if io::stdin().read_to_string(&mut src).is_err() {
                 // Immediately stop compilation if there was an issue reading
                 // the input (for example if the input stream is not UTF-8).
-                let reported = early_error_no_abort(
-                    error_format,
-                    "couldn't read from stdin, as it did not contain valid UTF-8",
-                );
-                return Err(reported);
+                let msg = "couldn't read from stdin, as it did not contain valid UTF-8";
+                diag.struct_err(msg).emit();
+                return Err(Error::InvalidInput(msg.to_owned()));
             }
-            Ok(Some(Input::Str {
+            Ok(Input::Str {
                 name: FileName::anon_source_code(&src),
                 input: src,
-            }))
+            })
         } else {
-            Ok(Some(Input::File(PathBuf::from(ifile))))
+            Ok(Input::File(PathBuf::from(ifile)))
         }
     } else if free_matches.is_empty() {
         diag.struct_err("missing file operand").emit();
-        Ok(None)
+        Err(Error::InputMissing)
     } else {
         diag.struct_err("too many file operands").emit();
-        Ok(None)
+        Err(Error::TooManyInputs)
     }
 }
 
@@ -130,16 +1317,42 @@ fn new_handler(
     source_map: Option<Lrc<SourceMap>>,
     diagnostic_width: Option<usize>,
     unstable_opts: &UnstableOptions,
+    can_emit_warnings: bool,
+    diagnostics_buffer: Option<&DiagnosticsBuffer>,
+    diagnostics_collector: Option<&DiagnosticsCollector>,
 ) -> rustc_errors::Handler {
     let fallback_bundle =
         rustc_errors::fallback_fluent_bundle(rustc_errors::DEFAULT_LOCALE_RESOURCES, false);
-    let emitter: Box<dyn Emitter + Send> = match error_format {
-        ErrorOutputType::HumanReadable(kind) => {
+    let emitter: Box<dyn Emitter + Send> = match (error_format, diagnostics_collector) {
+        (_, Some(collector)) => Box::new(CollectingEmitter {
+            source_map,
+            fallback_bundle,
+            collector: collector.clone(),
+            buffer: diagnostics_buffer.cloned(),
+            short_message: matches!(
+                error_format,
+                ErrorOutputType::HumanReadable(kind) if kind.unzip().0
+            ),
+            diagnostic_width,
+        }),
+        (ErrorOutputType::HumanReadable(kind), None) => {
             let (short, color_config) = kind.unzip();
-            Box::new(
-                EmitterWriter::stderr(
+            let emitter = match diagnostics_buffer {
+                Some(buffer) => EmitterWriter::new(
+                    Box::new(buffer.clone()),
+                    source_map,
+                    None,
+                    fallback_bundle,
+                    short,
+                    unstable_opts.teach,
+                    false,
+                    diagnostic_width,
+                    false,
+                    unstable_opts.track_diagnostics,
+                ),
+                None => EmitterWriter::stderr(
                     color_config,
-                    source_map.map(|sm| sm as _),
+                    source_map,
                     None,
                     fallback_bundle,
                     short,
@@ -147,19 +1360,34 @@ fn new_handler(
                     diagnostic_width,
                     false,
                     unstable_opts.track_diagnostics,
-                )
-                .ui_testing(unstable_opts.ui_testing),
-            )
+                ),
+            };
+            Box::new(emitter.ui_testing(unstable_opts.ui_testing))
         }
-        ErrorOutputType::Json {
-            pretty,
-            json_rendered,
-        } => {
+        (
+            ErrorOutputType::Json {
+                pretty,
+                json_rendered,
+            },
+            None,
+        ) => {
             let source_map =
                 source_map.unwrap_or_else(|| Lrc::new(SourceMap::new(FilePathMapping::empty())));
-            Box::new(
-                JsonEmitter::stderr(
+            let emitter = match diagnostics_buffer {
+                Some(buffer) => JsonEmitter::new(
+                    Box::new(buffer.clone()),
+                    Some(rustc_driver::diagnostics_registry()),
+                    source_map,
                     None,
+                    fallback_bundle,
+                    pretty,
+                    json_rendered,
+                    diagnostic_width,
+                    false,
+                    unstable_opts.track_diagnostics,
+                ),
+                None => JsonEmitter::stderr(
+                    Some(rustc_driver::diagnostics_registry()),
                     source_map,
                     None,
                     fallback_bundle,
@@ -168,107 +1396,416 @@ fn new_handler(
                     diagnostic_width,
                     false,
                     unstable_opts.track_diagnostics,
-                )
-                .ui_testing(unstable_opts.ui_testing),
-            )
+                ),
+            };
+            Box::new(emitter.ui_testing(unstable_opts.ui_testing))
         }
     };
 
     rustc_errors::Handler::with_emitter_and_flags(
         emitter,
-        unstable_opts.diagnostic_handler_flags(true),
+        unstable_opts.diagnostic_handler_flags(can_emit_warnings),
     )
 }
 
-fn create_config(matches: &getopts::Matches) -> Option<interface::Config> {
+/// Returns `true` if the compiler was built with the parallel front-end, which is the only one
+/// able to use several threads.
+fn is_parallel_compiler() -> bool {
+    // `Lrc` is an `Arc` in the parallel compiler and an `Rc` otherwise.
+    std::any::type_name::<Lrc<()>>().contains("Arc")
+}
+
+fn create_config(matches: &getopts::Matches, config: Config) -> Result<interface::Config, Error> {
+    if let Err(msg) = check_diagnostic_args(matches) {
+        return Err(invalid_arg(msg, ErrorOutputType::default(), &config));
+    }
     let color = config::parse_color(matches);
     let config::JsonConfig { json_rendered, .. } = config::parse_json(matches);
     let error_format = config::parse_error_format(matches, color, json_rendered);
     let diagnostic_width = matches.opt_get("diagnostic-width").unwrap_or_default();
+    if let Err(msg) = check_args(matches) {
+        return Err(invalid_arg(msg, error_format, &config));
+    }
 
-    let codegen_options = CodegenOptions::build(matches, error_format);
-    let unstable_opts = UnstableOptions::build(matches, error_format);
+    let mut codegen_options = CodegenOptions::build(matches, error_format);
+    let mut unstable_opts = UnstableOptions::build(matches, error_format);
 
-    let diag = new_handler(error_format, None, diagnostic_width, &unstable_opts);
+    let diag = new_handler(
+        error_format,
+        None,
+        diagnostic_width,
+        &unstable_opts,
+        true,
+        config.diagnostics_buffer.as_ref(),
+        config.diagnostics_collector.as_ref(),
+    );
 
-    let (lint_opts, describe_lints, lint_cap) = config::get_cmd_lint_options(matches, error_format);
+    match config.threads {
+        Some(0) => {
+            let msg = "the number of threads must be positive";
+            diag.struct_err(msg).emit();
+            return Err(Error::ArgParse(msg.to_owned()));
+        }
+        Some(threads) if threads > 1 && !is_parallel_compiler() => {
+            diag.struct_warn(&format!(
+                "the compiler wasn't built with the parallel front-end, so {threads} threads can't be \
+                 used"
+            ))
+            .emit();
+        }
+        Some(threads) => unstable_opts.threads = threads,
+        None => {}
+    }
+    if config.no_std {
+        unstable_opts.crate_attr.push("no_std".to_owned());
+    }
+    if config.no_core {
+        unstable_opts
+            .crate_attr
+            .extend(["feature(no_core)".to_owned(), "no_core".to_owned()]);
+    }
+    unstable_opts.crate_attr.extend(config.crate_attrs);
+    let injects_features = unstable_opts
+        .crate_attr
+        .iter()
+        .any(|attr| attr.trim_start().starts_with("feature"));
+    if config.output_dir.is_none() {
+        let ignored = [
+            ("--emit", matches.opt_present("emit")),
+            ("-o", matches.opt_present("o")),
+            ("--out-dir", matches.opt_present("out-dir")),
+            (
+                "-C incremental",
+                codegen_options.incremental.take().is_some(),
+            ),
+            (
+                "-Z self-profile",
+                !matches!(
+                    mem::replace(&mut unstable_opts.self_profile, SwitchWithOptPath::Disabled),
+                    SwitchWithOptPath::Disabled
+                ),
+            ),
+            ("-Z dump-mir", unstable_opts.dump_mir.take().is_some()),
+        ];
+        for (option, _) in ignored.iter().filter(|(_, present)| *present) {
+            diag.struct_warn(&format!(
+                "`{option}` is ignored because no file is written (use `Config::allow_output` to allow \
+                 it)"
+            ))
+            .emit();
+        }
+    }
+    if let Some(directory) = config.self_profile {
+        unstable_opts.self_profile = SwitchWithOptPath::Enabled(Some(directory));
+    }
+    // Without these defaults, `-O` would enable more optimizations and the inlining.
+    unstable_opts.mir_opt_level = config
+        .mir_opt_level
+        .map(usize::from)
+        .or(unstable_opts.mir_opt_level)
+        .or(Some(1));
+    unstable_opts.inline_mir = config
+        .inline_mir
+        .or(unstable_opts.inline_mir)
+        .or(Some(false));
 
-    let input = match make_input(error_format, &matches.free, &diag) {
-        Ok(Some(i)) => i,
-        Ok(None) => {
-            return None;
+    let (mut lint_opts, describe_lints, lint_cap) =
+        config::get_cmd_lint_options(matches, error_format);
+    let lint_cap = config.lint_cap.or(lint_cap);
+    let lint_names = config
+        .lint_opts
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    // The last level given for a lint is the one used.
+    lint_opts.extend(config.lint_opts);
+    let register_lints = config.register_lints;
+    // The compiler fails on unknown lints given on the command line, so the ones given with the
+    // `Config` are ignored and reported with a warning instead. This is done once all the lints
+    // are registered.
+    let register_lints = Box::new(move |sess: &Session, store: &mut LintStore| {
+        if let Some(register_lints) = &register_lints {
+            register_lints(sess, store);
         }
-        Err(e) => {
-            diag.struct_err(&format!("Failed to parse input: {:?}", e))
-                .emit();
-            return None;
+        for name in &lint_names {
+            if let CheckLintNameResult::NoLint(_) =
+                store.check_lint_name(name, None, &Default::default())
+            {
+                store.register_ignored(name);
+                sess.struct_warn(&format!("unknown lint: `{name}`"))
+                    .code(DiagnosticId::Lint {
+                        name: "unknown_lints".to_owned(),
+                        has_future_breakage: false,
+                        is_force_warn: false,
+                    })
+                    .emit();
+            }
+        }
+    }) as Box<dyn Fn(&Session, &mut LintStore) + marker::Send + Sync>;
+
+    let input = match (config.source_code, &config.virtual_crate) {
+        (None, Some(virtual_crate)) if matches.free.is_empty() => match &virtual_crate.root {
+            Some(root) => Input::File(root.clone()),
+            None => {
+                diag.struct_err("the virtual crate has no root").emit();
+                return Err(Error::InputMissing);
+            }
+        },
+        (_, Some(_)) => {
+            diag.struct_err("too many file operands").emit();
+            return Err(Error::TooManyInputs);
         }
+        (Some(source), None) if matches.free.is_empty() => Input::Str {
+            name: FileName::anon_source_code(&source),
+            input: source,
+        },
+        (Some(_), None) => {
+            diag.struct_err("too many file operands").emit();
+            return Err(Error::TooManyInputs);
+        }
+        (None, None) => match make_input(&matches.free, &diag)? {
+            Input::File(path) if path.is_relative() => match &config.working_dir {
+                Some(working_dir) => Input::File(working_dir.join(path)),
+                None => Input::File(path),
+            },
+            input => input,
+        },
     };
 
     let libs = matches
         .opt_strs("L")
         .iter()
         .map(|s| SearchPath::from_cli_opt(s, error_format))
-        .collect();
-    let externs = parse_externs(matches, &unstable_opts, error_format);
+        .collect::<Vec<_>>();
+    for (_, path) in &config.proc_macros {
+        check_proc_macro(path)?;
+    }
+    // The proc macros are found like the other crates: the compiler loads them for the host
+    // when it reads their metadata.
+    let config_externs = config.externs.into_iter().chain(
+        config
+            .proc_macros
+            .into_iter()
+            .map(|(name, path)| (name, Some(path), Vec::new())),
+    );
+    let externs = add_externs(
+        parse_externs(matches, &unstable_opts, error_format),
+        config_externs,
+    )?;
 
-    let cfgs = matches.opt_strs("cfg");
-    let check_cfgs = matches.opt_strs("check-cfg");
+    let mut cfgs = matches.opt_strs("cfg");
+    for (name, value) in config.cfgs {
+        if !rustc_lexer::is_ident(&name) {
+            return Err(Error::InvalidCfg(format!(
+                "`{name}` isn't a valid configuration option name"
+            )));
+        }
+        // The debug representation of a string is a valid string literal.
+        cfgs.push(match value {
+            Some(value) => format!("{name}={value:?}"),
+            None => name,
+        });
+    }
+    let mut check_cfgs = matches.opt_strs("check-cfg");
+    if let Some(features) = config.check_cfg_features {
+        let values = features
+            .iter()
+            .map(|name| format!(", {name:?}"))
+            .collect::<String>();
+        check_cfgs.push(format!("values(feature{values})"));
+    }
 
     let crate_types = match parse_crate_types_from_list(matches.opt_strs("crate-type")) {
+        Ok(_) if !config.crate_types.is_empty() => config.crate_types,
         Ok(types) => types,
         Err(e) => {
-            diag.struct_err(&format!("unknown crate type: {}", e))
-                .emit();
-            return None;
+            diag.struct_err(&e).emit();
+            return Err(Error::UnknownCrateType(e));
         }
     };
     let crate_name = matches.opt_str("crate-name");
+    let analysis_level = config.analysis_level;
+    let output_types = config
+        .emits
+        .iter()
+        .map(|(kind, _)| (output_type(*kind), None))
+        .collect::<Vec<_>>();
+    if output_types.contains(&(OutputType::LlvmAssembly, None)) {
+        // Like with `--emit=llvm-ir`, so only one file is written.
+        codegen_options.codegen_units = Some(1);
+    }
+
+    // The session creates its own handler which always writes to stderr, so we replace it once
+    // the `ParseSess` is created.
+    let collector = config.diagnostics_collector;
+    let buffer = config.diagnostics_buffer;
+    let parse_sess_created = (buffer.is_some() || collector.is_some()).then(|| {
+        // Same check as in `rustc_session::build_session`.
+        let warnings_allow = lint_opts
+            .iter()
+            .rfind(|(name, _)| name == "warnings")
+            .map_or(false, |(_, level)| *level == Level::Allow);
+        let can_emit_warnings = !warnings_allow && lint_cap != Some(Level::Allow);
+        let unstable_opts = unstable_opts.clone();
+        Box::new(move |parse_sess: &mut ParseSess| {
+            parse_sess.span_diagnostic = new_handler(
+                error_format,
+                Some(parse_sess.clone_source_map()),
+                diagnostic_width,
+                &unstable_opts,
+                can_emit_warnings,
+                buffer.as_ref(),
+                collector.as_ref(),
+            );
+        }) as Box<dyn FnOnce(&mut ParseSess) + marker::Send>
+    });
+
+    let maybe_sysroot = match matches.opt_str("sysroot") {
+        Some(sysroot) => {
+            let rustc = Path::new(&sysroot).join("bin").join("rustc");
+            if config.check_toolchain && rustc.is_file() {
+                check_toolchain(&rustc.display().to_string())?;
+            }
+            Some(PathBuf::from(sysroot))
+        }
+        // Nothing is loaded from the sysroot without `core`.
+        None if config.detect_sysroot && !config.no_core => {
+            Some(detect_sysroot(&rustc_path(), config.check_toolchain)?)
+        }
+        None => None,
+    };
+
+    let target_triple = match config.target {
+        Some(target) if target.ends_with(".json") => TargetTriple::from_path(Path::new(&target))
+            .map_err(|_| Error::ArgParse(format!("target file `{target}` does not exist")))?,
+        Some(target) => TargetTriple::from_triple(&target),
+        None if matches.opt_present("target") => config::parse_target_triple(matches, error_format),
+        None => TargetTriple::from_triple(config::host_triple()),
+    };
+    // The host libraries are always there.
+    if target_triple.triple() != config::host_triple() && !config.no_core {
+        check_target_libs(&target_triple, maybe_sysroot.as_deref(), &libs)?;
+    }
 
     let sessopts = config::Options {
-        maybe_sysroot: matches.opt_str("sysroot").map(PathBuf::from),
+        maybe_sysroot,
         search_paths: libs,
         crate_types,
         lint_opts,
         lint_cap,
         cg: codegen_options,
         externs,
-        target_triple: config::parse_target_triple(matches, error_format),
-        unstable_features: UnstableFeatures::from_environment(crate_name.as_deref()),
+        target_triple,
+        unstable_features: match UnstableFeatures::from_environment(crate_name.as_deref()) {
+            // The injected features must be enabled, like with `RUSTC_BOOTSTRAP=1`.
+            UnstableFeatures::Disallow if injects_features => UnstableFeatures::Cheat,
+            unstable_features => unstable_features,
+        },
         actually_rustdoc: false,
         unstable_opts,
         error_format,
         diagnostic_width,
-        edition: config::parse_crate_edition(matches),
+        edition: match config.edition {
+            Some(edition) => edition,
+            None => config::parse_crate_edition(matches),
+        },
         describe_lints,
         crate_name,
-        test: false,
+        test: config.test || matches.opt_present("test"),
+        output_types: OutputTypes::new(&output_types),
+        working_dir: match config.working_dir {
+            Some(working_dir) => RealFileName::LocalPath(working_dir),
+            None => Options::default().working_dir,
+        },
         ..Options::default()
     };
 
-    Some(interface::Config {
+    let mut file_loader = config.file_loader;
+    if let Some(virtual_crate) = config.virtual_crate {
+        file_loader = Some(Box::new(VirtualFileLoader::new(
+            virtual_crate.files,
+            file_loader.unwrap_or_else(|| Box::new(RealFileLoader)),
+        )));
+    }
+    if let Some((out_dir, files)) = config.generated_files {
+        file_loader = Some(Box::new(OverlayFileLoader::with_fallback(
+            files
+                .into_iter()
+                .map(|(path, content)| (out_dir.join(path), content))
+                .collect(),
+            file_loader.unwrap_or_else(|| Box::new(RealFileLoader)),
+        )));
+    }
+    // See `inject_include_overlay`.
+    let file_loader = file_loader.map(|file_loader| {
+        Box::new(IncludeFileLoader(file_loader)) as Box<dyn FileLoader + marker::Send + Sync>
+    });
+
+    let mut interface_config = interface::Config {
         opts: sessopts,
         crate_cfg: interface::parse_cfgspecs(cfgs),
         crate_check_cfg: interface::parse_check_cfg(check_cfgs),
         input,
         output_file: None,
-        output_dir: None,
-        file_loader: None,
+        output_dir: config
+            .output_dir
+            .or_else(|| config.emits.first().map(|(_, directory)| directory.clone())),
+        file_loader,
         lint_caps: Default::default(),
-        parse_sess_created: None,
-        register_lints: None,
-        override_queries: Some(|_sess, providers, _external_providers| {
-            // Most lints will require typechecking, so just don't run them.
-            providers.lint_mod = |_, _| {};
-            // Prevent `rustc_hir_analysis::check_crate` from calling `typeck` on all bodies.
-            providers.typeck_item_bodies = |_, _| {};
-            // hack so that `used_trait_imports` won't try to call typeck
-            providers.used_trait_imports = |_, _| {
-                static EMPTY_SET: LazyLock<UnordSet<LocalDefId>> = LazyLock::new(UnordSet::default);
-                &EMPTY_SET
-            };
-        }),
+        parse_sess_created,
+        register_lints: Some(register_lints),
+        override_queries: match analysis_level {
+            AnalysisLevel::HirOnly => Some(hir_only_providers),
+            AnalysisLevel::TypeckBodies => Some(typeck_bodies_providers),
+            AnalysisLevel::Full => Some(full_providers),
+        },
         make_codegen_backend: None,
         registry: rustc_driver::diagnostics_registry(),
-    })
+    };
+    if let Some(configure) = config.configure {
+        configure(&mut interface_config);
+    }
+    Ok(interface_config)
+}
+
+fn full_providers(_sess: &Session, providers: &mut Providers, _: &mut ExternProviders) {
+    crate::mir::provide(providers);
+    crate::borrowck::provide(providers);
+    cancellation_providers(providers);
+}
+
+fn typeck_bodies_providers(_sess: &Session, providers: &mut Providers, _: &mut ExternProviders) {
+    crate::mir::provide(providers);
+    crate::borrowck::provide(providers);
+    cancellation_providers(providers);
+    // Lints are run as part of the full analysis only.
+    providers.lint_mod = |_, _| {};
+}
+
+fn hir_only_providers(_sess: &Session, providers: &mut Providers, _: &mut ExternProviders) {
+    crate::mir::provide(providers);
+    crate::borrowck::provide(providers);
+    cancellation_providers(providers);
+    // Most lints will require typechecking, so just don't run them.
+    providers.lint_mod = |_, _| {};
+    // Prevent `rustc_hir_analysis::check_crate` from calling `typeck` on all bodies.
+    providers.typeck_item_bodies = |_, _| {};
+    // hack so that `used_trait_imports` won't try to call typeck
+    providers.used_trait_imports = |_, _| {
+        static EMPTY_SET: LazyLock<UnordSet<LocalDefId>> = LazyLock::new(UnordSet::default);
+        &EMPTY_SET
+    };
+}
+
+/// Checks the cancellation token before type-checking a body or checking the items of a module,
+/// which is where most of the time of the analysis is spent.
+fn cancellation_providers(providers: &mut Providers) {
+    providers.typeck = |tcx, def_id| {
+        abort_if_cancelled();
+        (rustc_interface::DEFAULT_QUERY_PROVIDERS.typeck)(tcx, def_id)
+    };
+    providers.check_mod_type_wf = |tcx, module| {
+        abort_if_cancelled();
+        (rustc_interface::DEFAULT_QUERY_PROVIDERS.check_mod_type_wf)(tcx, module)
+    };
 }
@@ -0,0 +1,383 @@
+//! Export of the items of the local crate as JSON, to analyze its structure with other tools
+//! (like a Python script) without linking to the compiler.
+//!
+//! [`export_hir_json`] returns an object with the `format_version` (see [`FORMAT_VERSION`]), the
+//! `crate_name` and the `items` of the root module. Each item is an object with:
+//!
+//! * `kind`: as returned by `DefKind::descr`, like `"function"`, `"struct"` or
+//!   `"implementation"`.
+//! * `name`: `null` for the items without one, like the impls and the glob imports.
+//! * `path`: see [`qualified_name`].
+//! * `visibility`: `"pub"`, `"pub(crate)"`, `"pub(in path::to::module)"` or `"private"`.
+//! * `generics` and `where_clauses`: lists of strings, see
+//!   [`ApiItem::generics`](crate::api::ApiItem::generics) and
+//!   [`ApiItem::where_clauses`](crate::api::ApiItem::where_clauses).
+//! * `span`: an object with the `file`, the `line` and the `column` of the beginning of the item
+//!   and its `end_line` and `end_column` (starting at 1, see [`line_col_range`]), and
+//!   `from_expansion`, `true` if the item was generated by a macro.
+//!
+//! Depending on their kind, the items have these fields as well:
+//!
+//! * functions and methods: the `signature` (like `fn(u32) -> bool`) and the names of the
+//!   `params`.
+//! * constants, statics, type aliases and associated types: their `type` (`null` for the
+//!   associated types without a default), and `mutable` for the statics.
+//! * structs and unions: their `struct_kind` (`"named"`, `"tuple"` or `"unit"`) and their
+//!   `fields`, which have a `name` (like `0` for the tuple structs), a `type`, a `visibility` and
+//!   a `span`.
+//! * enums: their `variants`, which have a `name`, a `struct_kind`, `fields`, a `discriminant`
+//!   (`null` if it isn't written) and a `span`.
+//! * impls: the `trait` (`null` for the inherent impls) and the `self_type`.
+//! * imports: the `target`, like `std::fmt::Debug` or `std::io::*`.
+//! * extern crates: the `original_name`, `null` if the crate isn't renamed.
+//! * macros: `macro_rules`, `false` for the macros 2.0.
+//! * modules, traits, impls and foreign modules: the `items` they contain. The other items
+//!   have `items` as well if they contain some, like a function declaring a struct in its body.
+//!
+//! The functions, the constants and the statics also have a `body` if
+//! [`HirJsonOptions::include_bodies`] is set.
+//!
+//! The paths and the types are written with the paths of the crates they're defined in, like
+//! `std::vec::Vec<u32>`. The opaque types (of the `impl Trait`) aren't items in the export.
+
+use rustc_hir::def_id::{LocalDefId, CRATE_DEF_ID, LOCAL_CRATE};
+use rustc_hir::{
+    BodyId, ForeignItem, ForeignItemKind, ImplItem, ImplItemKind, Item, ItemId, ItemKind, TraitFn,
+    TraitItem, TraitItemKind, UseKind, VariantData,
+};
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{TyCtxt, Visibility};
+use rustc_span::symbol::{kw, Ident};
+use rustc_span::Span;
+use serde_json::{json, Map, Value};
+
+use std::collections::HashMap;
+
+use crate::api::{generics, signature};
+use crate::paths::qualified_name;
+use crate::pretty::hir_pretty;
+use crate::spans::line_col_range;
+
+/// The version of the format of [`export_hir_json`]. It's increased when a field is removed or
+/// changes meaning, not when one is added.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Options of [`export_hir_json`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HirJsonOptions {
+    /// Add the `body` of the functions, the constants and the statics, printed as Rust code by
+    /// [`hir_pretty`] (`null` for the functions without one). It's most of the size of the export,
+    /// so it's disabled by default.
+    pub include_bodies: bool,
+}
+
+/// Returns the items of the local crate as JSON, in the order of the source code. The format is
+/// described in the [module documentation](self).
+pub fn export_hir_json(tcx: TyCtxt<'_>, options: HirJsonOptions) -> Value {
+    let hir = tcx.hir();
+    let mut children = HashMap::<_, Vec<_>>::new();
+    for item_id in hir.items() {
+        let parent = hir.get_parent_item(item_id.hir_id());
+        children.entry(parent.def_id).or_default().push(item_id);
+    }
+    let exporter = Exporter {
+        tcx,
+        options,
+        children,
+    };
+    json!({
+        "format_version": FORMAT_VERSION,
+        "crate_name": tcx.crate_name(LOCAL_CRATE).to_string(),
+        "items": exporter.nested_items(CRATE_DEF_ID),
+    })
+}
+
+struct Exporter<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    options: HirJsonOptions,
+    /// The items declared in each module, function, trait item, etc.
+    children: HashMap<LocalDefId, Vec<ItemId>>,
+}
+
+impl<'tcx> Exporter<'tcx> {
+    fn nested_items(&self, parent: LocalDefId) -> Vec<Value> {
+        self.children
+            .get(&parent)
+            .into_iter()
+            .flatten()
+            .filter_map(|&item_id| self.item(self.tcx.hir().item(item_id)))
+            .collect()
+    }
+
+    fn item(&self, item: &'tcx Item<'tcx>) -> Option<Value> {
+        let tcx = self.tcx;
+        let def_id = item.owner_id.def_id;
+        let mut object = self.common(def_id, item.ident, item.span);
+        match &item.kind {
+            ItemKind::Fn(_, _, body_id) => self.add_fn(&mut object, def_id, Some(*body_id)),
+            ItemKind::Const(_, body_id) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+                self.add_body(&mut object, Some(*body_id));
+            }
+            ItemKind::Static(_, mutability, body_id) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+                object.insert("mutable".into(), json!(mutability.is_mut()));
+                self.add_body(&mut object, Some(*body_id));
+            }
+            ItemKind::TyAlias(..) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+            }
+            ItemKind::Struct(data, _) | ItemKind::Union(data, _) => {
+                self.add_fields(&mut object, data)
+            }
+            ItemKind::Enum(enum_def, _) => {
+                let variants = enum_def
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let mut variant_object = Map::new();
+                        variant_object.insert("name".into(), json!(variant.ident.to_string()));
+                        self.add_fields(&mut variant_object, &variant.data);
+                        let discriminant = variant
+                            .disr_expr
+                            .map(|anon_const| hir_pretty(tcx, anon_const.hir_id));
+                        variant_object.insert("discriminant".into(), json!(discriminant));
+                        variant_object.insert("span".into(), self.span(variant.span));
+                        Value::Object(variant_object)
+                    })
+                    .collect::<Vec<_>>();
+                object.insert("variants".into(), json!(variants));
+            }
+            ItemKind::Trait(.., item_refs) => {
+                let items = item_refs
+                    .iter()
+                    .map(|item_ref| self.trait_item(tcx.hir().trait_item(item_ref.id)))
+                    .collect::<Vec<_>>();
+                object.insert("items".into(), json!(items));
+            }
+            ItemKind::Impl(impl_) => {
+                let trait_name = tcx.impl_trait_ref(def_id).map(|trait_ref| {
+                    with_no_trimmed_paths!(trait_ref
+                        .skip_binder()
+                        .print_only_trait_path()
+                        .to_string())
+                });
+                object.insert("trait".into(), json!(trait_name));
+                object.insert("self_type".into(), json!(self.type_string(def_id)));
+                let items = impl_
+                    .items
+                    .iter()
+                    .map(|item_ref| self.impl_item(tcx.hir().impl_item(item_ref.id)))
+                    .collect::<Vec<_>>();
+                object.insert("items".into(), json!(items));
+            }
+            ItemKind::ForeignMod { items, .. } => {
+                let items = items
+                    .iter()
+                    .map(|item_ref| self.foreign_item(tcx.hir().foreign_item(item_ref.id)))
+                    .collect::<Vec<_>>();
+                object.insert("items".into(), json!(items));
+            }
+            ItemKind::Mod(_) => {
+                object.insert("items".into(), json!(self.nested_items(def_id)));
+            }
+            // `use a::{b, c};` is lowered to `use a::b; use a::c;` with this item for `a`.
+            ItemKind::Use(_, UseKind::ListStem) => return None,
+            ItemKind::Use(path, use_kind) => {
+                let mut target = path
+                    .segments
+                    .iter()
+                    .map(|segment| match segment.ident.name {
+                        kw::PathRoot => String::new(),
+                        name => name.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("::");
+                if *use_kind == UseKind::Glob {
+                    target.push_str("::*");
+                }
+                object.insert("target".into(), json!(target));
+            }
+            ItemKind::ExternCrate(original_name) => {
+                let original_name = original_name.map(|name| name.to_string());
+                object.insert("original_name".into(), json!(original_name));
+            }
+            ItemKind::Macro(macro_def, _) => {
+                object.insert("macro_rules".into(), json!(macro_def.macro_rules));
+            }
+            ItemKind::OpaqueTy(_) => return None,
+            ItemKind::GlobalAsm(_) | ItemKind::TraitAlias(..) => {}
+        }
+        self.add_nested_items(&mut object, def_id);
+        Some(Value::Object(object))
+    }
+
+    fn trait_item(&self, item: &'tcx TraitItem<'tcx>) -> Value {
+        let def_id = item.owner_id.def_id;
+        let mut object = self.common(def_id, item.ident, item.span);
+        match item.kind {
+            TraitItemKind::Fn(_, TraitFn::Provided(body_id)) => {
+                self.add_fn(&mut object, def_id, Some(body_id))
+            }
+            TraitItemKind::Fn(_, TraitFn::Required(_)) => self.add_fn(&mut object, def_id, None),
+            TraitItemKind::Const(_, body_id) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+                self.add_body(&mut object, body_id);
+            }
+            TraitItemKind::Type(..) => {
+                let default = signature(self.tcx, def_id.to_def_id(), self.tcx.def_kind(def_id));
+                let default = (!default.is_empty()).then_some(default);
+                object.insert("type".into(), json!(default));
+            }
+        }
+        self.add_nested_items(&mut object, def_id);
+        Value::Object(object)
+    }
+
+    fn impl_item(&self, item: &'tcx ImplItem<'tcx>) -> Value {
+        let def_id = item.owner_id.def_id;
+        let mut object = self.common(def_id, item.ident, item.span);
+        match item.kind {
+            ImplItemKind::Fn(_, body_id) => self.add_fn(&mut object, def_id, Some(body_id)),
+            ImplItemKind::Const(_, body_id) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+                self.add_body(&mut object, Some(body_id));
+            }
+            ImplItemKind::Type(_) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+            }
+        }
+        self.add_nested_items(&mut object, def_id);
+        Value::Object(object)
+    }
+
+    fn foreign_item(&self, item: &'tcx ForeignItem<'tcx>) -> Value {
+        let def_id = item.owner_id.def_id;
+        let mut object = self.common(def_id, item.ident, item.span);
+        match item.kind {
+            ForeignItemKind::Fn(..) => self.add_fn(&mut object, def_id, None),
+            ForeignItemKind::Static(_, mutability) => {
+                object.insert("type".into(), json!(self.type_string(def_id)));
+                object.insert("mutable".into(), json!(mutability.is_mut()));
+            }
+            ForeignItemKind::Type => {}
+        }
+        Value::Object(object)
+    }
+
+    /// Returns the fields shared by all the items.
+    fn common(&self, def_id: LocalDefId, ident: Ident, span: Span) -> Map<String, Value> {
+        let tcx = self.tcx;
+        let kind = tcx.def_kind(def_id);
+        // The name of the imports isn't in their `DefPath`, so `opt_item_name` doesn't have it.
+        let name = (ident.name != kw::Empty).then(|| ident.to_string());
+        let (generics, where_clauses) = generics(tcx, def_id.to_def_id(), kind);
+        let mut object = Map::new();
+        object.insert("kind".into(), json!(kind.descr(def_id.to_def_id())));
+        object.insert("name".into(), json!(name));
+        object.insert(
+            "path".into(),
+            json!(qualified_name(tcx, def_id.to_def_id())),
+        );
+        object.insert("visibility".into(), json!(self.visibility(def_id)));
+        object.insert("generics".into(), json!(generics));
+        object.insert("where_clauses".into(), json!(where_clauses));
+        object.insert("span".into(), self.span(span));
+        object
+    }
+
+    fn add_fn(&self, object: &mut Map<String, Value>, def_id: LocalDefId, body_id: Option<BodyId>) {
+        let tcx = self.tcx;
+        let signature = signature(tcx, def_id.to_def_id(), tcx.def_kind(def_id));
+        let params = tcx
+            .fn_arg_names(def_id)
+            .iter()
+            .map(|ident| match ident.name {
+                // The parameters which are patterns, like `(a, b): (u32, u32)`.
+                kw::Empty => "_".to_owned(),
+                name => name.to_string(),
+            })
+            .collect::<Vec<_>>();
+        object.insert("signature".into(), json!(signature));
+        object.insert("params".into(), json!(params));
+        self.add_body(object, body_id);
+    }
+
+    fn add_body(&self, object: &mut Map<String, Value>, body_id: Option<BodyId>) {
+        if self.options.include_bodies {
+            let body = body_id.map(|body_id| {
+                let body = self.tcx.hir().body(body_id);
+                hir_pretty(self.tcx, body.value.hir_id)
+            });
+            object.insert("body".into(), json!(body));
+        }
+    }
+
+    fn add_fields(&self, object: &mut Map<String, Value>, data: &VariantData<'tcx>) {
+        let struct_kind = match data {
+            VariantData::Struct(..) => "named",
+            VariantData::Tuple(..) => "tuple",
+            VariantData::Unit(..) => "unit",
+        };
+        let fields = data
+            .fields()
+            .iter()
+            .map(|field| {
+                json!({
+                    "name": field.ident.to_string(),
+                    "type": self.type_string(field.def_id),
+                    "visibility": self.visibility(field.def_id),
+                    "span": self.span(field.span),
+                })
+            })
+            .collect::<Vec<_>>();
+        object.insert("struct_kind".into(), json!(struct_kind));
+        object.insert("fields".into(), json!(fields));
+    }
+
+    /// Adds the items declared inside the body of a function, a constant, etc.
+    fn add_nested_items(&self, object: &mut Map<String, Value>, def_id: LocalDefId) {
+        if !object.contains_key("items") {
+            let items = self.nested_items(def_id);
+            if !items.is_empty() {
+                object.insert("items".into(), json!(items));
+            }
+        }
+    }
+
+    fn type_string(&self, def_id: LocalDefId) -> String {
+        with_no_trimmed_paths!(self.tcx.type_of(def_id).to_string())
+    }
+
+    fn visibility(&self, def_id: LocalDefId) -> String {
+        let tcx = self.tcx;
+        let hir_id = tcx.hir().local_def_id_to_hir_id(def_id);
+        match tcx.visibility(def_id) {
+            Visibility::Public => "pub".to_owned(),
+            Visibility::Restricted(module) if module == tcx.parent_module(hir_id).to_def_id() => {
+                "private".to_owned()
+            }
+            Visibility::Restricted(module) if module == CRATE_DEF_ID.to_def_id() => {
+                "pub(crate)".to_owned()
+            }
+            Visibility::Restricted(module) => {
+                format!(
+                    "pub(in {})",
+                    with_no_trimmed_paths!(tcx.def_path_str(module))
+                )
+            }
+        }
+    }
+
+    fn span(&self, span: Span) -> Value {
+        let (file_name, lo, hi) = line_col_range(self.tcx, span);
+        json!({
+            "file": file_name.prefer_local().to_string(),
+            "line": lo.line,
+            "column": lo.col,
+            "end_line": hi.line,
+            "end_column": hi.col,
+            "from_expansion": span.from_expansion(),
+        })
+    }
+}
@@ -0,0 +1,215 @@
+//! Helpers to find the files included with `include!`, `include_str!` and `include_bytes!`, for
+//! example to check that the files generated by a build script are available (see
+//! [`Config::generated_files`]) before analyzing a crate. A missing file included with `include!`
+//! stops the compilation, so they're found in the parsed crate, before the expansion.
+
+use rustc_ast::ast::{Expr, ExprKind, Item, ItemKind, LitKind, MacCall, ModKind};
+use rustc_ast::ptr::P;
+use rustc_ast::token;
+use rustc_ast::tokenstream::TokenStream;
+use rustc_ast::visit::{self, Visitor};
+use rustc_ast_pretty::pprust;
+use rustc_parse::stream_to_parser;
+use rustc_session::Session;
+use rustc_span::symbol::{kw, sym};
+use rustc_span::{FileName, RealFileName, Symbol};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::ast::parse_out_of_line_module;
+use crate::spans::ast_location_string;
+use crate::{Config, Error, Stage};
+
+/// A call to `include!`, `include_str!` or `include_bytes!`, returned by [`included_files`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IncludeInfo {
+    /// The name of the macro, like `"include"`.
+    pub macro_name: String,
+    /// The argument of the macro, pretty-printed.
+    pub argument: String,
+    /// The path of the included file, `None` if the argument couldn't be evaluated (see
+    /// [`included_files`]).
+    pub path: Option<PathBuf>,
+    /// `true` if the included file exists. It's looked up with the file loader of the
+    /// [`Config`], so the files given to [`Config::generated_files`] or [`Config::file_overlay`]
+    /// are found.
+    pub found: bool,
+    /// The location of the macro call, like `src/lib.rs:3:1`.
+    pub location: String,
+}
+
+/// Returns the calls to `include!`, `include_str!` and `include_bytes!` of the crate, in the order
+/// of the modules, with the file each one includes.
+///
+/// The crate is only parsed and the out-of-line modules (`mod name;`) are loaded like
+/// [`cfg_inventory_approximate`](crate::cfgs::cfg_inventory_approximate) does, so the calls
+/// generated by macros, the ones in the arguments of other macros and the ones in the included
+/// files aren't found, and all the `#[cfg]`s are ignored. The argument is evaluated if it's made
+/// of literals, `concat!` and `env!`, where the variables of [`Config::env_overlay`] and the
+/// `OUT_DIR` of [`Config::generated_files`] are used. Like the compiler, relative paths are
+/// relative to the directory of the file containing the call.
+pub fn included_files(config: Config) -> Result<Vec<IncludeInfo>, Error> {
+    let env = config.injected_env();
+    config.with_ast(Stage::Parse, |krate, sess| {
+        let module_dir = match sess.source_map().span_to_filename(krate.spans.inner_span) {
+            FileName::Real(RealFileName::LocalPath(file)) => {
+                Some(file.parent().map(Path::to_owned).unwrap_or_default())
+            }
+            _ => None,
+        };
+        let mut collector = IncludeCollector {
+            sess,
+            env,
+            module_dir,
+            includes: Vec::new(),
+        };
+        visit::walk_crate(&mut collector, krate);
+        collector.includes
+    })
+}
+
+struct IncludeCollector<'a> {
+    sess: &'a Session,
+    /// The variables given by [`Config::injected_env`].
+    env: Option<(HashMap<String, String>, bool)>,
+    /// The directory of the submodules of the current module, `None` if the out-of-line modules
+    /// can't be loaded.
+    module_dir: Option<PathBuf>,
+    includes: Vec<IncludeInfo>,
+}
+
+impl IncludeCollector<'_> {
+    /// Returns the arguments of a macro call separated by commas, or `None` if they aren't
+    /// expressions.
+    fn arguments(&self, tokens: &TokenStream) -> Option<Vec<P<Expr>>> {
+        let mut parser = stream_to_parser(&self.sess.parse_sess, tokens.clone(), None);
+        let mut arguments = Vec::new();
+        while parser.token != token::Eof {
+            arguments.push(parser.parse_expr().map_err(|err| err.cancel()).ok()?);
+            if !parser.eat(&token::Comma) && parser.token != token::Eof {
+                return None;
+            }
+        }
+        Some(arguments)
+    }
+
+    /// Evaluates `expr` the way `concat!` would, if it's made of literals, `concat!` and `env!`.
+    fn evaluate(&self, expr: &Expr) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Lit(lit) => match LitKind::from_token_lit(*lit).ok()? {
+                LitKind::Str(value, _) => Some(value.to_string()),
+                LitKind::Char(value) => Some(value.to_string()),
+                LitKind::Int(value, _) => Some(value.to_string()),
+                LitKind::Bool(value) => Some(value.to_string()),
+                _ => None,
+            },
+            ExprKind::MacCall(mac) => {
+                let arguments = self.arguments(&mac.args.tokens)?;
+                match builtin_macro(mac)? {
+                    sym::concat => arguments
+                        .iter()
+                        .map(|argument| self.evaluate(argument))
+                        .collect(),
+                    sym::env if matches!(arguments.len(), 1 | 2) => {
+                        self.env_var(&self.evaluate(&arguments[0])?)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        match &self.env {
+            Some((vars, isolated)) => match vars.get(name) {
+                Some(value) => Some(value.clone()),
+                None if *isolated => None,
+                None => std::env::var(name).ok(),
+            },
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// Returns the path of the file included by `mac` (`include!` for example) if its argument
+    /// can be evaluated.
+    fn included_path(&self, mac: &MacCall) -> Option<PathBuf> {
+        let arguments = self.arguments(&mac.args.tokens)?;
+        let [argument] = arguments.as_slice() else { return None };
+        let path = PathBuf::from(self.evaluate(argument)?);
+        if path.is_absolute() {
+            return Some(path);
+        }
+        match self.sess.source_map().span_to_filename(mac.span()) {
+            FileName::Real(RealFileName::LocalPath(file)) => Some(file.parent()?.join(path)),
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for IncludeCollector<'_> {
+    fn visit_item(&mut self, item: &'ast Item) {
+        match &item.kind {
+            ItemKind::Mod(_, ModKind::Loaded(..)) => {
+                let module_dir = self.module_dir.clone();
+                if let Some(dir) = &mut self.module_dir {
+                    dir.push(item.ident.as_str());
+                }
+                visit::walk_item(self, item);
+                self.module_dir = module_dir;
+            }
+            ItemKind::Mod(_, ModKind::Unloaded) => {
+                visit::walk_item(self, item);
+                let Some(dir) = &self.module_dir else { return };
+                let Some((attrs, items, module_dir)) =
+                    parse_out_of_line_module(self.sess, item, dir)
+                else {
+                    return;
+                };
+                let previous = self.module_dir.replace(module_dir);
+                for attr in &attrs {
+                    self.visit_attribute(attr);
+                }
+                for item in &items {
+                    self.visit_item(item);
+                }
+                self.module_dir = previous;
+            }
+            _ => visit::walk_item(self, item),
+        }
+    }
+
+    fn visit_mac_call(&mut self, mac: &'ast MacCall) {
+        let Some(name) = builtin_macro(mac)
+            .filter(|name| matches!(*name, sym::include | sym::include_str | sym::include_bytes))
+        else {
+            return;
+        };
+        let path = self.included_path(mac);
+        let found = path
+            .as_ref()
+            .map_or(false, |path| self.sess.source_map().file_exists(path));
+        self.includes.push(IncludeInfo {
+            macro_name: name.to_string(),
+            argument: pprust::tts_to_string(&mac.args.tokens),
+            path,
+            found,
+            location: ast_location_string(self.sess.source_map(), mac.span()),
+        });
+    }
+}
+
+/// Returns the name of the macro called by `mac` if it may be a macro of the standard library:
+/// its path is only a name or starts with `std` or `core`.
+fn builtin_macro(mac: &MacCall) -> Option<Symbol> {
+    let (name, prefix) = mac.path.segments.split_last()?;
+    prefix
+        .iter()
+        .all(|segment| matches!(segment.ident.name, kw::PathRoot | sym::std | sym::core))
+        .then_some(name.ident.name)
+}
@@ -0,0 +1,293 @@
+//! Helpers to get the memory layout (size, alignment, field offsets, etc) of the types.
+//!
+//! The types are not checked with [`AnalysisLevel::HirOnly`](crate::AnalysisLevel) and computing
+//! the layout of an ill-formed type can make the compiler panic, so these functions should be
+//! used with [`AnalysisLevel::Full`](crate::AnalysisLevel) (or at least
+//! [`AnalysisLevel::TypeckBodies`](crate::AnalysisLevel)).
+
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_hir::ItemKind;
+use rustc_middle::ty::layout::{IntegerExt, LayoutCx, TyAndLayout};
+use rustc_middle::ty::util::IntTypeExt;
+use rustc_middle::ty::{self, ParamEnv, TyCtxt, VariantDef};
+use rustc_target::abi::{Integer, TagEncoding, VariantIdx, Variants};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::paths::{qualified_name, resolve_path_str};
+
+/// Layout of a type returned by [`layout_of_adts`] and [`layout_of_named`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdtLayout {
+    /// The path of the type as returned by [`qualified_name`].
+    pub name: String,
+    /// `Err` with the reason if the layout couldn't be computed, like for generic types.
+    pub layout: Result<TypeLayout, String>,
+}
+
+/// All the sizes and offsets are in bytes.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeLayout {
+    pub size: u64,
+    pub align: u64,
+    /// Fields of a struct or of an union, sorted by offset. It's empty for enums.
+    pub fields: Vec<FieldLayout>,
+    /// Variants of an enum, in the order in which they're declared. It's empty for structs and
+    /// unions.
+    pub variants: Vec<VariantLayout>,
+    /// Tag of an enum, if it has its own place (it's not the case if it's stored in a niche of the
+    /// fields of the variants, like with `Option<&T>`).
+    pub tag: Option<TagLayout>,
+    /// Number of bytes which aren't used by any field (nor the tag). For enums, it's the padding
+    /// of the variant with the least of it. It's always 0 for the type aliases of types which
+    /// aren't structs, enums or unions.
+    pub padding: u64,
+    /// The largest niche, the invalid values the compiler can use to store the tag of an enum
+    /// containing this type.
+    pub niche: Option<NicheLayout>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// The name of the field (the position for the tuple-like structs and variants).
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+    pub align: u64,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantLayout {
+    pub name: String,
+    /// Sorted by offset.
+    pub fields: Vec<FieldLayout>,
+    /// Number of bytes of the enum which aren't used by the fields of this variant nor the tag.
+    pub padding: u64,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagLayout {
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NicheLayout {
+    pub offset: u64,
+    pub size: u64,
+    /// Number of invalid values.
+    pub available: u128,
+}
+
+/// The `repr` attributes of a type, returned by [`repr_of`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReprInfo {
+    /// `#[repr(C)]`.
+    pub c: bool,
+    /// `#[repr(transparent)]`.
+    pub transparent: bool,
+    /// The integer type of the discriminant of an enum, like `#[repr(u8)]`.
+    pub int: Option<String>,
+    /// The value of `#[repr(packed(N))]` in bytes (`#[repr(packed)]` is `packed(1)`).
+    pub packed: Option<u64>,
+    /// The value of `#[repr(align(N))]` in bytes.
+    pub align: Option<u64>,
+}
+
+/// Returns the layouts of all the structs, enums and unions defined in the local crate. The types
+/// with generic parameters (lifetimes excepted) have no layout, they're returned with an error.
+pub fn layout_of_adts(tcx: TyCtxt<'_>) -> Vec<AdtLayout> {
+    tcx.hir()
+        .items()
+        .filter(|item_id| {
+            matches!(
+                tcx.hir().item(*item_id).kind,
+                ItemKind::Struct(..) | ItemKind::Enum(..) | ItemKind::Union(..)
+            )
+        })
+        .map(|item_id| adt_layout(tcx, item_id.owner_id.to_def_id()))
+        .collect()
+}
+
+/// Returns the layout of the struct, enum, union or type alias `path` points to (like
+/// `crate::module::Struct`), see [`resolve_path`](crate::paths::resolve_path) for the paths
+/// which can be used.
+///
+/// Returns `None` if `path` doesn't point to any of them.
+pub fn layout_of_named(tcx: TyCtxt<'_>, path: &str) -> Option<AdtLayout> {
+    resolve_path_str(tcx, path)
+        .into_iter()
+        .find_map(|res| match res {
+            Res::Def(
+                DefKind::Struct | DefKind::Enum | DefKind::Union | DefKind::TyAlias,
+                def_id,
+            ) => Some(adt_layout(tcx, def_id)),
+            _ => None,
+        })
+}
+
+/// Returns the `repr` attributes of the struct, enum or union `adt_def_id`.
+pub fn repr_of(tcx: TyCtxt<'_>, adt_def_id: DefId) -> ReprInfo {
+    let repr = tcx.adt_def(adt_def_id).repr();
+    ReprInfo {
+        c: repr.c(),
+        transparent: repr.transparent(),
+        int: repr.int.map(|int| int.to_ty(tcx).to_string()),
+        packed: repr.pack.map(|pack| pack.bytes()),
+        align: repr.align.map(|align| align.bytes()),
+    }
+}
+
+/// Returns the name and the discriminant of each variant of the enum `adt_def_id`, in the order
+/// in which they're declared. The explicit discriminants (like `A = 1 << 4`) are evaluated by the
+/// compiler and the other ones are the previous discriminant plus one. It's empty if
+/// `adt_def_id` isn't an enum.
+///
+/// The discriminants are only checked with [`AnalysisLevel::TypeckBodies`](crate::AnalysisLevel)
+/// and [`AnalysisLevel::Full`](crate::AnalysisLevel). With
+/// [`AnalysisLevel::HirOnly`](crate::AnalysisLevel), an implicit discriminant which overflows the
+/// integer type of the enum (like after `A = 127` with `#[repr(i8)]`) wraps around instead of
+/// making the compilation fail, and an explicit one which can't be evaluated is replaced by the
+/// previous discriminant plus one.
+pub fn enum_discriminants(tcx: TyCtxt<'_>, adt_def_id: DefId) -> Vec<(String, i128)> {
+    let adt = tcx.adt_def(adt_def_id);
+    if !adt.is_enum() {
+        return Vec::new();
+    }
+    adt.discriminants(tcx)
+        .map(|(index, discr)| {
+            let value = match *discr.ty.kind() {
+                ty::Int(int_ty) => Integer::from_int_ty(&tcx, int_ty)
+                    .size()
+                    .sign_extend(discr.val) as i128,
+                _ => discr.val as i128,
+            };
+            (adt.variant(index).name.to_string(), value)
+        })
+        .collect()
+}
+
+fn adt_layout(tcx: TyCtxt<'_>, def_id: DefId) -> AdtLayout {
+    let layout = if tcx.generics_of(def_id).requires_monomorphization(tcx) {
+        Err("the type is generic".to_owned())
+    } else {
+        let cx = LayoutCx {
+            tcx,
+            param_env: ParamEnv::reveal_all(),
+        };
+        let ty = tcx.erase_regions(tcx.type_of(def_id));
+        tcx.layout_of(cx.param_env.and(ty))
+            .map(|layout| type_layout(&cx, layout))
+            .map_err(|e| e.to_string())
+    };
+    AdtLayout {
+        name: qualified_name(tcx, def_id),
+        layout,
+    }
+}
+
+fn type_layout<'tcx>(cx: &LayoutCx<'tcx, TyCtxt<'tcx>>, layout: TyAndLayout<'tcx>) -> TypeLayout {
+    let tcx = cx.tcx;
+    let size = layout.size.bytes();
+    let niche = layout.largest_niche.map(|niche| NicheLayout {
+        offset: niche.offset.bytes(),
+        size: niche.value.size(&tcx).bytes(),
+        available: niche.available(&tcx),
+    });
+    let tag = match &layout.variants {
+        Variants::Multiple {
+            tag,
+            tag_encoding: TagEncoding::Direct,
+            tag_field,
+            ..
+        } => Some(TagLayout {
+            offset: layout.fields.offset(*tag_field).bytes(),
+            size: tag.size(&tcx).bytes(),
+        }),
+        _ => None,
+    };
+    let mut type_layout = TypeLayout {
+        size,
+        align: layout.align.abi.bytes(),
+        fields: Vec::new(),
+        variants: Vec::new(),
+        tag: tag.clone(),
+        padding: size,
+        niche,
+    };
+
+    match layout.ty.ty_adt_def() {
+        Some(adt) if adt.is_enum() => {
+            for (index, variant) in adt.variants().iter_enumerated() {
+                let fields = fields(cx, layout.for_variant(cx, index), variant);
+                let padding = padding(size, &fields, tag.as_ref());
+                type_layout.padding = type_layout.padding.min(padding);
+                type_layout.variants.push(VariantLayout {
+                    name: variant.name.to_string(),
+                    fields,
+                    padding,
+                });
+            }
+        }
+        Some(adt) => {
+            type_layout.fields = fields(cx, layout, adt.variant(VariantIdx::from_u32(0)));
+            type_layout.padding = padding(size, &type_layout.fields, None);
+        }
+        // Type aliases of types which aren't ADTs.
+        None => type_layout.padding = 0,
+    }
+    type_layout
+}
+
+fn fields<'tcx>(
+    cx: &LayoutCx<'tcx, TyCtxt<'tcx>>,
+    layout: TyAndLayout<'tcx>,
+    variant: &VariantDef,
+) -> Vec<FieldLayout> {
+    let mut fields = variant
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let field_layout = layout.field(cx, index);
+            FieldLayout {
+                name: field.name.to_string(),
+                offset: layout.fields.offset(index).bytes(),
+                size: field_layout.size.bytes(),
+                align: field_layout.align.abi.bytes(),
+            }
+        })
+        .collect::<Vec<_>>();
+    fields.sort_by_key(|field| field.offset);
+    fields
+}
+
+/// Fields can overlap in unions so we count the bytes covered by at least one of them.
+fn padding(size: u64, fields: &[FieldLayout], tag: Option<&TagLayout>) -> u64 {
+    let mut ranges = fields
+        .iter()
+        .map(|field| (field.offset, field.offset + field.size))
+        .chain(tag.map(|tag| (tag.offset, tag.offset + tag.size)))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+
+    let mut used = 0;
+    let mut end = 0;
+    for (start, range_end) in ranges {
+        let start = start.max(end);
+        if range_end > start {
+            used += range_end - start;
+            end = range_end;
+        }
+    }
+    size - used
+}
@@ -0,0 +1,66 @@
+use rustc_ast::tokenstream::TokenStream;
+use rustc_data_structures::sync::Lrc;
+use rustc_errors::Handler;
+use rustc_parse::parser::emit_unclosed_delims;
+use rustc_session::parse::ParseSess;
+use rustc_span::edition::Edition;
+use rustc_span::source_map::{FilePathMapping, SourceMap};
+use rustc_span::FileName;
+
+use crate::diagnostics::{CollectingEmitter, Diagnostic, DiagnosticsCollector};
+use crate::Error;
+
+/// Turns `source` into a [`TokenStream`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_ast/tokenstream/struct.TokenStream.html)
+/// and calls `callback` with it. Unlike [`lexer`](crate::lexer), the tokens are "glued" (`&&` is
+/// one token) and grouped into delimited trees. A shebang at the beginning of `source` is
+/// skipped.
+///
+/// Nothing is written to stderr: the diagnostics emitted while lexing (like invalid tokens or
+/// unclosed delimiters) are returned alongside the result. The lexer recovers from most errors, in
+/// which case `callback` is still called. It's not when there is a closing delimiter without an
+/// opening one (like `fn f() ) {}`) or an unterminated raw string.
+pub fn with_lexer<T, F: FnOnce(&TokenStream, &ParseSess) -> T>(
+    source: &str,
+    edition: Edition,
+    callback: F,
+) -> (Result<T, Error>, Vec<Diagnostic>) {
+    let collector = DiagnosticsCollector::new();
+    let res = rustc_span::create_session_if_not_set_then(edition, |_| {
+        let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let emitter = CollectingEmitter {
+            source_map: Some(source_map.clone()),
+            fallback_bundle: rustc_errors::fallback_fluent_bundle(
+                rustc_errors::DEFAULT_LOCALE_RESOURCES,
+                false,
+            ),
+            collector: collector.clone(),
+            buffer: None,
+            short_message: false,
+            diagnostic_width: None,
+        };
+        let handler = Handler::with_emitter(true, None, Box::new(emitter));
+        let sess = ParseSess::with_span_handler(handler, source_map);
+
+        let file = sess
+            .source_map()
+            .new_source_file(FileName::anon_source_code(source), source.to_owned());
+        let res = rustc_driver::catch_fatal_errors(|| {
+            rustc_parse::maybe_file_to_stream(&sess, file, None)
+        });
+        match res {
+            Ok(Ok((stream, mut unmatched))) => {
+                emit_unclosed_delims(&mut unmatched, &sess);
+                Ok(callback(&stream, &sess))
+            }
+            Ok(Err(diagnostics)) => {
+                for mut diag in diagnostics {
+                    sess.span_diagnostic.emit_diagnostic(&mut diag);
+                }
+                Err(Error::Parser("failed to lex source".to_owned()))
+            }
+            // The fatal error (like an unterminated raw string) was already emitted.
+            Err(_) => Err(Error::Parser("failed to lex source".to_owned())),
+        }
+    });
+    (res, collector.take())
+}
@@ -5,32 +5,272 @@
 
 // We need to import them like this otherwise it doesn't work.
 pub extern crate rustc_ast;
+pub extern crate rustc_ast_pretty;
+pub extern crate rustc_attr;
+pub extern crate rustc_borrowck;
 pub extern crate rustc_data_structures;
 pub extern crate rustc_driver;
+pub extern crate rustc_error_messages;
 pub extern crate rustc_errors;
 pub extern crate rustc_feature;
 pub extern crate rustc_hir;
+pub extern crate rustc_hir_analysis;
+pub extern crate rustc_hir_pretty;
 pub extern crate rustc_interface;
 pub extern crate rustc_lexer;
 pub extern crate rustc_lint;
+pub extern crate rustc_metadata;
 pub extern crate rustc_middle;
+pub extern crate rustc_mir_dataflow;
+pub extern crate rustc_mir_transform;
 pub extern crate rustc_parse;
 pub extern crate rustc_session;
 pub extern crate rustc_span;
+pub extern crate rustc_target;
+pub extern crate rustc_trait_selection;
 
+extern crate polonius_engine;
+extern crate stacker;
+
+use std::fmt;
+
+mod analyzer;
 mod ast;
+mod config;
+mod file_loader;
 mod hir;
+mod lexer;
+mod sysroot;
+
+pub mod api;
+pub mod async_fns;
+pub mod attrs;
+pub mod auto_traits;
+pub mod borrowck;
+#[cfg(feature = "serde")]
+pub mod cache;
+pub mod call_graph;
+pub mod cfgs;
+pub mod cargo;
+pub mod closures;
+pub mod consts;
+pub mod crate_graph;
+pub mod dependencies;
+pub mod deprecation;
+pub mod diagnostics;
+pub mod doc_index;
+pub mod doctests;
+pub mod dot;
+pub mod expansion;
+pub mod ffi;
+pub mod generics;
+pub mod hir_json;
+pub mod includes;
+pub mod layout;
+pub mod lints;
+pub mod lookup;
+pub mod metrics;
+pub mod mir;
+pub mod modules;
+pub mod panics;
+pub mod paths;
+pub mod pretty;
+pub mod profiling;
+pub mod recursion;
+pub mod rewrite;
+pub mod search;
+pub mod semver;
+pub mod snippets;
+pub mod source_files;
+pub mod spans;
+pub mod stable_ids;
+pub mod suggestions;
+pub mod test_discovery;
+pub mod traits;
+pub mod unsafety;
+pub mod unused;
+pub mod visit;
+pub mod watch;
+#[cfg(feature = "serde")]
+pub mod wrapper;
 
-pub use ast::with_ast_parser;
-pub use hir::with_tyctxt;
+pub use analyzer::Analyzer;
+pub use ast::{expand_to_string, with_ast_parser, with_expanded_ast, with_parsed_crate};
+pub use config::{
+    AnalysisLevel, CancellationToken, Config, DiagnosticsBuffer, EmitKind, ExternModifier,
+    LimitKind, Stage, VirtualCrate,
+};
+pub use hir::{
+    analyze_under_edition, with_compiler, with_lints, with_mir, with_tyctxt,
+    with_tyctxt_allow_errors, with_tyctxt_captured, with_tyctxt_collect_diagnostics,
+    with_tyctxt_configured, with_tyctxt_from_str, with_tyctxt_local, with_tyctxt_timeout,
+    with_tyctxt_virtual,
+};
+pub use lexer::with_lexer;
+pub use sysroot::required_nightly;
 
-/// Error returned by the API. If the parser encounters a problem, it'll always be `Error::Parser`.
-/// `Error::Other` is for users.
-pub enum Error<E> {
+/// Error returned by the API.
+#[derive(Debug)]
+pub enum Error {
+    /// The parser encountered a problem. Only returned by [`with_ast_parser`] and
+    /// [`with_lexer`].
     Parser(String),
-    Other(E),
+    /// The `rustc` arguments couldn't be parsed or are invalid (like an unknown `-C` option), so
+    /// the analysis can be run again with other arguments.
+    ArgParse(String),
+    /// No input file was provided in the `rustc` arguments.
+    InputMissing,
+    /// More than one input file was provided in the `rustc` arguments.
+    TooManyInputs,
+    /// The input couldn't be read (for example if stdin didn't contain valid UTF-8).
+    InvalidInput(String),
+    /// The value given to `--crate-type` isn't a known crate type. It contains the message
+    /// emitted by the compiler.
+    UnknownCrateType(String),
+    /// `--sysroot` wasn't passed and the sysroot couldn't be detected. `command` is the command
+    /// which was run to detect it.
+    SysrootDetection { command: String, reason: String },
+    /// Running cargo failed or its output couldn't be used. Only returned by the functions of the
+    /// [`cargo`] module.
+    Cargo(String),
+    /// The wrapper results couldn't be written or read. Only returned by the functions of the
+    /// `wrapper` module.
+    Wrapper(String),
+    /// A result couldn't be written into the cache. Only returned by the functions of the `cache`
+    /// module.
+    Cache(String),
+    /// The compiler emitted errors so the callback wasn't called.
+    CompilationFailed { error_count: usize },
+    /// A constant couldn't be evaluated. It contains the errors emitted by the compiler or the
+    /// reason why it can't be evaluated. Only returned by [`consts::eval_const`].
+    ConstEval(String),
+    /// The compiler panicked (because of a bug of the compiler or of a callback, or because a
+    /// query was called on erroneous code). `backtrace` is the backtrace of the panic.
+    InternalCompilerError { message: String, backtrace: String },
+    /// The compiler couldn't find `std` or `core` (the name of the crate it contains) in the
+    /// sysroot.
+    MissingSysrootCrate(String),
+    /// The libraries of the target given with `--target` or [`Config::target`] can't be found.
+    /// `hint` tells how to get them.
+    MissingTargetLibs { triple: String, hint: String },
+    /// A configuration option given to [`Config::cfg_flag`] or [`Config::cfg_kv`] is invalid.
+    InvalidCfg(String),
+    /// An extern crate given to [`Config::extern_crate`] (or a similar method) is invalid: its
+    /// name isn't a valid crate name, or the library given to [`Config::extern_proc_macro`] isn't
+    /// a proc-macro crate of the host.
+    InvalidExtern(String),
+    /// A dependency given to [`dependencies::compile_dependency`] doesn't compile. `diagnostics`
+    /// contains the diagnostics emitted by the compiler.
+    DependencyCompilation {
+        crate_name: String,
+        diagnostics: Vec<diagnostics::Diagnostic>,
+    },
+    /// A file given to [`Config::emit`] couldn't be written.
+    Emit(String),
+    /// The toolchain of the sysroot isn't the one this crate was built with (see
+    /// [`required_nightly`] and [`Config::check_toolchain`]). They contain the output of
+    /// `rustc --version` for both.
+    ToolchainMismatch { expected: String, found: String },
+    /// The analysis was cancelled with the token given to [`Config::cancellation_token`].
+    Cancelled,
+    /// The analysis didn't finish before the deadline given to [`with_tyctxt_timeout`].
+    TimedOut,
+    /// An analysis which timed out earlier is still running, so [`with_tyctxt_timeout`] couldn't
+    /// start the compiler before its deadline.
+    PreviousAnalysisRunning,
+    /// The compilation failed because it reached a limit of the compiler, `limit` is its value
+    /// (see [`Config::recursion_limit`] and [`Config::type_length_limit`]). `span` is where it
+    /// was reached, like the macro call whose expansion is too deep.
+    LimitExceeded {
+        which: LimitKind,
+        limit: usize,
+        span: Option<diagnostics::DiagnosticSpan>,
+    },
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parser(e) => write!(f, "parser error: {e}"),
+            Self::ArgParse(e) => write!(f, "failed to parse arguments: {e}"),
+            Self::InputMissing => write!(f, "missing file operand"),
+            Self::TooManyInputs => write!(f, "too many file operands"),
+            Self::InvalidInput(e) => write!(f, "invalid input: {e}"),
+            Self::UnknownCrateType(e) => f.write_str(e),
+            Self::SysrootDetection { command, reason } => write!(
+                f,
+                "failed to detect the sysroot with `{command}` (you can pass it with `--sysroot`): {reason}"
+            ),
+            Self::Cargo(e) => write!(f, "cargo error: {e}"),
+            Self::Wrapper(e) => write!(f, "wrapper error: {e}"),
+            Self::Cache(e) => write!(f, "cache error: {e}"),
+            Self::CompilationFailed { error_count } => {
+                write!(f, "compilation failed with {error_count} error(s)")
+            }
+            Self::ConstEval(e) => write!(f, "failed to evaluate constant: {e}"),
+            Self::InternalCompilerError { message, .. } => {
+                write!(f, "internal compiler error: {message}")
+            }
+            Self::MissingSysrootCrate(krate) => write!(
+                f,
+                "can't find crate for `{}` in the sysroot (pass the right one with `--sysroot`, or \
+                 use `Config::{}` if the crate doesn't depend on it)",
+                krate,
+                if krate == "std" { "no_std" } else { "no_core" }
+            ),
+            Self::MissingTargetLibs { triple, hint } => write!(
+                f,
+                "the libraries of the `{triple}` target can't be found: {hint}"
+            ),
+            Self::InvalidCfg(e) => write!(f, "invalid configuration option: {e}"),
+            Self::InvalidExtern(e) => write!(f, "invalid extern crate: {e}"),
+            Self::DependencyCompilation {
+                crate_name,
+                diagnostics,
+            } => {
+                write!(f, "failed to compile the `{crate_name}` dependency")?;
+                match diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.level == diagnostics::Level::Error)
+                {
+                    Some(diagnostic) => write!(f, ": {}", diagnostic.message),
+                    None => Ok(()),
+                }
+            }
+            Self::Emit(e) => write!(f, "failed to write an output file: {e}"),
+            Self::ToolchainMismatch { expected, found } => write!(
+                f,
+                "found `{found}` but `{expected}` is needed (you can select it with the `RUSTC` or \
+                 `RUSTUP_TOOLCHAIN` environment variables, or with `--sysroot`)"
+            ),
+            Self::Cancelled => write!(f, "the analysis was cancelled"),
+            Self::TimedOut => write!(f, "the analysis timed out"),
+            Self::PreviousAnalysisRunning => write!(
+                f,
+                "an analysis which timed out is still running, the compiler can't be used until it \
+                 finishes"
+            ),
+            Self::LimitExceeded { which, limit, span } => {
+                let which = match which {
+                    LimitKind::Recursion => "recursion",
+                    LimitKind::TypeLength => "type-length",
+                };
+                write!(f, "reached the {which} limit ({limit})")?;
+                if let Some(span) = span {
+                    write!(
+                        f,
+                        " at {}:{}:{}",
+                        span.file_name, span.line_start, span.column_start
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 /// Very basic lexer which return a lexer iterator. It doesn't handle errors or anything. For more
 /// advanced usage, take a look at [`with_ast_parser`] instead.
 pub fn lexer<'a>(source_code: &'a str) -> rustc_lexer::Cursor<'a> {
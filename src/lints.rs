@@ -0,0 +1,125 @@
+//! Helpers to run the lints of the compiler on a crate and get what they found.
+
+use rustc_lint::{BuiltinCombinedEarlyLintPass, BuiltinCombinedPreExpansionLintPass};
+use rustc_session::lint::LintId;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeSet;
+
+use crate::diagnostics::{DiagnosticSpan, DiagnosticsCollector, Level};
+use crate::{AnalysisLevel, Config, Error};
+
+const UNKNOWN_LINTS: &str = "unknown_lints";
+
+/// A lint emitted by the compiler, returned by [`run_lints`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFinding {
+    /// The name of the lint, like `unused_variables`.
+    pub lint: String,
+    /// [`Level::Error`] if the lint is denied (with `#![deny]` in the crate for example),
+    /// [`Level::Warning`] otherwise.
+    pub level: Level,
+    pub message: String,
+    /// The primary span of the lint.
+    pub span: Option<DiagnosticSpan>,
+    /// The lint as it would have been displayed by the compiler.
+    pub rendered: String,
+}
+
+/// Runs the compiler with `rustc_args` and returns the lints it emitted among the ones of
+/// `lint_filter`, in the order in which they were emitted. `lint_filter` can contain lint names
+/// (like `dead_code`) and lint groups (like `unused`).
+///
+/// The lints of `lint_filter` are set to `warn` and all the other lints of the compiler to
+/// `allow`, after the levels given in `rustc_args`. The lint attributes of the crate (like
+/// `#![allow(dead_code)]`) still apply.
+///
+/// If all the lints of `lint_filter` run on the AST (like `unused_parens` or
+/// `non_camel_case_types`), the crate is not type-checked. Otherwise the whole analysis is run
+/// (like with [`AnalysisLevel::Full`]), which is slower.
+///
+/// The names of `lint_filter` which aren't known by the compiler (except the ones of tools, like
+/// `clippy::all`) are reported by a finding whose lint is `unknown_lints`.
+///
+/// If the compiler emits errors which aren't lints, [`Error::CompilationFailed`] is returned.
+pub fn run_lints(rustc_args: &[String], lint_filter: &[&str]) -> Result<Vec<LintFinding>, Error> {
+    let store = rustc_lint::new_lint_store(false);
+    let early_lints = BuiltinCombinedPreExpansionLintPass::get_lints()
+        .into_iter()
+        .chain(BuiltinCombinedEarlyLintPass::get_lints())
+        .map(LintId::of)
+        .collect::<Vec<_>>();
+
+    let mut requested = BTreeSet::new();
+    let mut only_early_lints = true;
+    for name in lint_filter {
+        match store.find_lints(name) {
+            Ok(lint_ids) => {
+                for lint_id in lint_ids {
+                    only_early_lints &= early_lints.contains(&lint_id);
+                    requested.insert(lint_id.to_string());
+                }
+            }
+            // The lints of tools may be emitted at any time.
+            Err(_) if name.contains("::") => {
+                only_early_lints = false;
+                requested.insert(name.to_string());
+            }
+            // The other unknown lints are reported by `Config::warn`.
+            Err(_) => {
+                requested.insert(UNKNOWN_LINTS.to_owned());
+            }
+        }
+    }
+
+    let mut config = Config::new(rustc_args)
+        .analysis_level(if only_early_lints {
+            AnalysisLevel::HirOnly
+        } else {
+            AnalysisLevel::Full
+        })
+        // The lints denied in the crate are errors.
+        .allow_errors(true);
+    for lint in store.get_lints() {
+        let name = lint.name_lower();
+        // Allowing `warnings` would prevent all the warnings from being emitted.
+        if name != "warnings" {
+            config = config.allow(&name);
+        }
+    }
+    for name in lint_filter {
+        config = config.warn(name);
+    }
+    let collector = DiagnosticsCollector::new();
+    // The diagnostics are taken in the callback so the final "aborting due to previous error"
+    // isn't included.
+    let diagnostics = config
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|_| collector.take())?;
+
+    let is_lint = |name: &str| requested.contains(name) || store.find_lints(name).is_ok();
+    let error_count = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.level == Level::Error)
+        .filter(|diagnostic| !diagnostic.code.as_deref().map_or(false, is_lint))
+        .count();
+    if error_count > 0 {
+        return Err(Error::CompilationFailed { error_count });
+    }
+    Ok(diagnostics
+        .into_iter()
+        .filter_map(|diagnostic| {
+            let lint = diagnostic.code.clone()?;
+            requested.contains(&lint).then(|| LintFinding {
+                lint,
+                level: diagnostic.level,
+                message: diagnostic.message.clone(),
+                span: diagnostic.primary_span().cloned(),
+                rendered: diagnostic.rendered,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,506 @@
+//! Helpers to find what is at a given position in the source code, and where the definitions
+//! it refers to are.
+
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{DefId, LocalDefId, CRATE_DEF_ID};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{
+    BodyId, Expr, ExprKind, FieldDef, ForeignItem, HirId, ImplItem, Item, ItemKind, Node, Pat,
+    PathSegment, QPath, TraitItem, UseKind, Variant,
+};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{DefIdTree, Instance, InstanceDef, Ty, TyCtxt, TypeckResults};
+use rustc_span::symbol::{kw, Symbol};
+use rustc_span::{BytePos, FileName, Span};
+
+use std::path::{Path, PathBuf};
+
+use crate::api::module_children;
+use crate::spans::{line_col_range, span_from_position, LineCol};
+
+/// HIR node returned by [`node_at_position`].
+#[derive(Debug)]
+pub struct NodeAtPos<'tcx> {
+    pub hir_id: HirId,
+    pub node: Node<'tcx>,
+    pub span: Span,
+    /// The type of the node if it's an expression or a pattern.
+    pub ty: Option<Ty<'tcx>>,
+}
+
+/// Returns the innermost expression, pattern or item at the given position of `file` (`line`
+/// starts at 0 and `col_utf16` is counted in UTF-16 code units, like a
+/// [`Position`](crate::spans::Position)). A node ending right at the position is considered to be
+/// under it, so it works when the cursor is after an identifier too.
+///
+/// The nodes generated by macros are skipped, use [`node_at_position_with_expansions`] if you want
+/// them.
+///
+/// To get the type of an expression, its body is type-checked if it wasn't already, so you
+/// might want to use an [`AnalysisLevel`](crate::AnalysisLevel) which does it beforehand.
+pub fn node_at_position<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    file: &Path,
+    line: u32,
+    col_utf16: u32,
+) -> Option<NodeAtPos<'tcx>> {
+    find_node(tcx, file, line, col_utf16, false)
+}
+
+/// Same as [`node_at_position`] except that the nodes generated by macros are not skipped.
+pub fn node_at_position_with_expansions<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    file: &Path,
+    line: u32,
+    col_utf16: u32,
+) -> Option<NodeAtPos<'tcx>> {
+    find_node(tcx, file, line, col_utf16, true)
+}
+
+fn find_node<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    file: &Path,
+    line: u32,
+    col_utf16: u32,
+    with_expansions: bool,
+) -> Option<NodeAtPos<'tcx>> {
+    let pos = span_from_position(tcx, file, line, col_utf16)?.lo();
+    let mut finder = NodeFinder {
+        tcx,
+        pos,
+        with_expansions,
+        found: None,
+    };
+    tcx.hir().visit_all_item_likes_in_crate(&mut finder);
+
+    let (hir_id, span) = finder.found?;
+    let node = tcx.hir().get(hir_id);
+    let ty = match node {
+        Node::Expr(_) | Node::Pat(_) => {
+            let owner = tcx.hir().enclosing_body_owner(hir_id);
+            tcx.typeck(owner).node_type_opt(hir_id)
+        }
+        _ => None,
+    };
+    Some(NodeAtPos {
+        hir_id,
+        node,
+        span,
+        ty,
+    })
+}
+
+struct NodeFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    pos: BytePos,
+    with_expansions: bool,
+    found: Option<(HirId, Span)>,
+}
+
+impl<'tcx> NodeFinder<'tcx> {
+    fn check(&mut self, hir_id: HirId, span: Span) {
+        if (span.from_expansion() && !self.with_expansions)
+            || span.lo() > self.pos
+            || span.hi() < self.pos
+        {
+            return;
+        }
+        // Children are visited after their parent so they win if they have the same size.
+        match self.found {
+            Some((_, found)) if found.hi() - found.lo() < span.hi() - span.lo() => {}
+            _ => self.found = Some((hir_id, span)),
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for NodeFinder<'tcx> {
+    type NestedFilter = nested_filter::All;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_item(&mut self, item: &'tcx Item<'tcx>) {
+        self.check(item.hir_id(), item.span);
+        intravisit::walk_item(self, item);
+    }
+
+    fn visit_impl_item(&mut self, item: &'tcx ImplItem<'tcx>) {
+        self.check(item.hir_id(), item.span);
+        intravisit::walk_impl_item(self, item);
+    }
+
+    fn visit_trait_item(&mut self, item: &'tcx TraitItem<'tcx>) {
+        self.check(item.hir_id(), item.span);
+        intravisit::walk_trait_item(self, item);
+    }
+
+    fn visit_foreign_item(&mut self, item: &'tcx ForeignItem<'tcx>) {
+        self.check(item.hir_id(), item.span);
+        intravisit::walk_foreign_item(self, item);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        self.check(expr.hir_id, expr.span);
+        intravisit::walk_expr(self, expr);
+    }
+
+    fn visit_pat(&mut self, pat: &'tcx Pat<'tcx>) {
+        self.check(pat.hir_id, pat.span);
+        intravisit::walk_pat(self, pat);
+    }
+}
+
+/// The definition returned by [`definition_at`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefLocation {
+    /// The definition, local or from another crate. The constructors of the structs and of the
+    /// variants are replaced by their struct or variant.
+    pub def_id: DefId,
+    /// The span of the definition, like `fn foo()` or `struct Foo`. For the definitions of the
+    /// other crates, it comes from their metadata.
+    pub span: Span,
+    /// The path of the file of `span`, as given to the compiler for the local crate. For the other
+    /// crates, it's the path recorded in their metadata, which doesn't exist if the crate was
+    /// built on another machine and its paths were remapped (like the standard library unless the
+    /// `rust-src` component is installed). `None` if the file isn't on the file system.
+    pub file: Option<PathBuf>,
+    /// The beginning and the end of `span`, like with [`line_col_range`].
+    pub start: LineCol,
+    pub end: LineCol,
+    /// The imports and the re-exports which lead from the name under the position to the
+    /// definition, in that order.
+    pub reexports: Vec<Reexport>,
+}
+
+/// An import or a re-export (a `use` item) of a definition, see [`DefLocation::reexports`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reexport {
+    /// The module containing the `use` item.
+    pub module: DefId,
+    /// The name under which the definition is imported, which is another one with
+    /// `use path as name;`.
+    pub name: String,
+    /// The span of the `use` item, from the metadata for the other crates.
+    pub span: Span,
+}
+
+/// Returns the definition of the path or the method under the given position of `file` (`line`
+/// starts at 0 and `col_utf16` is counted in UTF-16 code units, like a
+/// [`Position`](crate::spans::Position)), like the "go to definition" of an IDE. If the position
+/// is on the name of a definition, it's the definition itself.
+///
+/// The imports and the re-exports through which the path goes are followed to the definition and
+/// returned in [`DefLocation::reexports`]. For the other crates, only the `use` item of the
+/// module named in the path is known, not the ones it goes through.
+///
+/// A method called through a trait (like `x.clone()` or `Clone::clone(&x)`) gives the method of
+/// the impl if it's known at this point. The methods are resolved with the results of the type
+/// checking, so the body is type-checked if it wasn't already. `None` is returned for the local
+/// variables.
+pub fn definition_at(
+    tcx: TyCtxt<'_>,
+    file: &Path,
+    line: u32,
+    col_utf16: u32,
+) -> Option<DefLocation> {
+    let pos = span_from_position(tcx, file, line, col_utf16)?.lo();
+    let mut finder = DefinitionFinder {
+        tcx,
+        pos,
+        body_owner: None,
+        typeck_results: None,
+        found: None,
+    };
+    tcx.hir().visit_all_item_likes_in_crate(&mut finder);
+    let found = finder.found?;
+
+    let def_id = match tcx.def_kind(found.def_id) {
+        DefKind::Ctor(..) => tcx.parent(found.def_id),
+        _ => found.def_id,
+    };
+    let reexports = found
+        .scope
+        .map(|(module, name)| reexport_chain(tcx, module, name, def_id))
+        .unwrap_or_default();
+    let span = tcx.def_span(def_id);
+    let (file_name, start, end) = line_col_range(tcx, span);
+    let file = match file_name {
+        FileName::Real(name) => Some(name.local_path_if_available().to_path_buf()),
+        _ => None,
+    };
+    Some(DefLocation {
+        def_id,
+        span,
+        file,
+        start,
+        end,
+        reexports,
+    })
+}
+
+/// Returns the `use` items followed to find `target` under the name `name` in `module`.
+fn reexport_chain(
+    tcx: TyCtxt<'_>,
+    mut module: DefId,
+    mut name: Symbol,
+    target: DefId,
+) -> Vec<Reexport> {
+    let is_target = |res: Res| match res {
+        Res::Def(DefKind::Ctor(..), def_id) => tcx.parent(def_id) == target,
+        Res::Def(_, def_id) => def_id == target,
+        _ => false,
+    };
+    let mut reexports = Vec::new();
+    let mut visited = FxHashSet::default();
+    while tcx.def_kind(module) == DefKind::Mod && visited.insert((module, name)) {
+        if tcx.opt_parent(target) == Some(module) {
+            break;
+        }
+        let Some(local_module) = module.as_local() else {
+            // The metadata only has the `use` items of the module, not the paths they import.
+            let child = tcx
+                .module_children(module)
+                .iter()
+                .find(|child| child.ident.name == name && is_target(child.res.expect_non_local()));
+            if let Some(child) = child {
+                reexports.push(Reexport {
+                    module,
+                    name: name.to_string(),
+                    span: child.span,
+                });
+            }
+            break;
+        };
+
+        let hir = tcx.hir();
+        let mut next = None;
+        for &item_id in hir.get_module(local_module).0.item_ids {
+            let item = hir.item(item_id);
+            let ItemKind::Use(path, kind) = item.kind else { continue };
+            let Some((last, prefix)) = path.segments.split_last() else { continue };
+            let next_module = prefix.iter().fold(Some(module), |scope, segment| {
+                path_module(tcx, segment, segment.res, scope)
+            });
+            match kind {
+                UseKind::Single
+                    if item.ident.name == name && path.res.iter().any(|&res| is_target(res)) =>
+                {
+                    next = Some((
+                        item.span,
+                        next_module.map(|module| (module, last.ident.name)),
+                    ));
+                    break;
+                }
+                UseKind::Glob if next.is_none() => {
+                    // The resolution of the glob import itself is `Res::Err`.
+                    let Res::Def(DefKind::Mod, glob_module) = last.res else { continue };
+                    let is_imported =
+                        module_children(tcx, glob_module, false, &mut FxHashSet::default())
+                            .into_iter()
+                            .any(|(child_name, def_id)| child_name == name && def_id == target);
+                    if is_imported {
+                        next = Some((item.span, Some((glob_module, name))));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some((span, next_scope)) = next else { break };
+        reexports.push(Reexport {
+            module,
+            name: name.to_string(),
+            span,
+        });
+        let Some((next_module, next_name)) = next_scope else { break };
+        module = next_module;
+        name = next_name;
+    }
+    reexports
+}
+
+/// Returns the module `segment` resolves to, `scope` being the module of the segments before it
+/// (or the module containing the path for the first one), as the resolution of the keywords (like
+/// `crate`) isn't kept in the HIR.
+fn path_module(
+    tcx: TyCtxt<'_>,
+    segment: &PathSegment<'_>,
+    res: Res,
+    scope: Option<DefId>,
+) -> Option<DefId> {
+    match (res, segment.ident.name) {
+        (Res::Def(DefKind::Mod, module), _) => Some(module),
+        (_, kw::Crate) => Some(CRATE_DEF_ID.to_def_id()),
+        (_, kw::SelfLower) => scope,
+        (_, kw::Super) => tcx.opt_parent(scope?),
+        _ => None,
+    }
+}
+
+/// What [`DefinitionFinder`] found under the position.
+struct FoundDefinition {
+    def_id: DefId,
+    /// The module in which the name under the position is looked up, with this name, if it's in a
+    /// path (like `a` for `a::b`, or the current module for `b`).
+    scope: Option<(DefId, Symbol)>,
+}
+
+struct DefinitionFinder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    pos: BytePos,
+    /// The owner of the body being visited and its typeck results.
+    body_owner: Option<LocalDefId>,
+    typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    found: Option<FoundDefinition>,
+}
+
+impl<'tcx> DefinitionFinder<'tcx> {
+    fn check(&mut self, res: Res, hir_id: HirId, span: Span, scope: Option<(DefId, Symbol)>) {
+        if self.found.is_some()
+            || span.from_expansion()
+            || span.lo() > self.pos
+            || span.hi() < self.pos
+        {
+            return;
+        }
+        let Res::Def(_, def_id) = res else { return };
+        self.found = Some(FoundDefinition {
+            def_id: self.impl_item(def_id, hir_id),
+            scope,
+        });
+    }
+
+    /// Returns the item of the impl called if `def_id` is an associated item of a trait which
+    /// can be resolved, or `def_id` otherwise.
+    fn impl_item(&self, def_id: DefId, hir_id: HirId) -> DefId {
+        let tcx = self.tcx;
+        let (Some(body_owner), Some(typeck_results)) = (self.body_owner, self.typeck_results) else {
+            return def_id;
+        };
+        let Some(substs) = typeck_results.node_substs_opt(hir_id) else { return def_id };
+        if tcx.trait_of_item(def_id).is_none() {
+            return def_id;
+        }
+        let param_env = tcx.param_env(tcx.typeck_root_def_id(body_owner.to_def_id()));
+        match Instance::resolve(tcx, param_env, def_id, substs) {
+            Ok(Some(instance)) if !matches!(instance.def, InstanceDef::Virtual(..)) => {
+                instance.def_id()
+            }
+            _ => def_id,
+        }
+    }
+
+    fn check_definition(&mut self, def_id: LocalDefId, ident_span: Span) {
+        let def_id = def_id.to_def_id();
+        self.check(
+            Res::Def(self.tcx.def_kind(def_id), def_id),
+            self.tcx.hir().local_def_id_to_hir_id(def_id.expect_local()),
+            ident_span,
+            None,
+        );
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for DefinitionFinder<'tcx> {
+    // `visit_all_item_likes_in_crate` already goes through all the items.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let owner = self.tcx.hir().body_owner_def_id(body_id);
+        let old_body_owner = self.body_owner.replace(owner);
+        let old_typeck_results = self.typeck_results.replace(self.tcx.typeck_body(body_id));
+        self.visit_body(self.tcx.hir().body(body_id));
+        self.body_owner = old_body_owner;
+        self.typeck_results = old_typeck_results;
+    }
+
+    fn visit_item(&mut self, item: &'tcx Item<'tcx>) {
+        // The name of a `use` item is the one it imports.
+        if !matches!(item.kind, ItemKind::Use(..)) {
+            self.check_definition(item.owner_id.def_id, item.ident.span);
+        }
+        intravisit::walk_item(self, item);
+    }
+
+    fn visit_trait_item(&mut self, item: &'tcx TraitItem<'tcx>) {
+        self.check_definition(item.owner_id.def_id, item.ident.span);
+        intravisit::walk_trait_item(self, item);
+    }
+
+    fn visit_impl_item(&mut self, item: &'tcx ImplItem<'tcx>) {
+        self.check_definition(item.owner_id.def_id, item.ident.span);
+        intravisit::walk_impl_item(self, item);
+    }
+
+    fn visit_foreign_item(&mut self, item: &'tcx ForeignItem<'tcx>) {
+        self.check_definition(item.owner_id.def_id, item.ident.span);
+        intravisit::walk_foreign_item(self, item);
+    }
+
+    fn visit_field_def(&mut self, field: &'tcx FieldDef<'tcx>) {
+        self.check_definition(field.def_id, field.ident.span);
+        intravisit::walk_field_def(self, field);
+    }
+
+    fn visit_variant(&mut self, variant: &'tcx Variant<'tcx>) {
+        self.check_definition(variant.def_id, variant.ident.span);
+        intravisit::walk_variant(self, variant);
+    }
+
+    fn visit_qpath(&mut self, qpath: &'tcx QPath<'tcx>, id: HirId, _span: Span) {
+        // The resolved paths are checked by `visit_path`, and the type-relative ones (like
+        // `Type::method`) can only be resolved in the bodies.
+        if let (QPath::TypeRelative(_, segment), Some(typeck_results)) =
+            (qpath, self.typeck_results)
+        {
+            self.check(
+                typeck_results.qpath_res(qpath, id),
+                id,
+                segment.ident.span,
+                None,
+            );
+        }
+        intravisit::walk_qpath(self, qpath, id);
+    }
+
+    fn visit_path(&mut self, path: &rustc_hir::Path<'tcx>, id: HirId) {
+        let mut scope = Some(self.tcx.parent_module(id).to_def_id());
+        for (i, segment) in path.segments.iter().enumerate() {
+            // The resolution of the last segment of the imports is `Res::Err`.
+            let res = if i == path.segments.len() - 1 {
+                path.res
+            } else {
+                segment.res
+            };
+            let module = path_module(self.tcx, segment, res, scope);
+            let res = match (res, module) {
+                (Res::Err, Some(module)) => Res::Def(DefKind::Mod, module),
+                _ => res,
+            };
+            let name = segment.ident.name;
+            let is_keyword = matches!(name, kw::Crate | kw::SelfLower | kw::Super);
+            let scope_and_name = scope.filter(|_| !is_keyword).map(|scope| (scope, name));
+            self.check(res, id, segment.ident.span, scope_and_name);
+            scope = module;
+        }
+        intravisit::walk_path(self, path);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(segment, ..) = expr.kind {
+            let def_id = self
+                .typeck_results
+                .and_then(|typeck_results| typeck_results.type_dependent_def_id(expr.hir_id));
+            if let Some(def_id) = def_id {
+                let res = Res::Def(self.tcx.def_kind(def_id), def_id);
+                self.check(res, expr.hir_id, segment.ident.span, None);
+            }
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
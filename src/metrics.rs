@@ -0,0 +1,228 @@
+//! Helpers to compute metrics of the functions of the crate, like their length or their
+//! cyclomatic complexity.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{Block, Expr, ExprKind, Local, Stmt};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::mir::TerminatorKind;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::paths::qualified_name;
+use crate::spans::location_string;
+
+/// Options of [`fn_metrics`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsOptions {
+    /// Compute the cyclomatic complexity from the MIR instead of the HIR. The MIR is only
+    /// available with [`AnalysisLevel::Full`](crate::AnalysisLevel).
+    pub use_mir: bool,
+    /// Include the functions generated by macros, like the methods of the derived impls.
+    pub include_macro_generated: bool,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FnMetrics {
+    /// See [`qualified_name`].
+    pub name: String,
+    /// Where the function is defined, like `src/lib.rs:12:1`.
+    pub location: String,
+    /// The number of lines of the function, from its signature to the end of its body.
+    pub lines: usize,
+    /// The number of statements in the body, including the ones of the nested blocks and of the
+    /// closures. The expression at the end of a block counts as a statement.
+    pub statements: usize,
+    /// The depth of the most nested block, the body of the function being at depth 1. The blocks
+    /// of the closures are included.
+    pub max_nesting: usize,
+    /// The number of parameters, including `self`.
+    pub params: usize,
+    /// The cyclomatic complexity: 1 plus the number of decision points.
+    pub complexity: usize,
+    pub complexity_source: ComplexitySource,
+}
+
+/// How [`FnMetrics::complexity`] was computed.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComplexitySource {
+    /// From the branches of the optimized MIR: each `switchInt` with `n` reachable targets adds
+    /// `n - 1`. The branches removed by the optimizations (like a condition which is always
+    /// `true`) aren't counted.
+    Mir,
+    /// Estimated from the HIR: each `if` (including the ones of the `while` loops) and each `&&`
+    /// or `||` adds 1, each `match` with `n` arms adds `n - 1` (a `for` loop or a `?` being a
+    /// `match` with 2 arms) and each match guard or `let ... else` adds 1.
+    Hir,
+}
+
+/// Returns the metrics of the functions and methods of the crate which have a body, in the order
+/// in which they're defined. The closures are part of the function they're defined in.
+///
+/// With [`MetricsOptions::use_mir`], the complexity is still estimated from the HIR for the
+/// functions which had type errors and for the `async` functions and the functions containing
+/// `async` blocks or generators, whose MIR contains the branches of the state machine.
+pub fn fn_metrics(tcx: TyCtxt<'_>, options: MetricsOptions) -> Vec<FnMetrics> {
+    let hir = tcx.hir();
+    let mut functions = hir
+        .body_owners()
+        .filter(|&def_id| {
+            matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn)
+                && (options.include_macro_generated || !tcx.def_span(def_id).from_expansion())
+        })
+        .map(|def_id| {
+            (
+                hir.span_with_body(hir.local_def_id_to_hir_id(def_id)),
+                def_id,
+            )
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by_key(|(span, _)| span.lo());
+    functions
+        .into_iter()
+        .map(|(span, def_id)| metrics(tcx, def_id, span, options))
+        .collect()
+}
+
+fn metrics(tcx: TyCtxt<'_>, def_id: LocalDefId, span: Span, options: MetricsOptions) -> FnMetrics {
+    let hir = tcx.hir();
+    let body = hir.body(hir.body_owned_by(def_id));
+    let mut visitor = MetricsVisitor {
+        tcx,
+        statements: 0,
+        depth: 0,
+        max_nesting: 0,
+        decisions: 0,
+        closures: vec![def_id],
+    };
+    visitor.visit_body(body);
+
+    let use_mir = options.use_mir
+        && tcx.typeck(def_id).tainted_by_errors.is_none()
+        && visitor
+            .closures
+            .iter()
+            .all(|&closure| tcx.generator_kind(closure).is_none());
+    let (complexity, complexity_source) = if use_mir {
+        (
+            1 + mir_decisions(tcx, &visitor.closures),
+            ComplexitySource::Mir,
+        )
+    } else {
+        (1 + visitor.decisions, ComplexitySource::Hir)
+    };
+    let source_map = tcx.sess.source_map();
+    let span = span.source_callsite();
+    FnMetrics {
+        name: qualified_name(tcx, def_id.to_def_id()),
+        location: location_string(tcx, tcx.def_span(def_id)),
+        lines: source_map.lookup_char_pos(span.hi()).line
+            - source_map.lookup_char_pos(span.lo()).line
+            + 1,
+        statements: visitor.statements,
+        max_nesting: visitor.max_nesting,
+        params: body.params.len(),
+        complexity,
+        complexity_source,
+    }
+}
+
+/// Returns the number of decision points in the optimized MIR of the bodies.
+fn mir_decisions(tcx: TyCtxt<'_>, bodies: &[LocalDefId]) -> usize {
+    bodies
+        .iter()
+        .map(|&def_id| {
+            let body = tcx.optimized_mir(def_id);
+            body.basic_blocks
+                .iter()
+                // The cleanup blocks only run during unwinding.
+                .filter(|data| !data.is_cleanup)
+                .map(|data| match &data.terminator().kind {
+                    TerminatorKind::SwitchInt { targets, .. } => {
+                        // A `match` on an enum has an `otherwise` target which can't be reached.
+                        let reachable = targets.all_targets().iter().filter(|&&target| {
+                            !matches!(
+                                body.basic_blocks[target].terminator().kind,
+                                TerminatorKind::Unreachable
+                            )
+                        });
+                        reachable.count().saturating_sub(1)
+                    }
+                    _ => 0,
+                })
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+struct MetricsVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    statements: usize,
+    depth: usize,
+    max_nesting: usize,
+    /// The decision points found in the HIR, see [`ComplexitySource::Hir`].
+    decisions: usize,
+    /// The function and the closures it contains.
+    closures: Vec<LocalDefId>,
+}
+
+impl<'tcx> Visitor<'tcx> for MetricsVisitor<'tcx> {
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_block(&mut self, block: &'tcx Block<'tcx>) {
+        // The blocks added by the desugaring of the loops aren't written by the user.
+        let is_desugared = block.span.desugaring_kind().is_some();
+        if !is_desugared {
+            self.depth += 1;
+            self.max_nesting = self.max_nesting.max(self.depth);
+        }
+        if block
+            .expr
+            .map_or(false, |expr| expr.span.desugaring_kind().is_none())
+        {
+            self.statements += 1;
+        }
+        intravisit::walk_block(self, block);
+        if !is_desugared {
+            self.depth -= 1;
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'tcx Stmt<'tcx>) {
+        if stmt.span.desugaring_kind().is_none() {
+            self.statements += 1;
+        }
+        intravisit::walk_stmt(self, stmt);
+    }
+
+    fn visit_local(&mut self, local: &'tcx Local<'tcx>) {
+        if local.els.is_some() {
+            self.decisions += 1;
+        }
+        intravisit::walk_local(self, local);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        match expr.kind {
+            ExprKind::If(..) => self.decisions += 1,
+            ExprKind::Match(_, arms, _) => {
+                self.decisions += arms.len().saturating_sub(1)
+                    + arms.iter().filter(|arm| arm.guard.is_some()).count();
+            }
+            ExprKind::Binary(op, ..) if op.node.is_lazy() => self.decisions += 1,
+            ExprKind::Closure(closure) => self.closures.push(closure.def_id),
+            _ => {}
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
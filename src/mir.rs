@@ -0,0 +1,269 @@
+//! Analyses of the [MIR](https://rustc-dev-guide.rust-lang.org/mir/index.html) of the local
+//! functions, whose results don't borrow anything from the compiler, and its textual
+//! representation.
+//!
+//! They use the optimized MIR, which requires the crate to be fully checked, so these functions
+//! should be used with [`with_mir`](crate::with_mir) (or with
+//! [`AnalysisLevel::Full`](crate::AnalysisLevel)). How much it's optimized can be set with
+//! [`Config::mir_opt_level`](crate::Config::mir_opt_level) and
+//! [`Config::inline_mir`](crate::Config::inline_mir).
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::mir::pretty::write_mir_fn;
+use rustc_middle::mir::{Body, Local, Location, TerminatorKind, VarDebugInfoContents};
+use rustc_middle::ty::query::Providers;
+use rustc_middle::ty::{InstanceDef, TyCtxt, WithOptConstParam};
+use rustc_mir_dataflow::impls::MaybeLiveLocals;
+use rustc_mir_dataflow::Analysis;
+use rustc_span::Span;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use crate::paths::qualified_name;
+
+thread_local! {
+    /// The rendered MIR of the functions as it's built, if [`Config::keep_built_mir`] is set.
+    ///
+    /// [`Config::keep_built_mir`]: crate::Config::keep_built_mir
+    static BUILT_MIR: RefCell<Option<HashMap<LocalDefId, String>>> = RefCell::new(None);
+}
+
+/// The phase of the MIR returned by [`mir_pretty`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirPhase {
+    /// The MIR as it's built from the THIR, before the borrow checking and the optimizations.
+    /// Take a look at [`Config::keep_built_mir`](crate::Config::keep_built_mir) to get it.
+    Built,
+    /// The MIR used for the code generation (and `rustc --emit mir`).
+    Optimized,
+}
+
+/// Returned by [`liveness`].
+#[derive(Clone, Debug)]
+pub struct LivenessResult {
+    /// The locals of the function, the index of a local in the list being `local.as_usize()`.
+    pub locals: Vec<LocalInfo>,
+    /// The statements and the terminators of the function, block by block.
+    pub points: Vec<ProgramPoint>,
+}
+
+/// A local of the MIR: a variable, an argument, the return place or a temporary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalInfo {
+    pub local: Local,
+    /// The name of the variable (or argument) in the source code, `None` for the temporaries.
+    pub name: Option<String>,
+    /// The span of the declaration of the variable, or of the expression for the temporaries.
+    pub span: Span,
+    pub ty: String,
+}
+
+/// A statement or a terminator, with the locals live before and after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramPoint {
+    pub location: Location,
+    pub span: Span,
+    /// `true` if it's the call of a function.
+    pub is_call: bool,
+    /// The locals whose value may be used by this point or after it.
+    pub live_before: Vec<Local>,
+    /// The locals whose value may be used after this point, on any path.
+    pub live_after: Vec<Local>,
+}
+
+impl ProgramPoint {
+    /// Returns the locals live both before and after this point: they're kept alive while it's
+    /// executed (like during a call).
+    pub fn live_across(&self) -> impl Iterator<Item = Local> + '_ {
+        self.live_before
+            .iter()
+            .copied()
+            .filter(|local| self.live_after.contains(local))
+    }
+}
+
+impl LivenessResult {
+    /// Returns the statement or the terminator at `location`.
+    pub fn point(&self, location: Location) -> Option<&ProgramPoint> {
+        self.points.iter().find(|point| point.location == location)
+    }
+
+    pub fn local(&self, local: Local) -> &LocalInfo {
+        &self.locals[local.as_usize()]
+    }
+
+    /// Returns the variables (the locals with a name) which are live across a function call,
+    /// along with the location of the call. The values of these variables must be kept (on the
+    /// stack, or in the future for an `async fn`) while the call is executed.
+    pub fn variables_live_across_calls(&self) -> Vec<(Location, &LocalInfo)> {
+        self.points
+            .iter()
+            .filter(|point| point.is_call)
+            .flat_map(|point| {
+                point
+                    .live_across()
+                    .map(|local| (point.location, self.local(local)))
+            })
+            .filter(|(_, local)| local.name.is_some())
+            .collect()
+    }
+}
+
+/// Runs the liveness analysis of the compiler on the MIR of `def_id`: a local is live at a point
+/// if its current value may be read afterwards. It returns `None` if `def_id` isn't a function, a
+/// method or a closure with a body.
+///
+/// Like in the compiler, a local is considered used when it's borrowed and not when the reference
+/// is used, and assigning a field of a local uses it instead of defining it. Dropping a local is a
+/// use too: a variable with a destructor is live until the end of its scope, unless it's moved on
+/// all the paths.
+pub fn liveness(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Option<LivenessResult> {
+    if !matches!(
+        tcx.def_kind(def_id),
+        DefKind::Fn | DefKind::AssocFn | DefKind::Closure | DefKind::Generator
+    ) || tcx.hir().maybe_body_owned_by(def_id).is_none()
+    {
+        return None;
+    }
+    let body = tcx.optimized_mir(def_id);
+
+    let mut locals = body
+        .local_decls
+        .iter_enumerated()
+        .map(|(local, decl)| LocalInfo {
+            local,
+            name: None,
+            span: decl.source_info.span,
+            ty: decl.ty.to_string(),
+        })
+        .collect::<Vec<_>>();
+    for var_debug_info in &body.var_debug_info {
+        if let VarDebugInfoContents::Place(place) = var_debug_info.value {
+            // The variables stored in a field of a local (like the captures of a closure) don't
+            // have their own local.
+            if let Some(local) = place.as_local() {
+                let info = &mut locals[local.as_usize()];
+                info.name = Some(var_debug_info.name.to_string());
+                info.span = var_debug_info.source_info.span;
+            }
+        }
+    }
+
+    let mut cursor = MaybeLiveLocals
+        .into_engine(tcx, body)
+        .iterate_to_fixpoint()
+        .into_results_cursor(body);
+    let mut points = Vec::new();
+    for (block, data) in body.basic_blocks.iter_enumerated() {
+        let len = data.statements.len();
+        for statement_index in 0..=len {
+            let location = Location {
+                block,
+                statement_index,
+            };
+            // The analysis goes backward, so its state after the effect of a point is the one
+            // before the point in the program.
+            cursor.seek_after_primary_effect(location);
+            let live_before = cursor.get().iter().collect();
+            cursor.seek_before_primary_effect(location);
+            let live_after = cursor.get().iter().collect();
+            let (span, is_call) = match data.statements.get(statement_index) {
+                Some(statement) => (statement.source_info.span, false),
+                None => {
+                    let terminator = data.terminator();
+                    let is_call = matches!(terminator.kind, TerminatorKind::Call { .. });
+                    (terminator.source_info.span, is_call)
+                }
+            };
+            points.push(ProgramPoint {
+                location,
+                span,
+                is_call,
+                live_before,
+                live_after,
+            });
+        }
+    }
+    Some(LivenessResult { locals, points })
+}
+
+/// Returns the MIR of `def_id` (a function, a closure, a constant, etc) in `phase`, rendered like
+/// with `rustc --emit mir`, or `None` if it doesn't have any.
+///
+/// The built MIR is consumed by the next passes, so it's only available if
+/// [`Config::keep_built_mir`](crate::Config::keep_built_mir) is set. The optimized MIR includes
+/// the promoted constants and, for the `const fn`s, the MIR used to evaluate them at compile
+/// time.
+pub fn mir_pretty(tcx: TyCtxt<'_>, def_id: LocalDefId, phase: MirPhase) -> Option<String> {
+    if !tcx.mir_keys(()).contains(&def_id) {
+        return None;
+    }
+    match phase {
+        MirPhase::Built => {
+            // Builds it if it wasn't already.
+            tcx.ensure().mir_built(WithOptConstParam::unknown(def_id));
+            BUILT_MIR.with(|built_mir| built_mir.borrow().as_ref()?.get(&def_id).cloned())
+        }
+        MirPhase::Optimized => {
+            let def_id = def_id.to_def_id();
+            let mut out = Vec::new();
+            // The same as `write_mir_pretty` without its header.
+            let mut render_body = |body| {
+                write_body(tcx, body, &mut out);
+                for body in tcx.promoted_mir(def_id) {
+                    writeln!(out).unwrap();
+                    write_body(tcx, body, &mut out);
+                }
+            };
+            if tcx.is_const_fn_raw(def_id) {
+                render_body(tcx.optimized_mir(def_id));
+                writeln!(out, "\n// MIR FOR CTFE").unwrap();
+                write_body(tcx, tcx.mir_for_ctfe(def_id), &mut out);
+            } else {
+                render_body(
+                    tcx.instance_mir(InstanceDef::Item(WithOptConstParam::unknown(def_id))),
+                );
+            }
+            Some(String::from_utf8_lossy(&out).into_owned())
+        }
+    }
+}
+
+/// Returns the MIR of all the items of the local crate which have one, see [`mir_pretty`]. The
+/// keys are their [`qualified_name`].
+pub fn all_mir_pretty(tcx: TyCtxt<'_>, phase: MirPhase) -> BTreeMap<String, String> {
+    tcx.mir_keys(())
+        .iter()
+        .filter_map(|&def_id| {
+            let mir = mir_pretty(tcx, def_id, phase)?;
+            Some((qualified_name(tcx, def_id.to_def_id()), mir))
+        })
+        .collect()
+}
+
+fn write_body<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>, out: &mut Vec<u8>) {
+    // Writing into a `Vec` can't fail.
+    write_mir_fn(tcx, body, &mut |_, _| Ok(()), out).unwrap();
+}
+
+pub(crate) fn keep_built_mir(keep: bool) {
+    BUILT_MIR.with(|built_mir| *built_mir.borrow_mut() = keep.then(HashMap::new));
+}
+
+/// Renders the MIR of the functions as it's built if [`keep_built_mir`] was called with `true`.
+pub(crate) fn provide(providers: &mut Providers) {
+    providers.mir_built = |tcx, def| {
+        let body = (rustc_interface::DEFAULT_QUERY_PROVIDERS.mir_built)(tcx, def);
+        BUILT_MIR.with(|built_mir| {
+            if let Some(built_mir) = built_mir.borrow_mut().as_mut() {
+                let mut out = Vec::new();
+                write_body(tcx, &body.borrow(), &mut out);
+                built_mir.insert(def.did, String::from_utf8_lossy(&out).into_owned());
+            }
+        });
+        body
+    };
+}
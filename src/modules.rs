@@ -0,0 +1,224 @@
+//! Helpers to get the tree of the modules of the local crate with the items they contain and the
+//! modules they depend on, for example to draw the architecture of a crate or to check layering
+//! rules.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId, CRATE_DEF_ID, LOCAL_CRATE};
+use rustc_hir::ItemKind;
+use rustc_middle::ty::{DefIdTree, TyCtxt, Visibility};
+use rustc_span::symbol::{kw, sym};
+use rustc_span::{FileName, Span};
+
+use std::collections::{HashMap, HashSet};
+
+use crate::paths::qualified_name;
+use crate::search::for_each_reference;
+
+/// Returned by [`module_tree`].
+#[derive(Clone, Debug)]
+pub struct ModuleTree {
+    /// The root module of the crate.
+    pub root: Module,
+    /// `true` if some bodies had type errors, so some of the [`Module::references`] may be
+    /// missing. See [`References::incomplete`](crate::search::References::incomplete).
+    pub incomplete: bool,
+}
+
+impl ModuleTree {
+    /// Returns all the modules, each one being followed by its submodules.
+    pub fn modules(&self) -> Vec<&Module> {
+        let mut modules = Vec::new();
+        let mut stack = vec![&self.root];
+        while let Some(module) = stack.pop() {
+            modules.push(module);
+            stack.extend(module.submodules.iter().rev());
+        }
+        modules
+    }
+
+    /// Returns the module with the given [`qualified_name`] (like `my_crate::db`).
+    pub fn find(&self, qualified_name: &str) -> Option<&Module> {
+        self.modules()
+            .into_iter()
+            .find(|module| module.qualified_name == qualified_name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Module {
+    pub def_id: LocalDefId,
+    /// The name of the module, which is the name of the crate for the root module.
+    pub name: String,
+    /// See [`qualified_name`].
+    pub qualified_name: String,
+    /// The file containing the items of the module.
+    pub file: FileName,
+    pub source: ModuleSource,
+    pub visibility: Visibility<DefId>,
+    /// Span of the whole module, which is the span of its file for the modules which aren't
+    /// inline.
+    pub span: Span,
+    /// The items of the module other than the submodules, grouped by kind. The groups are in the
+    /// order in which the first item of each kind is declared, and the items of a group in the
+    /// order in which they're declared.
+    pub items: Vec<ItemGroup>,
+    /// In the order in which they're declared.
+    pub submodules: Vec<Module>,
+    /// The other local modules the items of this module refer to: the modules containing the
+    /// definitions used in the module (including in its imports), and the modules used in paths
+    /// (like `db` in `db::query()`). The references from the submodules aren't included. Sorted by
+    /// qualified name.
+    pub references: Vec<LocalDefId>,
+}
+
+/// How a module is declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModuleSource {
+    /// The root module of the crate.
+    CrateRoot,
+    /// `mod name { ... }`.
+    Inline,
+    /// `mod name;`, the module being in `name.rs` or `name/mod.rs`.
+    File,
+    /// `#[path = "file.rs"] mod name;`.
+    PathAttribute,
+}
+
+#[derive(Clone, Debug)]
+pub struct ItemGroup {
+    pub kind: DefKind,
+    pub items: Vec<ModuleItem>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleItem {
+    pub def_id: LocalDefId,
+    /// `None` for the items without a name, like the impls, the glob imports or `const _`.
+    pub name: Option<String>,
+    pub visibility: Visibility<DefId>,
+    pub span: Span,
+}
+
+/// Returns the tree of the modules of the local crate. The modules declared inside of functions
+/// (and the items they contain) are not included, the items declared inside of functions are part
+/// of the function and not of the module.
+///
+/// The items generated by macros or injected by the compiler are included, like the impls of the
+/// derives or `extern crate std`. To find the [`Module::references`], all the bodies are
+/// type-checked (see [`find_references`](crate::search::find_references)).
+pub fn module_tree(tcx: TyCtxt<'_>) -> ModuleTree {
+    let mut references = HashMap::<LocalDefId, HashSet<LocalDefId>>::new();
+    let incomplete = for_each_reference(tcx, |def_id, hir_id, _, _| {
+        let Some(def_id) = def_id.as_local() else { return };
+        let referenced = tree_module(tcx, def_id);
+        let module = tree_module(tcx, hir_id.owner.def_id);
+        if referenced != module {
+            references.entry(module).or_default().insert(referenced);
+        }
+    });
+
+    let hir = tcx.hir();
+    let root = Module {
+        def_id: CRATE_DEF_ID,
+        name: tcx.crate_name(LOCAL_CRATE).to_string(),
+        qualified_name: qualified_name(tcx, CRATE_DEF_ID.to_def_id()),
+        file: tcx
+            .sess
+            .source_map()
+            .span_to_filename(hir.root_module().spans.inner_span),
+        source: ModuleSource::CrateRoot,
+        visibility: Visibility::Public,
+        span: hir.root_module().spans.inner_span,
+        items: Vec::new(),
+        submodules: Vec::new(),
+        references: Vec::new(),
+    };
+    ModuleTree {
+        root: fill_module(tcx, root, &mut references),
+        incomplete,
+    }
+}
+
+/// Adds the items, the submodules and the references of `module`.
+fn fill_module(
+    tcx: TyCtxt<'_>,
+    mut module: Module,
+    references: &mut HashMap<LocalDefId, HashSet<LocalDefId>>,
+) -> Module {
+    let hir = tcx.hir();
+    let source_map = tcx.sess.source_map();
+    for &item_id in hir.get_module(module.def_id).0.item_ids {
+        let item = hir.item(item_id);
+        let def_id = item.owner_id.def_id;
+        if let ItemKind::Mod(submodule) = item.kind {
+            let file = source_map.span_to_filename(submodule.spans.inner_span);
+            let source = if file == source_map.span_to_filename(item.span) {
+                ModuleSource::Inline
+            } else if hir
+                .attrs(item.hir_id())
+                .iter()
+                .any(|attr| attr.has_name(sym::path))
+            {
+                ModuleSource::PathAttribute
+            } else {
+                ModuleSource::File
+            };
+            let submodule = Module {
+                def_id,
+                name: item.ident.to_string(),
+                qualified_name: qualified_name(tcx, def_id.to_def_id()),
+                file,
+                source,
+                visibility: tcx.visibility(def_id),
+                span: submodule.spans.inner_span,
+                items: Vec::new(),
+                submodules: Vec::new(),
+                references: Vec::new(),
+            };
+            module
+                .submodules
+                .push(fill_module(tcx, submodule, references));
+            continue;
+        }
+        let kind = tcx.def_kind(def_id);
+        let item = ModuleItem {
+            def_id,
+            name: match item.ident.name {
+                kw::Empty | kw::Underscore => None,
+                name => Some(name.to_string()),
+            },
+            visibility: tcx.visibility(def_id),
+            span: item.span,
+        };
+        match module.items.iter_mut().find(|group| group.kind == kind) {
+            Some(group) => group.items.push(item),
+            None => module.items.push(ItemGroup {
+                kind,
+                items: vec![item],
+            }),
+        }
+    }
+
+    let mut module_references = references
+        .remove(&module.def_id)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<Vec<_>>();
+    module_references.sort_by_cached_key(|def_id| qualified_name(tcx, def_id.to_def_id()));
+    module.references = module_references;
+    module
+}
+
+/// Returns the module of the tree containing `def_id`, or `def_id` if it's a module of the tree.
+/// The modules declared inside of functions are part of the module of the function.
+fn tree_module(tcx: TyCtxt<'_>, def_id: LocalDefId) -> LocalDefId {
+    let mut module = def_id;
+    let mut current = Some(def_id);
+    while let Some(def_id) = current {
+        if tcx.def_kind(def_id) != DefKind::Mod {
+            module = tcx.parent_module_from_def_id(def_id);
+        }
+        current = tcx.opt_local_parent(def_id);
+    }
+    module
+}
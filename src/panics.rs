@@ -0,0 +1,345 @@
+//! Helpers to find the functions of the local crate which may panic.
+//!
+//! The analysis works on the optimized MIR, so it should be used with
+//! [`AnalysisLevel::Full`](crate::AnalysisLevel) and the crate must compile without errors.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::LangItem;
+use rustc_middle::mir::{AssertKind, Body, Operand, TerminatorKind};
+use rustc_middle::ty::{self, Instance, InstanceDef, InternalSubsts, ParamEnv, TyCtxt};
+use rustc_span::Span;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::paths::qualified_name;
+
+/// Options of [`panic_reachability_with_options`].
+#[derive(Clone, Debug, Default)]
+pub struct PanicOptions {
+    /// The [`qualified_name`] of the functions whose calls are considered acceptable, like
+    /// `core::option::Option::unwrap`. The calls to these functions are ignored, so they don't
+    /// make their callers panic. The panics of the checks added by the compiler are calls to
+    /// `core::panicking::panic_bounds_check` (for the indexing) and to `core::panicking::panic`
+    /// (for the arithmetic overflows and the divisions by zero).
+    pub allowed: Vec<String>,
+}
+
+/// Returned by [`panic_reachability`].
+#[derive(Clone, Debug, Default)]
+pub struct PanicReport {
+    /// The functions and methods of the local crate, in the order in which they're defined.
+    pub functions: Vec<FnPanics>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FnPanics {
+    pub def_id: LocalDefId,
+    /// See [`qualified_name`].
+    pub name: String,
+    pub verdict: PanicVerdict,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PanicVerdict {
+    /// The function can't panic, whatever the arguments.
+    PanicFree,
+    /// The function may panic. The calls lead from the function to a panicking function.
+    MayPanic { path: Vec<PanicCall> },
+    /// The function can't panic but it makes a call (or calls a function making a call) which
+    /// can't be resolved: through a trait object, a function pointer or a trait method of a type
+    /// parameter. The calls lead from the function to the one which can't be resolved.
+    Unknown { path: Vec<PanicCall> },
+}
+
+/// A call on the path of a [`PanicVerdict`], made by the function before it in the path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanicCall {
+    /// The [`qualified_name`] of the function which is called. For a call which can't be
+    /// resolved, it's the name of the trait method or the type of the function pointer.
+    pub callee: String,
+    pub span: Span,
+}
+
+/// Same as [`panic_reachability_with_options`] with the default options.
+pub fn panic_reachability(tcx: TyCtxt<'_>) -> PanicReport {
+    panic_reachability_with_options(tcx, &PanicOptions::default())
+}
+
+/// Returns whether each function (or method) of the local crate may panic, following the calls
+/// made by the function, including the ones of other crates, the ones of the closures and the
+/// drops. The checks added by the compiler (like the bounds checks of the indexing or the checks
+/// of the arithmetic overflows) count as calls to the functions they call when they fail.
+///
+/// The panicking functions are the ones of the `core::panicking` and `std::panicking` modules,
+/// as well as the functions of other crates which never return (their return type is `!`) when
+/// their MIR isn't available (so for the functions which aren't generic nor `#[inline]`, like
+/// `core::result::unwrap_failed`). The other functions whose MIR isn't available are considered
+/// panic-free. The panics which only happen while unwinding are ignored.
+///
+/// The generic functions are analyzed without knowing their generic arguments, so the calls of
+/// the trait methods of their type parameters make them [`PanicVerdict::Unknown`], unless they
+/// may panic anyway.
+pub fn panic_reachability_with_options(tcx: TyCtxt<'_>, options: &PanicOptions) -> PanicReport {
+    let mut graph = PanicGraph {
+        tcx,
+        allowed: options.allowed.iter().map(String::as_str).collect(),
+        nodes: Vec::new(),
+        index: HashMap::new(),
+        queue: VecDeque::new(),
+    };
+    let hir = tcx.hir();
+    let mut functions = hir
+        .body_owners()
+        .filter(|&def_id| matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn))
+        .collect::<Vec<_>>();
+    functions.sort_by_key(|&def_id| tcx.def_span(def_id).lo());
+    let roots = functions
+        .iter()
+        .map(|&def_id| {
+            let instance = Instance::new(
+                def_id.to_def_id(),
+                InternalSubsts::identity_for_item(tcx, def_id.to_def_id()),
+            );
+            graph.node(tcx.param_env_reveal_all_normalized(def_id), instance)
+        })
+        .collect::<Vec<_>>();
+    while let Some(node) = graph.queue.pop_front() {
+        graph.add_calls(node);
+    }
+
+    let panics = graph.paths(|call| matches!(call.callee, Callee::Panic(_)));
+    let unknown = graph.paths(|call| matches!(call.callee, Callee::Unknown(_)));
+    let functions = functions
+        .into_iter()
+        .zip(roots)
+        .map(|(def_id, node)| FnPanics {
+            def_id,
+            name: qualified_name(tcx, def_id.to_def_id()),
+            verdict: if panics[node].is_some() {
+                PanicVerdict::MayPanic {
+                    path: graph.path(node, &panics),
+                }
+            } else if unknown[node].is_some() {
+                PanicVerdict::Unknown {
+                    path: graph.path(node, &unknown),
+                }
+            } else {
+                PanicVerdict::PanicFree
+            },
+        })
+        .collect();
+    PanicReport { functions }
+}
+
+/// The functions reachable from the local functions, identified by their instance.
+struct PanicGraph<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    allowed: HashSet<&'a str>,
+    nodes: Vec<Node<'tcx>>,
+    index: HashMap<(ParamEnv<'tcx>, Instance<'tcx>), usize>,
+    /// The nodes whose calls haven't been added yet.
+    queue: VecDeque<usize>,
+}
+
+struct Node<'tcx> {
+    param_env: ParamEnv<'tcx>,
+    instance: Instance<'tcx>,
+    calls: Vec<Call>,
+}
+
+struct Call {
+    callee: Callee,
+    span: Span,
+}
+
+enum Callee {
+    Node(usize),
+    /// A panicking function, with its name.
+    Panic(String),
+    /// A call which can't be resolved, with the name of the trait method or the type of the
+    /// function pointer.
+    Unknown(String),
+}
+
+impl<'a, 'tcx> PanicGraph<'a, 'tcx> {
+    fn node(&mut self, param_env: ParamEnv<'tcx>, instance: Instance<'tcx>) -> usize {
+        *self.index.entry((param_env, instance)).or_insert_with(|| {
+            self.nodes.push(Node {
+                param_env,
+                instance,
+                calls: Vec::new(),
+            });
+            self.queue.push_back(self.nodes.len() - 1);
+            self.nodes.len() - 1
+        })
+    }
+
+    fn add_calls(&mut self, node: usize) {
+        let tcx = self.tcx;
+        let Node {
+            param_env,
+            instance,
+            ..
+        } = self.nodes[node];
+        let body = tcx.instance_mir(instance.def);
+        let mut calls = Vec::new();
+        // The cleanup blocks only run while unwinding.
+        for data in body.basic_blocks.iter().filter(|data| !data.is_cleanup) {
+            let terminator = data.terminator();
+            let span = terminator.source_info.span;
+            let callee = match &terminator.kind {
+                TerminatorKind::Call { func, .. } => self.callee(param_env, instance, body, func),
+                TerminatorKind::Drop { place, .. }
+                | TerminatorKind::DropAndReplace { place, .. } => {
+                    let ty = place.ty(body, tcx).ty;
+                    let ty =
+                        instance.try_subst_mir_and_normalize_erasing_regions(tcx, param_env, ty);
+                    let Ok(ty) = ty else { continue };
+                    match Instance::resolve_drop_in_place(tcx, ty) {
+                        // Nothing to drop.
+                        Instance {
+                            def: InstanceDef::DropGlue(_, None),
+                            ..
+                        } => None,
+                        drop_glue => self.instance_callee(param_env, drop_glue),
+                    }
+                }
+                TerminatorKind::Assert { msg, .. } => {
+                    let lang_item = match msg {
+                        AssertKind::BoundsCheck { .. } => LangItem::PanicBoundsCheck,
+                        _ => LangItem::Panic,
+                    };
+                    let def_id = tcx.require_lang_item(lang_item, Some(span));
+                    self.allowed_callee(def_id, Callee::Panic(qualified_name(tcx, def_id)))
+                }
+                _ => None,
+            };
+            if let Some(callee) = callee {
+                calls.push(Call { callee, span });
+            }
+        }
+        self.nodes[node].calls = calls;
+    }
+
+    /// Returns the callee of a call to `func`, made in the body of `instance`.
+    fn callee(
+        &mut self,
+        param_env: ParamEnv<'tcx>,
+        instance: Instance<'tcx>,
+        body: &Body<'tcx>,
+        func: &Operand<'tcx>,
+    ) -> Option<Callee> {
+        let tcx = self.tcx;
+        let func_ty = func.ty(body, tcx);
+        let func_ty = instance
+            .try_subst_mir_and_normalize_erasing_regions(tcx, param_env, func_ty)
+            .unwrap_or(func_ty);
+        let ty::FnDef(def_id, substs) = *func_ty.kind() else {
+            return Some(Callee::Unknown(func_ty.to_string()));
+        };
+        if matches!(tcx.def_kind(def_id), DefKind::Ctor(..)) {
+            return None;
+        }
+        match Instance::resolve(tcx, param_env, def_id, substs) {
+            Ok(Some(Instance {
+                def: InstanceDef::Virtual(..),
+                ..
+            }))
+            | Ok(None)
+            | Err(_) => self.allowed_callee(def_id, Callee::Unknown(qualified_name(tcx, def_id))),
+            Ok(Some(callee)) => self.instance_callee(param_env, callee),
+        }
+    }
+
+    fn instance_callee(
+        &mut self,
+        param_env: ParamEnv<'tcx>,
+        instance: Instance<'tcx>,
+    ) -> Option<Callee> {
+        let tcx = self.tcx;
+        let def_id = instance.def_id();
+        let name = qualified_name(tcx, def_id);
+        if self.allowed.contains(name.as_str()) {
+            return None;
+        }
+        if name.starts_with("core::panicking::") || name.starts_with("std::panicking::") {
+            return Some(Callee::Panic(name));
+        }
+        match instance.def {
+            InstanceDef::Intrinsic(_) => None,
+            InstanceDef::Item(def) if def.const_param_did.is_some() => None,
+            InstanceDef::Item(_) if !tcx.is_mir_available(def_id) => {
+                let never_returns = tcx.fn_sig(def_id).output().skip_binder().is_never();
+                never_returns.then_some(Callee::Panic(name))
+            }
+            _ => Some(Callee::Node(self.node(param_env, instance))),
+        }
+    }
+
+    /// Returns `callee` unless the calls to `def_id` are allowed.
+    fn allowed_callee(&self, def_id: DefId, callee: Callee) -> Option<Callee> {
+        if self
+            .allowed
+            .contains(qualified_name(self.tcx, def_id).as_str())
+        {
+            None
+        } else {
+            Some(callee)
+        }
+    }
+
+    /// Returns for each node the index of the call leading to the closest call matching
+    /// `is_target`, if there is one.
+    fn paths(&self, is_target: impl Fn(&Call) -> bool) -> Vec<Option<usize>> {
+        let mut callers = vec![Vec::new(); self.nodes.len()];
+        let mut next = vec![None; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        for (node, data) in self.nodes.iter().enumerate() {
+            for (i, call) in data.calls.iter().enumerate() {
+                if let Callee::Node(callee) = call.callee {
+                    callers[callee].push((node, i));
+                }
+            }
+            if let Some(i) = data.calls.iter().position(&is_target) {
+                next[node] = Some(i);
+                queue.push_back(node);
+            }
+        }
+        while let Some(node) = queue.pop_front() {
+            for &(caller, i) in &callers[node] {
+                if next[caller].is_none() {
+                    next[caller] = Some(i);
+                    queue.push_back(caller);
+                }
+            }
+        }
+        next
+    }
+
+    /// Returns the calls from `node` following `next` (returned by [`PanicGraph::paths`]).
+    fn path(&self, mut node: usize, next: &[Option<usize>]) -> Vec<PanicCall> {
+        let mut path = Vec::new();
+        while let Some(i) = next[node] {
+            let call = &self.nodes[node].calls[i];
+            let callee = match &call.callee {
+                Callee::Node(callee) => {
+                    node = *callee;
+                    qualified_name(self.tcx, self.nodes[node].instance.def_id())
+                }
+                Callee::Panic(name) | Callee::Unknown(name) => {
+                    path.push(PanicCall {
+                        callee: name.clone(),
+                        span: call.span,
+                    });
+                    break;
+                }
+            };
+            path.push(PanicCall {
+                callee,
+                span: call.span,
+            });
+        }
+        path
+    }
+}
@@ -0,0 +1,264 @@
+//! Helpers to go from a path (like `std::vec::Vec`) to the items it points to and the other way
+//! around.
+
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{DefId, LocalDefId, LOCAL_CRATE};
+use rustc_hir::definitions::DefPathData;
+use rustc_hir::{ItemKind, PrimTy, UseKind};
+use rustc_middle::ty::fast_reject::SimplifiedType;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{self, FloatTy, IntTy, Ty, TyCtxt, UintTy};
+use rustc_span::symbol::{kw, Symbol};
+
+/// Returns the items `path` points to. The first segment is the name of a crate (the local one,
+/// one of its dependencies, or `crate`) or a primitive type, the other ones are the names of the
+/// items inside it. Re-exports are followed, and the items of the inherent impls of types and of
+/// the traits can be used as well (like `["std", "vec", "Vec", "new"]`).
+///
+/// Since a name can be used in different namespaces (a module and a function for example) or by
+/// inherent impls of different types (like `["core", "ptr", "NonNull", "new"]`), all the
+/// matching items are returned. If nothing matches, the returned `Vec` is empty.
+pub fn resolve_path(tcx: TyCtxt<'_>, path: &[&str]) -> Vec<Res> {
+    let Some((base, segments)) = path.split_first() else { return Vec::new() };
+
+    let base = Symbol::intern(base);
+    let mut resolved = Vec::new();
+    if base == kw::Crate {
+        resolved.push(Res::Def(DefKind::Mod, LOCAL_CRATE.as_def_id()));
+    }
+    for &krate in std::iter::once(&LOCAL_CRATE).chain(tcx.crates(())) {
+        if tcx.crate_name(krate) == base {
+            resolved.push(Res::Def(DefKind::Mod, krate.as_def_id()));
+        }
+    }
+    if let Some(prim) = PrimTy::from_name(base) {
+        resolved.push(Res::PrimTy(prim));
+    }
+
+    for segment in segments {
+        let name = Symbol::intern(segment);
+        let mut children = Vec::new();
+        for res in resolved {
+            for child in children_by_name(tcx, res, name, &mut FxHashSet::default()) {
+                if !children.contains(&child) {
+                    children.push(child);
+                }
+            }
+        }
+        resolved = children;
+    }
+    resolved
+}
+
+/// Same as [`resolve_path`] but the path is written like in the code (like `std::vec::Vec`).
+pub fn resolve_path_str(tcx: TyCtxt<'_>, path: &str) -> Vec<Res> {
+    let path = path.strip_prefix("::").unwrap_or(path);
+    resolve_path(tcx, &path.split("::").map(str::trim).collect::<Vec<_>>())
+}
+
+/// Returns the path of `def_id`, starting with the name of the crate it's defined in (so
+/// `alloc::vec::Vec` and not `std::vec::Vec`). Unlike `TyCtxt::def_path_str`, it doesn't depend
+/// on the compiler options or on the items which are in scope.
+///
+/// Impl blocks are named `<impl Type>` or `<impl Trait for Type>`, without the generics. The
+/// items of an inherent impl are in the type path (`my_crate::module::Struct::method`) whereas the
+/// ones of a trait impl are in the impl block
+/// (`my_crate::module::<impl core::clone::Clone for my_crate::module::Struct>::clone`).
+/// Anonymous items are named like `{closure#0}` or `{constant#1}`, the number being their
+/// position among the anonymous items of the same kind in their parent.
+pub fn qualified_name(tcx: TyCtxt<'_>, def_id: DefId) -> String {
+    path_segments(tcx, def_id).join("::")
+}
+
+/// Same as [`qualified_name`] without the crate name.
+pub fn crate_relative_name(tcx: TyCtxt<'_>, def_id: DefId) -> String {
+    path_segments(tcx, def_id)[1..].join("::")
+}
+
+fn path_segments(tcx: TyCtxt<'_>, def_id: DefId) -> Vec<String> {
+    let key = tcx.def_key(def_id);
+    let Some(parent) = key.parent else { return vec![tcx.crate_name(def_id.krate).to_string()] };
+    let parent = DefId {
+        krate: def_id.krate,
+        index: parent,
+    };
+
+    let segment = if key.disambiguated_data.data == DefPathData::Impl {
+        let self_ty = tcx.type_of(def_id);
+        match tcx.trait_id_of_impl(def_id) {
+            Some(trait_id) => format!(
+                "<impl {} for {}>",
+                qualified_name(tcx, trait_id),
+                type_name(tcx, self_ty)
+            ),
+            None => format!("<impl {}>", type_name(tcx, self_ty)),
+        }
+    } else {
+        key.disambiguated_data.to_string()
+    };
+    let mut segments = match inherent_impl_adt(tcx, parent) {
+        // Inherent impls are always in the same crate as their type.
+        Some(adt) => path_segments(tcx, adt),
+        None => path_segments(tcx, parent),
+    };
+    segments.push(segment);
+    segments
+}
+
+/// If `def_id` is an inherent impl of a struct, an enum or an union, returns this type.
+fn inherent_impl_adt(tcx: TyCtxt<'_>, def_id: DefId) -> Option<DefId> {
+    if tcx.def_kind(def_id) != DefKind::Impl || tcx.trait_id_of_impl(def_id).is_some() {
+        return None;
+    }
+    tcx.type_of(def_id).ty_adt_def().map(|adt| adt.did())
+}
+
+fn type_name<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> String {
+    match ty.kind() {
+        ty::Adt(adt, _) => qualified_name(tcx, adt.did()),
+        // The compiler would print the local types inside them without the crate name.
+        ty::Array(elem, len) => format!("[{}; {len}]", type_name(tcx, *elem)),
+        ty::Slice(elem) => format!("[{}]", type_name(tcx, *elem)),
+        ty::Ref(_, inner, mutbl) => format!("&{}{}", mutbl.prefix_str(), type_name(tcx, *inner)),
+        ty::RawPtr(ptr) => format!(
+            "*{} {}",
+            if ptr.mutbl.is_mut() { "mut" } else { "const" },
+            type_name(tcx, ptr.ty)
+        ),
+        ty::Tuple(tys) if tys.len() == 1 => format!("({},)", type_name(tcx, tys[0])),
+        ty::Tuple(tys) => format!(
+            "({})",
+            tys.iter()
+                .map(|ty| type_name(tcx, ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => with_no_trimmed_paths!(ty.to_string()),
+    }
+}
+
+/// `visited_modules` prevents infinite recursion with local glob imports.
+fn children_by_name(
+    tcx: TyCtxt<'_>,
+    res: Res,
+    name: Symbol,
+    visited_modules: &mut FxHashSet<DefId>,
+) -> Vec<Res> {
+    let def_id = match res {
+        Res::PrimTy(prim) => {
+            return primitive_impls(tcx, prim)
+                .iter()
+                .flat_map(|&impl_id| associated_items_by_name(tcx, impl_id, name))
+                .collect();
+        }
+        Res::Def(_, def_id) => def_id,
+        _ => return Vec::new(),
+    };
+    match tcx.def_kind(def_id) {
+        DefKind::Mod => match def_id.as_local() {
+            Some(local_id) => local_module_children(tcx, local_id, name, visited_modules),
+            None => tcx
+                .module_children(def_id)
+                .iter()
+                .filter(|child| child.ident.name == name)
+                .map(|child| child.res.expect_non_local())
+                .collect(),
+        },
+        DefKind::Trait => associated_items_by_name(tcx, def_id, name),
+        kind @ (DefKind::Struct | DefKind::Enum | DefKind::Union | DefKind::ForeignTy) => {
+            let mut children = Vec::new();
+            if kind == DefKind::Enum {
+                children.extend(
+                    tcx.adt_def(def_id)
+                        .variants()
+                        .iter()
+                        .filter(|variant| variant.name == name)
+                        .map(|variant| Res::Def(DefKind::Variant, variant.def_id)),
+                );
+            }
+            for &impl_id in tcx.inherent_impls(def_id) {
+                children.extend(associated_items_by_name(tcx, impl_id, name));
+            }
+            children
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The children of local modules cannot be retrieved with `module_children` so we go through
+/// the HIR items.
+fn local_module_children(
+    tcx: TyCtxt<'_>,
+    module: LocalDefId,
+    name: Symbol,
+    visited_modules: &mut FxHashSet<DefId>,
+) -> Vec<Res> {
+    if !visited_modules.insert(module.to_def_id()) {
+        return Vec::new();
+    }
+    let hir = tcx.hir();
+    let mut children = Vec::new();
+    for &item_id in hir.get_module(module).0.item_ids {
+        let item = hir.item(item_id);
+        match item.kind {
+            ItemKind::Use(path, UseKind::Glob) => {
+                for &res in &path.res {
+                    children.extend(children_by_name(tcx, res, name, visited_modules));
+                }
+            }
+            _ if item.ident.name != name => {}
+            ItemKind::Use(path, UseKind::Single) => {
+                children.extend(path.res.iter().copied().filter(|res| *res != Res::Err));
+            }
+            ItemKind::ExternCrate(_) => {
+                if let Some(krate) = tcx.extern_mod_stmt_cnum(item.owner_id.def_id) {
+                    children.push(Res::Def(DefKind::Mod, krate.as_def_id()));
+                }
+            }
+            _ => {
+                let def_id = item.owner_id.to_def_id();
+                children.push(Res::Def(tcx.def_kind(def_id), def_id));
+            }
+        }
+    }
+    children
+}
+
+fn associated_items_by_name(tcx: TyCtxt<'_>, def_id: DefId, name: Symbol) -> Vec<Res> {
+    tcx.associated_item_def_ids(def_id)
+        .iter()
+        .filter(|&&item_id| tcx.item_name(item_id) == name)
+        .map(|&item_id| Res::Def(tcx.def_kind(item_id), item_id))
+        .collect()
+}
+
+/// Returns the inherent impls of a primitive type (they're in `core`, `alloc` and `std`).
+fn primitive_impls(tcx: TyCtxt<'_>, prim: PrimTy) -> &[DefId] {
+    let ty = match prim {
+        PrimTy::Bool => SimplifiedType::BoolSimplifiedType,
+        PrimTy::Char => SimplifiedType::CharSimplifiedType,
+        PrimTy::Str => SimplifiedType::StrSimplifiedType,
+        PrimTy::Int(int) => SimplifiedType::IntSimplifiedType(match int {
+            rustc_ast::IntTy::Isize => IntTy::Isize,
+            rustc_ast::IntTy::I8 => IntTy::I8,
+            rustc_ast::IntTy::I16 => IntTy::I16,
+            rustc_ast::IntTy::I32 => IntTy::I32,
+            rustc_ast::IntTy::I64 => IntTy::I64,
+            rustc_ast::IntTy::I128 => IntTy::I128,
+        }),
+        PrimTy::Uint(uint) => SimplifiedType::UintSimplifiedType(match uint {
+            rustc_ast::UintTy::Usize => UintTy::Usize,
+            rustc_ast::UintTy::U8 => UintTy::U8,
+            rustc_ast::UintTy::U16 => UintTy::U16,
+            rustc_ast::UintTy::U32 => UintTy::U32,
+            rustc_ast::UintTy::U64 => UintTy::U64,
+            rustc_ast::UintTy::U128 => UintTy::U128,
+        }),
+        PrimTy::Float(float) => SimplifiedType::FloatSimplifiedType(match float {
+            rustc_ast::FloatTy::F32 => FloatTy::F32,
+            rustc_ast::FloatTy::F64 => FloatTy::F64,
+        }),
+    };
+    tcx.incoherent_impls(ty)
+}
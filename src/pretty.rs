@@ -0,0 +1,274 @@
+//! Helpers to print the HIR nodes while debugging, either as Rust code or as a tree of nodes.
+
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{
+    Arm, Block, Expr, ExprKind, ForeignItem, HirId, ImplItem, Item, Local, Node, Param, Pat,
+    PatKind, Stmt, StmtKind, TraitItem, Ty, TyKind,
+};
+use rustc_hir_pretty::{generic_params_to_string, id_to_string, qpath_to_string, ty_to_string};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::symbol::kw;
+use rustc_span::Span;
+
+use std::fmt::Write;
+
+use crate::spans::line_col_range;
+
+/// Returns the HIR node `hir_id` (an item, an expression, a pattern, a type, etc) printed as Rust
+/// code by the compiler, like with `rustc -Zunpretty=hir`. It's the code after the expansion
+/// and the desugaring: the macros are expanded, a `for` loop is a `loop` with a `match`, etc.
+///
+/// The nodes which can't be printed on their own (the constructors of the structs and the variants,
+/// and the crate) are given by their description instead, like `ctor (path: S)`.
+pub fn hir_pretty(tcx: TyCtxt<'_>, hir_id: HirId) -> String {
+    match tcx.hir().get(hir_id) {
+        Node::GenericParam(param) => {
+            let params = generic_params_to_string(std::slice::from_ref(param));
+            // Without the surrounding `<>`.
+            params[1..params.len() - 1].to_owned()
+        }
+        Node::Field(field) => format!("{}: {}", field.ident, ty_to_string(field.ty)),
+        Node::Ctor(_) | Node::Crate(_) => tcx.hir().node_to_string(hir_id),
+        _ => id_to_string(&tcx.hir(), hir_id),
+    }
+}
+
+/// Returns the HIR node `hir_id` and its descendants, one node per line with its kind and its
+/// location, indented by their depth, like:
+///
+/// ```text
+/// Expr(Match) src/main.rs:3:5-6:6
+///   Expr(Path x) src/main.rs:3:11-3:12
+///   Arm src/main.rs:4:9-4:18
+///     Pat(Lit) src/main.rs:4:9-4:10
+///     Expr(Lit) src/main.rs:4:14-4:18
+/// ```
+///
+/// Only the items, the parameters, the statements, the blocks, the match arms, the expressions,
+/// the patterns and the types are shown: the other nodes (like the paths) are summarized in the
+/// line of their parent. The nested items and the bodies of the closures are included. The nodes
+/// deeper than `max_depth` (`0` being `hir_id`) aren't shown.
+///
+/// The nodes generated by a macro are marked with `(expanded)`, and their location is the one of
+/// the macro call.
+pub fn hir_debug_tree(tcx: TyCtxt<'_>, hir_id: HirId, max_depth: usize) -> String {
+    let mut printer = TreePrinter {
+        tcx,
+        max_depth,
+        depth: 0,
+        out: String::new(),
+    };
+    match tcx.hir().get(hir_id) {
+        Node::Item(item) => printer.visit_item(item),
+        Node::ForeignItem(item) => printer.visit_foreign_item(item),
+        Node::TraitItem(item) => printer.visit_trait_item(item),
+        Node::ImplItem(item) => printer.visit_impl_item(item),
+        Node::Param(param) => printer.visit_param(param),
+        Node::Stmt(stmt) => printer.visit_stmt(stmt),
+        Node::Local(local) => printer.visit_local(local),
+        Node::Block(block) => printer.visit_block(block),
+        Node::Arm(arm) => printer.visit_arm(arm),
+        Node::Expr(expr) => printer.visit_expr(expr),
+        Node::Pat(pat) => printer.visit_pat(pat),
+        Node::Ty(ty) => printer.visit_ty(ty),
+        _ => printer.line(
+            tcx.hir().node_to_string(hir_id),
+            tcx.hir().span(hir_id),
+            |_| {},
+        ),
+    }
+    printer.out
+}
+
+struct TreePrinter<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    max_depth: usize,
+    depth: usize,
+    out: String,
+}
+
+impl<'tcx> TreePrinter<'tcx> {
+    /// Writes the line of a node, then its children with `walk` if they aren't too deep.
+    fn line(&mut self, kind: String, span: Span, walk: impl FnOnce(&mut Self)) {
+        let (file_name, lo, hi) = line_col_range(self.tcx, span);
+        // Writing into a `String` can't fail.
+        write!(
+            self.out,
+            "{:indent$}{} {}:{}:{}-{}:{}",
+            "",
+            kind,
+            file_name.prefer_local(),
+            lo.line,
+            lo.col,
+            hi.line,
+            hi.col,
+            indent = self.depth * 2
+        )
+        .unwrap();
+        if span.from_expansion() {
+            self.out.push_str(" (expanded)");
+        }
+        self.out.push('\n');
+        if self.depth < self.max_depth {
+            self.depth += 1;
+            walk(self);
+            self.depth -= 1;
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for TreePrinter<'tcx> {
+    type NestedFilter = nested_filter::All;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_item(&mut self, item: &'tcx Item<'tcx>) {
+        let kind = match item.ident.name {
+            // Like the `use` items of the glob imports.
+            kw::Empty => format!("Item({})", item.kind.descr()),
+            name => format!("Item({} {})", item.kind.descr(), name),
+        };
+        self.line(kind, item.span, |this| intravisit::walk_item(this, item));
+    }
+
+    fn visit_foreign_item(&mut self, item: &'tcx ForeignItem<'tcx>) {
+        let kind = format!("ForeignItem({})", item.ident);
+        self.line(kind, item.span, |this| {
+            intravisit::walk_foreign_item(this, item)
+        });
+    }
+
+    fn visit_trait_item(&mut self, item: &'tcx TraitItem<'tcx>) {
+        let kind = format!("TraitItem({})", item.ident);
+        self.line(kind, item.span, |this| {
+            intravisit::walk_trait_item(this, item)
+        });
+    }
+
+    fn visit_impl_item(&mut self, item: &'tcx ImplItem<'tcx>) {
+        let kind = format!("ImplItem({})", item.ident);
+        self.line(kind, item.span, |this| {
+            intravisit::walk_impl_item(this, item)
+        });
+    }
+
+    fn visit_param(&mut self, param: &'tcx Param<'tcx>) {
+        self.line("Param".to_owned(), param.span, |this| {
+            intravisit::walk_param(this, param)
+        });
+    }
+
+    fn visit_stmt(&mut self, stmt: &'tcx Stmt<'tcx>) {
+        let kind = match stmt.kind {
+            StmtKind::Local(_) => "Local",
+            StmtKind::Item(_) => "Item",
+            StmtKind::Expr(_) => "Expr",
+            StmtKind::Semi(_) => "Semi",
+        };
+        self.line(format!("Stmt({kind})"), stmt.span, |this| {
+            intravisit::walk_stmt(this, stmt)
+        });
+    }
+
+    fn visit_local(&mut self, local: &'tcx Local<'tcx>) {
+        self.line("Local".to_owned(), local.span, |this| {
+            intravisit::walk_local(this, local)
+        });
+    }
+
+    fn visit_block(&mut self, block: &'tcx Block<'tcx>) {
+        self.line("Block".to_owned(), block.span, |this| {
+            intravisit::walk_block(this, block)
+        });
+    }
+
+    fn visit_arm(&mut self, arm: &'tcx Arm<'tcx>) {
+        self.line("Arm".to_owned(), arm.span, |this| {
+            intravisit::walk_arm(this, arm)
+        });
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        let kind = match &expr.kind {
+            ExprKind::Box(_) => "Box".to_owned(),
+            ExprKind::ConstBlock(_) => "ConstBlock".to_owned(),
+            ExprKind::Array(_) => "Array".to_owned(),
+            ExprKind::Call(..) => "Call".to_owned(),
+            ExprKind::MethodCall(segment, ..) => format!("MethodCall {}", segment.ident),
+            ExprKind::Tup(_) => "Tup".to_owned(),
+            ExprKind::Binary(op, ..) => format!("Binary {}", op.node.as_str()),
+            ExprKind::Unary(op, _) => format!("Unary {}", op.as_str()),
+            ExprKind::Lit(_) => "Lit".to_owned(),
+            ExprKind::Cast(..) => "Cast".to_owned(),
+            ExprKind::Type(..) => "Type".to_owned(),
+            ExprKind::DropTemps(_) => "DropTemps".to_owned(),
+            ExprKind::Let(_) => "Let".to_owned(),
+            ExprKind::If(..) => "If".to_owned(),
+            ExprKind::Loop(..) => "Loop".to_owned(),
+            ExprKind::Match(..) => "Match".to_owned(),
+            ExprKind::Closure(_) => "Closure".to_owned(),
+            ExprKind::Block(..) => "Block".to_owned(),
+            ExprKind::Assign(..) => "Assign".to_owned(),
+            ExprKind::AssignOp(op, ..) => format!("AssignOp {}=", op.node.as_str()),
+            ExprKind::Field(_, ident) => format!("Field {ident}"),
+            ExprKind::Index(..) => "Index".to_owned(),
+            ExprKind::Path(qpath) => format!("Path {}", qpath_to_string(qpath)),
+            ExprKind::AddrOf(..) => "AddrOf".to_owned(),
+            ExprKind::Break(..) => "Break".to_owned(),
+            ExprKind::Continue(_) => "Continue".to_owned(),
+            ExprKind::Ret(_) => "Ret".to_owned(),
+            ExprKind::InlineAsm(_) => "InlineAsm".to_owned(),
+            ExprKind::Struct(qpath, ..) => format!("Struct {}", qpath_to_string(qpath)),
+            ExprKind::Repeat(..) => "Repeat".to_owned(),
+            ExprKind::Yield(..) => "Yield".to_owned(),
+            ExprKind::Err => "Err".to_owned(),
+        };
+        self.line(format!("Expr({kind})"), expr.span, |this| {
+            intravisit::walk_expr(this, expr)
+        });
+    }
+
+    fn visit_pat(&mut self, pat: &'tcx Pat<'tcx>) {
+        let kind = match &pat.kind {
+            PatKind::Wild => "Wild".to_owned(),
+            PatKind::Binding(_, _, ident, _) => format!("Binding {ident}"),
+            PatKind::Struct(qpath, ..) => format!("Struct {}", qpath_to_string(qpath)),
+            PatKind::TupleStruct(qpath, ..) => format!("TupleStruct {}", qpath_to_string(qpath)),
+            PatKind::Or(_) => "Or".to_owned(),
+            PatKind::Path(qpath) => format!("Path {}", qpath_to_string(qpath)),
+            PatKind::Tuple(..) => "Tuple".to_owned(),
+            PatKind::Box(_) => "Box".to_owned(),
+            PatKind::Ref(..) => "Ref".to_owned(),
+            PatKind::Lit(_) => "Lit".to_owned(),
+            PatKind::Range(..) => "Range".to_owned(),
+            PatKind::Slice(..) => "Slice".to_owned(),
+        };
+        self.line(format!("Pat({kind})"), pat.span, |this| {
+            intravisit::walk_pat(this, pat)
+        });
+    }
+
+    fn visit_ty(&mut self, ty: &'tcx Ty<'tcx>) {
+        let kind = match &ty.kind {
+            TyKind::Slice(_) => "Slice".to_owned(),
+            TyKind::Array(..) => "Array".to_owned(),
+            TyKind::Ptr(_) => "Ptr".to_owned(),
+            TyKind::Ref(..) => "Ref".to_owned(),
+            TyKind::BareFn(_) => "BareFn".to_owned(),
+            TyKind::Never => "Never".to_owned(),
+            TyKind::Tup(_) => "Tup".to_owned(),
+            TyKind::Path(qpath) => format!("Path {}", qpath_to_string(qpath)),
+            TyKind::OpaqueDef(..) => "OpaqueDef".to_owned(),
+            TyKind::TraitObject(..) => "TraitObject".to_owned(),
+            TyKind::Typeof(_) => "Typeof".to_owned(),
+            TyKind::Infer => "Infer".to_owned(),
+            TyKind::Err => "Err".to_owned(),
+        };
+        self.line(format!("Ty({kind})"), ty.span, |this| {
+            intravisit::walk_ty(this, ty)
+        });
+    }
+}
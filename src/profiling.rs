@@ -0,0 +1,87 @@
+//! Helpers to measure where the time goes when the compiler analyzes a crate.
+//!
+//! [`PassTimings`] gives the duration of each stage of the analysis run before the callback (and
+//! of the callback itself). For a finer view (with each query and each compiler pass), the
+//! compiler can write its self-profile with [`Config::self_profile`], which can be read with the
+//! [`measureme`](https://github.com/rust-lang/measureme) tools (like `summarize`).
+
+use rustc_data_structures::profiling::get_resident_set_size;
+use rustc_middle::ty::TyCtxt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::marker;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Config, Error};
+
+/// How long a stage of the analysis took, collected by [`PassTimings`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassTiming {
+    /// The name of the stage, which is one of:
+    ///
+    /// * `parse`: the crate root and its modules are parsed.
+    /// * `expansion`: the macros are expanded and the names are resolved.
+    /// * `global_ctxt`: the `TyCtxt` is created.
+    /// * `typeck_bodies`: the bodies are type-checked, with [`AnalysisLevel::TypeckBodies`].
+    /// * `analysis`: the whole analysis is run, with [`AnalysisLevel::Full`].
+    /// * `callback`: the callback is called.
+    ///
+    /// [`AnalysisLevel::TypeckBodies`]: crate::AnalysisLevel::TypeckBodies
+    /// [`AnalysisLevel::Full`]: crate::AnalysisLevel::Full
+    pub name: String,
+    pub duration: Duration,
+    /// How much the resident memory of the process changed during the stage, in bytes, or `None`
+    /// if it can't be measured on this platform.
+    pub memory_delta: Option<i64>,
+}
+
+/// Collects the [`PassTiming`] of the stages of the analysis, in the order in which they ran. It
+/// can be cloned so you can keep a handle on it while it's used by the compiler.
+#[derive(Clone, Default)]
+pub struct PassTimings(Arc<Mutex<Vec<PassTiming>>>);
+
+impl PassTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the collected timings and empties the collector.
+    pub fn take(&self) -> Vec<PassTiming> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    /// Calls `f` and records how long it took as the stage `name`.
+    pub(crate) fn time<T>(timings: Option<&Self>, name: &str, f: impl FnOnce() -> T) -> T {
+        let Some(timings) = timings else { return f() };
+        let start_rss = get_resident_set_size();
+        let start = Instant::now();
+        let res = f();
+        let duration = start.elapsed();
+        let memory_delta = start_rss
+            .zip(get_resident_set_size())
+            .map(|(start, end)| end as i64 - start as i64);
+        timings.0.lock().unwrap().push(PassTiming {
+            name: name.to_owned(),
+            duration,
+            memory_delta,
+        });
+        res
+    }
+}
+
+/// Same as [`with_tyctxt`](crate::with_tyctxt) except that the timings of the stages of the
+/// analysis are returned alongside the result.
+pub fn with_tyctxt_profiled<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc_args: &[String],
+    callback: F,
+) -> (Result<T, Error>, Vec<PassTiming>) {
+    let timings = PassTimings::new();
+    let res = Config::new(rustc_args)
+        .pass_timings(timings.clone())
+        .with_tyctxt(callback);
+    (res, timings.take())
+}
@@ -0,0 +1,234 @@
+//! Helpers to find the recursive functions of the local crate, directly or through a cycle of
+//! calls, for example to forbid recursion in code which must run with a bounded stack.
+
+use rustc_data_structures::graph::scc::Sccs;
+use rustc_data_structures::graph::vec_graph::VecGraph;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::call_graph::{call_graph, CallKind, Callee};
+use crate::paths::qualified_name;
+
+/// A cycle of calls, returned by [`recursion_cycles`].
+#[derive(Clone, Debug)]
+pub struct Cycle {
+    /// The [`qualified_name`] of the functions, each one calling the next one and the last one
+    /// calling the first one. There is only one function for a direct recursion.
+    pub functions: Vec<String>,
+    /// The functions, in the same order as [`Cycle::functions`].
+    pub def_ids: Vec<LocalDefId>,
+    /// One call for each function, to the next function of the cycle.
+    pub calls: Vec<CycleCall>,
+    /// `true` if at least one of the calls is [`CycleCall::possible`].
+    pub possible: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CycleCall {
+    /// The first call found from a function to the next one, a call which isn't possible being
+    /// preferred.
+    pub span: Span,
+    pub kind: CallKind,
+    /// `true` if the call may not happen: it's either a call to a trait method on a trait object
+    /// or on a type parameter, which may call any local implementation of the method, or a
+    /// reference to the function (like `iter.map(function)` or a function pointer), which may be
+    /// called through the reference.
+    pub possible: bool,
+}
+
+/// Returns the cycles of calls between the functions (and methods) of the local crate, the calls
+/// made by the closures being made by the function they're defined in. The calls are found with
+/// [`call_graph`], see its limits.
+///
+/// There is a cycle for each function calling itself directly and, for each group of functions
+/// calling each other (a strongly connected component of the call graph), one of the shortest
+/// cycles between them. So when the cycles are broken, other ones may be found among the
+/// remaining calls. The cycles which only exist through possible calls (see
+/// [`CycleCall::possible`]) are reported after the other ones, once for each group of functions
+/// they connect. The calls of closures or function pointers which don't have a local function as
+/// a possible callee are ignored.
+pub fn recursion_cycles(tcx: TyCtxt<'_>) -> Vec<Cycle> {
+    let graph = call_graph(tcx);
+    let is_fn = |def_id| matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn);
+    // The functions are sorted by name so the cycles are always the same.
+    let mut functions = graph
+        .nodes
+        .values()
+        .map(|node| node.def_id)
+        .chain(graph.edges.iter().map(|edge| edge.caller_def_id))
+        .filter(|&def_id| is_fn(def_id))
+        .map(|def_id| (qualified_name(tcx, def_id.to_def_id()), def_id))
+        .collect::<Vec<_>>();
+    functions.sort_by(|(a_name, a), (b_name, b)| {
+        (a_name, a.local_def_index).cmp(&(b_name, b.local_def_index))
+    });
+    functions.dedup_by_key(|(_, def_id)| *def_id);
+    let index = functions
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, def_id))| (def_id, i))
+        .collect::<HashMap<_, _>>();
+
+    // The local implementations of the trait methods, by name of the trait method. A trait
+    // method with a default body implements itself.
+    let mut implementations = HashMap::<String, Vec<usize>>::new();
+    for (i, &(ref name, def_id)) in functions.iter().enumerate() {
+        let trait_item = match tcx.opt_associated_item(def_id.to_def_id()) {
+            Some(item) => item
+                .trait_item_def_id
+                .map(|trait_item| qualified_name(tcx, trait_item)),
+            None => None,
+        };
+        let name = match trait_item {
+            Some(trait_item) => trait_item,
+            None if tcx.trait_of_item(def_id.to_def_id()).is_some() => name.clone(),
+            None => continue,
+        };
+        implementations.entry(name).or_default().push(i);
+    }
+
+    let mut calls = HashMap::<(usize, usize), CycleCall>::new();
+    for edge in &graph.edges {
+        let Some(&caller) = index.get(&edge.caller_def_id) else { continue };
+        let (callees, possible) = match &edge.callee {
+            Callee::Resolved { def_id, .. } => {
+                let callee = def_id.as_local().and_then(|def_id| index.get(&def_id));
+                (
+                    callee.into_iter().copied().collect(),
+                    edge.kind == CallKind::Reference,
+                )
+            }
+            Callee::Dynamic { name: Some(name) } => {
+                (implementations.get(name).cloned().unwrap_or_default(), true)
+            }
+            Callee::Dynamic { name: None } => continue,
+        };
+        for callee in callees {
+            let call = CycleCall {
+                span: edge.span,
+                kind: edge.kind,
+                possible,
+            };
+            calls
+                .entry((caller, callee))
+                .and_modify(|old| {
+                    if old.possible && !possible {
+                        *old = call;
+                    }
+                })
+                .or_insert(call);
+        }
+    }
+    let mut pairs = calls.iter().collect::<Vec<_>>();
+    pairs.sort_by_key(|(&pair, _)| pair);
+    let mut successors = vec![Vec::new(); functions.len()];
+    let mut certain_successors = vec![Vec::new(); functions.len()];
+    for (&(caller, callee), call) in &pairs {
+        successors[caller].push(callee);
+        if !call.possible {
+            certain_successors[caller].push(callee);
+        }
+    }
+
+    let cycle = |nodes: Vec<usize>| {
+        let calls = (0..nodes.len())
+            .map(|i| calls[&(nodes[i], nodes[(i + 1) % nodes.len()])])
+            .collect::<Vec<_>>();
+        Cycle {
+            functions: nodes.iter().map(|&i| functions[i].0.clone()).collect(),
+            def_ids: nodes.iter().map(|&i| functions[i].1).collect(),
+            possible: calls.iter().any(|call| call.possible),
+            calls,
+        }
+    };
+    let mut cycles = Vec::new();
+
+    // The cycles made of certain calls.
+    let certain_sccs = strongly_connected_components(&certain_successors);
+    let mut certain_scc_sizes = vec![0; certain_sccs.num_sccs()];
+    for node in 0..functions.len() {
+        certain_scc_sizes[certain_sccs.scc(node)] += 1;
+    }
+    let mut reported = vec![false; certain_sccs.num_sccs()];
+    for node in 0..functions.len() {
+        if certain_successors[node].contains(&node) {
+            cycles.push(cycle(vec![node]));
+        }
+        let scc = certain_sccs.scc(node);
+        if reported[scc] || certain_scc_sizes[scc] == 1 {
+            continue;
+        }
+        reported[scc] = true;
+        let mut nodes = shortest_path(node, node, &certain_successors).unwrap();
+        nodes.pop();
+        cycles.push(cycle(nodes));
+    }
+
+    // The cycles needing possible calls: a possible call is needed if it goes out of the groups
+    // of functions which are already in a cycle.
+    let in_cycle = |node: usize| {
+        certain_successors[node].contains(&node) || certain_scc_sizes[certain_sccs.scc(node)] > 1
+    };
+    let sccs = strongly_connected_components(&successors);
+    let mut reported = vec![false; sccs.num_sccs()];
+    for (&(caller, callee), call) in pairs {
+        let scc = sccs.scc(caller);
+        if !call.possible
+            || reported[scc]
+            || sccs.scc(callee) != scc
+            || (certain_sccs.scc(caller) == certain_sccs.scc(callee) && in_cycle(caller))
+        {
+            continue;
+        }
+        reported[scc] = true;
+        let mut nodes = vec![caller];
+        if callee != caller {
+            let path = shortest_path(callee, caller, &successors).unwrap();
+            nodes.extend(&path[..path.len() - 1]);
+        }
+        cycles.push(cycle(nodes));
+    }
+    cycles
+}
+
+fn strongly_connected_components(successors: &[Vec<usize>]) -> Sccs<usize, usize> {
+    let edges = successors
+        .iter()
+        .enumerate()
+        .flat_map(|(node, successors)| successors.iter().map(move |&successor| (node, successor)))
+        .collect();
+    Sccs::new(&VecGraph::new(successors.len(), edges))
+}
+
+/// Returns the shortest path from `from` to `to` (both included) with at least one call, without
+/// going through the calls of a function to itself.
+fn shortest_path(from: usize, to: usize, successors: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        for &next in &successors[node] {
+            if next == node {
+                continue;
+            }
+            if next == to {
+                let mut path = vec![to, node];
+                let mut current = node;
+                while current != from {
+                    current = previous[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if next != from && !previous.contains_key(&next) {
+                previous.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
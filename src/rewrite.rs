@@ -0,0 +1,363 @@
+//! Helpers to rewrite the source files from spans, like the ones of the HIR, for example to write
+//! a refactoring tool.
+
+use rustc_data_structures::sync::Lrc;
+use rustc_span::hygiene::ExpnKind;
+use rustc_span::source_map::SourceMap;
+use rustc_span::{BytePos, FileName, Pos, SourceFile, Span};
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter;
+use std::path::PathBuf;
+
+/// The number of unchanged lines around the changes of the diffs.
+const CONTEXT_LINES: usize = 3;
+
+/// Error returned when an edit can't be added to a [`SourceRewriter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RewriteError {
+    /// The span comes from a macro expansion (or was generated by the compiler), so its code
+    /// isn't written in a file. The span of the macro call can be edited instead. The spans of
+    /// the desugarings (like the one of `?`) can be edited.
+    FromExpansion(Span),
+    /// The span spans several files.
+    InvalidSpan(Span),
+    /// The source code of the file isn't available, like for the files of the dependencies.
+    NoSource(FileName),
+    /// The span overlaps the one of an edit added before, `previous`.
+    Overlap { span: Span, previous: Span },
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FromExpansion(span) => {
+                write!(
+                    f,
+                    "{span:?} comes from a macro expansion and can't be edited"
+                )
+            }
+            Self::InvalidSpan(span) => write!(f, "{span:?} spans several files"),
+            Self::NoSource(file_name) => write!(
+                f,
+                "the source code of `{}` isn't available",
+                file_name.prefer_local()
+            ),
+            Self::Overlap { span, previous } => {
+                write!(f, "{span:?} overlaps a previous edit of {previous:?}")
+            }
+        }
+    }
+}
+
+/// A file rewritten by a [`SourceRewriter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RewrittenFile {
+    pub file_name: FileName,
+    /// The path of the file, `None` if the code isn't in a file of the file system (like the
+    /// code read from stdin or given with [`Config::source_code`](crate::Config::source_code)).
+    pub path: Option<PathBuf>,
+    /// The rewritten source code.
+    pub contents: String,
+    /// The changes as a unified diff (like `diff -u` gives) with 3 lines of context, from the
+    /// original source code to `contents`. It's empty if the edits didn't change anything.
+    pub diff: String,
+}
+
+/// Replaces the code between `start` and `end` (byte offsets in the original source of the file)
+/// with `text`. It's an insertion if `start == end`.
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+    span: Span,
+}
+
+impl Edit {
+    fn overlaps(&self, other: &Edit) -> bool {
+        // An insertion only overlaps the code replaced around it.
+        let inside = |pos: usize, edit: &Edit| edit.start < pos && pos < edit.end;
+        match (self.start == self.end, other.start == other.end) {
+            (true, true) => false,
+            (true, false) => inside(self.start, other),
+            (false, true) => inside(other.start, self),
+            (false, false) => self.start < other.end && other.start < self.end,
+        }
+    }
+}
+
+struct FileEdits {
+    file: Lrc<SourceFile>,
+    /// The source code of the file as it is on the disk.
+    source: String,
+    /// In the order in which they were added.
+    edits: Vec<Edit>,
+}
+
+/// Collects edits of the source files given by spans and applies them. The edits are only
+/// applied to the strings returned by [`rewrite`](Self::rewrite), the files aren't written.
+///
+/// The files are kept as they were read by the compiler, so the other changes made to the files
+/// since then are lost. Their line endings (and the byte order mark) are kept as they are.
+pub struct SourceRewriter<'a> {
+    source_map: &'a SourceMap,
+    /// The files with edits, by their position in the source map.
+    files: BTreeMap<BytePos, FileEdits>,
+}
+
+impl<'a> SourceRewriter<'a> {
+    /// `source_map` is the one of the session, given by `tcx.sess.source_map()`.
+    pub fn new(source_map: &'a SourceMap) -> Self {
+        Self {
+            source_map,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Inserts `text` before the code of `span`. The texts inserted at the same place are kept in
+    /// the order in which they're added, and they're inserted before the code which replaces the
+    /// one of a span starting there.
+    pub fn insert_before(&mut self, span: Span, text: &str) -> Result<(), RewriteError> {
+        self.add_edit(span.shrink_to_lo(), text)
+    }
+
+    /// Replaces the code of `span` with `text`.
+    pub fn replace(&mut self, span: Span, text: &str) -> Result<(), RewriteError> {
+        self.add_edit(span, text)
+    }
+
+    /// Removes the code of `span`.
+    pub fn delete(&mut self, span: Span) -> Result<(), RewriteError> {
+        self.add_edit(span, "")
+    }
+
+    /// Returns the edited files with their new source code, in the order in which they were
+    /// loaded by the compiler.
+    pub fn rewrite(&self) -> Vec<RewrittenFile> {
+        self.files
+            .values()
+            .map(|file_edits| {
+                let mut edits = file_edits.edits.iter().collect::<Vec<_>>();
+                // The sort is stable so the insertions stay in the order in which they were added.
+                edits.sort_by_key(|edit| (edit.start, edit.end));
+                let source = &file_edits.source;
+                let name = &file_edits.file.name;
+                RewrittenFile {
+                    file_name: name.clone(),
+                    path: match name {
+                        FileName::Real(name) => name.local_path().map(|path| path.to_path_buf()),
+                        _ => None,
+                    },
+                    contents: apply_edits(source, 0, &edits),
+                    diff: unified_diff(&name.prefer_local().to_string(), source, &edits),
+                }
+            })
+            .collect()
+    }
+
+    fn add_edit(&mut self, span: Span, text: &str) -> Result<(), RewriteError> {
+        if is_generated(span) {
+            return Err(RewriteError::FromExpansion(span));
+        }
+        // The dummy spans can't be told apart from the empty spans at the beginning of the first
+        // file.
+        let file = self.source_map.lookup_source_file(span.lo());
+        if span.hi() > file.end_pos {
+            return Err(RewriteError::InvalidSpan(span));
+        }
+        let file_edits = match self.files.entry(file.start_pos) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let Some(source) = original_source(&file) else {
+                    return Err(RewriteError::NoSource(file.name.clone()));
+                };
+                entry.insert(FileEdits {
+                    file: file.clone(),
+                    source,
+                    edits: Vec::new(),
+                })
+            }
+        };
+
+        let edit = Edit {
+            start: file.original_relative_byte_pos(span.lo()).to_usize(),
+            end: file.original_relative_byte_pos(span.hi()).to_usize(),
+            text: text.to_owned(),
+            span,
+        };
+        if let Some(previous) = file_edits.edits.iter().find(|other| other.overlaps(&edit)) {
+            return Err(RewriteError::Overlap {
+                span,
+                previous: previous.span,
+            });
+        }
+        file_edits.edits.push(edit);
+        Ok(())
+    }
+}
+
+/// Returns `true` if the code of `span` isn't written in the source files. The desugarings keep
+/// the code they come from.
+fn is_generated(mut span: Span) -> bool {
+    while span.from_expansion() {
+        let expn_data = span.ctxt().outer_expn_data();
+        if !matches!(expn_data.kind, ExpnKind::Desugaring(_)) {
+            return true;
+        }
+        span = expn_data.call_site;
+    }
+    false
+}
+
+/// Returns the source code of `file` before the compiler normalized it: the compiler removes the
+/// byte order mark and replaces the `\r\n` line endings with `\n`.
+fn original_source(file: &SourceFile) -> Option<String> {
+    let src = file.src.as_deref()?;
+    let mut source = String::with_capacity(src.len() + file.normalized_pos.len() + 3);
+    let mut last = 0;
+    for normalized_pos in &file.normalized_pos {
+        let pos = (normalized_pos.pos - file.start_pos).to_usize();
+        if pos == 0 {
+            source.push('\u{feff}');
+        } else {
+            // The position is the one after the `\n`.
+            source.push_str(&src[last..pos - 1]);
+            source.push('\r');
+            last = pos - 1;
+        }
+    }
+    source.push_str(&src[last..]);
+    Some(source)
+}
+
+/// Returns `source[offset..]` with the `edits` applied (their offsets are relative to the
+/// beginning of `source`). `edits` must be sorted.
+fn apply_edits(source: &str, offset: usize, edits: &[&Edit]) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut last = offset;
+    for edit in edits {
+        result.push_str(&source[last..edit.start]);
+        result.push_str(&edit.text);
+        last = edit.end;
+    }
+    result.push_str(&source[last..]);
+    result
+}
+
+/// Returns the unified diff of the sorted `edits` of `source`.
+fn unified_diff(file_name: &str, source: &str, edits: &[&Edit]) -> String {
+    let lines = source.split_inclusive('\n').collect::<Vec<_>>();
+    let line_starts = iter::once(0)
+        .chain(lines.iter().scan(0, |end, line| {
+            *end += line.len();
+            Some(*end)
+        }))
+        .collect::<Vec<_>>();
+    // The index of the line containing `offset`, which is `lines.len()` for the end of a source
+    // ending with a line ending (or of an empty source).
+    let line_of = |offset: usize| {
+        let line = line_starts.partition_point(|&start| start <= offset) - 1;
+        if line == lines.len() && !source.ends_with('\n') {
+            line.saturating_sub(1)
+        } else {
+            line
+        }
+    };
+    let line_start = |line: usize| line_starts[line.min(lines.len())];
+
+    // The changed lines: the first one, the one after the last one and their new text.
+    let mut changes = Vec::<(usize, usize, String)>::new();
+    let mut i = 0;
+    while i < edits.len() {
+        let first = line_of(edits[i].start);
+        let mut end = first;
+        let mut j = i;
+        loop {
+            while j < edits.len() && line_of(edits[j].start) <= end {
+                let edit = edits[j];
+                let last = if edit.end > edit.start {
+                    line_of(edit.end - 1)
+                } else {
+                    line_of(edit.start)
+                };
+                end = end.max((last + 1).min(lines.len()));
+                j += 1;
+            }
+            let text = apply_edits(&source[..line_start(end)], line_start(first), &edits[i..j]);
+            // The line ending was removed, so the next line is joined to the changed ones.
+            if !text.is_empty() && !text.ends_with('\n') && end < lines.len() {
+                end += 1;
+                continue;
+            }
+            if text != source[line_start(first)..line_start(end)] {
+                changes.push((first, end, text));
+            }
+            break;
+        }
+        i = j;
+    }
+
+    let mut diff = String::new();
+    if changes.is_empty() {
+        return diff;
+    }
+    diff.push_str(&format!("--- {file_name}\n+++ {file_name}\n"));
+    let push_line = |diff: &mut String, prefix: char, line: &str| {
+        diff.push(prefix);
+        diff.push_str(line);
+        if !line.ends_with('\n') {
+            diff.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+    // The number of lines added by the changes before the current hunk.
+    let mut added_lines = 0isize;
+    let mut changes = changes.into_iter().peekable();
+    while let Some(change) = changes.next() {
+        let mut hunk = vec![change];
+        while let Some(next) =
+            changes.next_if(|next| next.0 <= hunk.last().unwrap().1 + 2 * CONTEXT_LINES)
+        {
+            hunk.push(next);
+        }
+        let old_start = hunk[0].0.saturating_sub(CONTEXT_LINES);
+        let old_end = (hunk.last().unwrap().1 + CONTEXT_LINES).min(lines.len());
+
+        let mut body = String::new();
+        let mut new_count = old_end - old_start;
+        let mut line = old_start;
+        for (first, end, text) in &hunk {
+            for context in &lines[line..*first] {
+                push_line(&mut body, ' ', context);
+            }
+            for removed in &lines[*first..*end] {
+                push_line(&mut body, '-', removed);
+            }
+            let new_lines = text.split_inclusive('\n').collect::<Vec<_>>();
+            for added in &new_lines {
+                push_line(&mut body, '+', added);
+            }
+            new_count = new_count + new_lines.len() - (end - first);
+            line = *end;
+        }
+        for context in &lines[line..old_end] {
+            push_line(&mut body, ' ', context);
+        }
+
+        let old_count = old_end - old_start;
+        let new_start = (old_start as isize + added_lines) as usize;
+        // The ranges without lines start at the line before them.
+        let range_start = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            range_start(old_start, old_count),
+            old_count,
+            range_start(new_start, new_count),
+            new_count
+        ));
+        diff.push_str(&body);
+        added_lines += new_count as isize - old_count as isize;
+    }
+    diff
+}
@@ -0,0 +1,355 @@
+//! Helpers to search the definitions of the local crate by name, like the "workspace symbols" of
+//! an IDE, and the places where a definition is used.
+
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{BodyId, Expr, ExprKind, HirId, ItemKind, Node, Path, QPath};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{DefIdTree, TyCtxt, TypeckResults};
+use rustc_span::Span;
+
+use crate::expansion::{expansion_info, ExpansionOrigin};
+use crate::paths::qualified_name;
+
+/// How [`NamePattern`] compares the names of the definitions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NameMatcher {
+    /// The name is the given string.
+    Exact(String),
+    /// The name contains the given string.
+    Substring(String),
+    /// The name matches the glob, where `*` matches any number of characters and `?` matches a
+    /// single one, like `*_unchecked` or `get_?`.
+    Glob(String),
+}
+
+impl NameMatcher {
+    /// Returns `true` if `name` is matched.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NameMatcher::Exact(exact) => name == exact,
+            NameMatcher::Substring(substring) => name.contains(substring.as_str()),
+            NameMatcher::Glob(glob) => glob_matches(
+                &glob.chars().collect::<Vec<_>>(),
+                &name.chars().collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+/// Which definitions [`find_defs`] returns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamePattern {
+    /// Compared with the name of the definitions, which is the last segment of their path.
+    pub matcher: NameMatcher,
+    /// The kinds of definitions to return, or all of them if it's empty.
+    pub kinds: Vec<DefKind>,
+    /// Only return the definitions declared with `pub`, which may not be reachable from other
+    /// crates (see [`public_api`](crate::api::public_api) for those).
+    pub public_only: bool,
+}
+
+impl NamePattern {
+    /// The definitions named `name`.
+    pub fn exact(name: &str) -> Self {
+        Self::new(NameMatcher::Exact(name.to_owned()))
+    }
+
+    /// The definitions whose name contains `substring`.
+    pub fn substring(substring: &str) -> Self {
+        Self::new(NameMatcher::Substring(substring.to_owned()))
+    }
+
+    /// The definitions whose name matches `glob`, see [`NameMatcher::Glob`].
+    pub fn glob(glob: &str) -> Self {
+        Self::new(NameMatcher::Glob(glob.to_owned()))
+    }
+
+    fn new(matcher: NameMatcher) -> Self {
+        NamePattern {
+            matcher,
+            kinds: Vec::new(),
+            public_only: false,
+        }
+    }
+
+    /// Only returns the definitions of the given kinds, like `&[DefKind::Fn, DefKind::AssocFn]`
+    /// for the functions and the methods.
+    pub fn kinds(mut self, kinds: &[DefKind]) -> Self {
+        self.kinds = kinds.to_vec();
+        self
+    }
+
+    /// See [`NamePattern::public_only`].
+    pub fn public_only(mut self) -> Self {
+        self.public_only = true;
+        self
+    }
+}
+
+/// A definition returned by [`find_defs`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefMatch {
+    pub def_id: DefId,
+    /// See [`qualified_name`].
+    pub qualified_name: String,
+    pub kind: DefKind,
+    /// The span of the definition, like `fn foo()` or `struct Foo`.
+    pub span: Span,
+    /// Whether the definition is written in the source code or generated by a macro (like the
+    /// methods of the traits implemented with `#[derive]`).
+    pub origin: ExpansionOrigin,
+}
+
+/// Returns the definitions of the local crate whose name is matched by `pattern`, sorted by the
+/// position of their span. This includes the items, the associated items, the fields, the
+/// variants, the macros and the items generated by macros, but not the imports, the generic
+/// parameters and the definitions without a name (like the impls and the closures).
+pub fn find_defs(tcx: TyCtxt<'_>, pattern: &NamePattern) -> Vec<DefMatch> {
+    let mut matches = Vec::new();
+    for def_id in tcx.iter_local_def_id() {
+        let Some(kind) = tcx.opt_def_kind(def_id) else { continue };
+        let is_skipped = matches!(
+            kind,
+            DefKind::Use
+                | DefKind::ExternCrate
+                | DefKind::Ctor(..)
+                | DefKind::TyParam
+                | DefKind::ConstParam
+                | DefKind::LifetimeParam
+        );
+        if is_skipped || (!pattern.kinds.is_empty() && !pattern.kinds.contains(&kind)) {
+            continue;
+        }
+        let def_id = def_id.to_def_id();
+        let Some(name) = tcx.opt_item_name(def_id) else { continue };
+        if !pattern.matcher.matches(name.as_str())
+            || (pattern.public_only && !tcx.visibility(def_id).is_public())
+        {
+            continue;
+        }
+        matches.push(DefMatch {
+            def_id,
+            qualified_name: qualified_name(tcx, def_id),
+            kind,
+            span: tcx.def_span(def_id),
+            origin: expansion_info(tcx, def_id),
+        });
+    }
+    matches.sort_by_key(|def_match| def_match.span.lo());
+    matches
+}
+
+/// Matches `name` with `glob`, trying every length for each `*`.
+fn glob_matches(glob: &[char], name: &[char]) -> bool {
+    match glob.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| glob_matches(rest, &name[skipped..])),
+        Some(('?', rest)) => !name.is_empty() && glob_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && glob_matches(rest, &name[1..]),
+    }
+}
+
+/// How a definition is used, see [`Reference::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// A call of a function or a constructor, like `foo()`, `Foo::new()`, `x.method()` or
+    /// `Some(x)`.
+    Call,
+    /// An import, like `use crate::foo;` or `use crate::foo as bar;`.
+    Import,
+    /// A type or a trait, like `Foo` in `let x: Foo` or `Trait` in `impl Trait for Foo`.
+    Type,
+    /// A pattern, like `MAX` in `match x { MAX => {} }` or `Some` in `if let Some(x) = x {}`.
+    Pattern,
+    /// Any other use in an expression, like `foo` in `let f = foo;` or `MAX` in `MAX + 1`.
+    Expression,
+}
+
+/// A use of a definition, returned by [`find_references`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    /// The span of the name of the definition, like `foo` in `crate::foo()` or in
+    /// `use crate::foo as bar;`. The uses of `bar` are references too, with the span of `bar`.
+    pub span: Span,
+    pub kind: ReferenceKind,
+    /// `true` if the reference is generated by a macro (like the ones of a `#[derive]`, whose
+    /// span is the name of the derive macro).
+    pub from_expansion: bool,
+    /// The item (function, impl, struct, etc) containing the reference.
+    pub used_in: DefId,
+}
+
+/// Returned by [`find_references`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct References {
+    /// Sorted by span.
+    pub references: Vec<Reference>,
+    /// `true` if some bodies had type errors, so some of their method calls and type-relative
+    /// paths (like `Foo::new`) may not have been resolved.
+    pub incomplete: bool,
+}
+
+/// Returns the places where `def_id` (local or not) is used in the local crate: the paths in the
+/// items and in the bodies (including the prefixes of the paths, like `a` in `a::foo()`), the
+/// imports and the method calls. The uses of a struct or a variant include the uses of its
+/// constructor, and the uses of the fields aren't included. The references generated by a macro
+/// are included, see [`Reference::from_expansion`].
+///
+/// The method calls and the type-relative paths are resolved with the results of the type
+/// checking, so the bodies are type-checked if they weren't already. You might want to use
+/// [`AnalysisLevel::TypeckBodies`](crate::AnalysisLevel) to do it beforehand. A method called
+/// through a trait (like `x.clone()`) is resolved to the method of the trait, not to the one of
+/// the impl.
+pub fn find_references(tcx: TyCtxt<'_>, def_id: DefId) -> References {
+    let target = without_ctor(tcx, def_id);
+    let hir = tcx.hir();
+    let mut references = Vec::new();
+    let incomplete = for_each_reference(tcx, |def_id, hir_id, span, is_prefix| {
+        if def_id == target {
+            references.push(Reference {
+                span,
+                kind: reference_kind(tcx, hir_id, is_prefix),
+                from_expansion: span.from_expansion() || hir.span(hir_id).from_expansion(),
+                used_in: hir_id.owner.to_def_id(),
+            });
+        }
+    });
+    references.sort_by_key(|reference| reference.span.lo());
+    // The imports of several namespaces (like a unit struct) are visited once for each, and the
+    // prefix of a nested import (like `a` in `use a::{b, c};`) is in each of its `use` items.
+    references.dedup_by(|a, b| (a.span, a.kind) == (b.span, b.kind));
+    References {
+        references,
+        incomplete,
+    }
+}
+
+/// Calls `callback` for each reference of the local crate (see [`find_references`]) with the
+/// definition, the node containing the path, the span of the name and whether it's the prefix of
+/// a path (like `a` in `a::b`). Returns [`References::incomplete`].
+pub(crate) fn for_each_reference(
+    tcx: TyCtxt<'_>,
+    callback: impl FnMut(DefId, HirId, Span, bool),
+) -> bool {
+    let mut finder = ReferenceFinder {
+        tcx,
+        typeck_results: None,
+        callback,
+        incomplete: false,
+    };
+    tcx.hir().visit_all_item_likes_in_crate(&mut finder);
+    finder.incomplete
+}
+
+/// The constructors are referenced as their struct or variant.
+fn without_ctor(tcx: TyCtxt<'_>, def_id: DefId) -> DefId {
+    match tcx.def_kind(def_id) {
+        DefKind::Ctor(..) => tcx.parent(def_id),
+        _ => def_id,
+    }
+}
+
+/// Returns the kind of a reference depending on the node `hir_id` which contains the path.
+fn reference_kind(tcx: TyCtxt<'_>, hir_id: HirId, is_prefix: bool) -> ReferenceKind {
+    let hir = tcx.hir();
+    match hir.get(hir_id) {
+        Node::Expr(_) if is_prefix => ReferenceKind::Expression,
+        Node::Expr(Expr {
+            kind: ExprKind::MethodCall(..),
+            ..
+        }) => ReferenceKind::Call,
+        Node::Expr(expr) => match hir.get_parent(hir_id) {
+            Node::Expr(Expr {
+                kind: ExprKind::Call(callee, _),
+                ..
+            }) if callee.hir_id == expr.hir_id => ReferenceKind::Call,
+            _ => ReferenceKind::Expression,
+        },
+        Node::Pat(_) => ReferenceKind::Pattern,
+        Node::Ty(_) | Node::TraitRef(_) => ReferenceKind::Type,
+        Node::Item(item) if matches!(item.kind, ItemKind::Use(..)) => ReferenceKind::Import,
+        _ => ReferenceKind::Expression,
+    }
+}
+
+struct ReferenceFinder<'tcx, F> {
+    tcx: TyCtxt<'tcx>,
+    /// The typeck results of the body being visited.
+    typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    callback: F,
+    /// See [`References::incomplete`].
+    incomplete: bool,
+}
+
+impl<'tcx, F: FnMut(DefId, HirId, Span, bool)> ReferenceFinder<'tcx, F> {
+    /// Calls the callback if `res` is a definition. `is_prefix` is `true` for the segments before
+    /// the last one.
+    fn check(&mut self, res: Res, hir_id: HirId, span: Span, is_prefix: bool) {
+        if let Res::Def(_, def_id) = res {
+            (self.callback)(without_ctor(self.tcx, def_id), hir_id, span, is_prefix);
+        }
+    }
+}
+
+impl<'tcx, F: FnMut(DefId, HirId, Span, bool)> Visitor<'tcx> for ReferenceFinder<'tcx, F> {
+    // `visit_all_item_likes_in_crate` already goes through all the items.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let typeck_results = self.tcx.typeck_body(body_id);
+        self.incomplete |= typeck_results.tainted_by_errors.is_some();
+        let old_typeck_results = self.typeck_results.replace(typeck_results);
+        self.visit_body(self.tcx.hir().body(body_id));
+        self.typeck_results = old_typeck_results;
+    }
+
+    fn visit_qpath(&mut self, qpath: &'tcx QPath<'tcx>, id: HirId, _span: Span) {
+        // The resolved paths are checked by `visit_path`, and the type-relative ones (like
+        // `Type::method`) can only be resolved in the bodies.
+        if let (QPath::TypeRelative(_, segment), Some(typeck_results)) =
+            (qpath, self.typeck_results)
+        {
+            self.check(
+                typeck_results.qpath_res(qpath, id),
+                id,
+                segment.ident.span,
+                false,
+            );
+        }
+        intravisit::walk_qpath(self, qpath, id);
+    }
+
+    fn visit_path(&mut self, path: &Path<'tcx>, id: HirId) {
+        if let Some((last, prefix)) = path.segments.split_last() {
+            for segment in prefix {
+                self.check(segment.res, id, segment.ident.span, true);
+            }
+            // The resolution of the last segment of the imports is `Res::Err`.
+            self.check(path.res, id, last.ident.span, false);
+        }
+        intravisit::walk_path(self, path);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        if let ExprKind::MethodCall(segment, ..) = expr.kind {
+            let def_id = self
+                .typeck_results
+                .and_then(|typeck_results| typeck_results.type_dependent_def_id(expr.hir_id));
+            if let Some(def_id) = def_id {
+                self.check(
+                    Res::Def(self.tcx.def_kind(def_id), def_id),
+                    expr.hir_id,
+                    segment.ident.span,
+                    false,
+                );
+            }
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,324 @@
+//! Helpers to find the changes between two versions of the public API of a crate and whether
+//! they require a new major, minor or patch version.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::api::{public_api, ApiItem, ApiSummary};
+use crate::{with_tyctxt, Error};
+
+/// The version component which must be incremented for a change. The levels are ordered so the
+/// maximum of the changes is the one to use for the new version.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SemverLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+/// A change returned by [`semver_diff`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiChange {
+    /// The path of the item, see [`ApiItem::path`].
+    pub path: String,
+    pub kind: ApiChangeKind,
+    pub level: SemverLevel,
+    /// What changed, like `signature changed from "fn(u32)" to "fn(u64)"`.
+    pub description: String,
+    /// The location of the item in the new version, or in the old one if it was removed.
+    pub location: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Returns the changes between the `old` and the `new` versions of the public API of a crate,
+/// sorted by path. The items are matched by path and kind, so an item whose kind changed (like a
+/// struct becoming an enum) is removed and added.
+///
+/// * Removing an item, changing its signature, generics or `#[non_exhaustive]` status, adding
+///   where clauses or adding a variant to an enum without `#[non_exhaustive]` are major changes.
+///   So are adding an associated item without a default to a trait (the impls of other crates
+///   don't define it) and adding a field to a struct whose fields are all public or to a variant,
+///   without `#[non_exhaustive]` (the struct expressions of other crates don't set it).
+/// * Adding an item or removing where clauses are minor changes.
+/// * Changing the deprecation or the `#[doc(hidden)]` status of an item is a patch change.
+///
+/// The generic parameters (of the item and of its parent) are compared by position, so renaming
+/// one isn't a change whereas reordering them is one. The same goes for the lifetimes of the
+/// signatures which aren't generic parameters (the ones in `for<'a>`). The other changes of the
+/// items (like their location) are not reported.
+pub fn semver_diff(old: &ApiSummary, new: &ApiSummary) -> Vec<ApiChange> {
+    let old_items = items_by_key(old);
+    let new_items = items_by_key(new);
+    let old_parents = parents_by_path(old);
+    let new_parents = parents_by_path(new);
+    let mut changes = Vec::new();
+
+    for (key, old_item) in &old_items {
+        if !new_items.contains_key(key) {
+            changes.push(ApiChange {
+                path: old_item.path.clone(),
+                kind: ApiChangeKind::Removed,
+                level: SemverLevel::Major,
+                description: format!("{} removed", old_item.kind),
+                location: old_item.location.clone(),
+            });
+        }
+    }
+    for (key, new_item) in &new_items {
+        let mut add = |kind, level, description| {
+            changes.push(ApiChange {
+                path: new_item.path.clone(),
+                kind,
+                level,
+                description,
+                location: new_item.location.clone(),
+            })
+        };
+        let Some(old_item) = old_items.get(key) else {
+            let level = if is_breaking_addition(&old_parents, new_item) {
+                SemverLevel::Major
+            } else {
+                SemverLevel::Minor
+            };
+            let description = if level == SemverLevel::Major && new_item.kind != "variant" {
+                match new_item.kind.as_str() {
+                    "field" => "field added to a type built with struct expressions".to_owned(),
+                    kind => format!("{kind} without a default added to a trait"),
+                }
+            } else {
+                format!("{} added", new_item.kind)
+            };
+            add(ApiChangeKind::Added, level, description);
+            continue;
+        };
+
+        let old_generics = generic_names(&old_parents, old_item);
+        let new_generics = generic_names(&new_parents, new_item);
+        let old_signature = normalize(&old_item.signature, &old_generics);
+        let new_signature = normalize(&new_item.signature, &new_generics);
+        if old_signature != new_signature {
+            let description = if old_item.signature == new_item.signature {
+                "signature changed because the generic parameters were reordered".to_owned()
+            } else {
+                format!(
+                    "signature changed from {:?} to {:?}",
+                    old_item.signature, new_item.signature
+                )
+            };
+            add(ApiChangeKind::Changed, SemverLevel::Major, description);
+        }
+        let normalize_all = |texts: &[String], generics: &[&str]| {
+            texts
+                .iter()
+                .map(|text| normalize(text, generics))
+                .collect::<Vec<_>>()
+        };
+        if normalize_all(&old_item.generics, &old_generics)
+            != normalize_all(&new_item.generics, &new_generics)
+        {
+            add(
+                ApiChangeKind::Changed,
+                SemverLevel::Major,
+                format!(
+                    "generic parameters changed from {:?} to {:?}",
+                    old_item.generics, new_item.generics
+                ),
+            );
+        }
+        let old_clauses = normalize_all(&old_item.where_clauses, &old_generics);
+        let new_clauses = normalize_all(&new_item.where_clauses, &new_generics);
+        for (clause, normalized) in new_item.where_clauses.iter().zip(&new_clauses) {
+            if !old_clauses.contains(normalized) {
+                add(
+                    ApiChangeKind::Changed,
+                    SemverLevel::Major,
+                    format!("where clause `{clause}` added"),
+                );
+            }
+        }
+        for (clause, normalized) in old_item.where_clauses.iter().zip(&old_clauses) {
+            if !new_clauses.contains(normalized) {
+                add(
+                    ApiChangeKind::Changed,
+                    SemverLevel::Minor,
+                    format!("where clause `{clause}` removed"),
+                );
+            }
+        }
+        if old_item.non_exhaustive != new_item.non_exhaustive {
+            let (level, description) = if new_item.non_exhaustive {
+                (SemverLevel::Major, "`#[non_exhaustive]` added")
+            } else {
+                (SemverLevel::Minor, "`#[non_exhaustive]` removed")
+            };
+            add(ApiChangeKind::Changed, level, description.to_owned());
+        }
+        if !old_item.private_fields
+            && new_item.private_fields
+            && new_item.kind == "struct"
+            && !old_item.non_exhaustive
+        {
+            add(
+                ApiChangeKind::Changed,
+                SemverLevel::Major,
+                "private field added to a struct built with struct expressions".to_owned(),
+            );
+        }
+        if old_item.deprecation != new_item.deprecation {
+            let description = match &new_item.deprecation {
+                Some(_) if old_item.deprecation.is_none() => "deprecated",
+                Some(_) => "deprecation changed",
+                None => "not deprecated anymore",
+            };
+            add(
+                ApiChangeKind::Changed,
+                SemverLevel::Patch,
+                description.to_owned(),
+            );
+        }
+        if old_item.doc_hidden != new_item.doc_hidden {
+            let description = if new_item.doc_hidden {
+                "`#[doc(hidden)]` added"
+            } else {
+                "`#[doc(hidden)]` removed"
+            };
+            add(
+                ApiChangeKind::Changed,
+                SemverLevel::Patch,
+                description.to_owned(),
+            );
+        }
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Same as [`semver_diff`] but the public APIs are computed by running the compiler with
+/// `old_rustc_args` and `new_rustc_args` (see [`with_tyctxt`]).
+pub fn semver_diff_from_args(
+    old_rustc_args: &[String],
+    new_rustc_args: &[String],
+) -> Result<Vec<ApiChange>, Error> {
+    let old = with_tyctxt(old_rustc_args, public_api)?;
+    let new = with_tyctxt(new_rustc_args, public_api)?;
+    Ok(semver_diff(&old, &new))
+}
+
+/// The crate name is removed from the paths so renaming the crate isn't a change.
+fn items_by_key(summary: &ApiSummary) -> BTreeMap<(&str, &str), &ApiItem> {
+    summary
+        .items
+        .iter()
+        .map(|item| ((relative_path(&item.path), item.kind.as_str()), item))
+        .collect()
+}
+
+/// Returns the path without the crate name.
+fn relative_path(path: &str) -> &str {
+    path.split_once("::")
+        .map_or("", |(_, relative_path)| relative_path)
+}
+
+/// Returns the items which can contain other items with generic parameters or variants (so not
+/// the modules), by path without the crate name.
+fn parents_by_path(summary: &ApiSummary) -> BTreeMap<&str, &ApiItem> {
+    summary
+        .items
+        .iter()
+        .filter(|item| item.kind != "module")
+        .map(|item| (relative_path(&item.path), item))
+        .collect()
+}
+
+/// Returns the item containing `item` (like the enum of a variant).
+fn parent<'a>(parents: &BTreeMap<&str, &'a ApiItem>, item: &ApiItem) -> Option<&'a ApiItem> {
+    let (parent_path, _) = relative_path(&item.path).rsplit_once("::")?;
+    parents.get(parent_path).copied()
+}
+
+/// Returns `true` if adding `item` breaks the code of other crates using its parent, which was
+/// already in the old version (`old_parents`).
+fn is_breaking_addition(old_parents: &BTreeMap<&str, &ApiItem>, item: &ApiItem) -> bool {
+    let Some(parent) = parent(old_parents, item) else {
+        return false;
+    };
+    match (parent.kind.as_str(), item.kind.as_str()) {
+        // It breaks the exhaustive matches of the enum.
+        ("enum", "variant") => !parent.non_exhaustive,
+        // They break the struct expressions (and the patterns without `..` of the variants).
+        ("struct", "field") => !parent.non_exhaustive && !parent.private_fields,
+        ("variant", "field") => !parent.non_exhaustive,
+        // The impls of the trait don't define it.
+        ("trait", _) => !item.has_default,
+        _ => false,
+    }
+}
+
+/// Returns the names of the generic parameters of the parent of `item` followed by its own.
+fn generic_names<'a>(parents: &BTreeMap<&str, &'a ApiItem>, item: &'a ApiItem) -> Vec<&'a str> {
+    parent(parents, item)
+        .into_iter()
+        .flat_map(|parent| &parent.generics)
+        .chain(&item.generics)
+        .map(|param| {
+            // The constant parameters are like `const N: usize`.
+            param
+                .strip_prefix("const ")
+                .and_then(|param| param.split(':').next())
+                .unwrap_or(param)
+        })
+        .collect()
+}
+
+/// Replaces the generic parameters of `text` by their position in `generics` and the other
+/// lifetimes (except `'static` and `'_`) by their order of appearance.
+fn normalize(text: &str, generics: &[&str]) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut other_lifetimes = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let is_lifetime = c == '\'';
+        if !is_lifetime && !is_ident_char(c) {
+            normalized.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let start = c.len_utf8();
+        let len = rest[start..]
+            .find(|c| !is_ident_char(c))
+            .map_or(rest.len(), |len| len + start);
+        let (token, after) = rest.split_at(len);
+        rest = after;
+        if let Some(position) = generics.iter().position(|param| *param == token) {
+            normalized.push_str(&format!("#{position}"));
+        } else if is_lifetime && token != "'static" && token != "'_" {
+            let position = match other_lifetimes.iter().position(|name| *name == token) {
+                Some(position) => position,
+                None => {
+                    other_lifetimes.push(token);
+                    other_lifetimes.len() - 1
+                }
+            };
+            normalized.push_str(&format!("'#{position}"));
+        } else {
+            normalized.push_str(token);
+        }
+    }
+    normalized
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
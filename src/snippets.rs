@@ -0,0 +1,252 @@
+//! Helpers to analyze a fragment of code (an expression or statements) instead of a whole crate,
+//! like a playground telling the type of an expression.
+//!
+//! The fragment is wrapped into a function, `fn __snippet() { let _ = <fragment>; }`, and the
+//! spans are mapped back to the fragment with [`Snippet::fragment_range`] and
+//! [`Snippet::fragment_line_col`].
+
+use rustc_hir as hir;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::ty::{Ty, TyCtxt, TypeckResults};
+use rustc_session::config::CrateType;
+use rustc_span::{BytePos, FileName, Span};
+
+use std::marker;
+use std::ops::Range;
+
+use crate::diagnostics::{Diagnostic, DiagnosticSpan, DiagnosticsCollector};
+use crate::spans::LineCol;
+use crate::{AnalysisLevel, Config, Error};
+
+/// The name of the function the fragment is wrapped into.
+const SNIPPET_FN: &str = "__snippet";
+
+/// The file name of the [`DiagnosticSpan`]s returned by [`with_tyctxt_snippet`].
+const SNIPPET_FILE_NAME: &str = "<snippet>";
+
+/// What a fragment given to [`with_tyctxt_snippet`] contains.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SnippetKind {
+    /// An expression, like `vec![1, 2, 3].iter().sum::<i32>()`.
+    #[default]
+    Expression,
+    /// Statements, optionally followed by an expression like in a block: `let a = 1; a + 1`. It's
+    /// wrapped into a block, so [`Snippet::expr`] is this block.
+    Statements,
+}
+
+/// Options of [`with_tyctxt_snippet`].
+#[derive(Clone, Copy, Debug)]
+pub struct SnippetOptions {
+    pub kind: SnippetKind,
+    /// [`AnalysisLevel::TypeckBodies`] by default, so the type errors of the fragment are
+    /// reported.
+    pub analysis_level: AnalysisLevel,
+    /// Call the callback even if the fragment contains errors, see
+    /// [`Config::allow_errors`].
+    pub allow_errors: bool,
+}
+
+impl Default for SnippetOptions {
+    fn default() -> Self {
+        Self {
+            kind: SnippetKind::default(),
+            analysis_level: AnalysisLevel::TypeckBodies,
+            allow_errors: false,
+        }
+    }
+}
+
+/// The fragment given to [`with_tyctxt_snippet`], once it's compiled.
+pub struct Snippet<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    def_id: LocalDefId,
+    expr: &'tcx hir::Expr<'tcx>,
+    map: FragmentMap,
+    /// The position of the fragment in the source map.
+    start: BytePos,
+}
+
+impl<'tcx> Snippet<'tcx> {
+    pub fn tcx(&self) -> TyCtxt<'tcx> {
+        self.tcx
+    }
+
+    /// The function the fragment is wrapped into.
+    pub fn def_id(&self) -> LocalDefId {
+        self.def_id
+    }
+
+    /// The body of the function the fragment is wrapped into.
+    pub fn body(&self) -> &'tcx hir::Body<'tcx> {
+        self.tcx
+            .hir()
+            .body(self.tcx.hir().body_owned_by(self.def_id))
+    }
+
+    /// The expression of the fragment, or the block containing the statements with
+    /// [`SnippetKind::Statements`].
+    pub fn expr(&self) -> &'tcx hir::Expr<'tcx> {
+        self.expr
+    }
+
+    /// The type-checking results of the [`body`](Self::body), which can be used for all the
+    /// expressions of the fragment.
+    pub fn typeck_results(&self) -> &'tcx TypeckResults<'tcx> {
+        self.tcx.typeck(self.def_id)
+    }
+
+    /// The type of the [`expr`](Self::expr).
+    pub fn expr_ty(&self) -> Ty<'tcx> {
+        self.typeck_results().expr_ty(self.expr)
+    }
+
+    /// Returns the byte range of `span` in the fragment, or `None` if it isn't in the fragment.
+    /// The spans coming from a macro expansion (like the ones of `vec!`) give the range of the
+    /// macro call.
+    pub fn fragment_range(&self, span: Span) -> Option<Range<usize>> {
+        let span = span.source_callsite();
+        let start = span.lo().0.checked_sub(self.start.0)? as usize;
+        let end = span.hi().0.checked_sub(self.start.0)? as usize;
+        (end <= self.map.fragment.len()).then_some(start..end)
+    }
+
+    /// Returns where `span` starts and ends in the fragment, see
+    /// [`fragment_range`](Self::fragment_range).
+    pub fn fragment_line_col(&self, span: Span) -> Option<(LineCol, LineCol)> {
+        let range = self.fragment_range(span)?;
+        Some((self.map.line_col(range.start), self.map.line_col(range.end)))
+    }
+}
+
+/// Compiles `fragment` wrapped into a function and calls `callback` with it. `rustc_args` must not
+/// contain a file operand, like with [`with_tyctxt_from_str`](crate::with_tyctxt_from_str).
+///
+/// The diagnostics are returned instead of being written to stderr. The spans are mapped to the
+/// fragment (their file name becomes `<snippet>`) and the ones in the wrapper (like the end of the
+/// function when the fragment is incomplete) are moved to the nearest end of the fragment. The
+/// `rendered` diagnostics still show the wrapper.
+///
+/// If the fragment isn't an expression (or statements), for example if it closes the function, the
+/// callback isn't called and [`Error::InvalidInput`] is returned.
+pub fn with_tyctxt_snippet<T: marker::Send, F: FnOnce(&Snippet<'_>) -> T + marker::Send>(
+    fragment: &str,
+    rustc_args: &[String],
+    options: SnippetOptions,
+    callback: F,
+) -> (Result<T, Error>, Vec<Diagnostic>) {
+    let (prefix, suffix) = match options.kind {
+        SnippetKind::Expression => ("let _ = ", "\n;"),
+        SnippetKind::Statements => ("let _ = {\n", "\n};"),
+    };
+    let prefix = format!("#[allow(dead_code)]\nfn {SNIPPET_FN}() {{\n{prefix}");
+    let source = format!("{prefix}{fragment}{suffix}\n}}\n");
+    let map = FragmentMap {
+        fragment: fragment.to_owned(),
+        offset: prefix.len(),
+        file_name: FileName::anon_source_code(&source)
+            .prefer_local()
+            .to_string(),
+    };
+
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(rustc_args)
+        .source_code(&source)
+        .crate_type(CrateType::Rlib)
+        .analysis_level(options.analysis_level)
+        .allow_errors(options.allow_errors)
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|tcx| {
+            let snippet = find_snippet(tcx, map.clone())?;
+            Some(callback(&snippet))
+        })
+        .and_then(|value| {
+            value.ok_or_else(|| {
+                Error::InvalidInput("the fragment isn't an expression or statements".to_owned())
+            })
+        });
+
+    let mut diagnostics = collector.take();
+    for diagnostic in &mut diagnostics {
+        let spans = diagnostic
+            .spans
+            .iter_mut()
+            .chain(
+                diagnostic
+                    .children
+                    .iter_mut()
+                    .flat_map(|child| &mut child.spans),
+            )
+            .chain(
+                diagnostic
+                    .suggestions
+                    .iter_mut()
+                    .flat_map(|suggestion| &mut suggestion.parts)
+                    .map(|part| &mut part.span),
+            );
+        for span in spans {
+            map.map_span(span);
+        }
+    }
+    (res, diagnostics)
+}
+
+/// Returns the wrapper function and the expression of the fragment, if the fragment didn't
+/// change the structure of the wrapper.
+fn find_snippet(tcx: TyCtxt<'_>, map: FragmentMap) -> Option<Snippet<'_>> {
+    let hir = tcx.hir();
+    let item = hir
+        .items()
+        .map(|item_id| hir.item(item_id))
+        .find(|item| item.ident.as_str() == SNIPPET_FN)?;
+    let hir::ItemKind::Fn(_, _, body_id) = item.kind else { return None };
+    let hir::ExprKind::Block(block, _) = hir.body(body_id).value.kind else { return None };
+    let ([stmt], None) = (block.stmts, block.expr) else { return None };
+    let hir::StmtKind::Local(local) = stmt.kind else { return None };
+    let file = tcx.sess.source_map().lookup_source_file(item.span.lo());
+    Some(Snippet {
+        tcx,
+        def_id: item.owner_id.def_id,
+        expr: local.init?,
+        start: file.start_pos + BytePos(map.offset as u32),
+        map,
+    })
+}
+
+/// Maps the positions in the wrapper to the ones in the fragment.
+#[derive(Clone)]
+struct FragmentMap {
+    fragment: String,
+    /// The byte offset of the fragment in the wrapper.
+    offset: usize,
+    /// The name of the wrapper in the diagnostics.
+    file_name: String,
+}
+
+impl FragmentMap {
+    /// Returns the line and the column of the byte `offset` of the fragment.
+    fn line_col(&self, offset: usize) -> LineCol {
+        let before = &self.fragment[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        LineCol {
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    fn map_span(&self, span: &mut DiagnosticSpan) {
+        if span.file_name != self.file_name {
+            return;
+        }
+        let clamp = |byte: usize| byte.saturating_sub(self.offset).min(self.fragment.len());
+        span.file_name = SNIPPET_FILE_NAME.to_owned();
+        span.byte_start = clamp(span.byte_start);
+        span.byte_end = clamp(span.byte_end);
+        let start = self.line_col(span.byte_start);
+        let end = self.line_col(span.byte_end);
+        span.line_start = start.line;
+        span.column_start = start.col;
+        span.line_end = end.line;
+        span.column_end = end.col;
+    }
+}
@@ -0,0 +1,126 @@
+//! Helpers to list the files the analyzed crate is made of, for example to analyze it again when
+//! one of them changes, or to build a cache key.
+
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::LOCAL_CRATE;
+use rustc_span::{FileName, SourceFile, SourceFileHashAlgorithm};
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::file_loader::{include_literal, included_file};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Options of [`source_files_with_options`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SourceFilesOptions {
+    /// Include the files of the other crates whose spans are used (like the ones of the
+    /// standard library when a macro of it is expanded), with [`SourceFileOrigin::Extern`].
+    pub include_extern: bool,
+}
+
+/// Where the content of a [`SourceFileInfo`] comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SourceFileOrigin {
+    /// The file was read from the file system.
+    Disk,
+    /// The content isn't the one of the file on the file system: it was given by the file loader
+    /// of the [`Config`](crate::Config), like with
+    /// [`Config::file_overlay`](crate::Config::file_overlay), or the file changed since it was
+    /// read. The file may not exist.
+    Overlay,
+    /// The source code doesn't come from a file, like the one given to
+    /// [`Config::source_code`](crate::Config::source_code), stdin or the code generated by
+    /// [`Config::env_overlay`](crate::Config::env_overlay).
+    Anonymous,
+    /// The file belongs to another crate, see [`SourceFilesOptions::include_extern`].
+    Extern,
+}
+
+/// A file returned by [`source_files`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceFileInfo {
+    /// The name of the file in the diagnostics, like `src/lib.rs` or `<anon>`.
+    pub name: String,
+    /// The path of the file, `None` if it's [`SourceFileOrigin::Anonymous`]. It's relative if the
+    /// compiler was given a relative path.
+    pub path: Option<PathBuf>,
+    pub origin: SourceFileOrigin,
+    /// The number of lines.
+    pub lines: usize,
+    /// The hash of the content computed by the compiler, prefixed by the algorithm (chosen with
+    /// `-Z src-hash-algorithm`), like `md5:d41d8cd98f00b204e9800998ecf8427e`.
+    pub hash: String,
+}
+
+/// Returns the files of the local crate: the crate root, the modules and the files included with
+/// `include!`, `include_str!` or `include_bytes!`, in the order in which they were loaded. The
+/// code which doesn't come from a file is included too, see [`SourceFileOrigin::Anonymous`].
+pub fn source_files(tcx: TyCtxt<'_>) -> Vec<SourceFileInfo> {
+    source_files_with_options(tcx, SourceFilesOptions::default())
+}
+
+/// Same as [`source_files`] with options.
+pub fn source_files_with_options(
+    tcx: TyCtxt<'_>,
+    options: SourceFilesOptions,
+) -> Vec<SourceFileInfo> {
+    tcx.sess
+        .source_map()
+        .files()
+        .iter()
+        .filter(|file| options.include_extern || file.cnum == LOCAL_CRATE)
+        .map(|file| source_file_info(file))
+        .collect()
+}
+
+fn source_file_info(file: &SourceFile) -> SourceFileInfo {
+    let mut name = file.name.prefer_local().to_string();
+    let mut path = match &file.name {
+        FileName::Real(name) => Some(name.local_path_if_available().to_owned()),
+        _ => None,
+    };
+    // The content of the files included with `include_str!` and `include_bytes!` may be turned
+    // into a literal, see `Config::file_loader`.
+    let included = path.as_deref().and_then(included_file);
+    if let Some((included_path, _)) = &included {
+        name = included_path.display().to_string();
+        path = Some(included_path.clone());
+    }
+    let origin = match &path {
+        _ if file.cnum != LOCAL_CRATE => SourceFileOrigin::Extern,
+        Some(path) => {
+            let content = fs::read(path).ok().map(|content| match included {
+                Some((_, bytes)) => include_literal(&content, bytes),
+                None => String::from_utf8_lossy(&content).into_owned(),
+            });
+            match content {
+                Some(content) if file.src_hash.matches(&content) => SourceFileOrigin::Disk,
+                _ => SourceFileOrigin::Overlay,
+            }
+        }
+        None => SourceFileOrigin::Anonymous,
+    };
+    let algorithm = match file.src_hash.kind {
+        SourceFileHashAlgorithm::Md5 => "md5",
+        SourceFileHashAlgorithm::Sha1 => "sha1",
+        SourceFileHashAlgorithm::Sha256 => "sha256",
+    };
+    let hash = file
+        .src_hash
+        .hash_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    SourceFileInfo {
+        name,
+        path,
+        origin,
+        lines: file.count_lines(),
+        hash: format!("{algorithm}:{hash}"),
+    }
+}
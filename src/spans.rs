@@ -0,0 +1,188 @@
+//! Helpers to get the source code and the location of a `Span`.
+//!
+//! If a span comes from a macro expansion, the span of the macro call in the user code is used
+//! instead.
+
+use rustc_data_structures::sync::Lrc;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::source_map::SourceMap;
+use rustc_span::{BytePos, FileName, Pos, SourceFile, Span};
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A position in a source file. Both the line and the column start at 1 and the column is
+/// counted in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// A position as defined by the [Language Server Protocol](https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#position):
+/// both the line and the character start at 0 and the character is counted in UTF-16 code units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// Lines of code returned by [`snippet_with_context`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnippetWithContext {
+    pub file_name: FileName,
+    /// Line number (starting at 1) of the first item of `lines`.
+    pub start_line: usize,
+    /// The lines covered by the span and the context lines around them, without the line
+    /// endings.
+    pub lines: Vec<String>,
+}
+
+/// Returns the span of the code written by the user which `span` comes from.
+fn source_span(span: Span) -> Span {
+    span.source_callsite()
+}
+
+/// Returns the source code behind `span`.
+pub fn snippet(tcx: TyCtxt<'_>, span: Span) -> Option<String> {
+    tcx.sess
+        .source_map()
+        .span_to_snippet(source_span(span))
+        .ok()
+}
+
+/// Returns the whole lines covered by `span` with `context_lines` lines before and after them
+/// (less if the file starts or ends before).
+pub fn snippet_with_context(
+    tcx: TyCtxt<'_>,
+    span: Span,
+    context_lines: usize,
+) -> Option<SnippetWithContext> {
+    let file_lines = tcx
+        .sess
+        .source_map()
+        .span_to_lines(source_span(span))
+        .ok()?;
+    let file = file_lines.file;
+    let first = file_lines.lines.first()?.line_index;
+    let last = file_lines.lines.last()?.line_index;
+
+    let start = first.saturating_sub(context_lines);
+    let end = (last + context_lines).min(file.count_lines().saturating_sub(1));
+    let lines = (start..=end)
+        .map(|index| file.get_line(index).map(|line| line.into_owned()))
+        .collect::<Option<Vec<_>>>()?;
+    Some(SnippetWithContext {
+        file_name: file.name.clone(),
+        start_line: start + 1,
+        lines,
+    })
+}
+
+/// Returns the name of the file `span` is in and the positions of its beginning and of its end.
+pub fn line_col_range(tcx: TyCtxt<'_>, span: Span) -> (FileName, LineCol, LineCol) {
+    let source_map = tcx.sess.source_map();
+    let span = source_span(span);
+    let lo = source_map.lookup_char_pos(span.lo());
+    let hi = source_map.lookup_char_pos(span.hi());
+    (
+        lo.file.name.clone(),
+        LineCol {
+            line: lo.line,
+            col: lo.col.0 + 1,
+        },
+        LineCol {
+            line: hi.line,
+            col: hi.col.0 + 1,
+        },
+    )
+}
+
+/// Returns the beginning of `span` formatted like `src/lib.rs:12:1`, with the same line and
+/// column as [`line_col_range`].
+pub fn location_string(tcx: TyCtxt<'_>, span: Span) -> String {
+    let (file_name, lo, _) = line_col_range(tcx, span);
+    format!("{}:{}:{}", file_name.prefer_local(), lo.line, lo.col)
+}
+
+/// Same as [`location_string`] without a `TyCtxt`, for the spans of the AST.
+pub(crate) fn ast_location_string(source_map: &SourceMap, span: Span) -> String {
+    let lo = source_map.lookup_char_pos(source_span(span).lo());
+    format!(
+        "{}:{}:{}",
+        lo.file.name.prefer_local(),
+        lo.line,
+        lo.col.0 + 1
+    )
+}
+
+/// Returns an empty span at the given position of `file`: `line` starts at 0 and `col_utf16` is
+/// counted in UTF-16 code units, like a [`Position`]. If `col_utf16` is after the end of the line,
+/// the span is at the end of the line.
+///
+/// Returns `None` if `file` isn't part of the crate or if it doesn't have that many lines.
+pub fn span_from_position(tcx: TyCtxt<'_>, file: &Path, line: u32, col_utf16: u32) -> Option<Span> {
+    let file = find_source_file(tcx, file)?;
+    let line = line as usize;
+    if line >= file.count_lines() {
+        return None;
+    }
+    let text = file.get_line(line)?;
+    let mut offset = 0;
+    let mut utf16_offset = 0;
+    for c in text.chars() {
+        // If the column is in the middle of a character, we go after it.
+        if utf16_offset >= col_utf16 as usize {
+            break;
+        }
+        utf16_offset += c.len_utf16();
+        offset += c.len_utf8();
+    }
+    let pos = file.line_bounds(line).start + BytePos::from_usize(offset);
+    Some(Span::with_root_ctxt(pos, pos))
+}
+
+/// Returns the path of the file `span` is in and the positions of its beginning and of its end.
+///
+/// Returns `None` if `span` isn't in a file of the file system (if it's from a string for
+/// example).
+pub fn position_from_span(tcx: TyCtxt<'_>, span: Span) -> Option<(PathBuf, Range<Position>)> {
+    let span = source_span(span);
+    let file = tcx.sess.source_map().lookup_source_file(span.lo());
+    let path = match &file.name {
+        FileName::Real(name) => name.local_path()?.to_path_buf(),
+        _ => return None,
+    };
+    let start = position(&file, span.lo())?;
+    let end = position(&file, span.hi())?;
+    Some((path, start..end))
+}
+
+fn position(file: &SourceFile, pos: BytePos) -> Option<Position> {
+    let line = file.lookup_line(pos)?;
+    let offset = (pos - file.line_bounds(line).start).to_usize();
+    // Windows line endings are converted when the file is loaded, so we don't need to care about
+    // them.
+    let text = file.get_line(line)?;
+    Some(Position {
+        line: line as u32,
+        character: text.get(..offset)?.encode_utf16().count() as u32,
+    })
+}
+
+/// Paths of the source files are the ones given to the compiler, so they can be relative.
+fn find_source_file(tcx: TyCtxt<'_>, path: &Path) -> Option<Lrc<SourceFile>> {
+    let canonical = path.canonicalize().ok();
+    let source_map = tcx.sess.source_map();
+    let files = source_map.files();
+    files
+        .iter()
+        .find(|file| {
+            let FileName::Real(name) = &file.name else { return false };
+            name.local_path().map_or(false, |file_path| {
+                file_path == path
+                    || (canonical.is_some() && file_path.canonicalize().ok() == canonical)
+            })
+        })
+        .cloned()
+}
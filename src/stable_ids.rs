@@ -0,0 +1,157 @@
+//! Helpers to identify the definitions with ids which are the same between runs of the compiler,
+//! for example to store the results of an analysis in a database keyed by definition, and to get
+//! the hashes the compiler uses to identify the crates.
+//!
+//! The [`DefId`]s depend on the order in which the compiler creates the definitions and loads the
+//! extern crates, so they can change even if the code doesn't. A [`StableItemId`] is built from
+//! the [`DefPathHash`] the compiler itself uses to find the definitions in the incremental
+//! compilation cache.
+
+use rustc_data_structures::stable_hasher::StableHasher;
+use rustc_hir::def_id::{CrateNum, DefId, DefIndex, DefPathHash, LOCAL_CRATE};
+use rustc_metadata::creader::CStore;
+use rustc_middle::ty::TyCtxt;
+
+use std::fmt;
+use std::hash::Hash;
+use std::iter;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An id of a definition which is the same in all the runs of the compiler (and on all the
+/// machines) as long as the definition and the crate don't change, returned by [`stable_id`].
+///
+/// It's made of the [`StableCrateId`](rustc_span::def_id::StableCrateId) of the crate and of the
+/// hash of the path of the definition in the crate. So it changes if:
+/// - the definition or one of its parent modules is renamed or moved,
+/// - the definition has no name (like an impl or a closure) and the definitions of the same kind
+///   before it in the same parent are added or removed, since they're numbered,
+/// - the name of the crate, its type (an executable or a library), its `-C metadata` (which cargo
+///   computes from the version and the source of the package) or the version of the compiler
+///   changes.
+///
+/// The other changes, like editing the body of a function or adding items elsewhere, keep it.
+/// It's displayed as 32 hexadecimal digits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StableItemId {
+    /// The [`StableCrateId`](rustc_span::def_id::StableCrateId) of the crate.
+    krate: u64,
+    /// The local part of the [`DefPathHash`].
+    local: u64,
+}
+
+impl fmt::Display for StableItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}{:016x}", self.krate, self.local)
+    }
+}
+
+/// A crate loaded by the analyzed crate, returned by [`dep_crate_hashes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DependencyHash {
+    pub name: String,
+    /// See [`stable_crate_id`].
+    pub stable_crate_id: u64,
+    /// See [`crate_hash`].
+    pub hash: u64,
+}
+
+/// Returns the hash of the local crate computed by the compiler (its SVH, which is written in its
+/// metadata to check that the crates depending on it were compiled against the same version).
+/// It's the [`DependencyHash::hash`] of the crate for the crates depending on it, if it was
+/// compiled with the same options.
+///
+/// It changes when the code of the crate changes (including the spans of its definitions, so
+/// moving code around changes it), and so do the tracked options of the compiler (like the
+/// edition or `--emit`), the names of its files, the [`stable_crate_id`] and the hashes of the
+/// dependencies. The code is the one of the HIR, once the `#[cfg]`s are applied, so a `--cfg`
+/// only changes it if it changes the code which is compiled (see [`crate_hash_with_cfgs`]), and
+/// an analysis run with a different [`AnalysisLevel`](crate::AnalysisLevel) gives the same hash.
+pub fn crate_hash(tcx: TyCtxt<'_>) -> u64 {
+    tcx.crate_hash(LOCAL_CRATE).as_u64()
+}
+
+/// Returns the [`crate_hash`] of the local crate combined with its `cfg`s (the ones given with
+/// `--cfg` and the ones of the target), so a `--cfg` changes it even if no code depends on it.
+pub fn crate_hash_with_cfgs(tcx: TyCtxt<'_>) -> u64 {
+    let mut cfgs = tcx
+        .sess
+        .parse_sess
+        .config
+        .iter()
+        .map(|&(name, value)| (name.to_string(), value.map(|value| value.to_string())))
+        .collect::<Vec<_>>();
+    cfgs.sort();
+    let mut hasher = StableHasher::new();
+    crate_hash(tcx).hash(&mut hasher);
+    cfgs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the [`StableCrateId`](rustc_span::def_id::StableCrateId) of the local crate, which
+/// the compiler computes from the name of the crate, its type (an executable or a library), its
+/// `-C metadata` and the version of the compiler. It's the first part of the [`StableItemId`]s.
+pub fn stable_crate_id(tcx: TyCtxt<'_>) -> u64 {
+    tcx.stable_crate_id(LOCAL_CRATE).to_u64()
+}
+
+/// Returns the [`crate_hash`] and the [`stable_crate_id`] of all the crates loaded by the
+/// analyzed crate (the direct and indirect dependencies, and the crates of the sysroot), sorted
+/// by name and then by id. The hash of a dependency changes when it's compiled from a different
+/// code or with different options.
+pub fn dep_crate_hashes(tcx: TyCtxt<'_>) -> Vec<DependencyHash> {
+    let mut hashes = tcx
+        .crates(())
+        .iter()
+        .map(|&krate| DependencyHash {
+            name: tcx.crate_name(krate).to_string(),
+            stable_crate_id: tcx.stable_crate_id(krate).to_u64(),
+            hash: tcx.crate_hash(krate).as_u64(),
+        })
+        .collect::<Vec<_>>();
+    hashes.sort_by(|a, b| (&a.name, a.stable_crate_id).cmp(&(&b.name, b.stable_crate_id)));
+    hashes
+}
+
+/// Returns the id of `def_id` which is the same in the next runs, see [`StableItemId`]. It works
+/// for the definitions of all the crates.
+pub fn stable_id(tcx: TyCtxt<'_>, def_id: DefId) -> StableItemId {
+    let hash = tcx.def_path_hash(def_id);
+    StableItemId {
+        krate: hash.stable_crate_id().to_u64(),
+        local: hash.local_hash(),
+    }
+}
+
+/// Returns the definition whose [`stable_id`] (maybe computed by a previous run) is `id`, or
+/// `None` if it doesn't exist anymore (see [`StableItemId`]) or if its crate isn't loaded.
+///
+/// It's immediate for the definitions of the local crate, whereas all the definitions of an extern
+/// crate are looked at to find one of them.
+pub fn lookup_stable_id(tcx: TyCtxt<'_>, id: &StableItemId) -> Option<DefId> {
+    let krate = iter::once(LOCAL_CRATE)
+        .chain(tcx.crates(()).iter().copied())
+        .find(|&krate| tcx.stable_crate_id(krate).to_u64() == id.krate)?;
+    let hash = DefPathHash::new(tcx.stable_crate_id(krate), id.local);
+    if krate == LOCAL_CRATE {
+        let index = tcx
+            .definitions_untracked()
+            .def_path_hash_to_def_index_map()
+            .get(&hash)?;
+        return Some(DefId { krate, index });
+    }
+    // The compiler panics if the hash isn't in the metadata of the crate.
+    extern_def_ids(tcx, krate).find(|&def_id| tcx.def_path_hash(def_id) == hash)
+}
+
+/// Returns all the definitions of the extern crate `krate`.
+fn extern_def_ids(tcx: TyCtxt<'_>, krate: CrateNum) -> impl Iterator<Item = DefId> {
+    let count = CStore::from_tcx(tcx).num_def_ids_untracked(krate);
+    (0..count).map(move |index| DefId {
+        krate,
+        index: DefIndex::from_usize(index),
+    })
+}
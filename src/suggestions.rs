@@ -0,0 +1,211 @@
+//! Helpers to apply the suggestions of the collected diagnostics to the source files, like
+//! `cargo fix` does.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::diagnostics::{Applicability, Diagnostic, DiagnosticSpan, Suggestion};
+
+/// What [`apply_suggestions`] does with the patched files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ApplyMode {
+    /// The patched contents are only returned.
+    DryRun,
+    /// The patched contents are written into the files as well.
+    Write,
+}
+
+/// The suggestions applied to a file, returned by [`apply_suggestions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileEdit {
+    /// The name of the file as given in the diagnostics.
+    pub file_name: String,
+    /// The contents of the file with the suggestions applied, or `Err` with the reason if the file
+    /// couldn't be read (or written with [`ApplyMode::Write`]).
+    pub contents: Result<String, String>,
+    /// The suggestions applied to the file, in the order of the diagnostics.
+    pub applied: Vec<Suggestion>,
+    /// The suggestions which weren't applied because they overlap one which was applied before.
+    pub conflicts: Vec<Suggestion>,
+}
+
+/// A part of a suggestion, located in the contents of its file.
+struct Replacement<'a> {
+    start: usize,
+    end: usize,
+    text: &'a str,
+}
+
+impl Replacement<'_> {
+    /// Returns `true` if both replacements change the same code, or insert code at the same
+    /// place (in which case the order of the insertions would be ambiguous).
+    fn overlaps(&self, other: &Replacement<'_>) -> bool {
+        (self.start < other.end && other.start < self.end)
+            || (self.start == other.start && (self.start == self.end || other.start == other.end))
+    }
+}
+
+/// Applies the machine-applicable suggestions (see [`Applicability::MachineApplicable`]) of
+/// `diagnostics` to the files they point to, and returns the patched files sorted by name. The
+/// diagnostics can be collected with
+/// [`Config::collect_diagnostics`](crate::Config::collect_diagnostics).
+///
+/// The suggestions are applied in the order of the diagnostics. When a suggestion overlaps one
+/// which was applied before, it's skipped and reported in [`FileEdit::conflicts`], so running the
+/// compiler again may give more suggestions to apply. All the parts of a suggestion are applied
+/// together (even if they're in different files), or none of them. The suggestions given twice
+/// are applied once.
+///
+/// The files are read from the disk, so the diagnostics must come from the current version of
+/// the files, and the crate must not have been given with
+/// [`Config::source_code`](crate::Config::source_code) or
+/// [`Config::file_overlay`](crate::Config::file_overlay).
+pub fn apply_suggestions(diagnostics: &[Diagnostic], mode: ApplyMode) -> Vec<FileEdit> {
+    let suggestions = diagnostics
+        .iter()
+        .flat_map(|diagnostic| &diagnostic.suggestions)
+        .filter(|suggestion| {
+            suggestion.applicability == Applicability::MachineApplicable
+                && !suggestion.parts.is_empty()
+        });
+
+    let mut files = BTreeMap::new();
+    for suggestion in suggestions.clone() {
+        for part in &suggestion.parts {
+            files
+                .entry(part.span.file_name.as_str())
+                .or_insert_with(|| {
+                    fs::read_to_string(&part.span.file_name).map_err(|e| e.to_string())
+                });
+        }
+    }
+
+    let mut replacements = BTreeMap::<_, Vec<Replacement<'_>>>::new();
+    let mut applied = BTreeMap::<_, Vec<&Suggestion>>::new();
+    let mut conflicts = BTreeMap::<_, Vec<&Suggestion>>::new();
+    for suggestion in suggestions {
+        let parts = suggestion
+            .parts
+            .iter()
+            .map(|part| {
+                let contents = files[part.span.file_name.as_str()].as_ref().ok()?;
+                let (start, end) = byte_range(contents, &part.span)?;
+                let replacement = Replacement {
+                    start,
+                    end,
+                    text: &part.replacement,
+                };
+                Some((part.span.file_name.as_str(), replacement))
+            })
+            .collect::<Option<Vec<_>>>();
+        // The file couldn't be read or the span doesn't fit in it.
+        let Some(parts) = parts else { continue };
+
+        let is_duplicate = parts.iter().all(|(file_name, part)| {
+            replacements.get(file_name).map_or(false, |replacements| {
+                replacements.iter().any(|replacement| {
+                    (replacement.start, replacement.end, replacement.text)
+                        == (part.start, part.end, part.text)
+                })
+            })
+        });
+        if is_duplicate {
+            continue;
+        }
+        let overlaps = parts.iter().enumerate().any(|(i, (file_name, part))| {
+            let applied = replacements.get(file_name).into_iter().flatten();
+            let previous_parts = parts[..i]
+                .iter()
+                .filter(|(other_file_name, _)| other_file_name == file_name)
+                .map(|(_, part)| part);
+            applied
+                .chain(previous_parts)
+                .any(|replacement| replacement.overlaps(part))
+        });
+
+        let mut file_names = parts
+            .iter()
+            .map(|(file_name, _)| *file_name)
+            .collect::<Vec<_>>();
+        file_names.sort_unstable();
+        file_names.dedup();
+        if overlaps {
+            for file_name in file_names {
+                conflicts.entry(file_name).or_default().push(suggestion);
+            }
+        } else {
+            for file_name in file_names {
+                applied.entry(file_name).or_default().push(suggestion);
+            }
+            for (file_name, part) in parts {
+                replacements.entry(file_name).or_default().push(part);
+            }
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|(file_name, contents)| {
+            let contents = contents.and_then(|contents| {
+                let mut replacements = replacements.remove(file_name).unwrap_or_default();
+                replacements.sort_by_key(|replacement| (replacement.start, replacement.end));
+                let mut patched = String::with_capacity(contents.len());
+                let mut last = 0;
+                for replacement in replacements {
+                    patched.push_str(&contents[last..replacement.start]);
+                    patched.push_str(replacement.text);
+                    last = replacement.end;
+                }
+                patched.push_str(&contents[last..]);
+                if mode == ApplyMode::Write && patched != contents {
+                    fs::write(file_name, &patched).map_err(|e| e.to_string())?;
+                }
+                Ok(patched)
+            });
+            let cloned = |suggestions: Option<Vec<&Suggestion>>| {
+                suggestions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            };
+            FileEdit {
+                file_name: file_name.to_owned(),
+                contents,
+                applied: cloned(applied.remove(file_name)),
+                conflicts: cloned(conflicts.remove(file_name)),
+            }
+        })
+        .collect()
+}
+
+/// Returns the byte range of `span` in `contents`. The lines and the columns of the span are used
+/// instead of its byte offsets, which don't count the carriage returns (`\r\n` is normalized to
+/// `\n` by the compiler) nor the byte order mark.
+fn byte_range(contents: &str, span: &DiagnosticSpan) -> Option<(usize, usize)> {
+    let offset = |line: usize, column: usize| {
+        let text = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+        let line_start = match line {
+            0 => return None,
+            1 => 0,
+            _ => text.match_indices('\n').nth(line - 2)?.0 + 1,
+        };
+        let line_text = text[line_start..].split('\n').next()?;
+        let column = column.checked_sub(1)?;
+        let column_offset = match line_text.char_indices().nth(column) {
+            Some((offset, _)) => offset,
+            // The end of the line.
+            None if line_text.chars().count() == column => line_text.len(),
+            None => return None,
+        };
+        Some(contents.len() - text.len() + line_start + column_offset)
+    };
+    let start = offset(span.line_start, span.column_start)?;
+    let end = offset(span.line_end, span.column_end)?;
+    (start <= end).then_some((start, end))
+}
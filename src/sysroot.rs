@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+use crate::Error;
+
+/// Returns the `rustc` binary to use: the one from the `RUSTC` environment variable if it's set,
+/// otherwise the one in `PATH`. If it's a rustup proxy, it'll pick the toolchain from the
+/// `RUSTUP_TOOLCHAIN` environment variable (since it's inherited).
+pub(crate) fn rustc_path() -> String {
+    env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned())
+}
+
+/// Runs `rustc` with `args` and returns its output.
+fn run_rustc(rustc: &str, args: &[&str]) -> Result<String, Error> {
+    let mut command = format!("{} {}", rustc, args.join(" "));
+    if let Ok(toolchain) = env::var("RUSTUP_TOOLCHAIN") {
+        command = format!("RUSTUP_TOOLCHAIN={toolchain} {command}");
+    }
+    let error = |reason: String| Error::SysrootDetection {
+        command: command.clone(),
+        reason,
+    };
+
+    let output = Command::new(rustc)
+        .args(args)
+        .output()
+        .map_err(|e| error(e.to_string()))?;
+    if !output.status.success() {
+        return Err(error(format!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let output = String::from_utf8(output.stdout)
+        .map_err(|_| error("the output isn't valid UTF-8".to_owned()))?;
+    Ok(output.trim().to_owned())
+}
+
+/// The key of the caches: the `rustc` binary with the value of `RUSTUP_TOOLCHAIN`.
+type Key = (String, Option<String>);
+
+fn key(rustc: &str) -> Key {
+    (rustc.to_owned(), env::var("RUSTUP_TOOLCHAIN").ok())
+}
+
+/// Returns the version of the compiler this crate is linked to (like
+/// `1.69.0-nightly (c18a5e8a5 2023-01-25)`), which is the one of the toolchain it was built with.
+/// The sysroot used to analyze a crate must come from the same toolchain: its libraries can't be
+/// loaded by another version of the compiler.
+pub fn required_nightly() -> &'static str {
+    rustc_interface::util::rustc_version_str().unwrap_or("unknown")
+}
+
+/// Checks that `rustc` (usually [`rustc_path`]) is the version this crate is linked to, with
+/// `rustc --version`.
+///
+/// The result is cached for a given `rustc` and `RUSTUP_TOOLCHAIN`, so `rustc` is only run once
+/// per process if the check succeeds.
+pub(crate) fn check_toolchain(rustc: &str) -> Result<(), Error> {
+    static CHECKED: LazyLock<Mutex<HashSet<Key>>> = LazyLock::new(Default::default);
+
+    let key = key(rustc);
+    if CHECKED.lock().unwrap().contains(&key) {
+        return Ok(());
+    }
+    let expected = required_nightly();
+    let found = run_rustc(rustc, &["--version"])?;
+    if !versions_match(expected, &found) {
+        return Err(Error::ToolchainMismatch {
+            expected: format!("rustc {expected}"),
+            found,
+        });
+    }
+    CHECKED.lock().unwrap().insert(key);
+    Ok(())
+}
+
+/// Returns `true` if `found` (the output of `rustc --version`) is the version `expected` (given
+/// without the `rustc ` prefix). Only the commit hashes are compared when both have one, since
+/// the rest depends on how the compiler was built.
+fn versions_match(expected: &str, found: &str) -> bool {
+    fn commit_hash(version: &str) -> Option<&str> {
+        let (_, details) = version.split_once('(')?;
+        details.split_whitespace().next()
+    }
+
+    match (commit_hash(expected), commit_hash(found)) {
+        (Some(expected), Some(found)) => expected == found,
+        _ => found.ends_with(expected),
+    }
+}
+
+/// Runs `rustc --print sysroot` (`rustc` being usually [`rustc_path`]) to get the sysroot. If
+/// `check_toolchain` is `true`, the version of this `rustc` is checked first (see
+/// [`check_toolchain`]): the libraries of another toolchain cannot be used by the compiler we're
+/// linked to.
+///
+/// The sysroot found for a given `rustc` and `RUSTUP_TOOLCHAIN` is cached, so `rustc` is only run
+/// once per process.
+pub(crate) fn detect_sysroot(rustc: &str, check_toolchain: bool) -> Result<PathBuf, Error> {
+    static SYSROOTS: LazyLock<Mutex<HashMap<Key, PathBuf>>> = LazyLock::new(Default::default);
+
+    if check_toolchain {
+        self::check_toolchain(rustc)?;
+    }
+    let key = key(rustc);
+    if let Some(sysroot) = SYSROOTS.lock().unwrap().get(&key) {
+        return Ok(sysroot.clone());
+    }
+    let sysroot = detect_sysroot_uncached(rustc)?;
+    SYSROOTS.lock().unwrap().insert(key, sysroot.clone());
+    Ok(sysroot)
+}
+
+fn detect_sysroot_uncached(rustc: &str) -> Result<PathBuf, Error> {
+    let sysroot = PathBuf::from(run_rustc(rustc, &["--print", "sysroot"])?);
+    if !sysroot.is_dir() {
+        return Err(Error::SysrootDetection {
+            command: format!("{rustc} --print sysroot"),
+            reason: format!("`{}` isn't a directory", sysroot.display()),
+        });
+    }
+    Ok(sysroot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_toolchain, versions_match};
+    use crate::Error;
+
+    const EXPECTED: &str = "1.69.0-nightly (c18a5e8a5 2023-01-25)";
+
+    #[test]
+    fn same_commit() {
+        assert!(versions_match(
+            EXPECTED,
+            "rustc 1.69.0-nightly (c18a5e8a5 2023-01-25)"
+        ));
+        // Only the commit hash is compared.
+        assert!(versions_match(
+            EXPECTED,
+            "rustc 1.69.0-dev (c18a5e8a5 2023-01-26)"
+        ));
+    }
+
+    #[test]
+    fn other_commit() {
+        assert!(!versions_match(
+            EXPECTED,
+            "rustc 1.69.0-nightly (5e37043d6 2023-01-22)"
+        ));
+        assert!(!versions_match(
+            EXPECTED,
+            "rustc 1.67.0 (fc594f156 2023-01-24)"
+        ));
+    }
+
+    #[test]
+    fn without_commit() {
+        assert!(versions_match("1.69.0-dev", "rustc 1.69.0-dev"));
+        assert!(!versions_match("1.69.0-dev", "rustc 1.68.0-dev"));
+        assert!(!versions_match(EXPECTED, "rustc 1.69.0-dev"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fake_rustc() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("rustc-tools-fake-rustc-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rustc = dir.join("rustc");
+        fs::write(
+            &rustc,
+            "#!/bin/sh\necho 'rustc 1.0.0 (a59de37e9 2015-05-13)'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&rustc, fs::Permissions::from_mode(0o755)).unwrap();
+        let res = check_toolchain(&rustc.display().to_string());
+        let _ = fs::remove_dir_all(&dir);
+        match res {
+            Err(Error::ToolchainMismatch { found, .. }) => {
+                assert_eq!(found, "rustc 1.0.0 (a59de37e9 2015-05-13)")
+            }
+            res => panic!("{res:?}"),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Helpers to find the tests and the benchmarks of a crate without building its test harness.
+
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::ItemKind;
+use rustc_middle::ty::{DefIdTree, TyCtxt};
+use rustc_span::symbol::{sym, Symbol};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::paths::qualified_name;
+use crate::spans::location_string;
+use crate::{Config, Error};
+
+/// A test or a benchmark returned by [`discover_tests`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    /// The [`qualified_name`] of the function, without the disambiguator it gets because of the
+    /// constant added by the harness (so `my_crate::tests::it_works` and not
+    /// `my_crate::tests::it_works#1`).
+    pub name: String,
+    /// The name given to the test by libtest (like `tests::it_works`), which is the one used to
+    /// filter the tests on the command line.
+    pub test_name: String,
+    pub kind: TestKind,
+    /// `true` if the function has `#[ignore]`.
+    pub ignore: bool,
+    /// The reason given with `#[ignore = "reason"]`.
+    pub ignore_message: Option<String>,
+    /// `true` if the function has `#[should_panic]`.
+    pub should_panic: bool,
+    /// The message given with `#[should_panic(expected = "message")]` or
+    /// `#[should_panic = "message"]`.
+    pub expected_panic: Option<String>,
+    /// Where the function is defined, like `src/lib.rs:12:1`.
+    pub location: String,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestKind {
+    /// A function with `#[test]`.
+    Test,
+    /// A function with `#[bench]`.
+    Bench,
+}
+
+/// Runs the compiler with `rustc_args` in test mode (like `rustc --test`, see [`Config::test`])
+/// and returns the tests and the benchmarks of the crate, sorted by test name. The test harness
+/// is generated but the crate isn't built.
+///
+/// The tests are found after the expansion, so the ones written with the attributes of other
+/// crates which expand to `#[test]` (like `#[tokio::test]`) are included as well.
+pub fn discover_tests(rustc_args: &[String]) -> Result<Vec<TestCase>, Error> {
+    Config::new(rustc_args).test(true).with_tyctxt(test_cases)
+}
+
+/// Returns the tests and the benchmarks of the local crate, sorted by test name. The crate must
+/// have been compiled in test mode (see [`Config::test`]), otherwise the tests are removed by the
+/// expansion and nothing is found.
+pub fn test_cases(tcx: TyCtxt<'_>) -> Vec<TestCase> {
+    let mut tests = test_functions(tcx)
+        .into_iter()
+        .map(|(def_id, test_name)| {
+            let name = format!(
+                "{}::{}",
+                qualified_name(tcx, tcx.local_parent(def_id).to_def_id()),
+                tcx.item_name(def_id.to_def_id())
+            );
+            test_case(tcx, def_id, name, test_name)
+        })
+        .collect::<Vec<_>>();
+    tests.sort_by(|a, b| a.test_name.cmp(&b.test_name));
+    tests
+}
+
+/// Returns the functions of the tests and the benchmarks with their test name, in no particular
+/// order.
+pub(crate) fn test_functions(tcx: TyCtxt<'_>) -> Vec<(LocalDefId, Symbol)> {
+    let hir = tcx.hir();
+    // `#[test]` keeps the function and adds a constant with the same name (but another hygiene
+    // context) describing the test for the harness.
+    let mut functions = HashMap::new();
+    let mut markers = Vec::new();
+    for item_id in hir.items() {
+        let item = hir.item(item_id);
+        let def_id = item.owner_id.def_id;
+        match item.kind {
+            ItemKind::Fn(..) => {
+                functions.insert((tcx.local_parent(def_id), item.ident.name), def_id);
+            }
+            ItemKind::Const(..) => {
+                if let Some(test_name) = tcx
+                    .get_attr(def_id.to_def_id(), sym::rustc_test_marker)
+                    .and_then(|attr| attr.value_str())
+                {
+                    markers.push((tcx.local_parent(def_id), item.ident.name, test_name));
+                }
+            }
+            _ => {}
+        }
+    }
+    markers
+        .into_iter()
+        .filter_map(|(parent, name, test_name)| Some((*functions.get(&(parent, name))?, test_name)))
+        .collect()
+}
+
+fn test_case(tcx: TyCtxt<'_>, def_id: LocalDefId, name: String, test_name: Symbol) -> TestCase {
+    let ignore = tcx.get_attr(def_id.to_def_id(), sym::ignore);
+    let should_panic = tcx.get_attr(def_id.to_def_id(), sym::should_panic);
+    let expected_panic = should_panic.and_then(|attr| match attr.meta_item_list() {
+        Some(items) => items
+            .iter()
+            .find(|item| item.has_name(sym::expected))
+            .and_then(|item| item.value_str()),
+        None => attr.value_str(),
+    });
+    // The benchmarks take a `Bencher` whereas the tests don't have any parameter.
+    let kind = if tcx.fn_sig(def_id).skip_binder().inputs().is_empty() {
+        TestKind::Test
+    } else {
+        TestKind::Bench
+    };
+    TestCase {
+        name,
+        test_name: test_name.to_string(),
+        kind,
+        ignore: ignore.is_some(),
+        ignore_message: ignore
+            .and_then(|attr| attr.value_str())
+            .map(|message| message.to_string()),
+        should_panic: should_panic.is_some(),
+        expected_panic: expected_panic.map(|message| message.to_string()),
+        location: location_string(tcx, tcx.def_span(def_id)),
+    }
+}
@@ -0,0 +1,387 @@
+//! Helpers to find which types implement a trait, and whether a trait can be implemented for a
+//! type.
+
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{self, GenericArg, Ty, TyCtxt, TypeVisitable};
+use rustc_span::Span;
+use rustc_trait_selection::infer::{InferCtxtExt, TyCtxtInferExt};
+
+use crate::paths::resolve_path_str;
+
+/// Impl block returned by [`impls_of_trait`].
+#[derive(Clone, Debug)]
+pub struct ImplInfo {
+    pub def_id: DefId,
+    /// The type the trait is implemented for, with the full paths of the items (like
+    /// `module::Struct<T>` or `std::vec::Vec<T>`, the local items don't start with the crate
+    /// name).
+    pub self_ty: String,
+    /// For derived impls, it comes from the expansion of the derive macro.
+    pub span: Span,
+    /// `true` if the trait is implemented for a type parameter (like `impl<T: Debug> Trait for T`).
+    pub is_blanket: bool,
+    /// `true` if the impl was generated by a derive macro. It relies on the
+    /// `#[automatically_derived]` attribute, which the derive macros of the standard library and
+    /// of the most common crates (like `serde`) add.
+    pub is_derived: bool,
+}
+
+/// Returns the impls of the trait `trait_def_id` defined in the local crate, including the ones
+/// generated by macros like `#[derive]`. They're in the order in which they're defined.
+///
+/// Use [`trait_def_id_by_path`] to get `trait_def_id`.
+pub fn impls_of_trait(tcx: TyCtxt<'_>, trait_def_id: DefId) -> Vec<ImplInfo> {
+    tcx.hir()
+        .trait_impls(trait_def_id)
+        .iter()
+        .map(|impl_id| {
+            let def_id = impl_id.to_def_id();
+            let self_ty = tcx.type_of(def_id);
+            ImplInfo {
+                def_id,
+                self_ty: with_no_trimmed_paths!(self_ty.to_string()),
+                span: tcx.def_span(def_id),
+                is_blanket: matches!(self_ty.kind(), ty::Param(_)),
+                is_derived: tcx.is_builtin_derive(def_id),
+            }
+        })
+        .collect()
+}
+
+/// Returns the trait `path` (like `std::fmt::Debug` or `serde::Serialize`) points to, see
+/// [`resolve_path`](crate::paths::resolve_path) for the paths which can be used.
+///
+/// Returns `None` if it doesn't point to a trait, for example if the crate it's in isn't a
+/// dependency of the local crate.
+pub fn trait_def_id_by_path(tcx: TyCtxt<'_>, path: &str) -> Option<DefId> {
+    resolve_path_str(tcx, path)
+        .into_iter()
+        .find_map(|res| match res {
+            Res::Def(DefKind::Trait, def_id) => Some(def_id),
+            _ => None,
+        })
+}
+
+/// Returns `true` if `ty` implements the trait `trait_def_id`. `generic_args` are the generic
+/// arguments of the trait without the `Self` type (so it's empty for traits like `Send` or
+/// `Debug`, and it contains `T` for `From<T>`).
+///
+/// The where clauses of the items `ty` comes from are not taken into account, so a type parameter
+/// is considered to implement only the traits implemented for every type. For example, with
+/// `struct Wrapper<T>(Arc<T>)`, `Wrapper<u32>` is `Send` but `Wrapper<T>` (the type returned by
+/// `tcx.type_of`) isn't since it depends on `T`. The lifetimes are ignored.
+///
+/// The auto traits of `impl Trait` types depend on the body of the function returning them, which
+/// is type-checked if it wasn't already. Use [`AnalysisLevel::TypeckBodies`](crate::AnalysisLevel)
+/// or higher if you call it on many of them.
+pub fn implements_trait<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    ty: Ty<'tcx>,
+    trait_def_id: DefId,
+    generic_args: &[GenericArg<'tcx>],
+) -> bool {
+    let ty = tcx.erase_regions(ty);
+    if ty.has_escaping_bound_vars() {
+        return false;
+    }
+    let infcx = tcx.infer_ctxt().build();
+    infcx
+        .type_implements_trait(
+            trait_def_id,
+            std::iter::once(ty.into()).chain(generic_args.iter().copied()),
+            ty::ParamEnv::empty(),
+        )
+        .must_apply_modulo_regions()
+}
+
+/// Returned by [`coherence_check`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoherenceVerdict {
+    /// The impl respects the orphan rule.
+    Allowed,
+    /// The impl would be rejected by the compiler (with the error `E0117` or `E0210`).
+    OrphanViolation { reason: String },
+    /// It depends on names which can't be resolved, like the type parameters of the impl.
+    Unknown { reason: String },
+}
+
+/// Returns whether `impl Trait for SelfTy` would respect the orphan rule in the local crate, with
+/// `trait_path` like `std::convert::From<my_crate::Local>` and `self_ty_path` like
+/// `std::vec::Vec<u32>` or `&my_crate::Local`. The paths are resolved like with
+/// [`resolve_path`](crate::paths::resolve_path), so the names of the prelude (like `Vec`) must be
+/// written in full. The types can be references, raw pointers, tuples, slices, arrays, `dyn Trait`
+/// and paths with generic arguments (the lifetimes are ignored).
+///
+/// Like in the compiler, the impl is allowed if the trait is local, or if one of the self type
+/// and the generic arguments of the trait (in this order) is a local type and no type parameter
+/// appears before it outside of another type. A local type is a type defined in the local crate, a
+/// `dyn LocalTrait` or a `#[fundamental]` type (`&T`, `&mut T`, `Box<T>` or `Pin<T>`) of a local
+/// type, so `Vec<Local>` isn't local. The names which can't be resolved may be type parameters,
+/// the verdict is [`CoherenceVerdict::Unknown`] if it depends on them.
+///
+/// Only the orphan rule is checked, not the overlap with the other impls.
+pub fn coherence_check(tcx: TyCtxt<'_>, trait_path: &str, self_ty_path: &str) -> CoherenceVerdict {
+    let (trait_expr, self_ty) = match (parse_type(trait_path), parse_type(self_ty_path)) {
+        (Some(trait_expr @ TypeExpr::Path { .. }), Some(self_ty)) => (trait_expr, self_ty),
+        _ => {
+            return CoherenceVerdict::Unknown {
+                reason: format!("can't parse `{trait_path}` or `{self_ty_path}`"),
+            }
+        }
+    };
+    let TypeExpr::Path { path, args: trait_args } = trait_expr else { unreachable!() };
+    let Some(trait_def_id) = trait_def_id_by_path(tcx, &path) else {
+        return CoherenceVerdict::Unknown {
+            reason: format!("`{path}` isn't a trait"),
+        };
+    };
+    if trait_def_id.is_local() {
+        return CoherenceVerdict::Allowed;
+    }
+
+    let mut uncertain = None;
+    for ty in std::iter::once(&self_ty).chain(&trait_args) {
+        match locality(tcx, ty) {
+            Locality::Local => {
+                return match uncertain {
+                    Some(reason) => CoherenceVerdict::Unknown { reason },
+                    None => CoherenceVerdict::Allowed,
+                }
+            }
+            Locality::Uncertain(reason) => {
+                uncertain.get_or_insert(reason);
+            }
+            Locality::Foreign => {}
+        }
+    }
+    match uncertain {
+        Some(reason) => CoherenceVerdict::Unknown { reason },
+        None => CoherenceVerdict::OrphanViolation {
+            reason: format!(
+                "`{}` is defined in another crate and neither `{}` nor the generic arguments of \
+                 the trait are local types",
+                with_no_trimmed_paths!(tcx.def_path_str(trait_def_id)),
+                self_ty_path.trim()
+            ),
+        },
+    }
+}
+
+enum Locality {
+    Local,
+    Foreign,
+    /// It depends on a name which can't be resolved, which is in the reason.
+    Uncertain(String),
+}
+
+/// Returns whether `ty` is a local type for the orphan rule.
+fn locality(tcx: TyCtxt<'_>, ty: &TypeExpr) -> Locality {
+    match ty {
+        TypeExpr::Ref(ty) => locality(tcx, ty),
+        TypeExpr::Dyn(trait_path) => match trait_def_id_by_path(tcx, trait_path) {
+            Some(def_id) if def_id.is_local() => Locality::Local,
+            Some(_) => Locality::Foreign,
+            None => Locality::Uncertain(format!("`{trait_path}` isn't a trait")),
+        },
+        TypeExpr::Path { path, args } => {
+            let res = resolve_path_str(tcx, path).into_iter().find(|res| {
+                matches!(
+                    res,
+                    Res::PrimTy(_)
+                        | Res::Def(
+                            DefKind::Struct
+                                | DefKind::Enum
+                                | DefKind::Union
+                                | DefKind::TyAlias
+                                | DefKind::ForeignTy
+                                | DefKind::Trait,
+                            _
+                        )
+                )
+            });
+            match res {
+                Some(Res::Def(DefKind::Struct | DefKind::Enum | DefKind::Union, def_id)) => {
+                    if def_id.is_local() {
+                        Locality::Local
+                    } else if tcx.adt_def(def_id).is_fundamental() {
+                        args.first()
+                            .map_or(Locality::Foreign, |arg| locality(tcx, arg))
+                    } else {
+                        Locality::Foreign
+                    }
+                }
+                Some(Res::Def(DefKind::ForeignTy, def_id)) if def_id.is_local() => Locality::Local,
+                // A trait object written without `dyn`.
+                Some(Res::Def(DefKind::Trait, def_id)) if def_id.is_local() => Locality::Local,
+                Some(Res::Def(DefKind::TyAlias, def_id)) => {
+                    if tcx.generics_of(def_id).count() == 0 {
+                        ty_locality(tcx.type_of(def_id))
+                    } else {
+                        Locality::Uncertain(format!("`{path}` is a generic type alias"))
+                    }
+                }
+                Some(_) => Locality::Foreign,
+                None if path.contains("::") => {
+                    Locality::Uncertain(format!("`{path}` isn't a type"))
+                }
+                None => Locality::Uncertain(format!("`{path}` may be a type parameter")),
+            }
+        }
+        TypeExpr::Other => Locality::Foreign,
+    }
+}
+
+/// Same as [`locality`] for a type of the compiler.
+fn ty_locality(ty: Ty<'_>) -> Locality {
+    match *ty.kind() {
+        ty::Adt(adt, _) if adt.did().is_local() => Locality::Local,
+        ty::Adt(adt, substs) if adt.is_fundamental() => {
+            substs.types().next().map_or(Locality::Foreign, ty_locality)
+        }
+        ty::Ref(_, ty, _) => ty_locality(ty),
+        ty::Dynamic(predicates, ..)
+            if predicates
+                .principal_def_id()
+                .map_or(false, |def_id| def_id.is_local()) =>
+        {
+            Locality::Local
+        }
+        ty::Foreign(def_id) if def_id.is_local() => Locality::Local,
+        ty::Param(param) => Locality::Uncertain(format!("`{}` is a type parameter", param.name)),
+        _ => Locality::Foreign,
+    }
+}
+
+/// A type parsed by [`parse_type`], with only what matters to the orphan rule.
+enum TypeExpr {
+    /// A path with its generic arguments (without the lifetimes).
+    Path { path: String, args: Vec<TypeExpr> },
+    /// `&T` or `&mut T`.
+    Ref(Box<TypeExpr>),
+    /// `dyn Trait`, with the path of the principal trait.
+    Dyn(String),
+    /// The other types, which are never local (tuples, slices, raw pointers, etc).
+    Other,
+}
+
+/// Parses a type like `&std::vec::Vec<(u32, my_crate::Local)>`.
+fn parse_type(s: &str) -> Option<TypeExpr> {
+    let mut parser = TypeParser { s: s.trim() };
+    let ty = parser.ty()?;
+    parser.s.is_empty().then_some(ty)
+}
+
+struct TypeParser<'a> {
+    /// What remains to be parsed, without leading whitespace.
+    s: &'a str,
+}
+
+impl<'a> TypeParser<'a> {
+    fn eat(&mut self, token: &str) -> bool {
+        let Some(rest) = self.s.strip_prefix(token) else { return false };
+        // A keyword mustn't be the beginning of an identifier.
+        if token.ends_with(|c: char| c.is_alphanumeric())
+            && rest.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+        {
+            return false;
+        }
+        self.s = rest.trim_start();
+        true
+    }
+
+    fn ty(&mut self) -> Option<TypeExpr> {
+        if self.eat("&") {
+            self.lifetime();
+            self.eat("mut");
+            return Some(TypeExpr::Ref(Box::new(self.ty()?)));
+        }
+        if self.eat("*") {
+            if !self.eat("const") && !self.eat("mut") {
+                return None;
+            }
+            self.ty()?;
+            return Some(TypeExpr::Other);
+        }
+        if self.eat("(") {
+            while !self.eat(")") {
+                self.ty()?;
+                if !self.eat(",") && !self.s.starts_with(')') {
+                    return None;
+                }
+            }
+            return Some(TypeExpr::Other);
+        }
+        if self.eat("[") {
+            self.ty()?;
+            // The length of an array is skipped.
+            let end = self.s.find(']')?;
+            self.s = self.s[end..].trim_start();
+            self.eat("]");
+            return Some(TypeExpr::Other);
+        }
+        if self.eat("!") || self.eat("_") {
+            return Some(TypeExpr::Other);
+        }
+        if self.eat("dyn") {
+            let TypeExpr::Path { path, .. } = self.path()? else { return None };
+            // The other bounds (like `+ Send + 'a`) don't matter.
+            while self.eat("+") {
+                if !self.lifetime() {
+                    self.path()?;
+                }
+            }
+            return Some(TypeExpr::Dyn(path));
+        }
+        self.path()
+    }
+
+    fn path(&mut self) -> Option<TypeExpr> {
+        let mut path = String::new();
+        if self.eat("::") {
+            path.push_str("::");
+        }
+        loop {
+            let len = self
+                .s
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(self.s.len());
+            if len == 0 {
+                return None;
+            }
+            path.push_str(&self.s[..len]);
+            self.s = self.s[len..].trim_start();
+            if !self.eat("::") {
+                break;
+            }
+            if self.s.starts_with('<') {
+                // The turbofish, like in `Vec::<u32>`.
+                break;
+            }
+            path.push_str("::");
+        }
+        let mut args = Vec::new();
+        if self.eat("<") {
+            while !self.eat(">") {
+                if !self.lifetime() {
+                    args.push(self.ty()?);
+                }
+                if !self.eat(",") && !self.s.starts_with('>') {
+                    return None;
+                }
+            }
+        }
+        Some(TypeExpr::Path { path, args })
+    }
+
+    /// Skips a lifetime, if there is one.
+    fn lifetime(&mut self) -> bool {
+        let Some(rest) = self.s.strip_prefix('\'') else { return false };
+        let len = rest
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        self.s = rest[len..].trim_start();
+        true
+    }
+}
@@ -0,0 +1,256 @@
+//! Helpers to find the `unsafe` code of the crate.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_hir::{
+    BlockCheckMode, BodyId, Expr, ExprKind, ForeignItem, ForeignItemKind, HirId, ImplItem,
+    ImplItemKind, Item, ItemKind, TraitItem, TraitItemKind, UnsafeSource, Unsafety,
+};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+use rustc_span::{ExpnKind, Span};
+use rustc_target::spec::abi::Abi;
+
+use std::collections::BTreeMap;
+
+use crate::paths::qualified_name;
+
+/// Returned by [`unsafe_inventory`].
+#[derive(Clone, Debug, Default)]
+pub struct UnsafeReport {
+    /// All the unsafe code, in the order in which it's found.
+    pub items: Vec<UnsafeItem>,
+    /// The number of items of each kind by module, the key being the
+    /// [`qualified_name`] of the module.
+    pub per_module: BTreeMap<String, UnsafeCounts>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UnsafeItem {
+    pub kind: UnsafeKind,
+    /// Span in the code written by the user: if the unsafe code was generated by a macro, it's
+    /// the span of the macro call.
+    pub span: Span,
+    /// If the unsafe code was generated by a macro, the name of the macro called by the user.
+    pub macro_name: Option<String>,
+    /// The module the code is in.
+    pub module: LocalDefId,
+    /// The function (or method) containing the unsafe block or the union field access. The
+    /// closures are part of the function they're defined in. It's `None` for the unsafe code which
+    /// isn't in a function (in the initializer of a `const` for example) and for unsafe items.
+    pub enclosing_fn: Option<LocalDefId>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnsafeKind {
+    /// An `unsafe fn` or a function declared in an `extern` block. `is_extern` is `true` for the
+    /// latter and for the functions using another ABI than the Rust one (like
+    /// `unsafe extern "C" fn`).
+    Fn { def_id: LocalDefId, is_extern: bool },
+    /// An `unsafe` block written by the user (or a macro). The ones the compiler generates
+    /// internally are not included.
+    Block,
+    /// An `unsafe impl`.
+    Impl { def_id: LocalDefId },
+    /// An access to the field of an union, like `my_union.field`.
+    UnionFieldAccess,
+}
+
+/// Number of unsafe items of each kind in a module.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UnsafeCounts {
+    pub fns: usize,
+    pub blocks: usize,
+    pub impls: usize,
+    pub union_field_accesses: usize,
+    /// How many of the items above come from macro expansions.
+    pub from_expansion: usize,
+}
+
+/// Returns all the unsafe functions, blocks and impls of the local crate, as well as the union
+/// field accesses.
+///
+/// To find the union field accesses, the bodies are type-checked if they weren't already, so you
+/// might want to use an [`AnalysisLevel`](crate::AnalysisLevel) which does it beforehand.
+pub fn unsafe_inventory(tcx: TyCtxt<'_>) -> UnsafeReport {
+    let mut visitor = UnsafeVisitor {
+        tcx,
+        body_owner: None,
+        typeck_results: None,
+        report: UnsafeReport::default(),
+    };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+    visitor.report
+}
+
+struct UnsafeVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// The owner of the body being visited and its typeck results.
+    body_owner: Option<LocalDefId>,
+    typeck_results: Option<&'tcx TypeckResults<'tcx>>,
+    report: UnsafeReport,
+}
+
+impl<'tcx> UnsafeVisitor<'tcx> {
+    fn add(&mut self, kind: UnsafeKind, hir_id: HirId, span: Span) {
+        // The outermost expansion is the one called from the user code.
+        let macro_name = span
+            .macro_backtrace()
+            .last()
+            .and_then(|expn_data| match expn_data.kind {
+                ExpnKind::Macro(_, name) => Some(name.to_string()),
+                _ => None,
+            });
+        let module = self.tcx.parent_module(hir_id);
+        let enclosing_fn = match kind {
+            UnsafeKind::Block | UnsafeKind::UnionFieldAccess => self.body_owner.and_then(|owner| {
+                let root = self.tcx.typeck_root_def_id(owner.to_def_id());
+                matches!(self.tcx.def_kind(root), DefKind::Fn | DefKind::AssocFn)
+                    .then(|| root.expect_local())
+            }),
+            UnsafeKind::Fn { .. } | UnsafeKind::Impl { .. } => None,
+        };
+
+        let counts = self
+            .report
+            .per_module
+            .entry(qualified_name(self.tcx, module.to_def_id()))
+            .or_default();
+        match kind {
+            UnsafeKind::Fn { .. } => counts.fns += 1,
+            UnsafeKind::Block => counts.blocks += 1,
+            UnsafeKind::Impl { .. } => counts.impls += 1,
+            UnsafeKind::UnionFieldAccess => counts.union_field_accesses += 1,
+        }
+        if span.from_expansion() {
+            counts.from_expansion += 1;
+        }
+        self.report.items.push(UnsafeItem {
+            kind,
+            span: span.source_callsite(),
+            macro_name,
+            module,
+            enclosing_fn,
+        });
+    }
+
+    fn add_fn(&mut self, def_id: LocalDefId, hir_id: HirId, span: Span, abi: Abi) {
+        self.add(
+            UnsafeKind::Fn {
+                def_id,
+                is_extern: abi != Abi::Rust,
+            },
+            hir_id,
+            span,
+        );
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for UnsafeVisitor<'tcx> {
+    // `visit_all_item_likes_in_crate` already goes through all the items.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_nested_body(&mut self, body_id: BodyId) {
+        let old_body_owner = self
+            .body_owner
+            .replace(self.tcx.hir().body_owner_def_id(body_id));
+        let old_typeck_results = self.typeck_results.replace(self.tcx.typeck_body(body_id));
+        self.visit_body(self.tcx.hir().body(body_id));
+        self.body_owner = old_body_owner;
+        self.typeck_results = old_typeck_results;
+    }
+
+    fn visit_item(&mut self, item: &'tcx Item<'tcx>) {
+        match &item.kind {
+            ItemKind::Fn(sig, ..) if sig.header.unsafety == Unsafety::Unsafe => {
+                self.add_fn(
+                    item.owner_id.def_id,
+                    item.hir_id(),
+                    item.span,
+                    sig.header.abi,
+                );
+            }
+            ItemKind::Impl(impl_) if impl_.unsafety == Unsafety::Unsafe => {
+                self.add(
+                    UnsafeKind::Impl {
+                        def_id: item.owner_id.def_id,
+                    },
+                    item.hir_id(),
+                    item.span,
+                );
+            }
+            _ => {}
+        }
+        intravisit::walk_item(self, item);
+    }
+
+    fn visit_impl_item(&mut self, item: &'tcx ImplItem<'tcx>) {
+        match &item.kind {
+            ImplItemKind::Fn(sig, _) if sig.header.unsafety == Unsafety::Unsafe => {
+                self.add_fn(
+                    item.owner_id.def_id,
+                    item.hir_id(),
+                    item.span,
+                    sig.header.abi,
+                );
+            }
+            _ => {}
+        }
+        intravisit::walk_impl_item(self, item);
+    }
+
+    fn visit_trait_item(&mut self, item: &'tcx TraitItem<'tcx>) {
+        match &item.kind {
+            TraitItemKind::Fn(sig, _) if sig.header.unsafety == Unsafety::Unsafe => {
+                self.add_fn(
+                    item.owner_id.def_id,
+                    item.hir_id(),
+                    item.span,
+                    sig.header.abi,
+                );
+            }
+            _ => {}
+        }
+        intravisit::walk_trait_item(self, item);
+    }
+
+    fn visit_foreign_item(&mut self, item: &'tcx ForeignItem<'tcx>) {
+        // Calling any function declared in an `extern` block is unsafe.
+        if let ForeignItemKind::Fn(..) = item.kind {
+            self.add(
+                UnsafeKind::Fn {
+                    def_id: item.owner_id.def_id,
+                    is_extern: true,
+                },
+                item.hir_id(),
+                item.span,
+            );
+        }
+        intravisit::walk_foreign_item(self, item);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        match expr.kind {
+            ExprKind::Block(block, _)
+                if block.rules == BlockCheckMode::UnsafeBlock(UnsafeSource::UserProvided) =>
+            {
+                self.add(UnsafeKind::Block, expr.hir_id, block.span);
+            }
+            ExprKind::Field(base, _) => {
+                let is_union = self.typeck_results.map_or(false, |typeck_results| {
+                    typeck_results.expr_ty_adjusted(base).is_union()
+                });
+                if is_union {
+                    self.add(UnsafeKind::UnionFieldAccess, expr.hir_id, expr.span);
+                }
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,253 @@
+//! Helpers to find the items of the crate which aren't used, including the public ones which
+//! aren't covered by the `dead_code` lint.
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::{ItemKind, Node};
+use rustc_middle::ty::{DefIdTree, TyCtxt};
+use rustc_session::lint::builtin::DEAD_CODE;
+use rustc_session::lint::Level;
+use rustc_span::symbol::sym;
+use rustc_span::Span;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::paths::qualified_name;
+use crate::test_discovery::test_functions;
+
+/// Options of [`unused_items`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnusedOptions {
+    /// Only report the items of the public API of the crate, which is what the `dead_code` lint
+    /// doesn't cover.
+    pub public_only: bool,
+}
+
+/// Returned by [`unused_items`].
+#[derive(Clone, Debug, Default)]
+pub struct UnusedReport {
+    /// The items, in the order of their definition.
+    pub items: Vec<ItemUsage>,
+}
+
+impl UnusedReport {
+    /// Returns the items which have the given usage.
+    pub fn with_usage(&self, usage: Usage) -> impl Iterator<Item = &ItemUsage> {
+        self.items.iter().filter(move |item| item.usage == usage)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ItemUsage {
+    pub def_id: LocalDefId,
+    /// See [`qualified_name`].
+    pub name: String,
+    pub kind: DefKind,
+    /// Whether the item is part of the public API of the crate.
+    pub is_public: bool,
+    pub usage: Usage,
+    /// Span of the whole definition.
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Usage {
+    /// The item is used by the code of the crate which isn't test code.
+    UsedInternally,
+    /// The item is only used by the tests.
+    UsedOnlyInTests,
+    /// Nothing in the crate uses the item. If it's public, it may still be used by other crates.
+    Unused,
+}
+
+/// Classifies the items of the crate depending on where they're used.
+///
+/// The references to the items are found with
+/// [`find_references`](crate::search::find_references), ignoring the imports and the references
+/// from the item itself (like a recursive call or a method of the impl of a struct using the
+/// struct). The private items which aren't referenced but which the liveness analysis of the
+/// `dead_code` lint considers used (like through a trait) are [`Usage::UsedInternally`].
+///
+/// Are not reported:
+/// - the items for which the `dead_code` lint is allowed (like with `#[allow(dead_code)]`),
+/// - the items which are exported to other languages (like with `#[no_mangle]`),
+/// - the `main` function,
+/// - the items of the trait impls, which are used through the trait,
+/// - the tests and the rest of the test code (the test functions and what's in a module with a
+///   `#[cfg(test)]` attribute),
+/// - the items generated by a macro, the imports, the fields and the variants.
+///
+/// The tests are only there if the crate has been compiled in test mode (see
+/// [`Config::test`](crate::Config::test)), otherwise the items used only in the tests are
+/// reported as [`Usage::Unused`].
+pub fn unused_items(tcx: TyCtxt<'_>, opts: UnusedOptions) -> UnusedReport {
+    let mut tests = TestCode::new(tcx);
+    let effective_visibilities = tcx.effective_visibilities(());
+    let entry_fn = tcx.entry_fn(()).map(|(def_id, _)| def_id);
+    let candidates = tcx
+        .hir_crate_items(())
+        .definitions()
+        .filter(|&def_id| {
+            is_candidate(tcx, def_id)
+                && Some(def_id.to_def_id()) != entry_fn
+                && (!opts.public_only || effective_visibilities.is_exported(def_id))
+                && !tests.contains(def_id)
+        })
+        .collect::<HashSet<_>>();
+
+    let mut used = HashSet::new();
+    let mut used_in_tests = HashSet::new();
+    crate::search::for_each_reference(tcx, |def_id, hir_id, _, _| {
+        let Some(def_id) = def_id.as_local().filter(|def_id| candidates.contains(def_id)) else {
+            return;
+        };
+        let owner = hir_id.owner.def_id;
+        if is_import(tcx, owner) || is_within(tcx, owner, def_id) {
+            return;
+        }
+        if tests.contains(owner) {
+            used_in_tests.insert(def_id);
+        } else {
+            used.insert(def_id);
+        }
+    });
+
+    let (live_symbols, _) = tcx.live_symbols_and_ignored_derived_traits(());
+    let mut items = candidates
+        .into_iter()
+        .map(|def_id| {
+            let is_public = effective_visibilities.is_exported(def_id);
+            let usage = if used.contains(&def_id) {
+                Usage::UsedInternally
+            } else if used_in_tests.contains(&def_id) {
+                Usage::UsedOnlyInTests
+            } else if !effective_visibilities.is_reachable(def_id) && live_symbols.contains(&def_id)
+            {
+                Usage::UsedInternally
+            } else {
+                Usage::Unused
+            };
+            ItemUsage {
+                def_id,
+                name: qualified_name(tcx, def_id.to_def_id()),
+                kind: tcx.def_kind(def_id),
+                is_public,
+                usage,
+                span: tcx.def_span(def_id),
+            }
+        })
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| item.span.lo());
+    UnusedReport { items }
+}
+
+fn is_candidate(tcx: TyCtxt<'_>, def_id: LocalDefId) -> bool {
+    let kind = tcx.def_kind(def_id);
+    let is_item = match kind {
+        DefKind::Fn
+        | DefKind::Const
+        | DefKind::Static(_)
+        | DefKind::Struct
+        | DefKind::Enum
+        | DefKind::Union
+        | DefKind::Trait
+        | DefKind::TraitAlias
+        | DefKind::TyAlias => true,
+        DefKind::AssocFn | DefKind::AssocConst | DefKind::AssocTy => {
+            match tcx.def_kind(tcx.local_parent(def_id)) {
+                DefKind::Trait => true,
+                DefKind::Impl => tcx.impl_trait_ref(tcx.local_parent(def_id)).is_none(),
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+    if !is_item || tcx.def_span(def_id).from_expansion() {
+        return false;
+    }
+    let hir_id = tcx.hir().local_def_id_to_hir_id(def_id);
+    if tcx.lint_level_at_node(DEAD_CODE, hir_id).0 == Level::Allow {
+        return false;
+    }
+    match kind {
+        DefKind::Fn | DefKind::AssocFn | DefKind::Static(_) => {
+            !tcx.codegen_fn_attrs(def_id).contains_extern_indicator()
+        }
+        _ => true,
+    }
+}
+
+fn is_import(tcx: TyCtxt<'_>, def_id: LocalDefId) -> bool {
+    matches!(
+        tcx.hir().find_by_def_id(def_id),
+        Some(Node::Item(item)) if matches!(item.kind, ItemKind::Use(..))
+    )
+}
+
+/// Whether `def_id` is `item`, is in `item` or is in an inherent impl of `item`.
+fn is_within(tcx: TyCtxt<'_>, def_id: LocalDefId, item: LocalDefId) -> bool {
+    let mut current = Some(def_id);
+    while let Some(def_id) = current {
+        if def_id == item || impl_self_ty(tcx, def_id) == Some(item.to_def_id()) {
+            return true;
+        }
+        current = tcx.opt_local_parent(def_id);
+    }
+    false
+}
+
+/// Returns the type implemented by an inherent impl.
+fn impl_self_ty(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Option<DefId> {
+    if tcx.def_kind(def_id) != DefKind::Impl || tcx.impl_trait_ref(def_id).is_some() {
+        return None;
+    }
+    tcx.type_of(def_id).ty_adt_def().map(|adt| adt.did())
+}
+
+/// Finds whether the definitions are in the test code, with a cache.
+struct TestCode<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    functions: HashSet<LocalDefId>,
+    cache: HashMap<LocalDefId, bool>,
+}
+
+impl<'tcx> TestCode<'tcx> {
+    fn new(tcx: TyCtxt<'tcx>) -> Self {
+        TestCode {
+            tcx,
+            functions: test_functions(tcx)
+                .into_iter()
+                .map(|(def_id, _)| def_id)
+                .collect(),
+            cache: HashMap::new(),
+        }
+    }
+
+    fn contains(&mut self, def_id: LocalDefId) -> bool {
+        if let Some(&is_test) = self.cache.get(&def_id) {
+            return is_test;
+        }
+        let is_test = self.functions.contains(&def_id)
+            || self
+                .tcx
+                .has_attr(def_id.to_def_id(), sym::rustc_test_marker)
+            || self.has_cfg_test(def_id)
+            || self
+                .tcx
+                .opt_local_parent(def_id)
+                .map_or(false, |parent| self.contains(parent));
+        self.cache.insert(def_id, is_test);
+        is_test
+    }
+
+    /// Whether the definition has a `#[cfg(test)]` attribute.
+    fn has_cfg_test(&self, def_id: LocalDefId) -> bool {
+        let hir = self.tcx.hir();
+        hir.attrs(hir.local_def_id_to_hir_id(def_id))
+            .iter()
+            .filter(|attr| attr.has_name(sym::cfg))
+            .filter_map(|attr| attr.meta_item_list())
+            .flatten()
+            .any(|item| item.has_name(sym::test))
+    }
+}
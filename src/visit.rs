@@ -0,0 +1,127 @@
+//! Closure-based helpers to go through the items, the functions and the expressions of the crate
+//! without writing a whole `Visitor`.
+
+use rustc_hir::intravisit::{self, FnKind, Visitor};
+use rustc_hir::{Body, BodyId, Expr, FnDecl, HirId, Item};
+use rustc_middle::hir::nested_filter;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::LocalDefId;
+use rustc_span::Span;
+
+use std::ops::ControlFlow;
+
+use crate::expansion::{expansion_info, ExpansionFilter};
+
+/// Calls `callback` on every item of the crate, including the ones defined inside other items
+/// (like a function declared in the body of another one). The associated items of traits and
+/// impls aren't `Item`s so they are not included: use [`for_each_fn`] to get the methods.
+pub fn for_each_item<'tcx, F: FnMut(&'tcx Item<'tcx>)>(tcx: TyCtxt<'tcx>, mut callback: F) {
+    // `hir_crate_items` contains the nested items as well.
+    for item_id in tcx.hir().items() {
+        callback(tcx.hir().item(item_id));
+    }
+}
+
+/// Like [`for_each_item`] but only calls `callback` on the items kept by `filter`, depending on
+/// whether they were generated by a macro (see [`expansion_info`]). With
+/// [`ExpansionFilter::SkipGenerated`], the items written inside a generated item (like the
+/// functions given to a macro which wraps them in a module) are still included.
+pub fn for_each_item_filtered<'tcx, F: FnMut(&'tcx Item<'tcx>)>(
+    tcx: TyCtxt<'tcx>,
+    filter: ExpansionFilter,
+    mut callback: F,
+) {
+    for_each_item(tcx, |item| {
+        if filter.includes(&expansion_info(tcx, item.owner_id.to_def_id())) {
+            callback(item);
+        }
+    });
+}
+
+/// Calls `callback` on every function of the crate which has a body with its declaration and its
+/// body: free functions, methods of impls, methods of traits with a default implementation and
+/// closures. The functions defined inside other ones are included too.
+///
+/// Use `tcx.def_kind(def_id)` to know which kind of function it is.
+pub fn for_each_fn<'tcx, F: FnMut(LocalDefId, &'tcx FnDecl<'tcx>, &'tcx Body<'tcx>)>(
+    tcx: TyCtxt<'tcx>,
+    callback: F,
+) {
+    let mut visitor = FnVisitor { tcx, callback };
+    tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+}
+
+/// Calls `callback` on every expression of the body `body_id`, parents before their children,
+/// until it returns `ControlFlow::Break`. The bodies of the closures are visited as they are part
+/// of the expression but not the ones of the nested items.
+///
+/// Returns the value of the `ControlFlow::Break` if there was one.
+pub fn for_each_expr_in_body<'tcx, B, F: FnMut(&'tcx Expr<'tcx>) -> ControlFlow<B>>(
+    tcx: TyCtxt<'tcx>,
+    body_id: BodyId,
+    callback: F,
+) -> Option<B> {
+    let mut visitor = ExprVisitor {
+        tcx,
+        callback,
+        result: None,
+    };
+    visitor.visit_body(tcx.hir().body(body_id));
+    visitor.result
+}
+
+struct FnVisitor<'tcx, F> {
+    tcx: TyCtxt<'tcx>,
+    callback: F,
+}
+
+impl<'tcx, F: FnMut(LocalDefId, &'tcx FnDecl<'tcx>, &'tcx Body<'tcx>)> Visitor<'tcx>
+    for FnVisitor<'tcx, F>
+{
+    // `visit_all_item_likes_in_crate` already goes through all the items, visiting the nested
+    // ones here would give them twice.
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_fn(
+        &mut self,
+        kind: FnKind<'tcx>,
+        decl: &'tcx FnDecl<'tcx>,
+        body_id: BodyId,
+        _: Span,
+        hir_id: HirId,
+    ) {
+        let def_id = self.tcx.hir().local_def_id(hir_id);
+        (self.callback)(def_id, decl, self.tcx.hir().body(body_id));
+        intravisit::walk_fn(self, kind, decl, body_id, hir_id);
+    }
+}
+
+struct ExprVisitor<'tcx, B, F> {
+    tcx: TyCtxt<'tcx>,
+    callback: F,
+    result: Option<B>,
+}
+
+impl<'tcx, B, F: FnMut(&'tcx Expr<'tcx>) -> ControlFlow<B>> Visitor<'tcx>
+    for ExprVisitor<'tcx, B, F>
+{
+    type NestedFilter = nested_filter::OnlyBodies;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx Expr<'tcx>) {
+        if self.result.is_some() {
+            return;
+        }
+        match (self.callback)(expr) {
+            ControlFlow::Break(value) => self.result = Some(value),
+            ControlFlow::Continue(()) => intravisit::walk_expr(self, expr),
+        }
+    }
+}
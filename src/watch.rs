@@ -0,0 +1,195 @@
+//! Helpers to analyze a crate again each time one of its files changes, for example to lint the
+//! files as they're saved.
+//!
+//! The files are polled from a background thread, comparing their modification times, so there's
+//! no need for a file-system notification library.
+
+use rustc_middle::ty::TyCtxt;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::marker;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::file_loader::RecordingFileLoader;
+use crate::source_files::{source_files, SourceFileOrigin};
+use crate::{Config, Error};
+
+/// Options of [`watch_with_options`].
+#[derive(Clone, Copy, Debug)]
+pub struct WatchOptions {
+    /// How often the modification times of the files are checked. 500 milliseconds by default.
+    pub poll_interval: Duration,
+    /// Once a change is seen, the crate is analyzed when the files haven't changed for this
+    /// duration, so that saving several files at once only gives one run. 100 milliseconds by
+    /// default.
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(500),
+            debounce: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Returned by [`watch`], to stop watching the files.
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Stops watching the files and waits for the current run to finish, so `on_result` isn't
+    /// called anymore once it returns. Dropping the handle stops watching the files too, without
+    /// waiting.
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // The panics of the callbacks are already given to `on_result`, or are the ones of
+            // `on_result` itself, which stopped the thread.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Same as [`watch_with_options`] with the default options.
+pub fn watch<T, F, R>(rustc_args: &[String], callback: F, on_result: R) -> WatchHandle
+where
+    T: marker::Send + 'static,
+    F: Fn(TyCtxt<'_>) -> T + marker::Send + Sync + 'static,
+    R: FnMut(Result<T, Error>) + marker::Send + 'static,
+{
+    watch_with_options(rustc_args, WatchOptions::default(), callback, on_result)
+}
+
+/// Analyzes the crate like [`with_tyctxt`](crate::with_tyctxt) from a background thread, and
+/// again each time one of its files changes, giving the result of each run to `on_result`.
+///
+/// The files watched are the ones read by the compiler (the crate root, the modules and the
+/// files included with `include!`), the ones of [`source_files`] if the compilation succeeded,
+/// and the files of the modules which couldn't be found, so creating them triggers a run. If a run
+/// fails, the files of the previous runs are still watched as well.
+pub fn watch_with_options<T, F, R>(
+    rustc_args: &[String],
+    options: WatchOptions,
+    callback: F,
+    mut on_result: R,
+) -> WatchHandle
+where
+    T: marker::Send + 'static,
+    F: Fn(TyCtxt<'_>) -> T + marker::Send + Sync + 'static,
+    R: FnMut(Result<T, Error>) + marker::Send + 'static,
+{
+    let rustc_args = rustc_args.to_vec();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = thread::spawn({
+        let stop = Arc::clone(&stop);
+        move || {
+            let mut watched = BTreeSet::new();
+            while !stop.load(Ordering::Relaxed) {
+                let loaded = Arc::new(Mutex::new(BTreeSet::new()));
+                let result = Config::new(&rustc_args)
+                    .file_loader(Box::new(RecordingFileLoader(Arc::clone(&loaded))))
+                    .with_tyctxt(|tcx| {
+                        let files = source_files(tcx)
+                            .into_iter()
+                            .filter(|file| file.origin != SourceFileOrigin::Anonymous)
+                            .filter_map(|file| file.path)
+                            .collect::<Vec<_>>();
+                        (callback(tcx), files)
+                    });
+                let mut loaded = mem::take(&mut *loaded.lock().unwrap());
+                let result = match result {
+                    Ok((value, files)) => {
+                        loaded.extend(files);
+                        watched = loaded;
+                        Ok(value)
+                    }
+                    Err(err) => {
+                        watched.append(&mut loaded);
+                        Err(err)
+                    }
+                };
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                on_result(result);
+                if !wait_for_change(&watched, options, &stop) {
+                    return;
+                }
+            }
+        }
+    });
+    WatchHandle {
+        stop,
+        thread: Some(thread),
+    }
+}
+
+/// Waits until one of the `files` changes and then until they stop changing. Returns `false` if
+/// the watch was stopped in the meantime.
+fn wait_for_change(files: &BTreeSet<PathBuf>, options: WatchOptions, stop: &AtomicBool) -> bool {
+    let mut times = modification_times(files);
+    loop {
+        if !sleep(options.poll_interval, stop) {
+            return false;
+        }
+        let new_times = modification_times(files);
+        if new_times != times {
+            times = new_times;
+            break;
+        }
+    }
+    loop {
+        if !sleep(options.debounce, stop) {
+            return false;
+        }
+        let new_times = modification_times(files);
+        if new_times == times {
+            return true;
+        }
+        times = new_times;
+    }
+}
+
+/// Sleeps for `duration` by small steps, returns `false` if the watch was stopped in the
+/// meantime.
+fn sleep(duration: Duration, stop: &AtomicBool) -> bool {
+    const STEP: Duration = Duration::from_millis(10);
+    let mut remaining = duration;
+    while remaining > Duration::ZERO {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let step = remaining.min(STEP);
+        thread::sleep(step);
+        remaining -= step;
+    }
+    !stop.load(Ordering::Relaxed)
+}
+
+/// The modification times of `files`, `None` for the ones which don't exist.
+fn modification_times(files: &BTreeSet<PathBuf>) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
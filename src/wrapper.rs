@@ -0,0 +1,155 @@
+//! Helpers to run an analysis from a `RUSTC_WRAPPER`, so cargo computes the `rustc` arguments
+//! for you.
+//!
+//! Create a small binary calling [`run`]:
+//!
+//! ```ignore (needs-rustc-private)
+//! #![feature(rustc_private)]
+//!
+//! fn main() {
+//!     rustc_tools::wrapper::run(|tcx| tcx.hir().items().count());
+//! }
+//! ```
+//!
+//! Then run cargo with it:
+//!
+//! ```text
+//! RUSTC_WRAPPER=path/to/the/binary RUSTC_TOOLS_OUTPUT=results.json cargo check
+//! ```
+//!
+//! and read the results with [`read_results`]. Note that cargo doesn't run the compiler (and so
+//! the wrapper) on crates which are already up-to-date, so you might need to run `cargo clean -p`
+//! (or to touch the crate files) before.
+
+use rustc_middle::ty::TyCtxt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::marker;
+use std::path::Path;
+use std::process::{self, Command};
+
+use crate::sysroot::detect_sysroot;
+use crate::{Config, DiagnosticsBuffer, Error};
+
+/// Environment variable containing the path of the file in which the results are written.
+pub const OUTPUT_ENV: &str = "RUSTC_TOOLS_OUTPUT";
+/// Environment variable containing the name of the crate to analyze (as given to
+/// `--crate-name`). If it's not set, all the workspace members selected by cargo are analyzed.
+pub const CRATE_NAME_ENV: &str = "RUSTC_TOOLS_CRATE_NAME";
+
+/// To be called from the `main` function of the binary set as `RUSTC_WRAPPER`.
+///
+/// It always runs the real `rustc` with the arguments given by cargo. Then, if the invocation
+/// targets a crate to analyze (a workspace member selected by cargo and not a dependency nor a
+/// build script), `callback` is called with the same arguments and its result is appended to the
+/// file in the [`OUTPUT_ENV`] environment variable.
+///
+/// The compiler diagnostics are only emitted by the real `rustc` and the analysis doesn't write
+/// any file, so the outputs expected by cargo (like with `--emit=dep-info,metadata`) are the ones
+/// of the real `rustc`. It never returns: the process exits with the real `rustc` exit code, or
+/// with `1` if the analysis failed.
+pub fn run<T: Serialize + marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    callback: F,
+) -> ! {
+    // The first argument is the path of this binary and the second one the path of `rustc`.
+    let mut args = env::args().skip(1);
+    let Some(rustc) = args.next() else {
+        eprintln!("error: this binary is meant to be used as `RUSTC_WRAPPER`");
+        process::exit(1);
+    };
+    let args = args.collect::<Vec<_>>();
+
+    let status = match Command::new(&rustc).args(&args).status() {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("error: failed to run `{rustc}`: {e}");
+            process::exit(1);
+        }
+    };
+    if !status.success() || !should_analyze(&args) {
+        process::exit(status.code().unwrap_or(1));
+    }
+    if let Err(e) = analyze(&rustc, args, callback) {
+        eprintln!("error: {e}");
+        process::exit(1);
+    }
+    process::exit(0);
+}
+
+/// Reads the results written by [`run`] into `path`. Each item is the name of the analyzed crate
+/// and the value returned by the callback.
+pub fn read_results<T: DeserializeOwned>(path: &Path) -> Result<Vec<(String, T)>, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::Wrapper(format!("cannot read `{}`: {}", path.display(), e)))?;
+    content
+        .lines()
+        .map(|line| serde_json::from_str(line).map_err(|e| Error::Wrapper(e.to_string())))
+        .collect()
+}
+
+fn should_analyze(args: &[String]) -> bool {
+    // Cargo sets this variable for the packages selected on its command line only.
+    if env::var_os("CARGO_PRIMARY_PACKAGE").is_none() {
+        return false;
+    }
+    // Cargo also runs the compiler to get information about it, like `rustc -vV` or
+    // `rustc - --crate-name ___ --print=file-names`.
+    if args
+        .iter()
+        .any(|arg| arg.starts_with("--print") || arg == "-vV" || arg == "-")
+    {
+        return false;
+    }
+    let Some(crate_name) = crate_name(args) else { return false };
+    // The build scripts of the selected packages are selected as well.
+    if crate_name.starts_with("build_script_") {
+        return false;
+    }
+    match env::var(CRATE_NAME_ENV) {
+        Ok(expected) => expected.replace('-', "_") == crate_name,
+        Err(_) => true,
+    }
+}
+
+fn crate_name(args: &[String]) -> Option<&str> {
+    args.iter().enumerate().find_map(|(pos, arg)| {
+        if arg == "--crate-name" {
+            args.get(pos + 1).map(String::as_str)
+        } else {
+            arg.strip_prefix("--crate-name=")
+        }
+    })
+}
+
+fn analyze<T: Serialize + marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+    rustc: &str,
+    mut args: Vec<String>,
+    callback: F,
+) -> Result<(), Error> {
+    let crate_name = crate_name(&args).unwrap_or_default().to_owned();
+    // Cargo doesn't pass `--sysroot`, so we want the one of the `rustc` it uses.
+    if !args.iter().any(|arg| arg.starts_with("--sysroot")) {
+        let sysroot = detect_sysroot(rustc, true)?;
+        args.extend(["--sysroot".to_owned(), sysroot.display().to_string()]);
+    }
+    let result = Config::new(&args)
+        .diagnostics_buffer(DiagnosticsBuffer::new())
+        .with_tyctxt(callback)?;
+
+    let Ok(output) = env::var(OUTPUT_ENV) else { return Ok(()) };
+    let mut line = serde_json::to_string(&(crate_name, result))
+        .map_err(|e| Error::Wrapper(format!("cannot serialize the result: {e}")))?;
+    line.push('\n');
+    // Several crates can be analyzed at the same time so each result is written at once.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&output)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| Error::Wrapper(format!("cannot write into `{output}`: {e}")))
+}
@@ -0,0 +1,80 @@
+#![feature(rustc_private)]
+
+use rustc_tools::async_fns::async_fn_info;
+use rustc_tools::visit::for_each_fn;
+use rustc_tools::{AnalysisLevel, Config};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn mutex_guard_held_across_await() {
+    let source = "
+use std::sync::Mutex;
+
+async fn tick() {}
+
+pub async fn locked(counter: &Mutex<u32>) {
+    let mut guard = counter.lock().unwrap();
+    tick().await;
+    *guard += 1;
+}
+
+pub async fn unlocked(counter: &Mutex<u32>) {
+    *counter.lock().unwrap() += 1;
+    tick().await;
+}
+";
+    let res = Config::new(&args(&["--crate-type=lib", "--edition=2021"]))
+        .source_code(source)
+        .analysis_level(AnalysisLevel::Full)
+        .with_tyctxt(|tcx| {
+            let mut infos = Vec::new();
+            for_each_fn(tcx, |def_id, _, _| {
+                if tcx.is_closure(def_id.to_def_id()) {
+                    return;
+                }
+                if let Some(info) = async_fn_info(tcx, def_id) {
+                    let snippet = info.await_points.first().map(|point| {
+                        let span = point.span.source_callsite();
+                        tcx.sess.source_map().span_to_snippet(span).unwrap()
+                    });
+                    let await_points = info
+                        .await_points
+                        .into_iter()
+                        .map(|point| point.held_types)
+                        .collect::<Vec<_>>();
+                    infos.push((
+                        tcx.item_name(def_id.to_def_id()).to_string(),
+                        await_points,
+                        snippet,
+                        info.future_size.is_ok(),
+                    ));
+                }
+            });
+            infos
+        });
+    let infos = res.unwrap();
+    assert_eq!(infos.len(), 3, "{infos:?}");
+    let (_, await_points, ..) = infos.iter().find(|(name, ..)| name == "tick").unwrap();
+    assert!(await_points.is_empty());
+    let (_, await_points, snippet, has_size) =
+        infos.iter().find(|(name, ..)| name == "locked").unwrap();
+    assert_eq!(await_points.len(), 1);
+    assert!(
+        await_points[0]
+            .iter()
+            .any(|ty| ty == "std::sync::MutexGuard<'_, u32>"),
+        "{await_points:?}"
+    );
+    assert_eq!(snippet.as_deref(), Some(".await"));
+    assert!(has_size);
+
+    let (_, await_points, ..) = infos.iter().find(|(name, ..)| name == "unlocked").unwrap();
+    assert_eq!(await_points.len(), 1);
+    assert!(
+        !await_points[0].iter().any(|ty| ty.contains("MutexGuard")),
+        "{await_points:?}"
+    );
+}
@@ -0,0 +1,105 @@
+#![feature(rustc_private)]
+
+use rustc_tools::attrs::{attr_meta_items, doc_comment, has_attr, MetaItemValue};
+use rustc_tools::rustc_hir::def_id::DefId;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::with_tyctxt_from_str;
+
+/// An item with all the flavors of attributes.
+const SOURCE: &str = r##"#![feature(register_tool)]
+#![register_tool(my_tool)]
+
+///   Indented doc comment.
+///
+///     Code block.
+#[doc = r#"Raw "doc" string."#]
+#[cfg_attr(all(), my_tool::skip, doc = "From cfg_attr.")]
+#[cfg_attr(any(), my_tool::never)]
+#[my_tool::config(name = "x", 2, flag, nested(true, 'c'))]
+#[my_tool::config = "second"]
+#[my_tool::config]
+pub struct Documented;
+
+/**
+ * Block
+ *   comment.
+ */
+pub struct Block;
+
+pub struct Bare;
+"##;
+
+fn item(tcx: TyCtxt<'_>, name: &str) -> DefId {
+    tcx.hir()
+        .items()
+        .find(|&id| tcx.hir().item(id).ident.as_str() == name)
+        .unwrap()
+        .owner_id
+        .to_def_id()
+}
+
+fn run<T: Send, F: FnOnce(TyCtxt<'_>) -> T + Send>(callback: F) -> T {
+    with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], callback).unwrap()
+}
+
+#[test]
+fn doc_comments() {
+    let (documented, block, bare) = run(|tcx| {
+        (
+            doc_comment(tcx, item(tcx, "Documented")),
+            doc_comment(tcx, item(tcx, "Block")),
+            doc_comment(tcx, item(tcx, "Bare")),
+        )
+    });
+    assert_eq!(
+        documented.unwrap(),
+        "Indented doc comment.
+
+  Code block.
+Raw \"doc\" string.
+From cfg_attr."
+    );
+    assert_eq!(block.unwrap(), "Block\n  comment.");
+    assert_eq!(bare, None);
+}
+
+#[test]
+fn tool_attributes() {
+    let res = run(|tcx| {
+        let documented = item(tcx, "Documented");
+        [
+            has_attr(tcx, documented, &["my_tool", "skip"]),
+            has_attr(tcx, documented, &["my_tool", "never"]),
+            has_attr(tcx, documented, &["my_tool", "config"]),
+            has_attr(tcx, documented, &["skip"]),
+            has_attr(tcx, item(tcx, "Bare"), &["my_tool", "skip"]),
+        ]
+    });
+    // The condition of the second `cfg_attr` is false.
+    assert_eq!(res, [true, false, true, false, false]);
+}
+
+#[test]
+fn meta_items() {
+    let res = run(|tcx| attr_meta_items(tcx, item(tcx, "Documented"), &["my_tool", "config"]));
+    let path = |name: &str| MetaItemValue::Path(name.to_owned());
+    assert_eq!(
+        res,
+        [
+            MetaItemValue::NameValue {
+                name: "name".to_owned(),
+                value: Box::new(MetaItemValue::Str("x".to_owned())),
+            },
+            MetaItemValue::Int(2),
+            path("flag"),
+            MetaItemValue::List {
+                name: "nested".to_owned(),
+                items: vec![
+                    MetaItemValue::Bool(true),
+                    MetaItemValue::Other("'c'".to_owned())
+                ],
+            },
+            MetaItemValue::Str("second".to_owned()),
+        ]
+    );
+}
@@ -0,0 +1,104 @@
+#![feature(rustc_private)]
+
+use rustc_tools::auto_traits::{auto_trait_audit, AutoTrait, AutoTraitVerdict, ChainLink};
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = r#"
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct Shared {
+    pub count: Rc<u32>,
+}
+
+pub struct Counter {
+    pub cell: RefCell<u32>,
+}
+
+pub struct Raw {
+    pub ptr: *const u8,
+}
+
+pub struct Plain {
+    pub value: u32,
+}
+"#;
+
+#[test]
+fn rc_refcell_and_raw_pointer_fields() {
+    let traits = [AutoTrait::Send, AutoTrait::Sync, AutoTrait::Unpin];
+    let verdicts = with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], |tcx| {
+        auto_trait_audit(tcx, &traits)
+    })
+    .unwrap();
+    let names: Vec<_> = verdicts
+        .iter()
+        .map(|verdict| verdict.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "rust_out::Shared",
+            "rust_out::Counter",
+            "rust_out::Raw",
+            "rust_out::Plain"
+        ]
+    );
+    let explanations: Vec<_> = verdicts
+        .iter()
+        .map(|verdict| traits.map(|auto_trait| verdict.explanation(auto_trait)))
+        .collect();
+    assert_eq!(
+        explanations,
+        [
+            [
+                Some("rust_out::Shared -> count: `std::rc::Rc<u32>` isn't Send (explicitly)"),
+                Some("rust_out::Shared -> count: `std::rc::Rc<u32>` isn't Sync (explicitly)"),
+                None,
+            ],
+            [
+                None,
+                Some(
+                    "rust_out::Counter -> cell: `std::cell::RefCell<u32>` isn't Sync (explicitly)"
+                ),
+                None,
+            ],
+            [
+                Some("rust_out::Raw -> ptr: `*const u8` isn't Send (explicitly)"),
+                Some("rust_out::Raw -> ptr: `*const u8` isn't Sync (explicitly)"),
+                None,
+            ],
+            [None, None, None],
+        ]
+        .map(|row| row.map(|explanation| explanation.map(str::to_owned)))
+    );
+    // `RefCell<T>` is `Send` when `T: Send`, only its `Sync` impl is negative.
+    assert_eq!(
+        verdicts[1].traits,
+        [
+            AutoTraitVerdict {
+                auto_trait: AutoTrait::Send,
+                implemented: true,
+                chain: Vec::new(),
+            },
+            AutoTraitVerdict {
+                auto_trait: AutoTrait::Sync,
+                implemented: false,
+                chain: vec![ChainLink {
+                    step: "cell".to_owned(),
+                    ty: "std::cell::RefCell<u32>".to_owned(),
+                    auto_trait: AutoTrait::Sync,
+                    negative_impl: true,
+                }],
+            },
+            AutoTraitVerdict {
+                auto_trait: AutoTrait::Unpin,
+                implemented: true,
+                chain: Vec::new(),
+            },
+        ]
+    );
+    assert!(verdicts
+        .iter()
+        .all(|verdict| verdict.assumptions.is_empty()));
+}
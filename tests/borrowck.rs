@@ -0,0 +1,92 @@
+#![feature(rustc_private)]
+
+use rustc_tools::borrowck::borrowck_facts;
+use rustc_tools::rustc_ast::Mutability;
+use rustc_tools::rustc_middle::mir::Location;
+use rustc_tools::rustc_span::BytePos;
+use rustc_tools::{AnalysisLevel, Config};
+
+/// A [`BorrowInfo`](rustc_tools::borrowck::BorrowInfo) with its spans as text.
+#[derive(Debug)]
+struct Borrow {
+    snippet: String,
+    place: String,
+    mutability: Mutability,
+    live_at: Vec<Location>,
+    live_range: (BytePos, BytePos),
+    live_snippet: String,
+}
+
+#[test]
+fn shared_then_mutable_borrow() {
+    let source = "pub fn shared_then_mut() -> usize {
+    let mut v = vec![1, 2];
+    let first = &v;
+    let len = first.len();
+    let last = &mut v;
+    last.push(3);
+    len
+}
+";
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(source)
+        .analysis_level(AnalysisLevel::Full)
+        .borrowck_facts(true)
+        .with_tyctxt(|tcx| {
+            let def_id = tcx.hir().body_owners().next().unwrap();
+            let source_map = tcx.sess.source_map();
+            borrowck_facts(tcx, def_id)
+                .unwrap()
+                .into_iter()
+                .map(|borrow| {
+                    let live_span = borrow.live_span.unwrap();
+                    Borrow {
+                        snippet: source_map.span_to_snippet(borrow.span).unwrap(),
+                        place: borrow.place,
+                        mutability: borrow.mutability,
+                        live_at: borrow.live_at,
+                        live_range: (live_span.lo(), live_span.hi()),
+                        live_snippet: source_map.span_to_snippet(live_span).unwrap(),
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+    let borrows = res.unwrap();
+    // The method call borrows `*last` again.
+    assert_eq!(borrows.len(), 3, "{borrows:?}");
+    let summary = |borrow: &Borrow| {
+        (
+            borrow.snippet.clone(),
+            borrow.place.clone(),
+            borrow.mutability,
+        )
+    };
+    let (shared, mutable) = (&borrows[0], &borrows[1]);
+    assert_eq!(
+        summary(shared),
+        ("&v".to_owned(), "v".to_owned(), Mutability::Not)
+    );
+    assert_eq!(
+        summary(mutable),
+        ("&mut v".to_owned(), "v".to_owned(), Mutability::Mut)
+    );
+    assert_eq!(
+        shared.live_snippet,
+        "first = &v;\n    let len = first.len()"
+    );
+    assert_eq!(mutable.live_snippet, "last = &mut v;\n    last.push(3)");
+    // The shared borrow is dead once `len` is computed, before `v` is borrowed mutably.
+    assert!(shared.live_range.1 < mutable.live_range.0);
+    assert!(shared
+        .live_at
+        .iter()
+        .all(|location| !mutable.live_at.contains(location)));
+    assert_eq!(
+        summary(&borrows[2]),
+        (
+            "last.push(3)".to_owned(),
+            "*last".to_owned(),
+            Mutability::Mut
+        )
+    );
+}
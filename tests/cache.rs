@@ -0,0 +1,47 @@
+#![feature(rustc_private)]
+#![cfg(feature = "serde")]
+
+use rustc_tools::cache::{fingerprint, CachedAnalyzer};
+
+use std::env;
+use std::fs;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn cache_hits_until_a_file_changes() {
+    let dir = env::temp_dir().join(format!("rustc-tools-cache-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    let module = dir.join("module.rs");
+    fs::write(&root, "pub mod module;").unwrap();
+    fs::write(&module, "pub fn a() {}").unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+
+    let cache = CachedAnalyzer::new(dir.join("cache"));
+    let run = || {
+        cache
+            .run_or_load(&args, |tcx| {
+                RUNS.fetch_add(1, Ordering::SeqCst);
+                tcx.hir().items().count()
+            })
+            .unwrap()
+    };
+    let items = run();
+    assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+    // The result is loaded without running the compiler.
+    assert_eq!(run(), items);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+
+    let before = fingerprint(&args).unwrap();
+    fs::write(&module, "pub fn a() {}\npub fn b() {}").unwrap();
+    assert_ne!(fingerprint(&args).unwrap(), before);
+    assert_eq!(run(), items + 1);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+    assert_eq!(run(), items + 1);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,81 @@
+#![feature(rustc_private)]
+
+use rustc_tools::call_graph::{call_graph, CallKind, Callee};
+use rustc_tools::with_tyctxt_from_str;
+
+#[test]
+fn trait_calls_through_generics() {
+    let source = "pub trait Shape {
+    fn sides(&self) -> u32;
+}
+
+pub struct Square;
+
+impl Shape for Square {
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+pub fn generic<S: Shape>(shape: &S) -> u32 {
+    shape.sides()
+}
+
+pub fn object(shape: &dyn Shape) -> u32 {
+    shape.sides()
+}
+
+pub fn concrete() -> u32 {
+    generic(&Square) + object(&Square) + Square.sides()
+}
+";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(source, &args, |tcx| {
+        let graph = call_graph(tcx);
+        let callees = |caller| {
+            graph
+                .callees(caller)
+                .map(|edge| (edge.callee.clone(), edge.kind))
+                .map(|(callee, kind)| match callee {
+                    Callee::Resolved { name, .. } => (Some(name), kind),
+                    Callee::Dynamic { name } => (name.map(|name| format!("dynamic {name}")), kind),
+                })
+                .collect::<Vec<_>>()
+        };
+        let unreachable = graph
+            .unreachable_fns(tcx, &["krate::generic", "krate::object"])
+            .into_iter()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        (
+            callees("krate::generic"),
+            callees("krate::object"),
+            callees("krate::concrete"),
+            unreachable,
+        )
+    });
+    let (generic, object, concrete, unreachable) = res.unwrap();
+    let some = |name: &str| Some(name.to_owned());
+    assert_eq!(
+        generic,
+        [(some("dynamic krate::Shape::sides"), CallKind::MethodCall)]
+    );
+    assert_eq!(
+        object,
+        [(some("dynamic krate::Shape::sides"), CallKind::MethodCall)]
+    );
+    let square_sides = "krate::<impl krate::Shape for krate::Square>::sides";
+    assert_eq!(
+        concrete,
+        [
+            (some("krate::generic"), CallKind::Call),
+            (some("krate::object"), CallKind::Call),
+            (some(square_sides), CallKind::MethodCall),
+        ]
+    );
+    // The dynamic calls can't be followed.
+    assert_eq!(unreachable, [square_sides, "krate::concrete"]);
+}
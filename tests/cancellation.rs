@@ -0,0 +1,51 @@
+#![feature(rustc_private)]
+
+use rustc_tools::{AnalysisLevel, CancellationToken, Config, Error};
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A crate whose analysis takes a few seconds.
+fn big_crate() -> String {
+    (0..20_000)
+        .map(|i| format!("pub fn f{i}(x: u64) -> u64 {{ let v: Vec<u64> = vec![x; 3]; v.iter().map(|y| y * {i}).sum() }}\n"))
+        .collect()
+}
+
+#[test]
+fn cancelled_before_start() {
+    let token = CancellationToken::new();
+    token.cancel();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code("pub fn f() {}")
+        .cancellation_token(token)
+        .with_tyctxt(|_| ());
+    assert!(matches!(res, Err(Error::Cancelled)), "{res:?}");
+}
+
+#[test]
+fn cancelled_during_the_analysis() {
+    let token = CancellationToken::new();
+    let canceller = thread::spawn({
+        let token = token.clone();
+        move || {
+            thread::sleep(Duration::from_millis(200));
+            token.cancel();
+            Instant::now()
+        }
+    });
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(&big_crate())
+        .analysis_level(AnalysisLevel::TypeckBodies)
+        .cancellation_token(token)
+        .with_tyctxt(|_| ());
+    let returned = Instant::now();
+    let cancelled = canceller.join().unwrap();
+    assert!(matches!(res, Err(Error::Cancelled)), "{res:?}");
+    // It returns once the body being type-checked is done, not at the end of the analysis.
+    assert!(
+        returned - cancelled < Duration::from_secs(2),
+        "{:?}",
+        returned - cancelled
+    );
+}
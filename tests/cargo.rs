@@ -0,0 +1,39 @@
+#![feature(rustc_private)]
+
+use rustc_tools::cargo::args_for_package;
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+#[test]
+fn fixture_workspace() {
+    // The dependencies are looked for where cargo writes them, not in `target/debug/deps`.
+    let target_dir = env::temp_dir().join(format!("rustc-tools-cargo-{}", process::id()));
+    env::set_var("CARGO_TARGET_DIR", &target_dir);
+
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workspace");
+    let args = args_for_package(&workspace.join("Cargo.toml"), Some("app")).unwrap();
+    let renamed = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("renamed="))
+        .unwrap_or_else(|| panic!("{args:?}"));
+    assert!(Path::new(renamed).starts_with(&target_dir), "{renamed}");
+    let dependency_dir = format!(
+        "dependency={}",
+        Path::new(renamed).parent().unwrap().display()
+    );
+    assert!(args.contains(&dependency_dir), "{args:?}");
+
+    let crates = with_tyctxt(&args, |tcx| {
+        tcx.crates(())
+            .iter()
+            .map(|&krate| tcx.crate_name(krate).to_string())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+    assert!(crates.contains(&"helper".to_owned()), "{crates:?}");
+    let _ = fs::remove_dir_all(target_dir);
+}
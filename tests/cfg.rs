@@ -0,0 +1,196 @@
+#![feature(rustc_private)]
+
+use std::env;
+use std::fs;
+use std::process;
+
+use rustc_tools::cfgs::{cfg_inventory, cfg_inventory_approximate, CfgReport, CfgSet};
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::{AnalysisLevel, Config, Error};
+
+const SOURCE: &str = "#[cfg(feature = \"foo\")]
+pub fn gated() {}
+
+#[cfg(all(custom, width = \"64\"))]
+pub fn custom() {}
+
+#[cfg(feature = \"unknown\")]
+pub fn unknown() {}
+";
+
+const PLATFORM: &str = "pub fn everywhere() {}
+
+#[cfg(unix)]
+pub fn open() {}
+
+#[cfg(windows)]
+pub fn open() {}
+
+#[cfg(windows)]
+pub mod registry {
+    pub struct Key;
+}
+
+#[cfg(not(unix))]
+pub const SEPARATOR: char = '\\\\';
+";
+
+/// The names of the items of the crate analyzed with `config`.
+fn item_names(config: Config) -> Result<Vec<String>, Error> {
+    config.source_code(SOURCE).with_tyctxt(|tcx| {
+        tcx.hir()
+            .items()
+            .map(|id| tcx.hir().item(id).ident.to_string())
+            .filter(|name| !name.is_empty() && name != "std")
+            .collect()
+    })
+}
+
+fn config() -> Config {
+    Config::new(&["--crate-type=lib".to_owned()])
+}
+
+#[test]
+fn feature_gated_item() {
+    assert!(item_names(config()).unwrap().is_empty());
+    assert_eq!(item_names(config().feature("foo")).unwrap(), ["gated"]);
+    assert_eq!(
+        item_names(config().cfg_flag("custom").cfg_kv("width", "64")).unwrap(),
+        ["custom"]
+    );
+}
+
+#[test]
+fn invalid_cfg_name() {
+    let res = item_names(config().cfg_kv("not valid", "64"));
+    assert!(matches!(res, Err(Error::InvalidCfg(_))), "{res:?}");
+}
+
+#[test]
+fn unexpected_feature() {
+    let collector = DiagnosticsCollector::new();
+    let res = item_names(
+        config()
+            .analysis_level(AnalysisLevel::Full)
+            .collect_diagnostics(collector.clone())
+            .feature("foo")
+            .check_cfg_features(&["foo"]),
+    );
+    assert_eq!(res.unwrap(), ["gated"]);
+    let diagnostics = collector.take();
+    assert_eq!(
+        diagnostics[0].code.as_deref(),
+        Some("unexpected_cfgs"),
+        "{diagnostics:?}"
+    );
+    assert_eq!(diagnostics[0].message, "unexpected `cfg` condition value");
+    // The other one is the summary.
+    assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+}
+
+/// The configurations of a crate built for Linux or for Windows, from the standard library of the
+/// current target.
+fn platforms() -> [CfgSet; 2] {
+    let pair = |name: &str, value: Option<&str>| (name.to_owned(), value.map(str::to_owned));
+    [
+        CfgSet {
+            name: "unix".to_owned(),
+            ..CfgSet::default()
+        },
+        CfgSet {
+            name: "windows".to_owned(),
+            set: vec![pair("windows", None), pair("target_os", Some("windows"))],
+            unset: vec![
+                pair("unix", None),
+                pair("target_os", None),
+                pair("target_family", None),
+            ],
+        },
+    ]
+}
+
+#[test]
+fn unix_and_windows_inventory() {
+    let dir = env::temp_dir().join(format!("rustc-tools-cfg-inventory-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("lib.rs");
+    fs::write(&file, PLATFORM).unwrap();
+    let args = ["--crate-type=lib".to_owned(), file.display().to_string()];
+    let report = cfg_inventory(&args, &platforms());
+    let approximate = cfg_inventory_approximate(&args);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let report = report.unwrap();
+    assert_eq!(report.configs, ["unix", "windows"]);
+    assert!(!report.approximate);
+    let prefix = format!("{}:", file.display());
+    let items: Vec<_> = report
+        .items
+        .iter()
+        .map(|item| {
+            (
+                item.name.as_str(),
+                item.kind.as_str(),
+                item.location.strip_prefix(&prefix).unwrap(),
+                item.configs.join(","),
+                item.cfgs.join(","),
+            )
+        })
+        .collect();
+    // `everywhere` exists in both configurations so it isn't reported, and the two `open`
+    // functions are told apart by their location.
+    assert_eq!(
+        items,
+        [
+            (
+                "lib::SEPARATOR",
+                "constant",
+                "15:1",
+                "windows".to_owned(),
+                "not(unix)".to_owned()
+            ),
+            (
+                "lib::open",
+                "function",
+                "4:1",
+                "unix".to_owned(),
+                "unix".to_owned()
+            ),
+            (
+                "lib::open",
+                "function",
+                "7:1",
+                "windows".to_owned(),
+                "windows".to_owned()
+            ),
+            (
+                "lib::registry",
+                "module",
+                "10:1",
+                "windows".to_owned(),
+                "windows".to_owned()
+            ),
+            (
+                "lib::registry::Key",
+                "struct",
+                "11:5",
+                "windows".to_owned(),
+                "windows".to_owned()
+            ),
+        ]
+    );
+
+    // Without evaluating the `#[cfg]`s, the same items are found, without their configurations.
+    let approximate = approximate.unwrap();
+    assert!(approximate.approximate);
+    assert!(approximate.configs.is_empty());
+    let names_and_cfgs = |report: &CfgReport| {
+        report
+            .items
+            .iter()
+            .map(|item| (item.name.clone(), item.location.clone(), item.cfgs.clone()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names_and_cfgs(&approximate), names_and_cfgs(&report));
+    assert!(approximate.items.iter().all(|item| item.configs.is_empty()));
+}
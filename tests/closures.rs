@@ -0,0 +1,130 @@
+#![feature(rustc_private)]
+
+use rustc_tools::closures::{closure_captures, is_move_closure, CaptureKind};
+use rustc_tools::visit::for_each_fn;
+use rustc_tools::{with_tyctxt_from_str, AnalysisLevel, Config};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Returns the captures of the closures of `source` (as `(place, kind)`) and whether they're
+/// `move` closures, in the order in which they're defined.
+fn captures(source: &str, edition: &str) -> Vec<(Vec<(String, CaptureKind)>, bool)> {
+    Config::new(&args(&["--crate-type=lib", edition]))
+        .source_code(source)
+        .analysis_level(AnalysisLevel::Full)
+        .with_tyctxt(|tcx| {
+            let mut closures = Vec::new();
+            for_each_fn(tcx, |def_id, _, _| {
+                if tcx.is_closure(def_id.to_def_id()) {
+                    let captures = closure_captures(tcx, def_id)
+                        .into_iter()
+                        .map(|capture| (capture.place, capture.kind))
+                        .collect();
+                    closures.push((captures, is_move_closure(tcx, def_id)));
+                }
+            });
+            closures
+        })
+        .unwrap()
+}
+
+fn place(place: &str, kind: CaptureKind) -> (String, CaptureKind) {
+    (place.to_owned(), kind)
+}
+
+#[test]
+fn all_capture_kinds() {
+    let source = "
+pub fn captures(name: String, mut names: Vec<String>, count: &mut u32) {
+    let by_ref = || name.len();
+    by_ref();
+    let mut by_mut_ref = || names.push(String::new());
+    by_mut_ref();
+    let mut by_unique_ref = || *count += 1;
+    by_unique_ref();
+    let by_value = || drop(name);
+    by_value();
+    let len = names.len();
+    let moved = move || len + names.len();
+    moved();
+}
+";
+    assert_eq!(
+        captures(source, "--edition=2021"),
+        [
+            (vec![place("name", CaptureKind::ByRef)], false),
+            (vec![place("names", CaptureKind::ByMutRef)], false),
+            // `*count` is captured, which is written `count`.
+            (vec![place("count", CaptureKind::ByMutRef)], false),
+            (vec![place("name", CaptureKind::ByValue)], false),
+            (
+                vec![
+                    place("len", CaptureKind::ByValue),
+                    place("names", CaptureKind::ByValue),
+                ],
+                true
+            ),
+        ]
+    );
+}
+
+#[test]
+fn disjoint_fields() {
+    let source = "
+pub struct Pair(pub String, pub Vec<u32>);
+
+pub fn fields(mut pair: Pair) {
+    let first = &pair.0;
+    let mut push = || pair.1.push(1);
+    push();
+    let _ = first;
+}
+";
+    // Since the 2021 edition, only the field used by the closure is captured.
+    assert_eq!(
+        captures(source, "--edition=2021"),
+        [(vec![place("pair.1", CaptureKind::ByMutRef)], false)]
+    );
+    // Before, the whole variable is.
+    assert_eq!(
+        captures(
+            "pub fn first(mut pair: (u8, u8)) { let mut c = || pair.1 += 1; c(); }",
+            "--edition=2018"
+        ),
+        [(vec![place("pair", CaptureKind::ByMutRef)], false)]
+    );
+    // And a whole `&mut` reference is captured with a unique borrow to modify what it points to.
+    assert_eq!(
+        captures(
+            "pub fn count(count: &mut u32) { let mut c = || *count += 1; c(); }",
+            "--edition=2018"
+        ),
+        [(vec![place("count", CaptureKind::ByUniqueRef)], false)]
+    );
+}
+
+#[test]
+fn async_block() {
+    let res = with_tyctxt_from_str(
+        "pub fn block(name: String) -> impl std::future::Future<Output = usize> {
+    async move { name.len() }
+}",
+        &args(&["--crate-type=lib", "--edition=2021"]),
+        |tcx| {
+            let mut captures = Vec::new();
+            for_each_fn(tcx, |def_id, _, _| {
+                if tcx.is_closure(def_id.to_def_id()) {
+                    captures.extend(closure_captures(tcx, def_id));
+                    assert!(is_move_closure(tcx, def_id));
+                }
+            });
+            captures
+                .into_iter()
+                .map(|capture| (capture.name, capture.kind))
+                .collect::<Vec<_>>()
+        },
+    );
+    assert_eq!(res.unwrap(), [("name".to_owned(), CaptureKind::ByValue)]);
+}
@@ -0,0 +1,53 @@
+#![feature(rustc_private)]
+
+use rustc_tools::consts::{eval_const, ConstValueRepr};
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::{Config, Error};
+
+const SOURCE: &str = r#"
+pub const NAME: &str = "rustc-tools";
+pub const TABLE: [(u8, char); 2] = [(1, 'a'), (2, 'b')];
+pub const BAD: u8 = [1, 2, 3][3];
+"#;
+
+fn eval(tcx: TyCtxt<'_>, name: &str) -> Result<ConstValueRepr, Error> {
+    let item = tcx
+        .hir()
+        .items()
+        .find(|&id| tcx.hir().item(id).ident.as_str() == name)
+        .unwrap();
+    eval_const(tcx, item.owner_id.to_def_id())
+}
+
+#[test]
+fn eval_consts() {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|tcx| (eval(tcx, "NAME"), eval(tcx, "TABLE"), eval(tcx, "BAD")));
+    let (name, table, bad) = res.unwrap();
+    assert_eq!(name.unwrap(), ConstValueRepr::Str("rustc-tools".to_owned()));
+    assert_eq!(
+        table.unwrap(),
+        ConstValueRepr::Array(vec![
+            ConstValueRepr::Tuple(vec![ConstValueRepr::Uint(1), ConstValueRepr::Char('a')]),
+            ConstValueRepr::Tuple(vec![ConstValueRepr::Uint(2), ConstValueRepr::Char('b')]),
+        ])
+    );
+    match bad {
+        Err(Error::ConstEval(error)) => {
+            assert!(error.contains("index out of bounds"), "{error}")
+        }
+        bad => panic!("{bad:?}"),
+    }
+    // The error is emitted as well.
+    let diagnostics = collector.take();
+    assert!(
+        diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message == "evaluation of constant value failed"),
+        "{diagnostics:?}"
+    );
+}
@@ -0,0 +1,57 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::{AnalysisLevel, Config};
+
+const SOURCE: &str = "pub type Never = !;
+
+fn unused() {}
+";
+
+/// Returns the type of `Never` if the crate compiles, and the codes of the diagnostics.
+fn check(attrs: &[&str]) -> (Option<String>, Vec<Option<String>>) {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::Full)
+        .inject_crate_attrs(attrs)
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|tcx| {
+            let never = tcx
+                .hir()
+                .items()
+                .find(|&id| tcx.hir().item(id).ident.as_str() == "Never")
+                .unwrap();
+            tcx.type_of(never.owner_id.to_def_id()).to_string()
+        });
+    let codes = collector
+        .take()
+        .into_iter()
+        .map(|diagnostic| diagnostic.code)
+        .collect();
+    (res.ok(), codes)
+}
+
+#[test]
+fn never_type_feature() {
+    let (ty, codes) = check(&[]);
+    assert_eq!(ty, None);
+    assert!(codes.contains(&Some("E0658".to_owned())), "{codes:?}");
+
+    let (ty, codes) = check(&["feature(never_type)"]);
+    assert_eq!(ty.as_deref(), Some("!"));
+    // The other one is the summary.
+    assert_eq!(codes, [Some("dead_code".to_owned()), None]);
+
+    // The attributes of several calls are all injected.
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::Full)
+        .inject_crate_attrs(&["feature(never_type)"])
+        .inject_crate_attrs(&["allow(dead_code)"])
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|_| ());
+    assert!(res.is_ok());
+    assert!(collector.take().is_empty());
+}
@@ -0,0 +1,46 @@
+#![feature(rustc_private)]
+
+use rustc_tools::crate_graph::crate_graph;
+use rustc_tools::dependencies::compile_dependency;
+use rustc_tools::Config;
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn real_dependency() {
+    let dir = env::temp_dir().join(format!("rustc-tools-crate-graph-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let shapes = dir.join("shapes.rs");
+    fs::write(&shapes, "pub fn sides() -> u32 { 4 }\n").unwrap();
+
+    let rmeta = compile_dependency(&shapes, "shapes", &dir, &[]);
+    let res = rmeta.as_ref().map(|rmeta| {
+        Config::new(&args(&["--crate-type=lib"]))
+            .source_code("pub fn sides() -> u32 { shapes::sides() }")
+            .extern_crate("shapes", rmeta)
+            .with_tyctxt(crate_graph)
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let crates = res.unwrap().unwrap();
+    let rmeta = rmeta.unwrap();
+
+    let shapes = crates
+        .iter()
+        .find(|info| info.name == "shapes")
+        .unwrap_or_else(|| panic!("{crates:?}"));
+    assert_eq!(shapes.path, rmeta);
+    assert!(!shapes.is_proc_macro);
+    assert!(!shapes.is_std_injected);
+
+    let std = crates.iter().find(|info| info.name == "std").unwrap();
+    assert!(std.is_std_injected);
+    assert_ne!(std.stable_crate_id, shapes.stable_crate_id);
+    // `std` is loaded first, by the injected `extern crate std;`.
+    assert_eq!(crates[0].name, "std");
+}
@@ -0,0 +1,90 @@
+#![feature(rustc_private)]
+
+use rustc_tools::dependencies::compile_dependency;
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::{Config, Error};
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn two_crates() {
+    let dir = env::temp_dir().join(format!("rustc-tools-dependencies-{}", process::id()));
+    let out_dir = dir.join("out");
+    fs::create_dir_all(&out_dir).unwrap();
+    let shapes = dir.join("shapes.rs");
+    // Needs the 2018 edition for `async` and the `--cfg` for `Square`.
+    fs::write(
+        &shapes,
+        "#[cfg(squares)]
+pub struct Square(pub u32);
+
+pub trait Area {
+    fn area(&self) -> u32;
+}
+
+pub async fn ready() {}
+",
+    )
+    .unwrap();
+    let broken = dir.join("broken.rs");
+    fs::write(&broken, "pub fn broken() -> u32 { \"not a number\" }\n").unwrap();
+
+    let rmeta = compile_dependency(
+        &shapes,
+        "shapes",
+        &out_dir,
+        &args(&["--edition=2018", "--cfg=squares"]),
+    );
+    let broken = compile_dependency(&broken, "broken", &out_dir, &[]);
+    let res = rmeta.as_ref().map(|rmeta| {
+        Config::new(&args(&["--crate-type=lib", "--edition=2021"]))
+            .source_code(
+                "use shapes::{Area, Square};
+
+pub struct Total(pub Vec<Square>);
+
+impl Area for Total {
+    fn area(&self) -> u32 {
+        self.0.iter().map(|square| square.0 * square.0).sum()
+    }
+}
+",
+            )
+            .extern_crate("shapes", rmeta)
+            .with_tyctxt(impl_trait_names)
+    });
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(
+        rmeta.as_ref().unwrap().file_name().unwrap(),
+        "libshapes.rmeta"
+    );
+    assert_eq!(res.unwrap().unwrap(), ["shapes::Area"]);
+    match broken {
+        Err(Error::DependencyCompilation {
+            crate_name,
+            diagnostics,
+        }) => {
+            assert_eq!(crate_name, "broken");
+            assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+            assert_eq!(diagnostics[0].code.as_deref(), Some("E0308"));
+        }
+        res => panic!("{res:?}"),
+    }
+}
+
+/// The traits implemented in the local crate.
+fn impl_trait_names(tcx: TyCtxt<'_>) -> Vec<String> {
+    tcx.hir()
+        .items()
+        .filter(|id| tcx.def_kind(id.owner_id) == DefKind::Impl)
+        .filter_map(|id| tcx.impl_trait_ref(id.owner_id.to_def_id()))
+        .map(|trait_ref| tcx.def_path_str(trait_ref.skip_binder().def_id))
+        .collect()
+}
@@ -0,0 +1,69 @@
+#![feature(rustc_private)]
+
+use rustc_tools::api::ApiDeprecation;
+use rustc_tools::deprecation::{deprecations, uses_of_deprecated};
+use rustc_tools::with_tyctxt_from_str;
+
+#[test]
+fn deprecated_fn_called_from_two_modules() {
+    let source = "#[deprecated(since = \"1.2.0\", note = \"use `new_api`\")]
+pub fn old_api() {}
+
+pub mod first {
+    #[allow(deprecated)]
+    pub fn call() {
+        crate::old_api();
+    }
+}
+
+pub mod second {
+    use crate::old_api;
+
+    pub fn call_twice() {
+        old_api();
+        old_api();
+    }
+}
+";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(source, &args, |tcx| {
+        let items = deprecations(tcx)
+            .into_iter()
+            .map(|item| (item.name, item.deprecation))
+            .collect::<Vec<_>>();
+        let uses = uses_of_deprecated(tcx)
+            .into_iter()
+            .map(|usage| (usage.name, usage.deprecation, usage.used_in, usage.location))
+            .collect::<Vec<_>>();
+        (items, uses)
+    });
+    let (items, uses) = res.unwrap();
+    let deprecation = ApiDeprecation {
+        since: Some("1.2.0".to_owned()),
+        note: Some("use `new_api`".to_owned()),
+    };
+    assert_eq!(
+        items,
+        [("krate::old_api".to_owned(), Some(deprecation.clone()))]
+    );
+    let usage = |used_in: &str, location: &str| {
+        (
+            "krate::old_api".to_owned(),
+            deprecation.clone(),
+            used_in.to_owned(),
+            location.to_owned(),
+        )
+    };
+    // The `allow` only silences the lint. Like with the lint, the imports are not uses.
+    assert_eq!(
+        uses,
+        [
+            usage("krate::first::call", "<anon>:7:9"),
+            usage("krate::second::call_twice", "<anon>:15:9"),
+            usage("krate::second::call_twice", "<anon>:16:9"),
+        ]
+    );
+}
@@ -0,0 +1,100 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::{
+    emit_error, emit_warning, emit_with_suggestion, Applicability, Level,
+};
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::rustc_span::{BytePos, Span};
+use rustc_tools::{with_tyctxt_captured, with_tyctxt_collect_diagnostics, Error};
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn compilation_errors_are_captured() {
+    let (res, diagnostics) = with_tyctxt_captured(&args(&["/nonexistent/lib.rs"]), |_| ());
+    assert!(res.is_err());
+    assert!(diagnostics.contains("/nonexistent/lib.rs"), "{diagnostics}");
+}
+
+#[test]
+fn invalid_edition_is_captured() {
+    let (res, diagnostics) = with_tyctxt_captured(&args(&["--edition", "2099", "lib.rs"]), |_| ());
+    assert!(matches!(res, Err(Error::ArgParse(_))), "{res:?}");
+    assert!(
+        diagnostics.contains("argument for `--edition` must be one of"),
+        "{diagnostics}"
+    );
+}
+
+#[test]
+fn invalid_codegen_option_is_captured() {
+    let (res, diagnostics) = with_tyctxt_captured(&args(&["-C", "bogus=1", "lib.rs"]), |_| ());
+    assert!(matches!(res, Err(Error::ArgParse(_))), "{res:?}");
+    assert!(
+        diagnostics.contains("unknown codegen option: `bogus`"),
+        "{diagnostics}"
+    );
+
+    let (res, diagnostics) =
+        with_tyctxt_collect_diagnostics(&args(&["-C", "bogus=1", "lib.rs"]), |_| ());
+    assert!(matches!(res, Err(Error::ArgParse(_))), "{res:?}");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, Level::Error);
+    assert_eq!(diagnostics[0].message, "unknown codegen option: `bogus`");
+}
+
+/// The span of the bytes `lo..hi` of the root file.
+fn span(tcx: TyCtxt<'_>, lo: u32, hi: u32) -> Span {
+    let file = &tcx.sess.source_map().files()[0];
+    Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(hi))
+}
+
+#[test]
+fn emitted_diagnostics_point_to_their_span() {
+    let dir = env::temp_dir().join(format!("rustc-tools-emit-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    let source = "pub fn first() {}\npub fn second() {}\npub fn third() {}\n";
+    fs::write(&root, source).unwrap();
+    let name = |item: &str| {
+        let lo = source.find(item).unwrap() as u32;
+        (lo, lo + item.len() as u32)
+    };
+    let (first, second, third) = (name("first"), name("second"), name("third"));
+    let (res, diagnostics) = with_tyctxt_captured(
+        &args(&["--crate-type=lib", root.to_str().unwrap()]),
+        |tcx| {
+            emit_warning(
+                tcx,
+                span(tcx, first.0, first.1),
+                Some("W001"),
+                "first warning",
+            );
+            emit_error(tcx, span(tcx, second.0, second.1), None, "second error");
+            emit_with_suggestion(
+                tcx,
+                span(tcx, third.0, third.1),
+                "bad name",
+                "fourth",
+                Applicability::MachineApplicable,
+            );
+        },
+    );
+    let _ = fs::remove_dir_all(&dir);
+    assert!(res.is_ok(), "{res:?}");
+    let root = root.display();
+    for expected in [
+        format!("warning[W001]: first warning\n --> {root}:1:8"),
+        format!("error: second error\n --> {root}:2:8"),
+        format!("warning: bad name\n --> {root}:3:8"),
+        "help: try: `fourth`".to_owned(),
+    ] {
+        assert!(diagnostics.contains(&expected), "{diagnostics}");
+    }
+}
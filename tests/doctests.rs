@@ -0,0 +1,121 @@
+#![feature(rustc_private)]
+
+use rustc_tools::doctests::{doctests, DoctestAttributes};
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn code_blocks_of_comments_and_included_files() {
+    let dir = env::temp_dir().join(format!("rustc-tools-doctests-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "#![doc = include_str!(\"README.md\")]
+
+/// Adds one.
+///
+/// ```
+/// # use krate::add_one;
+/// assert_eq!(add_one(1), 2);
+/// ```
+///
+///     let indented = \"not a doctest\";
+///
+/// ```text
+/// not Rust
+/// ```
+///
+/// ```should_panic,edition2018
+/// ## not hidden
+/// panic!();
+/// ```
+pub fn add_one(x: u32) -> u32 {
+    x + 1
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("README.md"),
+        "# Krate
+
+~~~no_run
+loop {}
+~~~
+
+```compile_fail,E0425
+undefined();
+```
+",
+    )
+    .unwrap();
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+        root.display().to_string(),
+    ];
+    let res = with_tyctxt(&args, |tcx| {
+        doctests(tcx)
+            .into_iter()
+            .map(|doctest| {
+                let snippet = tcx.sess.source_map().span_to_snippet(doctest.span).unwrap();
+                (doctest.item, doctest.code, doctest.attributes, snippet)
+            })
+            .collect::<Vec<_>>()
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let doctests = res.unwrap();
+    let items_and_code = doctests
+        .iter()
+        .map(|(item, code, _, _)| (item.as_str(), code.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items_and_code,
+        [
+            ("krate", "loop {}"),
+            ("krate", "undefined();"),
+            (
+                "krate::add_one",
+                "use krate::add_one;\nassert_eq!(add_one(1), 2);"
+            ),
+            ("krate::add_one", "# not hidden\npanic!();"),
+        ]
+    );
+    assert_eq!(
+        doctests[0].2,
+        DoctestAttributes {
+            no_run: true,
+            ..DoctestAttributes::default()
+        }
+    );
+    assert_eq!(
+        doctests[1].2,
+        DoctestAttributes {
+            compile_fail: true,
+            error_codes: vec!["E0425".to_owned()],
+            ..DoctestAttributes::default()
+        }
+    );
+    assert_eq!(doctests[2].2, DoctestAttributes::default());
+    assert_eq!(
+        doctests[3].2,
+        DoctestAttributes {
+            should_panic: true,
+            edition: Some("2018".to_owned()),
+            ..DoctestAttributes::default()
+        }
+    );
+    // The spans go from the opening line to the closing one, the included file is in the
+    // attribute.
+    assert_eq!(doctests[0].3, "#![doc = include_str!(\"README.md\")]");
+    assert!(
+        doctests[2].3.starts_with("/// ```\n/// # use"),
+        "{}",
+        doctests[2].3
+    );
+    assert!(doctests[2].3.ends_with("/// ```"), "{}", doctests[2].3);
+}
@@ -0,0 +1,49 @@
+#![feature(rustc_private)]
+
+use rustc_tools::call_graph::call_graph;
+use rustc_tools::dot::{module_tree_dot, to_dot_with_options, DotOptions};
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = "pub mod shapes {
+    pub trait Shape {
+        fn sides(&self) -> u32;
+    }
+
+    pub struct Square;
+
+    impl Shape for Square {
+        fn sides(&self) -> u32 {
+            4
+        }
+    }
+
+    pub fn total(shapes: &[&dyn Shape]) -> u32 {
+        shapes.iter().map(|shape| shape.sides()).sum()
+    }
+}
+
+pub fn run() -> u32 {
+    let square = shapes::Square;
+    shapes::total(&[&square]) + std::cmp::max(1, 2)
+}
+";
+
+#[test]
+fn golden_files() {
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(SOURCE, &args, |tcx| {
+        let options = DotOptions {
+            cluster_by_module: true,
+        };
+        (
+            to_dot_with_options(&call_graph(tcx), options),
+            module_tree_dot(tcx),
+        )
+    });
+    let (call_graph, module_tree) = res.unwrap();
+    assert_eq!(call_graph, include_str!("fixtures/dot/call_graph.dot"));
+    assert_eq!(module_tree, include_str!("fixtures/dot/module_tree.dot"));
+}
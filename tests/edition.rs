@@ -0,0 +1,47 @@
+#![feature(rustc_private)]
+
+use rustc_tools::analyze_under_edition;
+use rustc_tools::rustc_span::edition::Edition;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn try_as_identifier() {
+    let dir = env::temp_dir().join(format!("rustc-tools-edition-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(&root, "pub fn try() {}\n").unwrap();
+    // The `--edition` of the arguments is overridden.
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--edition=2021".to_owned(),
+        root.display().to_string(),
+    ];
+    let run = |edition| {
+        analyze_under_edition(&args, edition, |tcx| {
+            tcx.hir()
+                .items()
+                .any(|id| tcx.hir().item(id).ident.as_str() == "try")
+        })
+    };
+    let old = run(Edition::Edition2015);
+    let new = run(Edition::Edition2018);
+    let _ = fs::remove_dir_all(&dir);
+    let (res, diagnostics) = old;
+    assert!(res.unwrap());
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+    // The parser recovers, so the function is still there.
+    let (res, diagnostics) = new;
+    assert!(res.unwrap());
+    assert_eq!(
+        diagnostics[0].message,
+        "expected identifier, found reserved keyword `try`"
+    );
+    assert_eq!(
+        diagnostics[0].children[0].message,
+        "`try` is a keyword since the 2018 edition and this code is compiled with the 2018 edition"
+    );
+}
@@ -0,0 +1,78 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::{DiagnosticsCollector, Level};
+use rustc_tools::{with_tyctxt, with_tyctxt_from_str, Config, Error};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn invalid_arguments() {
+    for invalid in [
+        &["--edition", "2099"][..],
+        &["-C", "bogus=1"],
+        &["-Z", "bogus"],
+        &["--color", "sometimes"],
+        &["--cap-lints", "loud"],
+    ] {
+        let res = with_tyctxt_from_str("fn main() {}", &args(invalid), |_| ());
+        assert!(
+            matches!(res, Err(Error::ArgParse(_))),
+            "{invalid:?}: {res:?}"
+        );
+    }
+}
+
+#[test]
+fn unknown_option() {
+    let res = with_tyctxt(&args(&["--bogus", "lib.rs"]), |_| ());
+    assert!(matches!(res, Err(Error::ArgParse(_))), "{res:?}");
+}
+
+#[test]
+fn inputs() {
+    let res = with_tyctxt(&[], |_| ());
+    assert!(matches!(res, Err(Error::InputMissing)), "{res:?}");
+    let res = with_tyctxt(&args(&["a.rs", "b.rs"]), |_| ());
+    assert!(matches!(res, Err(Error::TooManyInputs)), "{res:?}");
+}
+
+#[test]
+fn unknown_crate_type() {
+    let res = with_tyctxt_from_str("fn main() {}", &args(&["--crate-type", "exe"]), |_| ());
+    assert!(matches!(res, Err(Error::UnknownCrateType(_))), "{res:?}");
+}
+
+#[test]
+fn compilation_failed() {
+    let res = with_tyctxt_from_str("fn main() { undefined(); }", &[], |_| ());
+    assert!(
+        matches!(res, Err(Error::CompilationFailed { error_count: 1 })),
+        "{res:?}"
+    );
+}
+
+#[test]
+fn parse_error() {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&[])
+        .source_code("fn main() { let x: }")
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|_| ());
+    assert!(
+        matches!(res, Err(Error::CompilationFailed { error_count: 1 })),
+        "{res:?}"
+    );
+
+    let diagnostics = collector.take();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics[0].level, Level::Error);
+    assert_eq!(diagnostics[0].message, "expected type, found `}`");
+    let span = diagnostics[0]
+        .spans
+        .iter()
+        .find(|span| span.is_primary)
+        .unwrap();
+    assert_eq!((span.line_start, span.column_start), (1, 20));
+}
@@ -0,0 +1,44 @@
+#![feature(rustc_private)]
+
+use rustc_tools::expand_to_string;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn macro_rules_expansion() {
+    let dir = env::temp_dir().join(format!("rustc-tools-expand-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
+
+pub fn area(side: u32) -> u32 {
+    square!(side)
+}
+",
+    )
+    .unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let res = expand_to_string(&args);
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(
+        res.unwrap(),
+        "#![feature(prelude_import)]
+#![no_std]
+#[prelude_import]
+use ::std::prelude::rust_2015::*;
+#[macro_use]
+extern crate std;
+macro_rules! square { ($x : expr) => { $x * $x } ; }
+
+pub fn area(side: u32) -> u32 { side * side }
+"
+    );
+}
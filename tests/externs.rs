@@ -0,0 +1,59 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_span::def_id::LOCAL_CRATE;
+use rustc_tools::Config;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+
+/// Compiles `dir/dep.rs` with `rustc --emit=<emit>` into `dir`.
+fn rustc_dep(dir: &Path, emit: &str) {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let status = Command::new(rustc)
+        .args(["--crate-type=lib", "--crate-name=dep", "--edition=2021"])
+        .arg(format!("--emit={emit}"))
+        .arg("--out-dir")
+        .arg(dir)
+        .arg(dir.join("dep.rs"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn metadata_only_dependency() {
+    let dir = env::temp_dir().join(format!("rustc-tools-externs-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("dep.rs"), "pub fn answer() -> u32 { 42 }\n").unwrap();
+    rustc_dep(&dir, "metadata");
+    let rmeta = dir.join("libdep.rmeta");
+    let source = "pub fn call() -> u32 { dep::answer() }\n";
+
+    let analyze = |path: &Path| {
+        Config::new(&["--crate-type=lib".to_owned(), "--edition=2021".to_owned()])
+            .source_code(source)
+            .extern_crate("dep", path)
+            .with_tyctxt(|tcx| {
+                let dep = *tcx
+                    .crates(())
+                    .iter()
+                    .find(|&&krate| tcx.crate_name(krate).as_str() == "dep")
+                    .unwrap();
+                assert_ne!(dep, LOCAL_CRATE);
+                let source = tcx.used_crate_source(dep);
+                (
+                    source.rmeta.as_ref().map(|(path, _)| path.clone()),
+                    source.rlib.is_some(),
+                )
+            })
+    };
+    let only_rmeta = analyze(&rmeta);
+    // With both, the `.rmeta` next to the given `.rlib` is used.
+    rustc_dep(&dir, "metadata,link");
+    let both = analyze(&dir.join("libdep.rlib"));
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(only_rmeta.unwrap(), (Some(rmeta.clone()), false));
+    assert_eq!(both.unwrap(), (Some(rmeta), false));
+}
@@ -0,0 +1,79 @@
+#![feature(rustc_private)]
+
+use rustc_tools::ffi::ffi_surface;
+use rustc_tools::layout::ReprInfo;
+use rustc_tools::with_tyctxt_from_str;
+
+#[test]
+fn packed_struct_and_fn_pointers() {
+    let source = "#[repr(C, packed(2))]
+pub struct Header {
+    pub tag: u8,
+    pub callback: extern \"C\" fn(*const Header, i32) -> u32,
+    pub optional: Option<unsafe extern \"C\" fn()>,
+}
+
+extern \"C\" {
+    pub fn register(header: *mut Header, on_error: extern \"C\" fn(i32));
+}
+
+#[no_mangle]
+pub extern \"C\" fn on_error(code: i32) {
+    let _ = code;
+}
+";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(source, &args, |tcx| {
+        let surface = ffi_surface(tcx);
+        let functions = surface
+            .functions
+            .into_iter()
+            .map(|function| (function.name, function.symbol, function.inputs))
+            .collect::<Vec<_>>();
+        let types = surface
+            .types
+            .into_iter()
+            .map(|ty| (ty.name, ty.repr, ty.fields))
+            .collect::<Vec<_>>();
+        (functions, types)
+    });
+    let (functions, types) = res.unwrap();
+    let pair = |name: &str, ty: &str| (name.to_owned(), ty.to_owned());
+    assert_eq!(
+        types,
+        [(
+            "krate::Header".to_owned(),
+            ReprInfo {
+                c: true,
+                packed: Some(2),
+                ..ReprInfo::default()
+            },
+            vec![
+                pair("tag", "u8"),
+                pair("callback", "extern \"C\" fn(*const Header, i32) -> u32"),
+                pair("optional", "std::option::Option<unsafe extern \"C\" fn()>"),
+            ]
+        )]
+    );
+    assert_eq!(
+        functions,
+        [
+            (
+                "krate::{extern#0}::register".to_owned(),
+                Some("register".to_owned()),
+                vec![
+                    pair("header", "*mut Header"),
+                    pair("on_error", "extern \"C\" fn(i32)"),
+                ]
+            ),
+            (
+                "krate::on_error".to_owned(),
+                Some("on_error".to_owned()),
+                vec![pair("code", "i32")]
+            ),
+        ]
+    );
+}
@@ -0,0 +1,102 @@
+#![feature(rustc_private)]
+
+use rustc_tools::consts::{eval_const, ConstValueRepr};
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::{Config, DiagnosticsBuffer};
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process;
+
+fn eval(tcx: TyCtxt<'_>, name: &str) -> ConstValueRepr {
+    let item = tcx
+        .hir()
+        .items()
+        .find(|&id| tcx.hir().item(id).ident.as_str() == name)
+        .unwrap();
+    eval_const(tcx, item.owner_id.to_def_id()).unwrap()
+}
+
+#[test]
+fn unsaved_files() {
+    let dir = env::temp_dir().join(format!("rustc-tools-file-overlay-{}", process::id()));
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "mod unsaved;
+pub mod nested;
+pub const TEXT: &str = include_str!(\"text.txt\");
+pub const BYTES: &[u8] = include_bytes!(\"text.txt\");
+pub const NESTED: &str = nested::DATA;
+",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("nested/mod.rs"),
+        "pub const DATA: &str = include_str!(\"data.txt\");",
+    )
+    .unwrap();
+    fs::write(dir.join("text.txt"), "saved").unwrap();
+    fs::write(dir.join("nested/data.txt"), "saved").unwrap();
+
+    // The submodule only exists in the editor, and the text files weren't saved.
+    let overlay = HashMap::from([
+        (dir.join("unsaved.rs"), "pub fn in_overlay() {}".to_owned()),
+        (dir.join("text.txt"), "unsaved\r\n\"quoted\"".to_owned()),
+        (dir.join("nested/data.txt"), "unsaved nested".to_owned()),
+    ]);
+    let res = Config::new(&["--crate-type=lib".to_owned(), root.display().to_string()])
+        .file_overlay(overlay)
+        .with_tyctxt(|tcx| {
+            let names = tcx
+                .hir()
+                .items()
+                .map(|id| tcx.hir().item(id).ident.to_string())
+                .collect::<Vec<_>>();
+            (
+                names,
+                eval(tcx, "TEXT"),
+                eval(tcx, "BYTES"),
+                eval(tcx, "NESTED"),
+            )
+        });
+    let _ = fs::remove_dir_all(&dir);
+    let (names, text, bytes, nested) = res.unwrap();
+    assert!(names.contains(&"unsaved".to_owned()), "{names:?}");
+    assert!(names.contains(&"in_overlay".to_owned()), "{names:?}");
+    assert_eq!(
+        text,
+        ConstValueRepr::Str("unsaved\r\n\"quoted\"".to_owned())
+    );
+    assert_eq!(
+        bytes,
+        ConstValueRepr::Bytes(b"unsaved\r\n\"quoted\"".to_vec())
+    );
+    assert_eq!(nested, ConstValueRepr::Str("unsaved nested".to_owned()));
+}
+
+#[test]
+fn missing_include() {
+    let dir = env::temp_dir().join(format!("rustc-tools-missing-include-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub const A: &str = include_str!(\"missing.txt\");\npub const B: &[u8] = include_bytes!(\"missing.bin\");",
+    )
+    .unwrap();
+    let buffer = DiagnosticsBuffer::new();
+    let res = Config::new(&["--crate-type=lib".to_owned(), root.display().to_string()])
+        .file_overlay(HashMap::new())
+        .diagnostics_buffer(buffer.clone())
+        .with_tyctxt(|_| ());
+    let _ = fs::remove_dir_all(&dir);
+    assert!(res.is_err());
+    // Like without a file loader, it isn't a fatal error.
+    let output = buffer.take();
+    assert!(output.contains("couldn't read"), "{output}");
+    assert!(output.contains("missing.txt"), "{output}");
+    assert!(output.contains("missing.bin"), "{output}");
+}
@@ -0,0 +1,28 @@
+//! A derive macro implementing `Hello` for a struct, without any dependency:
+//!
+//! ```ignore
+//! #[derive(Hello)]
+//! struct Name;
+//! // Gives: impl Hello for Name { const NAME: &'static str = "Name"; }
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::{TokenStream, TokenTree};
+
+#[proc_macro_derive(Hello)]
+pub fn derive_hello(input: TokenStream) -> TokenStream {
+    let mut tokens = input.into_iter();
+    let name = loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => {
+                break tokens.next().unwrap().to_string();
+            }
+            Some(_) => {}
+            None => panic!("`Hello` can only be derived for structs"),
+        }
+    };
+    format!("impl Hello for {name} {{ const NAME: &'static str = \"{name}\"; }}")
+        .parse()
+        .unwrap()
+}
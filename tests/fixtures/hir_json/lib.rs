@@ -0,0 +1,52 @@
+use std::fmt::Debug;
+
+pub const SIDES: u32 = 4;
+
+static mut COUNT: usize = 0;
+
+#[derive(Clone)]
+pub struct Square {
+    pub side: u32,
+    name: String,
+}
+
+pub struct Meters(pub f64);
+
+pub enum Shape {
+    Square(Square),
+    Circle { radius: u32 },
+    Empty = 7,
+}
+
+pub trait Area {
+    type Unit;
+
+    fn area(&self) -> u32;
+}
+
+impl Area for Square {
+    type Unit = Meters;
+
+    fn area(&self) -> u32 {
+        self.side * self.side
+    }
+}
+
+pub mod util {
+    pub(crate) fn largest<T: PartialOrd + Copy>(values: &[T]) -> Option<T>
+    where
+        T: super::Debug,
+    {
+        values.iter().copied().reduce(|a, b| if b > a { b } else { a })
+    }
+}
+
+macro_rules! count {
+    () => {
+        unsafe { COUNT += 1 }
+    };
+}
+
+pub fn tick() {
+    count!();
+}
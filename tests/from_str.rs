@@ -0,0 +1,61 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_hir::intravisit::{self, Visitor};
+use rustc_tools::rustc_hir::{Pat, PatKind};
+use rustc_tools::rustc_middle::hir::nested_filter;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::with_tyctxt_from_str;
+
+struct BindingsVisitor<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    bindings: Vec<(String, String)>,
+}
+
+impl<'tcx> Visitor<'tcx> for BindingsVisitor<'tcx> {
+    type NestedFilter = nested_filter::All;
+
+    fn nested_visit_map(&mut self) -> Self::Map {
+        self.tcx.hir()
+    }
+
+    fn visit_pat(&mut self, pat: &'tcx Pat<'tcx>) {
+        if let PatKind::Binding(_, hir_id, ident, _) = pat.kind {
+            let ty = self.tcx.typeck(hir_id.owner.def_id).node_type(hir_id);
+            self.bindings.push((ident.to_string(), ty.to_string()));
+        }
+        intravisit::walk_pat(self, pat);
+    }
+}
+
+fn bindings(source: &str, rustc_args: &[String]) -> Vec<(String, String)> {
+    with_tyctxt_from_str(source, rustc_args, |tcx| {
+        let mut visitor = BindingsVisitor {
+            tcx,
+            bindings: Vec::new(),
+        };
+        tcx.hir().visit_all_item_likes_in_crate(&mut visitor);
+        visitor.bindings
+    })
+    .unwrap()
+}
+
+#[test]
+fn finds_binding() {
+    assert_eq!(
+        bindings("fn main() { let x: u32 = 1; }", &[]),
+        [("x".to_owned(), "u32".to_owned())],
+    );
+}
+
+#[test]
+fn repeated_calls_with_args() {
+    let source = "#[cfg(feature = \"big\")] type T = u64;
+                  #[cfg(not(feature = \"big\"))] type T = u8;
+                  fn main() { let y: T = 1; }";
+    assert_eq!(bindings(source, &[]), [("y".to_owned(), "u8".to_owned())]);
+    let args = ["--cfg", "feature=\"big\"", "--edition", "2021"].map(String::from);
+    assert_eq!(
+        bindings(source, &args),
+        [("y".to_owned(), "u64".to_owned())]
+    );
+}
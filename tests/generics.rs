@@ -0,0 +1,150 @@
+#![feature(rustc_private)]
+
+use rustc_tools::generics::{
+    generics_of_item, generics_of_item_with_options, AssocBinding, GenericParam, GenericParamKind,
+    GenericsInfo, GenericsOptions, WherePredicate, WherePredicateKind,
+};
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = r#"
+pub fn take<'a, T: Iterator<Item = U> + 'a, U, const N: usize>(iter: T) -> [Option<U>; N] {
+    let _ = iter;
+    [(); N].map(|_| None)
+}
+
+pub fn show<V: ?Sized + std::fmt::Debug>(value: &V) -> String {
+    format!("{:?}", value)
+}
+"#;
+
+fn param(name: &str, kind: GenericParamKind, display: &str) -> GenericParam {
+    GenericParam {
+        name: name.to_owned(),
+        kind,
+        synthetic: false,
+        display: display.to_owned(),
+    }
+}
+
+fn type_kind(maybe_unsized: bool) -> GenericParamKind {
+    GenericParamKind::Type {
+        default: None,
+        maybe_unsized,
+    }
+}
+
+fn trait_bound(bounded: &str, trait_path: &str, implicit: bool) -> WherePredicate {
+    WherePredicate {
+        display: format!("{bounded}: {trait_path}"),
+        kind: WherePredicateKind::Trait {
+            bounded: bounded.to_owned(),
+            trait_path: trait_path.to_owned(),
+            generic_args: Vec::new(),
+            assoc_bindings: Vec::new(),
+            for_lifetimes: Vec::new(),
+            implicit,
+        },
+    }
+}
+
+/// Returns the generics of the functions of [`SOURCE`], in the order in which they're defined.
+fn generics(options: GenericsOptions) -> Vec<GenericsInfo> {
+    with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], |tcx| {
+        tcx.hir()
+            .body_owners()
+            .filter(|def_id| tcx.def_kind(*def_id) == DefKind::Fn)
+            .map(|def_id| generics_of_item_with_options(tcx, def_id.to_def_id(), options))
+            .collect()
+    })
+    .unwrap()
+}
+
+#[test]
+fn bounds_with_binding_and_outlives() {
+    let take = generics(GenericsOptions::default()).remove(0);
+    assert_eq!(
+        take,
+        GenericsInfo {
+            params: vec![
+                param("'a", GenericParamKind::Lifetime, "'a"),
+                param("T", type_kind(false), "T"),
+                param("U", type_kind(false), "U"),
+                param(
+                    "N",
+                    GenericParamKind::Const {
+                        ty: "usize".to_owned(),
+                        default: None,
+                    },
+                    "const N: usize",
+                ),
+            ],
+            predicates: vec![
+                WherePredicate {
+                    display: "T: std::iter::Iterator<Item = U>".to_owned(),
+                    kind: WherePredicateKind::Trait {
+                        bounded: "T".to_owned(),
+                        trait_path: "std::iter::Iterator".to_owned(),
+                        generic_args: Vec::new(),
+                        assoc_bindings: vec![AssocBinding {
+                            name: "Item".to_owned(),
+                            term: "U".to_owned(),
+                        }],
+                        for_lifetimes: Vec::new(),
+                        implicit: false,
+                    },
+                },
+                WherePredicate {
+                    display: "T: 'a".to_owned(),
+                    kind: WherePredicateKind::TypeOutlives {
+                        bounded: "T".to_owned(),
+                        lifetime: "'a".to_owned(),
+                    },
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn implicit_sized_bounds() {
+    let explicit = generics(GenericsOptions::default());
+    assert_eq!(
+        explicit[1],
+        GenericsInfo {
+            params: vec![param("V", type_kind(true), "V")],
+            predicates: vec![trait_bound("V", "std::fmt::Debug", false)],
+        }
+    );
+
+    let implicit = generics(GenericsOptions {
+        implicit_sized: true,
+    });
+    let sized: Vec<_> = implicit[0]
+        .predicates
+        .iter()
+        .filter(|predicate| {
+            matches!(
+                predicate.kind,
+                WherePredicateKind::Trait { implicit: true, .. }
+            )
+        })
+        .cloned()
+        .collect();
+    assert_eq!(
+        sized,
+        [
+            trait_bound("T", "std::marker::Sized", true),
+            trait_bound("U", "std::marker::Sized", true),
+        ]
+    );
+    // `?Sized` opts out of the implicit bound.
+    assert_eq!(implicit[1], explicit[1]);
+    // Without the option, the plain API gives the same result as the default options.
+    let plain = with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], |tcx| {
+        let take = tcx.hir().body_owners().next().unwrap();
+        generics_of_item(tcx, take.to_def_id())
+    })
+    .unwrap();
+    assert_eq!(plain, explicit[0]);
+}
@@ -0,0 +1,50 @@
+#![feature(rustc_private)]
+
+use rustc_tools::hir_json::{export_hir_json, HirJsonOptions};
+use rustc_tools::{AnalysisLevel, Config};
+use serde_json::Value;
+
+/// Returns the number of `body` fields in `value`.
+fn bodies(value: &Value) -> usize {
+    match value {
+        Value::Array(values) => values.iter().map(bodies).sum(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| usize::from(key == "body") + bodies(value))
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[test]
+fn golden_file() {
+    let res = Config::new(&[
+        "--crate-type=lib".to_owned(),
+        "--crate-name=shapes".to_owned(),
+    ])
+    .source_code(include_str!("fixtures/hir_json/lib.rs"))
+    .analysis_level(AnalysisLevel::HirOnly)
+    .with_tyctxt(|tcx| {
+        (
+            export_hir_json(tcx, HirJsonOptions::default()),
+            export_hir_json(
+                tcx,
+                HirJsonOptions {
+                    include_bodies: true,
+                },
+            ),
+        )
+    });
+    let (export, with_bodies) = res.unwrap();
+    let expected: Value =
+        serde_json::from_str(include_str!("fixtures/hir_json/expected.json")).unwrap();
+    assert_eq!(export, expected);
+
+    // The bodies aren't exported by default, which keeps the export small.
+    assert_eq!(bodies(&export), 0);
+    let size = serde_json::to_string_pretty(&export).unwrap().len();
+    assert!(size < 16 * 1024, "{size}");
+    // The constant, the static and the 5 functions (a `null` one for the trait method).
+    assert_eq!(bodies(&with_bodies), 7);
+    assert!(serde_json::to_string_pretty(&with_bodies).unwrap().len() > size);
+}
@@ -0,0 +1,47 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_span::DUMMY_SP;
+use rustc_tools::{with_tyctxt_from_str, Config, DiagnosticsBuffer, Error};
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static HOST_HOOK_CALLED: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn ice_is_returned() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        HOST_HOOK_CALLED.store(true, Ordering::SeqCst);
+        default_hook(info);
+    }));
+
+    // The message of the bug is emitted as a diagnostic.
+    let buffer = DiagnosticsBuffer::new();
+    let res = Config::new(&[])
+        .source_code("fn main() {}")
+        .diagnostics_buffer(buffer.clone())
+        .with_tyctxt(|tcx| {
+            tcx.sess.diagnostic().span_bug(DUMMY_SP, "deliberate bug");
+        });
+    assert!(
+        matches!(res, Err(Error::InternalCompilerError { .. })),
+        "{res:?}"
+    );
+    let diagnostics = buffer.take();
+    assert!(diagnostics.contains("deliberate bug"), "{diagnostics}");
+
+    // A delayed bug is only reported once the session is over, if there were no errors.
+    let res = with_tyctxt_from_str("fn main() {}", &[], |tcx| {
+        tcx.sess.delay_span_bug(DUMMY_SP, "deliberate delayed bug");
+    });
+    assert!(
+        matches!(res, Err(Error::InternalCompilerError { .. })),
+        "{res:?}"
+    );
+
+    // The hook of the host application is restored.
+    assert!(!HOST_HOOK_CALLED.load(Ordering::SeqCst));
+    let _ = panic::catch_unwind(|| panic!("host panic"));
+    assert!(HOST_HOOK_CALLED.load(Ordering::SeqCst));
+}
@@ -0,0 +1,88 @@
+#![feature(rustc_private)]
+
+use rustc_tools::consts::{eval_const, ConstValueRepr};
+use rustc_tools::includes::included_files;
+use rustc_tools::Config;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const SOURCE: &str = r#"include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+pub const TEXT: &str = include_str!(concat!(env!("OUT_DIR"), "/generated.txt"));
+pub const BYTES: &[u8] = include_bytes!("bytes.bin");
+"#;
+
+fn config(root: &Path, out_dir: &Path) -> Config {
+    let generated = HashMap::from([
+        (
+            PathBuf::from("generated.rs"),
+            "pub fn generated() {}".to_owned(),
+        ),
+        (PathBuf::from("generated.txt"), "generated text".to_owned()),
+    ]);
+    Config::new(&["--crate-type=lib".to_owned(), root.display().to_string()])
+        .generated_files(out_dir, generated)
+}
+
+#[test]
+fn generated_includes() {
+    let dir = env::temp_dir().join(format!("rustc-tools-includes-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(&root, SOURCE).unwrap();
+    // `OUT_DIR` doesn't exist.
+    let out_dir = dir.join("out");
+
+    // `bytes.bin` is missing.
+    let includes = included_files(config(&root, &out_dir)).unwrap();
+    let found = includes
+        .iter()
+        .map(|include| {
+            (
+                include.macro_name.as_str(),
+                include.path.clone().unwrap(),
+                include.found,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            ("include", out_dir.join("generated.rs"), true),
+            ("include_str", out_dir.join("generated.txt"), true),
+            ("include_bytes", dir.join("bytes.bin"), false),
+        ]
+    );
+    assert_eq!(includes[2].location, format!("{}:3:26", root.display()));
+    assert!(config(&root, &out_dir).with_tyctxt(|_| ()).is_err());
+
+    // Once it's written, the crate compiles.
+    fs::write(dir.join("bytes.bin"), [0xff, 0, b'a']).unwrap();
+    let includes = included_files(config(&root, &out_dir)).unwrap();
+    assert!(includes.iter().all(|include| include.found), "{includes:?}");
+    let res = config(&root, &out_dir).with_tyctxt(|tcx| {
+        let hir = tcx.hir();
+        let eval = |name: &str| {
+            let item = hir
+                .items()
+                .find(|&id| hir.item(id).ident.as_str() == name)?;
+            eval_const(tcx, item.owner_id.to_def_id()).ok()
+        };
+        let generated = hir
+            .items()
+            .any(|id| hir.item(id).ident.as_str() == "generated");
+        (generated, eval("TEXT"), eval("BYTES"))
+    });
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(
+        res.unwrap(),
+        (
+            true,
+            Some(ConstValueRepr::Str("generated text".to_owned())),
+            Some(ConstValueRepr::Bytes(vec![0xff, 0, b'a'])),
+        )
+    );
+}
@@ -0,0 +1,82 @@
+#![feature(rustc_private)]
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process;
+
+use rustc_tools::consts::{eval_const, ConstValueRepr};
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::Config;
+
+const LIB: &str = r#"mod child;
+
+pub const DATA: &str = include_str!("data.txt");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const PROCESS: &str = match option_env!("RUSTC_TOOLS_ISOLATION") {
+    Some(value) => value,
+    None => "unset",
+};
+pub const CHILD: &str = child::NAME;
+"#;
+
+fn eval(tcx: TyCtxt<'_>, name: &str) -> String {
+    let item = tcx
+        .hir()
+        .items()
+        .find(|&id| tcx.hir().item(id).ident.as_str() == name)
+        .unwrap();
+    match eval_const(tcx, item.owner_id.to_def_id()).unwrap() {
+        ConstValueRepr::Str(value) => value,
+        value => panic!("{value:?}"),
+    }
+}
+
+/// Analyzes `lib.rs` (given with a relative path) in the crate directory, returning the values of
+/// its constants.
+fn values(config: Config) -> Vec<String> {
+    config
+        .with_tyctxt(|tcx| {
+            ["DATA", "VERSION", "PROCESS", "CHILD"]
+                .map(|name| eval(tcx, name))
+                .to_vec()
+        })
+        .unwrap()
+}
+
+#[test]
+fn working_dir_and_env_overlay() {
+    let dir = env::temp_dir().join(format!("rustc-tools-isolation-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("lib.rs"), LIB).unwrap();
+    fs::write(dir.join("child.rs"), "pub const NAME: &str = \"child\";\n").unwrap();
+    fs::write(dir.join("data.txt"), "data").unwrap();
+    env::set_var("RUSTC_TOOLS_ISOLATION", "process");
+    env::set_var("CARGO_PKG_VERSION", "0.0.0-process");
+    let cwd = env::current_dir().unwrap();
+    let args = ["--crate-type=lib".to_owned(), "lib.rs".to_owned()];
+
+    let vars = HashMap::from([("CARGO_PKG_VERSION".to_owned(), "1.2.3".to_owned())]);
+    let isolated = values(Config::new(&args).working_dir(&dir).env_overlay(vars));
+    let inherited = values(Config::new(&args).working_dir(&dir));
+    // Without the working directory, `lib.rs` is looked for in the current directory.
+    let collector = DiagnosticsCollector::new();
+    let outside = Config::new(&args)
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|_| ());
+    let cwd_after = env::current_dir().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(isolated, ["data", "1.2.3", "unset", "child"]);
+    assert_eq!(inherited, ["data", "0.0.0-process", "process", "child"]);
+    assert!(outside.is_err());
+    let diagnostics = collector.take();
+    assert!(
+        diagnostics[0].message.starts_with("couldn't read lib.rs"),
+        "{diagnostics:?}"
+    );
+    // Neither the directory nor the variables of the process are changed.
+    assert_eq!(cwd_after, cwd);
+    assert_eq!(env::var("CARGO_PKG_VERSION").unwrap(), "0.0.0-process");
+}
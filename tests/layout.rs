@@ -0,0 +1,102 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::layout::{enum_discriminants, repr_of, ReprInfo};
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::{AnalysisLevel, Config, Error};
+
+/// The discriminants of the enum named `name`.
+fn discriminants(tcx: TyCtxt<'_>, name: &str) -> Vec<(String, i128)> {
+    let item = tcx
+        .hir()
+        .items()
+        .find(|&id| tcx.hir().item(id).ident.as_str() == name)
+        .unwrap();
+    enum_discriminants(tcx, item.owner_id.to_def_id())
+}
+
+#[test]
+fn negative_discriminants() {
+    let source = "#[repr(i8)]
+pub enum Signed {
+    Min = -128,
+    Next,
+    Shifted = -(1 << 4),
+    Zero = 0,
+}
+
+pub enum Plain {
+    Negative = -3,
+    Implicit,
+}
+";
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(source)
+        .analysis_level(AnalysisLevel::TypeckBodies)
+        .with_tyctxt(|tcx| {
+            let item = tcx
+                .hir()
+                .items()
+                .find(|&id| tcx.hir().item(id).ident.as_str() == "Signed")
+                .unwrap();
+            (
+                discriminants(tcx, "Signed"),
+                discriminants(tcx, "Plain"),
+                repr_of(tcx, item.owner_id.to_def_id()),
+            )
+        });
+    let (signed, plain, repr) = res.unwrap();
+    let variant = |name: &str, value| (name.to_owned(), value);
+    assert_eq!(
+        signed,
+        [
+            variant("Min", -128),
+            variant("Next", -127),
+            variant("Shifted", -16),
+            variant("Zero", 0),
+        ]
+    );
+    // The default integer type is `isize`.
+    assert_eq!(plain, [variant("Negative", -3), variant("Implicit", -2)]);
+    assert_eq!(
+        repr,
+        ReprInfo {
+            int: Some("i8".to_owned()),
+            ..ReprInfo::default()
+        }
+    );
+}
+
+#[test]
+fn repr_i8_overflow() {
+    let source = "#[repr(i8)]
+pub enum Overflow {
+    Max = 127,
+    Wrapped,
+}
+";
+    let collector = DiagnosticsCollector::new();
+    let run = |analysis_level| {
+        Config::new(&["--crate-type=lib".to_owned()])
+            .source_code(source)
+            .analysis_level(analysis_level)
+            .collect_diagnostics(collector.clone())
+            .with_tyctxt(|tcx| discriminants(tcx, "Overflow"))
+    };
+    // The overflow is only detected when the bodies are type-checked.
+    assert_eq!(
+        run(AnalysisLevel::HirOnly).unwrap(),
+        [("Max".to_owned(), 127), ("Wrapped".to_owned(), -128)]
+    );
+    assert!(collector.take().is_empty());
+    let res = run(AnalysisLevel::TypeckBodies);
+    assert!(
+        matches!(res, Err(Error::CompilationFailed { .. })),
+        "{res:?}"
+    );
+    let diagnostics = collector.take();
+    assert_eq!(
+        diagnostics[0].message, "enum discriminant overflowed",
+        "{diagnostics:?}"
+    );
+}
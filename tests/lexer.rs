@@ -0,0 +1,51 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_ast::token::TokenKind;
+use rustc_tools::rustc_ast::tokenstream::{TokenStream, TokenTree};
+use rustc_tools::rustc_span::edition::Edition;
+use rustc_tools::with_lexer;
+
+/// The kinds of the tokens of `stream` (without the delimiters) as displayed by `Debug`.
+fn token_kinds(stream: &TokenStream, kinds: &mut Vec<String>) {
+    for tree in stream.trees() {
+        match tree {
+            TokenTree::Token(token, _) => kinds.push(format!("{:?}", token.kind)),
+            TokenTree::Delimited(_, _, stream) => token_kinds(stream, kinds),
+        }
+    }
+}
+
+#[test]
+fn raw_strings_lifetimes_and_shebang() {
+    let source = "#!/usr/bin/env run-cargo-script
+fn f<'a>(x: &'a str) -> &'a str { let _ = r#\"raw \"str\"\"#; x }";
+    let (res, diagnostics) = with_lexer(source, Edition::Edition2021, |stream, _| {
+        let mut kinds = Vec::new();
+        token_kinds(stream, &mut kinds);
+        kinds
+    });
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let kinds = res.unwrap();
+
+    // The shebang is skipped.
+    assert_eq!(kinds[0], "Ident(\"fn\", false)");
+    let count = |kind: &str| kinds.iter().filter(|k| *k == kind).count();
+    assert_eq!(count("Lifetime(\"'a\")"), 3);
+    assert_eq!(
+        count("Literal(Lit { kind: StrRaw(1), symbol: \"raw \\\"str\\\"\", suffix: None })"),
+        1,
+        "{kinds:?}"
+    );
+    // `->` is a single token.
+    assert_eq!(count(&format!("{:?}", TokenKind::RArrow)), 1);
+}
+
+#[test]
+fn unterminated_raw_string() {
+    let (res, diagnostics) = with_lexer("r#\"never closed", Edition::Edition2021, |_, _| ());
+    assert!(res.is_err());
+    assert!(
+        diagnostics[0].message.contains("unterminated raw string"),
+        "{diagnostics:?}"
+    );
+}
@@ -0,0 +1,40 @@
+#![feature(rustc_private)]
+
+use rustc_tools::{Config, Error, LimitKind};
+
+/// A macro expanded 100 times recursively.
+fn macro_heavy() -> String {
+    let tokens = "x ".repeat(100);
+    format!(
+        "macro_rules! count {{
+    () => {{ 0 }};
+    ($x:tt $($rest:tt)*) => {{ 1 + count!($($rest)*) }};
+}}
+
+pub const COUNT: u32 = count!({tokens});
+"
+    )
+}
+
+#[test]
+fn recursion_limit() {
+    let source = macro_heavy();
+    let res = Config::new(&[])
+        .source_code(&source)
+        .recursion_limit(64)
+        .with_tyctxt(|_| ());
+    match res {
+        Err(Error::LimitExceeded {
+            which: LimitKind::Recursion,
+            limit: 64,
+            span: Some(span),
+        }) => assert_eq!(span.line_start, 3, "{span:?}"),
+        res => panic!("{res:?}"),
+    }
+
+    let res = Config::new(&[])
+        .source_code(&source)
+        .recursion_limit(256)
+        .with_tyctxt(|_| ());
+    assert!(res.is_ok(), "{res:?}");
+}
@@ -0,0 +1,68 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::{DiagnosticsCollector, Level};
+use rustc_tools::rustc_session::lint::Level as LintLevel;
+use rustc_tools::{AnalysisLevel, Config, Error};
+
+const SOURCE: &str = "fn unused() {}\n";
+
+#[test]
+fn denied_lint_is_a_collected_error() {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::Full)
+        .collect_diagnostics(collector.clone())
+        .deny("dead_code")
+        .with_tyctxt(|_| ());
+    assert!(
+        matches!(res, Err(Error::CompilationFailed { .. })),
+        "{res:?}"
+    );
+    let diagnostics = collector.take();
+    let dead_code = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.code.as_deref() == Some("dead_code"))
+        .unwrap_or_else(|| panic!("{diagnostics:?}"));
+    assert_eq!(dead_code.level, Level::Error);
+    assert_eq!(dead_code.message, "function `unused` is never used");
+}
+
+#[test]
+fn last_level_wins_and_unknown_lints_warn() {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned(), "-Ddead_code".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::Full)
+        .collect_diagnostics(collector.clone())
+        .allow("dead_code")
+        .deny("dead_code")
+        .warn("dead_code")
+        .warn("not_a_lint")
+        .with_tyctxt(|_| ());
+    assert!(res.is_ok(), "{res:?}");
+    let diagnostics = collector.take();
+    let levels = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.code.as_deref(), diagnostic.level))
+        .collect::<Vec<_>>();
+    assert!(
+        levels.contains(&(Some("dead_code"), Level::Warning)),
+        "{diagnostics:?}"
+    );
+    assert!(
+        levels.contains(&(Some("unknown_lints"), Level::Warning)),
+        "{diagnostics:?}"
+    );
+
+    // The lints can't be more than warnings with a cap.
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::Full)
+        .collect_diagnostics(collector.clone())
+        .deny("dead_code")
+        .cap_lints(LintLevel::Warn)
+        .with_tyctxt(|_| ());
+    assert!(res.is_ok(), "{res:?}");
+    assert_eq!(collector.take()[0].level, Level::Warning);
+}
@@ -0,0 +1,149 @@
+#![feature(rustc_private)]
+
+use rustc_tools::lookup::{definition_at, node_at_position};
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::rustc_middle::ty::DefIdTree;
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn nested_closures_and_method_chain() {
+    let dir = env::temp_dir().join(format!("rustc-tools-lookup-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub fn run(values: Vec<u32>) -> u32 {
+    let add = |x: u32| {
+        let double = |y: u64| y * 2;
+        double(x as u64) as u32 + 1
+    };
+    values.iter().map(|v| add(*v)).filter(|v| *v > 3).sum()
+}
+",
+    )
+    .unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let res = with_tyctxt(&args, |tcx| {
+        let node = |line, col| {
+            let node = node_at_position(tcx, &root, line, col).unwrap();
+            let snippet = tcx.sess.source_map().span_to_snippet(node.span).unwrap();
+            (snippet, node.ty.unwrap().to_string())
+        };
+        // The closures nested in a closure.
+        assert_eq!(node(2, 30), ("y".to_owned(), "u64".to_owned()));
+        assert_eq!(node(3, 16), ("x".to_owned(), "u32".to_owned()));
+        let (snippet, ty) = node(3, 9);
+        assert_eq!(snippet, "double");
+        assert!(ty.starts_with("[closure@"), "{ty}");
+        assert_eq!(node(5, 30), ("*v".to_owned(), "u32".to_owned()));
+
+        // Each method of the chain is the call with the receiver before it.
+        let (snippet, ty) = node(5, 20);
+        assert_eq!(snippet, "values.iter().map(|v| add(*v))");
+        assert!(ty.starts_with("std::iter::Map<"), "{ty}");
+        let (snippet, ty) = node(5, 37);
+        assert_eq!(snippet, "values.iter().map(|v| add(*v)).filter(|v| *v > 3)");
+        assert!(ty.starts_with("std::iter::Filter<"), "{ty}");
+        let (snippet, ty) = node(5, 56);
+        assert!(snippet.ends_with(".sum()"), "{snippet}");
+        assert_eq!(ty, "u32");
+    });
+    let _ = fs::remove_dir_all(&dir);
+    res.unwrap();
+}
+
+#[test]
+fn method_call_to_an_impl_in_another_module() {
+    let dir = env::temp_dir().join(format!("rustc-tools-definition-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "mod shapes {
+    pub use self::square::Square;
+
+    mod square {
+        pub struct Square(pub u32);
+    }
+}
+
+mod impls {
+    use crate::shapes::Square;
+
+    pub trait Area {
+        fn area(&self) -> u32;
+    }
+
+    impl Square {
+        pub fn sides(&self) -> u32 {
+            4
+        }
+    }
+
+    impl Area for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+    }
+}
+
+use impls::Area;
+
+pub fn run() -> u32 {
+    let square = shapes::Square(2);
+    square.area() + square.sides()
+}
+",
+    )
+    .unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let res = with_tyctxt(&args, |tcx| {
+        let definition = |line, col| {
+            let location = definition_at(tcx, &root, line, col).unwrap();
+            let parent = tcx.parent(location.def_id);
+            let reexports = location
+                .reexports
+                .iter()
+                .map(|reexport| reexport.name.clone())
+                .collect::<Vec<_>>();
+            (
+                tcx.def_kind(location.def_id),
+                tcx.def_kind(parent),
+                location.file.unwrap(),
+                location.start.line,
+                reexports,
+            )
+        };
+        // The positions start at line 0, and the locations at line 1. `area` is called through
+        // the trait, it's the method of the impl.
+        let area = definition(32, 12);
+        let sides = definition(32, 27);
+        // The tuple struct is its constructor, re-exported by `shapes`.
+        let square = definition(31, 27);
+        (area, sides, square)
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let (area, sides, square) = res.unwrap();
+    assert_eq!(
+        area,
+        (DefKind::AssocFn, DefKind::Impl, root.clone(), 23, vec![])
+    );
+    assert_eq!(
+        sides,
+        (DefKind::AssocFn, DefKind::Impl, root.clone(), 17, vec![])
+    );
+    assert_eq!(
+        square,
+        (
+            DefKind::Struct,
+            DefKind::Mod,
+            root,
+            5,
+            vec!["Square".to_owned()]
+        )
+    );
+}
@@ -0,0 +1,69 @@
+#![feature(rustc_private)]
+
+use rustc_tools::metrics::{fn_metrics, ComplexitySource, MetricsOptions};
+use rustc_tools::{AnalysisLevel, Config};
+
+const SOURCE: &str = "pub fn classify(values: &[Option<u32>], limit: u32) -> u32 {
+    let mut total = 0;
+    for value in values {
+        match value {
+            Some(0) => {}
+            Some(n) => match n % 3 {
+                0 => total += 1,
+                1 => total += 2,
+                _ => {
+                    if *n > limit {
+                        total += 3;
+                    }
+                }
+            },
+            None => break,
+        }
+    }
+    total
+}
+
+#[derive(Debug)]
+pub struct Generated;
+";
+
+#[test]
+fn nested_match_and_loop() {
+    let metrics = |options| {
+        let res = Config::new(&["--crate-type=lib".to_owned()])
+            .source_code(SOURCE)
+            .analysis_level(AnalysisLevel::Full)
+            .with_tyctxt(|tcx| fn_metrics(tcx, options));
+        res.unwrap()
+    };
+    let hir = metrics(MetricsOptions::default());
+    // The `fmt` method of the derive isn't included.
+    assert_eq!(hir.len(), 1);
+    let classify = &hir[0];
+    assert_eq!(classify.name, "rust_out::classify");
+    assert_eq!(classify.location, "<anon>:1:1");
+    assert_eq!(classify.lines, 19);
+    // `let`, the `for` loop, the `match` in its body, the `if` and its statement, and `total`.
+    assert_eq!(classify.statements, 6);
+    // The body, the one of the loop, the one of the last arm and the one of the `if`.
+    assert_eq!(classify.max_nesting, 4);
+    assert_eq!(classify.params, 2);
+    // 1, plus 1 for the `for` loop, 2 for each `match` and 1 for the `if`.
+    assert_eq!(classify.complexity, 7);
+    assert_eq!(classify.complexity_source, ComplexitySource::Hir);
+
+    let mir = metrics(MetricsOptions {
+        use_mir: true,
+        include_macro_generated: true,
+    });
+    assert_eq!(mir.len(), 2);
+    assert_eq!(
+        (mir[0].complexity, mir[0].complexity_source),
+        (7, ComplexitySource::Mir)
+    );
+    assert_eq!(
+        mir[1].name,
+        "rust_out::<impl core::fmt::Debug for rust_out::Generated>::fmt"
+    );
+    assert_eq!(mir[1].complexity, 1);
+}
@@ -0,0 +1,185 @@
+#![feature(rustc_private)]
+
+use rustc_tools::mir::{all_mir_pretty, liveness, mir_pretty, MirPhase};
+use rustc_tools::rustc_middle::mir::{BasicBlock, Local, Location};
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::rustc_span::def_id::LocalDefId;
+use rustc_tools::{with_mir, with_tyctxt_from_str, AnalysisLevel, Config};
+
+use std::env;
+use std::fs;
+use std::process;
+
+/// The function named `name`.
+fn function(tcx: TyCtxt<'_>, name: &str) -> LocalDefId {
+    tcx.hir()
+        .body_owners()
+        .find(|&def_id| tcx.item_name(def_id.to_def_id()).as_str() == name)
+        .unwrap()
+}
+
+#[test]
+fn basic_blocks() {
+    let dir = env::temp_dir().join(format!("rustc-tools-mir-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub fn straight(x: u32) -> u32 { x }
+pub fn branches(x: bool) -> u32 { if x { 1 } else { 2 } }
+",
+    )
+    .unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let res = with_mir(&args, |tcx| {
+        let blocks = |name| tcx.optimized_mir(function(tcx, name)).basic_blocks.len();
+        (blocks("straight"), blocks("branches"))
+    });
+    let _ = fs::remove_dir_all(&dir);
+    // A `switchInt` to a block for each branch, which all go to the `return`.
+    assert_eq!(res.unwrap(), (1, 4));
+}
+
+#[test]
+fn liveness_of_branches() {
+    let source = "fn consume(x: u32) -> u32 { x }
+
+pub fn branching(flag: bool, a: u32, b: u32) -> u32 {
+    if flag { consume(a) + b } else { a }
+}
+";
+    let res = with_tyctxt_from_str(source, &["--crate-type=lib".to_owned()], |tcx| {
+        let result = liveness(tcx, function(tcx, "branching")).unwrap();
+        let names = |locals: &[Local]| {
+            locals
+                .iter()
+                .filter_map(|&local| result.local(local).name.clone())
+                .collect::<Vec<_>>()
+        };
+        let snippet = |span| tcx.sess.source_map().span_to_snippet(span).unwrap();
+        let point = |block: usize, statement_index| {
+            let location = Location {
+                block: BasicBlock::from_usize(block),
+                statement_index,
+            };
+            let point = result.point(location).unwrap();
+            (
+                snippet(point.span),
+                names(&point.live_before),
+                names(&point.live_after),
+            )
+        };
+        let across_calls = result
+            .variables_live_across_calls()
+            .into_iter()
+            .map(|(location, local)| {
+                (
+                    snippet(result.point(location).unwrap().span),
+                    local.name.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // The first statement reads `flag` to branch on it (the terminator of the block), the
+        // `else` branch returns `a` and the other one calls `consume`.
+        (point(0, 0), point(0, 1), point(4, 0), across_calls)
+    });
+    let vars = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+    };
+    let (start, switch, otherwise, across_calls) = res.unwrap();
+    assert_eq!(
+        start,
+        (
+            "flag".to_owned(),
+            vars(&["flag", "a", "b"]),
+            vars(&["a", "b"])
+        )
+    );
+    assert_eq!(
+        switch,
+        ("flag".to_owned(), vars(&["a", "b"]), vars(&["a", "b"]))
+    );
+    // `b` is only used by the other branch.
+    assert_eq!(otherwise, ("a".to_owned(), vars(&["a"]), vars(&[])));
+    assert_eq!(
+        across_calls,
+        [("consume(a)".to_owned(), Some("b".to_owned()))]
+    );
+}
+
+#[test]
+fn mir_snapshot() {
+    let source = "pub fn double(x: u32) -> u32 {
+    if x > 10 { x } else { x * 2 }
+}
+";
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(source)
+        .analysis_level(AnalysisLevel::Full)
+        .keep_built_mir(true)
+        .with_tyctxt(|tcx| {
+            let double = function(tcx, "double");
+            (
+                mir_pretty(tcx, double, MirPhase::Optimized).unwrap(),
+                mir_pretty(tcx, double, MirPhase::Built).unwrap(),
+                all_mir_pretty(tcx, MirPhase::Optimized),
+            )
+        });
+    let (optimized, built, all) = res.unwrap();
+    assert_eq!(optimized, include_str!("fixtures/mir/double.mir"));
+    for label in ["bb0: {", "bb2: {", "bb4: {"] {
+        assert!(optimized.contains(label), "{optimized}");
+    }
+    // The built MIR still has the empty blocks, the storage markers and the unwinding.
+    assert!(!optimized.contains("bb5: {"), "{optimized}");
+    assert!(built.contains("bb5: {"), "{built}");
+    assert!(built.contains("bb6 (cleanup): {"), "{built}");
+    assert!(built.contains("StorageLive(_2);"), "{built}");
+    assert_eq!(all.len(), 1);
+    assert_eq!(all["rust_out::double"], optimized);
+}
+
+#[test]
+fn opt_levels() {
+    let source = "pub fn branches(x: u32) -> u32 {
+    let y = if x > 10 { 1 } else { 1 };
+    match y {
+        0 => 5,
+        _ => x + y,
+    }
+}
+
+#[inline]
+fn positive(x: u32) -> u32 {
+    if x > 3 { x } else { 0 }
+}
+
+pub fn caller(x: u32) -> u32 {
+    positive(x)
+}
+";
+    let blocks = |config: Config| {
+        config
+            .source_code(source)
+            .analysis_level(AnalysisLevel::Full)
+            .with_tyctxt(|tcx| {
+                let blocks = |name| tcx.optimized_mir(function(tcx, name)).basic_blocks.len();
+                (blocks("branches"), blocks("caller"))
+            })
+            .unwrap()
+    };
+    let args = ["--crate-type=lib".to_owned()];
+    assert_eq!(blocks(Config::new(&args).mir_opt_level(0)), (8, 2));
+    // The default level doesn't simplify the branches either.
+    assert_eq!(blocks(Config::new(&args)), (8, 2));
+    // The constant `y` is propagated, so there's only the addition (with its overflow check).
+    assert_eq!(blocks(Config::new(&args).mir_opt_level(3)), (2, 2));
+    // The call is replaced by the body of `positive`.
+    assert_eq!(
+        blocks(Config::new(&args).mir_opt_level(3).inline_mir(true)),
+        (2, 3)
+    );
+}
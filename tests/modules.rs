@@ -0,0 +1,146 @@
+#![feature(rustc_private)]
+
+use rustc_tools::modules::{module_tree, ModuleSource};
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::rustc_span::FileName;
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+#[test]
+fn nested_inline_and_path_modules() {
+    let dir = env::temp_dir().join(format!("rustc-tools-modules-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub mod outer {
+    pub mod inner {
+        pub(crate) mod deepest {
+            pub fn depth() -> u32 {
+                3
+            }
+
+            pub struct Level;
+        }
+    }
+
+    pub fn depth() -> u32 {
+        inner::deepest::depth() - 2
+    }
+}
+
+#[path = \"weird.rs\"]
+mod renamed;
+",
+    )
+    .unwrap();
+    let weird = dir.join("weird.rs");
+    fs::write(
+        &weird,
+        "pub fn total() -> u32 {
+    crate::outer::depth() + crate::outer::inner::deepest::depth()
+}
+",
+    )
+    .unwrap();
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=layers".to_owned(),
+        root.display().to_string(),
+    ];
+    let res = with_tyctxt(&args, |tcx| {
+        let tree = module_tree(tcx);
+        assert!(!tree.incomplete);
+        tree.modules()
+            .into_iter()
+            .map(|module| {
+                let file = match &module.file {
+                    FileName::Real(name) => name.local_path().unwrap().to_path_buf(),
+                    name => panic!("{name:?}"),
+                };
+                let items = module
+                    .items
+                    .iter()
+                    .map(|group| {
+                        let names = group
+                            .items
+                            .iter()
+                            .map(|item| item.name.clone().unwrap_or_default());
+                        (group.kind, names.collect::<Vec<_>>())
+                    })
+                    .collect::<Vec<_>>();
+                let references = module
+                    .references
+                    .iter()
+                    .map(|&def_id| tcx.def_path_str(def_id.to_def_id()))
+                    .collect::<Vec<_>>();
+                (
+                    module.qualified_name.clone(),
+                    module.source,
+                    file,
+                    items,
+                    references,
+                )
+            })
+            .collect::<Vec<_>>()
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let modules = res.unwrap();
+
+    type Items = Vec<(DefKind, Vec<String>)>;
+    let module = |name: &str, source, file: &PathBuf, items: Items, references: &[&str]| {
+        (
+            format!("layers::{name}"),
+            source,
+            file.clone(),
+            items,
+            references
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>(),
+        )
+    };
+    let names = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+    };
+    // The root module is `layers`, which is skipped along with the injected items.
+    assert_eq!(
+        modules[1..],
+        [
+            module(
+                "outer",
+                ModuleSource::Inline,
+                &root,
+                vec![(DefKind::Fn, names(&["depth"]))],
+                &["outer::inner", "outer::inner::deepest"],
+            ),
+            module("outer::inner", ModuleSource::Inline, &root, vec![], &[]),
+            module(
+                "outer::inner::deepest",
+                ModuleSource::Inline,
+                &root,
+                vec![
+                    (DefKind::Fn, names(&["depth"])),
+                    (DefKind::Struct, names(&["Level"])),
+                ],
+                &[],
+            ),
+            module(
+                "renamed",
+                ModuleSource::PathAttribute,
+                &weird,
+                vec![(DefKind::Fn, names(&["total"]))],
+                &["outer", "outer::inner", "outer::inner::deepest"],
+            ),
+        ]
+    );
+    assert_eq!(modules[0].0, "layers");
+    assert_eq!(modules[0].1, ModuleSource::CrateRoot);
+}
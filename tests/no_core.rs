@@ -0,0 +1,37 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_hir::LangItem;
+use rustc_tools::{AnalysisLevel, Config};
+
+#[test]
+fn sized_lang_item_stub() {
+    let source = "#![feature(lang_items)]
+
+#[lang = \"sized\"]
+pub trait Sized {}
+
+#[lang = \"copy\"]
+pub trait Copy {}
+
+pub fn identity(x: u32) -> u32 {
+    x
+}
+";
+    // The sysroot isn't needed, so an invalid one doesn't matter.
+    let res = Config::new(&[
+        "--crate-type=lib".to_owned(),
+        "--sysroot=/nonexistent".to_owned(),
+    ])
+    .source_code(source)
+    .no_core(true)
+    .analysis_level(AnalysisLevel::TypeckBodies)
+    .with_tyctxt(|tcx| {
+        let sized = tcx.lang_items().get(LangItem::Sized).unwrap();
+        (
+            tcx.crates(()).len(),
+            sized.is_local(),
+            tcx.item_name(sized).to_string(),
+        )
+    });
+    assert_eq!(res.unwrap(), (0, true, "Sized".to_owned()));
+}
@@ -0,0 +1,90 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::{DiagnosticsCollector, Level};
+use rustc_tools::{AnalysisLevel, Config, EmitKind};
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn nothing_is_written() {
+    let res = Config::new(&args(&["--emit=link", "-o", "/nonexistent/x"]))
+        .source_code("fn main() {}")
+        .analysis_level(AnalysisLevel::Full)
+        .with_tyctxt(|_| ());
+    assert!(res.is_ok(), "{res:?}");
+
+    let dir = env::temp_dir().join(format!("rustc-tools-outputs-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("x").display().to_string();
+    let out_dir = dir.join("out").display().to_string();
+    let incremental = format!("incremental={}", dir.join("incr").display());
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&args(&[
+        "--emit=link,metadata",
+        "-o",
+        &output,
+        "--out-dir",
+        &out_dir,
+        "-C",
+        &incremental,
+    ]))
+    .source_code("fn main() {}")
+    .analysis_level(AnalysisLevel::Full)
+    .collect_diagnostics(collector.clone())
+    .with_tyctxt(|_| ());
+    let written = fs::read_dir(&dir).unwrap().count();
+    let _ = fs::remove_dir_all(&dir);
+    assert!(res.is_ok(), "{res:?}");
+    assert_eq!(written, 0);
+
+    let warnings = collector
+        .take()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.level == Level::Warning)
+        .map(|diagnostic| diagnostic.message)
+        .collect::<Vec<_>>();
+    for option in ["--emit", "-o", "--out-dir", "-C incremental"] {
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.starts_with(&format!("`{option}` is ignored"))),
+            "{warnings:?}"
+        );
+    }
+}
+
+#[test]
+fn emit_metadata_and_mir() {
+    let dir = env::temp_dir().join(format!("rustc-tools-outputs-emit-{}", process::id()));
+    let res = Config::new(&args(&["--crate-name=shapes", "--crate-type=lib"]))
+        .source_code("pub fn sides() -> u32 { 4 }")
+        .emit(EmitKind::Metadata, dir.join("metadata"))
+        .emit(EmitKind::Mir, dir.join("mir"))
+        .with_tyctxt_emit(|_| ());
+    let contents = res.as_ref().ok().map(|(_, paths)| {
+        paths
+            .iter()
+            .map(|path| fs::read(path).unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let ((), paths) = res.unwrap();
+    assert_eq!(
+        paths,
+        [
+            dir.join("metadata").join("libshapes.rmeta"),
+            dir.join("mir").join("shapes.mir"),
+        ]
+    );
+    let contents = contents.unwrap();
+    assert!(!contents[0].is_empty());
+    let mir = String::from_utf8(contents[1].clone()).unwrap();
+    assert!(mir.contains("fn sides() -> u32"), "{mir}");
+    assert!(mir.contains("_0 = const 4_u32;"), "{mir}");
+}
@@ -0,0 +1,100 @@
+#![feature(rustc_private)]
+
+use rustc_tools::panics::{
+    panic_reachability, panic_reachability_with_options, PanicOptions, PanicReport, PanicVerdict,
+};
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::{AnalysisLevel, Config};
+
+const SOURCE: &str = "pub fn top(value: Option<u32>) -> u32 {
+    middle(value)
+}
+
+fn middle(value: Option<u32>) -> u32 {
+    bottom(value)
+}
+
+fn bottom(value: Option<u32>) -> u32 {
+    value.unwrap()
+}
+
+pub fn safe(value: Option<u32>) -> u32 {
+    value.unwrap_or(0)
+}
+";
+
+/// The callees and the locations of the calls of a path.
+type Calls = Vec<(String, String)>;
+
+/// Returns the verdicts of `report` with the calls of their path, without the last one (which is
+/// in `core`).
+fn verdicts(tcx: TyCtxt<'_>, report: PanicReport) -> Vec<(String, Option<Calls>)> {
+    let source_map = tcx.sess.source_map();
+    report
+        .functions
+        .into_iter()
+        .map(|function| {
+            let path = match function.verdict {
+                PanicVerdict::PanicFree => None,
+                PanicVerdict::MayPanic { path } => {
+                    assert_eq!(path.last().unwrap().callee, "core::panicking::panic");
+                    let calls = path[..path.len() - 1].iter().map(|call| {
+                        (
+                            call.callee.clone(),
+                            source_map.span_to_diagnostic_string(call.span),
+                        )
+                    });
+                    Some(calls.collect())
+                }
+                verdict => panic!("{verdict:?}"),
+            };
+            (function.name, path)
+        })
+        .collect()
+}
+
+#[test]
+fn unwrap_two_levels_down() {
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::Full)
+        .with_tyctxt(|tcx| {
+            let options = PanicOptions {
+                allowed: vec!["core::option::Option::unwrap".to_owned()],
+            };
+            (
+                verdicts(tcx, panic_reachability(tcx)),
+                verdicts(tcx, panic_reachability_with_options(tcx, &options)),
+            )
+        });
+    let (verdicts, allowed) = res.unwrap();
+    let call = |callee: &str, location: &str| (callee.to_owned(), location.to_owned());
+    let unwrap = call("core::option::Option::unwrap", "<anon>:10:5: 10:19");
+    assert_eq!(
+        verdicts,
+        [
+            (
+                "rust_out::top".to_owned(),
+                Some(vec![
+                    call("rust_out::middle", "<anon>:2:5: 2:18"),
+                    call("rust_out::bottom", "<anon>:6:5: 6:18"),
+                    unwrap.clone(),
+                ])
+            ),
+            (
+                "rust_out::middle".to_owned(),
+                Some(vec![
+                    call("rust_out::bottom", "<anon>:6:5: 6:18"),
+                    unwrap.clone()
+                ])
+            ),
+            ("rust_out::bottom".to_owned(), Some(vec![unwrap])),
+            ("rust_out::safe".to_owned(), None),
+        ]
+    );
+    // Nothing else may panic.
+    assert!(
+        allowed.iter().all(|(_, path)| path.is_none()),
+        "{allowed:?}"
+    );
+}
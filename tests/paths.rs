@@ -0,0 +1,138 @@
+#![feature(rustc_private)]
+
+use rustc_tools::paths::{qualified_name, resolve_path, resolve_path_str};
+use rustc_tools::rustc_hir::def::{DefKind, Res};
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn local_and_core_items() {
+    let dir = env::temp_dir().join(format!("rustc-tools-paths-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub mod shapes {
+    pub struct Square;
+
+    impl Square {
+        pub fn new() -> Self {
+            Square
+        }
+    }
+}
+",
+    )
+    .unwrap();
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+        root.display().to_string(),
+    ];
+    let res = with_tyctxt(&args, |tcx| {
+        let names = |res: Vec<Res>| {
+            res.into_iter()
+                .map(|res| match res {
+                    Res::Def(kind, def_id) => (kind, qualified_name(tcx, def_id)),
+                    res => panic!("unexpected {res:?}"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let square = "krate::shapes::Square".to_owned();
+        // A unit struct is both a type and a constructor.
+        let resolved = names(resolve_path(tcx, &["krate", "shapes", "Square"]));
+        assert!(
+            resolved.contains(&(DefKind::Struct, square.clone())),
+            "{resolved:?}"
+        );
+        assert_eq!(
+            names(resolve_path(tcx, &["crate", "shapes", "Square", "new"])),
+            [(DefKind::AssocFn, format!("{square}::new"))]
+        );
+        assert_eq!(
+            names(resolve_path(tcx, &["core", "mem", "transmute"])),
+            [(
+                DefKind::Fn,
+                "core::intrinsics::{extern#0}::transmute".to_owned()
+            )]
+        );
+        assert_eq!(
+            resolve_path(tcx, &["core", "mem", "transmute"]),
+            resolve_path_str(tcx, "::core::mem::transmute")
+        );
+        assert!(resolve_path(tcx, &["krate", "shapes", "Circle"]).is_empty());
+    });
+    let _ = fs::remove_dir_all(&dir);
+    res.unwrap();
+}
+
+#[test]
+fn qualified_names() {
+    let dir = env::temp_dir().join(format!("rustc-tools-qualified-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub mod shapes {
+    #[derive(Clone)]
+    pub struct Square(pub u32);
+
+    impl Square {
+        pub fn area(&self) -> u32 {
+            let sides = [self.0; 2];
+            sides.iter().map(|side| side * 2).sum::<u32>() / 2
+        }
+    }
+
+    pub trait Shape {
+        const SIDES: u32;
+    }
+
+    impl Shape for Square {
+        const SIDES: u32 = 4;
+    }
+
+    impl Shape for [Square; 2] {
+        const SIDES: u32 = 8;
+    }
+
+    impl Shape for (&Square, *const Square) {
+        const SIDES: u32 = 8;
+    }
+}
+",
+    )
+    .unwrap();
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+        root.display().to_string(),
+    ];
+    let res = with_tyctxt(&args, |tcx| {
+        let mut names = tcx
+            .hir()
+            .body_owners()
+            .map(|def_id| qualified_name(tcx, def_id.to_def_id()))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    });
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(
+        res.unwrap(),
+        [
+            "krate::shapes::<impl core::clone::Clone for krate::shapes::Square>::clone",
+            "krate::shapes::<impl krate::shapes::Shape for (&krate::shapes::Square, *const krate::shapes::Square)>::SIDES",
+            "krate::shapes::<impl krate::shapes::Shape for [krate::shapes::Square; 2]>::SIDES",
+            "krate::shapes::<impl krate::shapes::Shape for [krate::shapes::Square; 2]>::{constant#0}",
+            "krate::shapes::<impl krate::shapes::Shape for krate::shapes::Square>::SIDES",
+            "krate::shapes::Square::area",
+            "krate::shapes::Square::area::{closure#0}",
+            "krate::shapes::Square::area::{constant#0}",
+        ]
+    );
+}
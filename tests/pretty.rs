@@ -0,0 +1,91 @@
+#![feature(rustc_private)]
+
+use rustc_tools::pretty::{hir_debug_tree, hir_pretty};
+use rustc_tools::rustc_hir::ExprKind;
+use rustc_tools::visit::for_each_expr_in_body;
+use rustc_tools::with_tyctxt_from_str;
+
+use std::ops::ControlFlow;
+
+const SOURCE: &str = "pub fn describe(n: Option<u32>) -> &'static str {
+    match n {
+        Some(0) => \"zero\",
+        Some(x) if x > 9 => \"big\",
+        Some(_) | None => \"other\",
+    }
+}
+";
+
+#[test]
+fn match_expression() {
+    let res = with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], |tcx| {
+        let def_id = tcx.hir().body_owners().next().unwrap();
+        let body = tcx.hir().body_owned_by(def_id);
+        let expr = for_each_expr_in_body(tcx, body, |expr| match expr.kind {
+            ExprKind::Match(..) => ControlFlow::Break(expr),
+            _ => ControlFlow::Continue(()),
+        })
+        .unwrap();
+        (
+            hir_pretty(tcx, tcx.hir().local_def_id_to_hir_id(def_id)),
+            hir_pretty(tcx, expr.hir_id),
+            hir_debug_tree(tcx, expr.hir_id, usize::MAX),
+            hir_debug_tree(tcx, expr.hir_id, 1),
+        )
+    });
+    let (item, expr, tree, shallow_tree) = res.unwrap();
+    // The indentation is the one of the compiler when the node is printed on its own.
+    assert_eq!(
+        item,
+        "fn describe(n: Option<u32>)
+    ->
+        &'static str {
+        match n {
+                Some(0) => \"zero\",
+                Some(x) if x > 9 => \"big\",
+                Some(_) | None => \"other\",
+            }
+    }"
+    );
+    assert_eq!(
+        expr,
+        "match n {
+        Some(0) => \"zero\",
+        Some(x) if x > 9 => \"big\",
+        Some(_) | None => \"other\",
+    }"
+    );
+    assert_eq!(
+        tree,
+        "Expr(Match) <anon>:2:5-6:6
+  Expr(Path n) <anon>:2:11-2:12
+  Arm <anon>:3:9-3:26
+    Pat(TupleStruct Some) <anon>:3:9-3:16
+      Pat(Lit) <anon>:3:14-3:15
+        Expr(Lit) <anon>:3:14-3:15
+    Expr(Lit) <anon>:3:20-3:26
+  Arm <anon>:4:9-4:34
+    Pat(TupleStruct Some) <anon>:4:9-4:16
+      Pat(Binding x) <anon>:4:14-4:15
+    Expr(Binary >) <anon>:4:20-4:25
+      Expr(Path x) <anon>:4:20-4:21
+      Expr(Lit) <anon>:4:24-4:25
+    Expr(Lit) <anon>:4:29-4:34
+  Arm <anon>:5:9-5:34
+    Pat(Or) <anon>:5:9-5:23
+      Pat(TupleStruct Some) <anon>:5:9-5:16
+        Pat(Wild) <anon>:5:14-5:15
+      Pat(Path None) <anon>:5:19-5:23
+    Expr(Lit) <anon>:5:27-5:34
+"
+    );
+    assert_eq!(
+        shallow_tree,
+        "Expr(Match) <anon>:2:5-6:6
+  Expr(Path n) <anon>:2:11-2:12
+  Arm <anon>:3:9-3:26
+  Arm <anon>:4:9-4:34
+  Arm <anon>:5:9-5:34
+"
+    );
+}
@@ -0,0 +1,132 @@
+#![feature(rustc_private)]
+
+use rustc_tools::attrs::{attr_meta_items, has_attr, MetaItemValue};
+use rustc_tools::consts::{eval_const, ConstValueRepr};
+use rustc_tools::rustc_session::config::CrateType;
+use rustc_tools::visit::for_each_fn;
+use rustc_tools::{with_tyctxt, Config, Error};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+fn hello_derive_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hello_derive/lib.rs")
+}
+
+/// Builds the `hello_derive` fixture into `out_dir` and returns the path of the library.
+fn build_hello_derive(out_dir: &Path) -> PathBuf {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let status = Command::new(rustc)
+        .args([
+            "--crate-type=proc-macro",
+            "--crate-name=hello_derive",
+            "--edition=2021",
+        ])
+        .arg("--out-dir")
+        .arg(out_dir)
+        .arg(hello_derive_root())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    out_dir.join(format!(
+        "{}hello_derive{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    ))
+}
+
+#[test]
+fn local_derive() {
+    let dir = env::temp_dir().join(format!("rustc-tools-proc-macros-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let library = build_hello_derive(&dir);
+    let source = "use hello_derive::Hello;
+
+pub trait Hello {
+    const NAME: &'static str;
+}
+
+#[derive(Hello)]
+pub struct World;
+
+pub const GREETING: &str = World::NAME;
+";
+    let res = Config::new(&["--crate-type=lib".to_owned(), "--edition=2021".to_owned()])
+        .source_code(source)
+        .extern_proc_macro("hello_derive", library)
+        .with_tyctxt(|tcx| {
+            let greeting = tcx
+                .hir()
+                .items()
+                .find(|&id| tcx.hir().item(id).ident.as_str() == "GREETING")
+                .unwrap();
+            eval_const(tcx, greeting.owner_id.to_def_id())
+        });
+
+    // A library which isn't a proc-macro crate is rejected before running the compiler.
+    fs::write(dir.join("not_a_macro.rs"), "pub fn f() {}\n").unwrap();
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let status = Command::new(rustc)
+        .args([
+            "--crate-type=dylib",
+            "--crate-name=not_a_macro",
+            "--out-dir",
+        ])
+        .arg(&dir)
+        .arg(dir.join("not_a_macro.rs"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let not_a_macro = dir.join(format!(
+        "{}not_a_macro{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    ));
+    let invalid = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code("")
+        .extern_proc_macro("not_a_macro", not_a_macro)
+        .with_tyctxt(|_| ());
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(
+        res.unwrap().unwrap(),
+        ConstValueRepr::Str("World".to_owned())
+    );
+    match invalid {
+        Err(Error::InvalidExtern(e)) => assert!(e.ends_with("isn't a proc-macro crate"), "{e}"),
+        res => panic!("{res:?}"),
+    }
+}
+
+#[test]
+fn derive_functions_of_a_proc_macro_crate() {
+    // The crate type is inferred from the `#[proc_macro_derive]`.
+    let args = [
+        "--edition=2021".to_owned(),
+        hello_derive_root().display().to_string(),
+    ];
+    let res = with_tyctxt(&args, |tcx| {
+        let mut derives = Vec::new();
+        for_each_fn(tcx, |def_id, _, _| {
+            let def_id = def_id.to_def_id();
+            if has_attr(tcx, def_id, &["proc_macro_derive"]) {
+                derives.push((
+                    tcx.item_name(def_id).to_string(),
+                    attr_meta_items(tcx, def_id, &["proc_macro_derive"]),
+                ));
+            }
+        });
+        (tcx.sess.crate_types().to_vec(), derives)
+    });
+    let (crate_types, derives) = res.unwrap();
+    assert_eq!(crate_types, [CrateType::ProcMacro]);
+    assert_eq!(
+        derives,
+        [(
+            "derive_hello".to_owned(),
+            vec![MetaItemValue::Path("Hello".to_owned())]
+        )]
+    );
+}
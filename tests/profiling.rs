@@ -0,0 +1,45 @@
+#![feature(rustc_private)]
+
+use rustc_tools::profiling::{with_tyctxt_profiled, PassTimings};
+use rustc_tools::{AnalysisLevel, Config};
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn stages_are_timed() {
+    let dir = env::temp_dir().join(format!("rustc-tools-profiling-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(&root, "pub fn one() -> u32 { 1 }\n").unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+
+    let (res, timings) = with_tyctxt_profiled(&args, |_| ());
+    res.unwrap();
+    let names = timings
+        .iter()
+        .map(|timing| timing.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["parse", "expansion", "global_ctxt", "callback"]);
+
+    let collector = PassTimings::new();
+    let res = Config::new(&args)
+        .analysis_level(AnalysisLevel::Full)
+        .pass_timings(collector.clone())
+        .with_tyctxt(|_| ());
+    let _ = fs::remove_dir_all(&dir);
+    res.unwrap();
+    let timings = collector.take();
+    let names = timings
+        .iter()
+        .map(|timing| timing.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["parse", "expansion", "global_ctxt", "analysis", "callback"]
+    );
+    // The expansion loads `std`, so it can't be instantaneous.
+    assert!(!timings[1].duration.is_zero(), "{timings:?}");
+    assert!(collector.take().is_empty());
+}
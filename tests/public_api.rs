@@ -0,0 +1,65 @@
+#![feature(rustc_private)]
+
+use rustc_tools::api::public_api;
+use rustc_tools::with_tyctxt_from_str;
+
+#[test]
+fn reexport_chains_and_globs() {
+    let source = "mod private {
+    pub mod inner {
+        pub struct Deep;
+    }
+
+    pub fn helper() {}
+}
+
+pub mod middle {
+    pub use crate::private::inner::Deep as Renamed;
+}
+
+pub use middle::Renamed as Top;
+
+pub mod prelude {
+    pub use crate::private::*;
+}
+";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(source, &args, |tcx| {
+        public_api(tcx)
+            .items
+            .into_iter()
+            .map(|item| (item.path, item.definition, item.kind))
+            .collect::<Vec<_>>()
+    });
+    let item = |path: &str, definition: &str, kind: &str| {
+        (path.to_owned(), definition.to_owned(), kind.to_owned())
+    };
+    // Each path is followed to the original definition.
+    assert_eq!(
+        res.unwrap(),
+        [
+            item("krate::Top", "krate::private::inner::Deep", "struct"),
+            item("krate::middle", "krate::middle", "module"),
+            item(
+                "krate::middle::Renamed",
+                "krate::private::inner::Deep",
+                "struct"
+            ),
+            item("krate::prelude", "krate::prelude", "module"),
+            item(
+                "krate::prelude::helper",
+                "krate::private::helper",
+                "function"
+            ),
+            item("krate::prelude::inner", "krate::private::inner", "module"),
+            item(
+                "krate::prelude::inner::Deep",
+                "krate::private::inner::Deep",
+                "struct"
+            ),
+        ]
+    );
+}
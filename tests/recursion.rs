@@ -0,0 +1,61 @@
+#![feature(rustc_private)]
+
+use rustc_tools::recursion::recursion_cycles;
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = r#"
+fn direct(n: u32) -> u32 {
+    if n == 0 { 0 } else { direct(n - 1) }
+}
+
+fn a(n: u32) {
+    if n > 0 { b(n - 1) }
+}
+
+fn b(n: u32) {
+    if n > 0 { c(n - 1) }
+}
+
+fn c(n: u32) {
+    if n > 0 { a(n - 1) }
+}
+
+// Same name, but no recursion.
+mod first {
+    pub fn run() {
+        super::second::run()
+    }
+}
+
+mod second {
+    pub fn run() {}
+}
+
+fn main() {
+    direct(1);
+    a(1);
+    first::run();
+}
+"#;
+
+#[test]
+fn cycles() {
+    let cycles = with_tyctxt_from_str(SOURCE, &["--crate-name=krate".to_owned()], |tcx| {
+        recursion_cycles(tcx)
+            .into_iter()
+            .map(|cycle| {
+                assert!(!cycle.possible);
+                assert_eq!(cycle.calls.len(), cycle.functions.len());
+                cycle.functions
+            })
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+    assert_eq!(
+        cycles,
+        [
+            vec!["krate::a", "krate::b", "krate::c"],
+            vec!["krate::direct"]
+        ]
+    );
+}
@@ -0,0 +1,71 @@
+#![feature(rustc_private)]
+
+use rustc_tools::dependencies::compile_dependency;
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::stable_ids::dep_crate_hashes;
+use rustc_tools::with_tyctxt_from_str;
+
+use std::env;
+use std::fs;
+use std::process;
+use std::thread;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// The names and the hashes of the crates loaded by the local crate.
+fn loaded_crates(tcx: TyCtxt<'_>) -> Vec<(String, u64)> {
+    dep_crate_hashes(tcx)
+        .into_iter()
+        .map(|dep| (dep.name, dep.hash))
+        .collect()
+}
+
+#[test]
+fn sequential_analyses_of_different_crates() {
+    let dir = env::temp_dir().join(format!("rustc-tools-repeated-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dep_root = dir.join("dep.rs");
+    fs::write(&dep_root, "pub fn answer() -> u32 { 42 }").unwrap();
+    let dep = compile_dependency(&dep_root, "dep", &dir, &[]).unwrap();
+
+    let first = with_tyctxt_from_str(
+        "pub fn empty() -> Vec<u8> { Vec::new() }",
+        &args(&["--crate-type=lib", "--crate-name=first"]),
+        loaded_crates,
+    )
+    .unwrap();
+    let extern_dep = format!("dep={}", dep.display());
+    let second = with_tyctxt_from_str(
+        "fn main() { let _ = dep::answer(); }",
+        &args(&["--crate-name=second", "--extern", &extern_dep]),
+        loaded_crates,
+    )
+    .unwrap();
+    let _ = fs::remove_dir_all(dir);
+
+    // The crates of the sysroot are the same ones in both runs.
+    let std = |crates: &[(String, u64)]| crates.iter().find(|(name, _)| name == "std").cloned();
+    assert!(std(&first).is_some(), "{first:?}");
+    assert_eq!(std(&first), std(&second));
+    assert!(!first.iter().any(|(name, _)| name == "dep"), "{first:?}");
+    assert!(second.iter().any(|(name, _)| name == "dep"), "{second:?}");
+}
+
+#[test]
+fn concurrent_analyses() {
+    let threads = (0..4)
+        .map(|i| {
+            thread::spawn(move || {
+                let source = format!("pub const N: usize = {i};");
+                with_tyctxt_from_str(&source, &args(&["--crate-type=lib"]), |tcx| {
+                    tcx.hir().items().count()
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        assert!(thread.join().unwrap().unwrap() > 0);
+    }
+}
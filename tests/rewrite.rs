@@ -0,0 +1,112 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rewrite::{RewriteError, RewrittenFile, SourceRewriter};
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::rustc_span::{BytePos, Span};
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = "pub fn area(width: u32, height: u32) -> u32 {
+    width * height
+}
+";
+
+/// The span of the `n`th occurrence of `text` in the source code.
+fn span(tcx: TyCtxt<'_>, text: &str, n: usize) -> Span {
+    let file = &tcx.sess.source_map().files()[0];
+    let lo = SOURCE.match_indices(text).nth(n).unwrap().0 as u32;
+    let hi = lo + text.len() as u32;
+    Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(hi))
+}
+
+/// Rewrites [`SOURCE`] with the edits added by `edit`.
+fn rewrite<F: FnOnce(TyCtxt<'_>, &mut SourceRewriter<'_>) + Send>(edit: F) -> RewrittenFile {
+    let res = with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], |tcx| {
+        let mut rewriter = SourceRewriter::new(tcx.sess.source_map());
+        edit(tcx, &mut rewriter);
+        rewriter.rewrite()
+    });
+    let mut files = res.unwrap();
+    assert_eq!(files.len(), 1);
+    files.remove(0)
+}
+
+#[test]
+fn adjacent_edits() {
+    let file = rewrite(|tcx, rewriter| {
+        // `width`, then `: u32` right after it, then an insertion where both meet.
+        rewriter.replace(span(tcx, "width", 0), "w").unwrap();
+        rewriter.replace(span(tcx, ": u32", 0), ": u64").unwrap();
+        rewriter
+            .insert_before(span(tcx, ": u32", 0), "_unused")
+            .unwrap();
+        rewriter.delete(span(tcx, "width * ", 0)).unwrap();
+        // The end of `width * ` is the beginning of `height`.
+        rewriter
+            .insert_before(span(tcx, "height", 1), "2 * ")
+            .unwrap();
+        assert_eq!(
+            rewriter.replace(span(tcx, "th: ", 0), ""),
+            Err(RewriteError::Overlap {
+                span: span(tcx, "th: ", 0),
+                previous: span(tcx, "width", 0),
+            })
+        );
+    });
+    assert_eq!(file.path, None);
+    assert_eq!(
+        file.contents,
+        "pub fn area(w_unused: u64, height: u32) -> u32 {
+    2 * height
+}
+"
+    );
+    assert_eq!(
+        file.diff,
+        "--- <anon>
++++ <anon>
+@@ -1,3 +1,3 @@
+-pub fn area(width: u32, height: u32) -> u32 {
+-    width * height
++pub fn area(w_unused: u64, height: u32) -> u32 {
++    2 * height
+ }
+"
+    );
+}
+
+#[test]
+fn edits_at_file_start_and_end() {
+    let file = rewrite(|tcx, rewriter| {
+        let start = span(tcx, "pub", 0);
+        let end = span(tcx, "}\n", 0).shrink_to_hi();
+        rewriter.insert_before(start, "//! Areas.\n\n").unwrap();
+        rewriter.replace(start, "pub(crate)").unwrap();
+        rewriter.insert_before(end, "\npub fn zero() {}\n").unwrap();
+        rewriter.delete(span(tcx, "}\n", 0)).unwrap();
+    });
+    assert_eq!(
+        file.contents,
+        "//! Areas.
+
+pub(crate) fn area(width: u32, height: u32) -> u32 {
+    width * height
+
+pub fn zero() {}
+"
+    );
+    assert_eq!(
+        file.diff,
+        "--- <anon>
++++ <anon>
+@@ -1,3 +1,6 @@
+-pub fn area(width: u32, height: u32) -> u32 {
++//! Areas.
++
++pub(crate) fn area(width: u32, height: u32) -> u32 {
+     width * height
+-}
++
++pub fn zero() {}
+"
+    );
+}
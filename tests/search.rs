@@ -0,0 +1,150 @@
+#![feature(rustc_private)]
+
+use rustc_tools::expansion::ExpansionOrigin;
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::search::{find_defs, find_references, NamePattern, ReferenceKind};
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = "pub mod shapes {
+    #[derive(Clone)]
+    pub struct Square {
+        pub side: u32,
+    }
+
+    pub fn clone_square(square: &Square) -> Square {
+        square.clone()
+    }
+}
+
+mod util {
+    fn clone_twice<T: Clone>(value: &T) -> (T, T) {
+        (value.clone(), value.clone())
+    }
+
+    pub struct Cloned;
+}
+";
+
+/// Returns the qualified names, the kinds and the derive (if any) of the definitions matched by
+/// `pattern`.
+fn find(pattern: NamePattern) -> Vec<(String, DefKind, Option<String>)> {
+    let res = with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], |tcx| {
+        find_defs(tcx, &pattern)
+            .into_iter()
+            .map(|def_match| {
+                let derive = match def_match.origin {
+                    ExpansionOrigin::Written => None,
+                    ExpansionOrigin::Derive { trait_name, .. } => Some(trait_name),
+                    origin => panic!("{origin:?}"),
+                };
+                (def_match.qualified_name, def_match.kind, derive)
+            })
+            .collect()
+    });
+    res.unwrap()
+}
+
+#[test]
+fn similar_names_across_modules() {
+    let written = |name: &str, kind| (name.to_owned(), kind, None);
+    assert_eq!(
+        find(NamePattern::substring("lone")),
+        [
+            (
+                "rust_out::shapes::<impl core::clone::Clone for rust_out::shapes::Square>::clone"
+                    .to_owned(),
+                DefKind::AssocFn,
+                Some("Clone".to_owned())
+            ),
+            written("rust_out::shapes::clone_square", DefKind::Fn),
+            written("rust_out::util::clone_twice", DefKind::Fn),
+            written("rust_out::util::Cloned", DefKind::Struct),
+        ]
+    );
+    assert_eq!(
+        find(NamePattern::glob("clone_*").public_only()),
+        [written("rust_out::shapes::clone_square", DefKind::Fn)]
+    );
+    assert_eq!(
+        find(NamePattern::glob("?lone*").kinds(&[DefKind::Struct])),
+        [written("rust_out::util::Cloned", DefKind::Struct)]
+    );
+    // Only the method generated by the derive is named `clone`.
+    assert_eq!(find(NamePattern::exact("clone")).len(), 1);
+}
+
+#[test]
+fn references_to_a_function() {
+    let source = "mod shapes {
+    pub fn area(side: u32) -> u32 {
+        side * side
+    }
+}
+
+use shapes::area as surface;
+
+pub fn direct() -> u32 {
+    shapes::area(2)
+}
+
+pub fn renamed() -> u32 {
+    surface(3)
+}
+
+pub fn pointer() -> fn(u32) -> u32 {
+    shapes::area
+}
+";
+    let res = with_tyctxt_from_str(source, &["--crate-type=lib".to_owned()], |tcx| {
+        let area = find_defs(tcx, &NamePattern::exact("area"))[0].def_id;
+        let references = find_references(tcx, area);
+        assert!(!references.incomplete);
+        let source_map = tcx.sess.source_map();
+        references
+            .references
+            .into_iter()
+            .map(|reference| {
+                assert!(!reference.from_expansion);
+                (
+                    source_map.span_to_diagnostic_string(reference.span),
+                    source_map.span_to_snippet(reference.span).unwrap(),
+                    reference.kind,
+                    tcx.opt_item_name(reference.used_in)
+                        .map(|name| name.to_string()),
+                )
+            })
+            .collect::<Vec<_>>()
+    });
+    let reference = |location: &str, snippet: &str, kind, used_in: Option<&str>| {
+        (
+            location.to_owned(),
+            snippet.to_owned(),
+            kind,
+            used_in.map(str::to_owned),
+        )
+    };
+    assert_eq!(
+        res.unwrap(),
+        [
+            reference("<anon>:7:13: 7:17", "area", ReferenceKind::Import, None),
+            reference(
+                "<anon>:10:13: 10:17",
+                "area",
+                ReferenceKind::Call,
+                Some("direct")
+            ),
+            reference(
+                "<anon>:14:5: 14:12",
+                "surface",
+                ReferenceKind::Call,
+                Some("renamed")
+            ),
+            reference(
+                "<anon>:18:13: 18:17",
+                "area",
+                ReferenceKind::Expression,
+                Some("pointer")
+            ),
+        ]
+    );
+}
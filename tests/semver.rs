@@ -0,0 +1,199 @@
+#![feature(rustc_private)]
+
+use rustc_tools::api::public_api;
+use rustc_tools::semver::{semver_diff, ApiChangeKind, SemverLevel};
+use rustc_tools::with_tyctxt_from_str;
+
+use ApiChangeKind::{Added, Changed, Removed};
+use SemverLevel::{Major, Minor, Patch};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// The path, kind and level of the changes between the crates `old` and `new`.
+fn diff(old: &str, new: &str) -> Vec<(String, ApiChangeKind, SemverLevel)> {
+    let args = args(&["--crate-name=krate", "--crate-type=lib"]);
+    let old = with_tyctxt_from_str(old, &args, public_api).unwrap();
+    let new = with_tyctxt_from_str(new, &args, public_api).unwrap();
+    semver_diff(&old, &new)
+        .into_iter()
+        .map(|change| (change.path, change.kind, change.level))
+        .collect()
+}
+
+fn change(
+    path: &str,
+    kind: ApiChangeKind,
+    level: SemverLevel,
+) -> (String, ApiChangeKind, SemverLevel) {
+    (path.to_owned(), kind, level)
+}
+
+#[test]
+fn items() {
+    assert_eq!(
+        diff(
+            "pub fn a() {}\npub fn b() {}",
+            "pub fn a() {}\npub fn c() {}"
+        ),
+        [
+            change("krate::b", Removed, Major),
+            change("krate::c", Added, Minor),
+        ]
+    );
+    // The private items aren't part of the API.
+    assert_eq!(diff("fn a() {}", "fn b() {}"), []);
+}
+
+#[test]
+fn signatures() {
+    assert_eq!(
+        diff("pub fn f(_: u32) {}", "pub fn f(_: u64) {}"),
+        [change("krate::f", Changed, Major)]
+    );
+    // Renaming a generic parameter isn't a change, reordering them is one.
+    assert_eq!(
+        diff(
+            "pub fn f<T, U>(_: T, _: U) {}",
+            "pub fn f<A, B>(_: A, _: B) {}"
+        ),
+        []
+    );
+    assert_eq!(
+        diff(
+            "pub fn f<T, U>(_: T, _: U) {}",
+            "pub fn f<U, T>(_: T, _: U) {}"
+        ),
+        [change("krate::f", Changed, Major)]
+    );
+    assert_eq!(
+        diff(
+            "pub fn f<T: Clone>(_: T) {}",
+            "pub fn f<T>(_: T) where T: Clone + Send {}"
+        ),
+        [change("krate::f", Changed, Major)]
+    );
+}
+
+#[test]
+fn variants() {
+    assert_eq!(
+        diff("pub enum E { A }", "pub enum E { A, B }"),
+        [change("krate::E::B", Added, Major)]
+    );
+    assert_eq!(
+        diff(
+            "#[non_exhaustive]\npub enum E { A }",
+            "#[non_exhaustive]\npub enum E { A, B }"
+        ),
+        [change("krate::E::B", Added, Minor)]
+    );
+    // A new enum is only a new item.
+    assert_eq!(
+        diff("", "pub enum E { A }"),
+        [
+            change("krate::E", Added, Minor),
+            change("krate::E::A", Added, Minor),
+        ]
+    );
+}
+
+#[test]
+fn trait_items() {
+    let old = "pub trait T { fn f(&self); }";
+    assert_eq!(
+        diff(old, "pub trait T { fn f(&self); fn g(&self); }"),
+        [change("krate::T::g", Added, Major)]
+    );
+    assert_eq!(
+        diff(old, "pub trait T { fn f(&self); fn g(&self) {} }"),
+        [change("krate::T::g", Added, Minor)]
+    );
+    assert_eq!(
+        diff(old, "pub trait T { fn f(&self); type Item; }"),
+        [change("krate::T::Item", Added, Major)]
+    );
+    assert_eq!(
+        diff(old, "pub trait T { fn f(&self); const N: u8; }"),
+        [change("krate::T::N", Added, Major)]
+    );
+    assert_eq!(
+        diff(old, "pub trait T { fn f(&self); const N: u8 = 1; }"),
+        [change("krate::T::N", Added, Minor)]
+    );
+}
+
+#[test]
+fn fields() {
+    assert_eq!(
+        diff(
+            "pub struct S { pub a: u8 }",
+            "pub struct S { pub a: u8, pub b: u8 }"
+        ),
+        [change("krate::S::b", Added, Major)]
+    );
+    assert_eq!(
+        diff("pub struct S(pub u8);", "pub struct S(pub u8, pub u8);"),
+        [change("krate::S::1", Added, Major)]
+    );
+    // The struct can't be built by other crates.
+    assert_eq!(
+        diff(
+            "pub struct S { pub a: u8, c: u8 }",
+            "pub struct S { pub a: u8, pub b: u8, c: u8 }"
+        ),
+        [change("krate::S::b", Added, Minor)]
+    );
+    assert_eq!(
+        diff(
+            "#[non_exhaustive]\npub struct S { pub a: u8 }",
+            "#[non_exhaustive]\npub struct S { pub a: u8, pub b: u8 }"
+        ),
+        [change("krate::S::b", Added, Minor)]
+    );
+    // Adding a private field breaks the struct expressions too.
+    assert_eq!(
+        diff(
+            "pub struct S { pub a: u8 }",
+            "pub struct S { pub a: u8, c: u8 }"
+        ),
+        [change("krate::S", Changed, Major)]
+    );
+    assert_eq!(
+        diff(
+            "pub enum E { V { a: u8 } }",
+            "pub enum E { V { a: u8, b: u8 } }"
+        ),
+        [change("krate::E::V::b", Added, Major)]
+    );
+}
+
+#[test]
+fn attributes() {
+    assert_eq!(
+        diff("pub fn f() {}", "#[deprecated]\npub fn f() {}"),
+        [change("krate::f", Changed, Patch)]
+    );
+    assert_eq!(
+        diff("pub fn f() {}", "#[doc(hidden)]\npub fn f() {}"),
+        [change("krate::f", Changed, Patch)]
+    );
+    assert_eq!(
+        diff("pub struct S;", "#[non_exhaustive]\npub struct S;"),
+        [change("krate::S", Changed, Major)]
+    );
+}
+
+#[test]
+fn new_location() {
+    let args = args(&["--crate-name=krate", "--crate-type=lib"]);
+    let old = with_tyctxt_from_str("pub fn f() {}", &args, public_api).unwrap();
+    let new =
+        with_tyctxt_from_str("\n\npub fn f(_: u8) {}\npub fn g() {}", &args, public_api).unwrap();
+    let locations = semver_diff(&old, &new)
+        .into_iter()
+        .map(|change| change.location)
+        .collect::<Vec<_>>();
+    assert_eq!(locations, ["<anon>:3:1", "<anon>:4:1"]);
+}
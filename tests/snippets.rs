@@ -0,0 +1,85 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_hir as hir;
+use rustc_tools::snippets::{with_tyctxt_snippet, SnippetKind, SnippetOptions};
+use rustc_tools::spans::LineCol;
+
+/// `é` takes two bytes but one column.
+const FRAGMENT: &str = "let word = \"é\";
+let n: u8 = word.len();
+vec![n, 1].len() + n";
+
+fn line_col(line: usize, col: usize) -> LineCol {
+    LineCol { line, col }
+}
+
+#[test]
+fn spans_map_to_the_fragment() {
+    let options = SnippetOptions {
+        kind: SnippetKind::Statements,
+        allow_errors: true,
+        ..SnippetOptions::default()
+    };
+    let (res, diagnostics) = with_tyctxt_snippet(FRAGMENT, &[], options, |snippet| {
+        let hir::ExprKind::Block(block, _) = snippet.expr().kind else { panic!() };
+        let hir::ExprKind::Binary(_, left, right) = block.expr.unwrap().kind else { panic!() };
+        let spans = [
+            block.stmts[0].span,
+            block.stmts[1].span,
+            left.span,
+            right.span,
+        ];
+        (
+            snippet.expr_ty().to_string(),
+            spans.map(|span| snippet.fragment_range(span).unwrap()),
+            spans.map(|span| snippet.fragment_line_col(span).unwrap()),
+            // The wrapper isn't part of the fragment.
+            snippet.fragment_range(snippet.body().value.span),
+        )
+    });
+    let (ty, ranges, line_cols, wrapper) = res.unwrap();
+    assert_eq!(ty, "usize");
+    let texts = ranges.map(|range| &FRAGMENT[range]);
+    // The span of `vec!` is the one of the macro call.
+    assert_eq!(
+        texts,
+        [
+            "let word = \"é\";",
+            "let n: u8 = word.len();",
+            "vec![n, 1].len()",
+            "n"
+        ]
+    );
+    assert_eq!(
+        line_cols,
+        [
+            (line_col(1, 1), line_col(1, 16)),
+            (line_col(2, 1), line_col(2, 24)),
+            (line_col(3, 1), line_col(3, 17)),
+            (line_col(3, 20), line_col(3, 21)),
+        ]
+    );
+    assert_eq!(wrapper, None);
+
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .filter_map(|diagnostic| Some((diagnostic.code.as_deref()?, diagnostic.primary_span()?)))
+        .map(|(code, span)| {
+            assert_eq!(span.file_name, "<snippet>");
+            (
+                code,
+                &FRAGMENT[span.byte_start..span.byte_end],
+                (span.line_start, span.column_start),
+                (span.line_end, span.column_end),
+            )
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            ("E0308", "word.len()", (2, 13), (2, 23)),
+            ("E0308", "n", (3, 20), (3, 21)),
+            ("E0277", "+", (3, 18), (3, 19)),
+        ]
+    );
+}
@@ -0,0 +1,89 @@
+#![feature(rustc_private)]
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process;
+
+use rustc_tools::source_files::{
+    source_files, source_files_with_options, SourceFileOrigin, SourceFilesOptions,
+};
+use rustc_tools::Config;
+
+#[test]
+fn root_and_two_modules() {
+    let dir = env::temp_dir().join(format!("rustc-tools-source-files-{}", process::id()));
+    fs::create_dir_all(dir.join("b")).unwrap();
+    fs::write(dir.join("lib.rs"), "pub mod a;\npub mod b;\n").unwrap();
+    fs::write(dir.join("a.rs"), "pub fn a() {}\n").unwrap();
+    fs::write(
+        dir.join("b/mod.rs"),
+        "pub fn b() {\n    println!(\"b\");\n}\n",
+    )
+    .unwrap();
+    let args = [
+        "--crate-type=lib".to_owned(),
+        dir.join("lib.rs").display().to_string(),
+    ];
+    let res = Config::new(&args).with_tyctxt(|tcx| {
+        (
+            source_files(tcx),
+            source_files_with_options(
+                tcx,
+                SourceFilesOptions {
+                    include_extern: true,
+                },
+            ),
+        )
+    });
+    let overlay = HashMap::from([(
+        dir.join("a.rs"),
+        "pub fn a() {}\npub fn c() {}\n".to_owned(),
+    )]);
+    let overlaid = Config::new(&args)
+        .file_overlay(overlay)
+        .with_tyctxt(source_files);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let (files, with_extern) = res.unwrap();
+    let summary: Vec<_> = files
+        .iter()
+        .map(|file| (file.path.clone().unwrap(), file.origin, file.lines))
+        .collect();
+    // `println!` comes from the standard library, whose files are extern.
+    assert_eq!(
+        summary,
+        [
+            (dir.join("lib.rs"), SourceFileOrigin::Disk, 2),
+            (dir.join("a.rs"), SourceFileOrigin::Disk, 1),
+            (dir.join("b/mod.rs"), SourceFileOrigin::Disk, 3),
+        ]
+    );
+    for file in &files {
+        assert_eq!(file.name, file.path.as_ref().unwrap().display().to_string());
+        assert!(file.hash.starts_with("md5:"), "{}", file.hash);
+    }
+    assert_eq!(with_extern[..3], files[..]);
+    assert!(with_extern.len() > 3);
+    assert!(with_extern[3..]
+        .iter()
+        .all(|file| file.origin == SourceFileOrigin::Extern));
+
+    let overlaid = overlaid.unwrap();
+    let origins: Vec<_> = overlaid
+        .iter()
+        .map(|file| (file.path.is_some(), file.origin, file.lines))
+        .collect();
+    // The macros reading the included files from the overlay are generated into their own file.
+    assert_eq!(
+        origins,
+        [
+            (true, SourceFileOrigin::Disk, 2),
+            (false, SourceFileOrigin::Anonymous, 10),
+            (true, SourceFileOrigin::Overlay, 2),
+            (true, SourceFileOrigin::Disk, 3),
+        ]
+    );
+    assert_eq!(overlaid[1].name, "<include overlay>");
+    assert_ne!(overlaid[2].hash, files[1].hash);
+}
@@ -0,0 +1,133 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::rustc_span::{BytePos, FileName, Span};
+use rustc_tools::spans::{
+    position_from_span, snippet, snippet_with_context, span_from_position, Position,
+};
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn crlf_and_emoji_round_trip() {
+    let dir = env::temp_dir().join(format!("rustc-tools-spans-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.rs");
+    // The emoji is 2 UTF-16 code units and 4 bytes long.
+    fs::write(
+        &path,
+        "fn main() {\r\n    let s = \"😀\"; let x = 1;\r\n    let _ = (s, x);\r\n}\r\n",
+    )
+    .unwrap();
+
+    let res = with_tyctxt(&[path.to_str().unwrap().to_owned()], |tcx| {
+        let x = Position {
+            line: 1,
+            character: 22,
+        };
+        let span = span_from_position(tcx, &path, x.line, x.character).unwrap();
+        let (file, range) = position_from_span(tcx, span).unwrap();
+        assert_eq!(file, path);
+        assert_eq!(range, x..x);
+        assert_eq!(
+            snippet(tcx, span.with_hi(span.lo() + BytePos(1))).as_deref(),
+            Some("x")
+        );
+
+        // The next line starts after the `\r\n`.
+        let next = span_from_position(tcx, &path, 2, 4).unwrap();
+        assert_eq!(
+            position_from_span(tcx, next).unwrap().1.start,
+            Position {
+                line: 2,
+                character: 4
+            }
+        );
+        // A column after the end of the line is at its end.
+        let end = span_from_position(tcx, &path, 1, 100).unwrap();
+        assert_eq!(
+            position_from_span(tcx, end).unwrap().1.start,
+            Position {
+                line: 1,
+                character: 28
+            }
+        );
+    });
+    let _ = fs::remove_dir_all(dir);
+    res.unwrap();
+}
+
+/// The span of the item named `name`.
+fn item_span(tcx: TyCtxt<'_>, name: &str) -> Span {
+    let item = tcx
+        .hir()
+        .items()
+        .find(|&item| {
+            tcx.opt_item_name(item.owner_id.to_def_id())
+                .map_or(false, |n| n.as_str() == name)
+        })
+        .unwrap();
+    tcx.def_span(item.owner_id)
+}
+
+#[test]
+fn snippets_across_files() {
+    let dir = env::temp_dir().join(format!("rustc-tools-snippets-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "mod shapes;
+
+macro_rules! unit {
+    ($name:ident) => {
+        pub struct $name;
+    };
+}
+
+unit!(Marker);
+",
+    )
+    .unwrap();
+    let shapes = dir.join("shapes.rs");
+    fs::write(
+        &shapes,
+        "// Shapes.
+
+pub fn square(side: u32) -> u32 {
+    side * side
+}
+",
+    )
+    .unwrap();
+
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let res = with_tyctxt(&args, |tcx| {
+        let square = item_span(tcx, "square");
+        assert_eq!(
+            snippet(tcx, square).as_deref(),
+            Some("pub fn square(side: u32) -> u32")
+        );
+        let context = snippet_with_context(tcx, square, 1).unwrap();
+        assert_eq!(context.file_name, FileName::from(shapes.clone()));
+        assert_eq!(context.start_line, 2);
+        assert_eq!(
+            context.lines,
+            ["", "pub fn square(side: u32) -> u32 {", "    side * side"]
+        );
+
+        // The struct generated by the macro is at the macro call in the root file.
+        let marker = item_span(tcx, "Marker");
+        assert_eq!(snippet(tcx, marker).as_deref(), Some("unit!(Marker)"));
+        let context = snippet_with_context(tcx, marker, 1).unwrap();
+        assert_eq!(context.file_name, FileName::from(root.clone()));
+        assert_eq!(context.start_line, 8);
+        // There is no line after the last one.
+        assert_eq!(context.lines, ["", "unit!(Marker);"]);
+    });
+    let _ = fs::remove_dir_all(&dir);
+    res.unwrap();
+}
@@ -0,0 +1,115 @@
+#![feature(rustc_private)]
+
+use rustc_tools::dependencies::compile_dependency;
+use rustc_tools::stable_ids::{
+    crate_hash, crate_hash_with_cfgs, dep_crate_hashes, lookup_stable_id, stable_id, StableItemId,
+};
+use rustc_tools::{with_tyctxt_from_str, Config, EmitKind};
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// The stable id of the item named `name`, checked with `lookup_stable_id`.
+fn item_id(source: &str, name: &str) -> Option<StableItemId> {
+    let args = args(&["--crate-name=krate", "--crate-type=lib"]);
+    with_tyctxt_from_str(source, &args, |tcx| {
+        let hir = tcx.hir();
+        let def_id = hir
+            .items()
+            .find(|&id| hir.item(id).ident.as_str() == name)?
+            .owner_id
+            .to_def_id();
+        let id = stable_id(tcx, def_id);
+        assert_eq!(lookup_stable_id(tcx, &id), Some(def_id));
+        Some(id)
+    })
+    .unwrap()
+}
+
+#[test]
+fn stable_across_runs() {
+    let source = "pub mod m { pub fn f() {} }\npub fn g() {}";
+    let first = item_id(source, "f").unwrap();
+    assert_eq!(item_id(source, "f"), Some(first));
+    assert_eq!(first.to_string().len(), 32);
+    // Editing the code elsewhere keeps the id.
+    let edited = "pub struct S;\npub mod m { pub fn f() { let _ = 1; } }\npub fn g() {}";
+    assert_eq!(item_id(edited, "f"), Some(first));
+
+    // Renaming the function or its module changes it.
+    let renamed = item_id("pub mod m { pub fn f2() {} }\npub fn g() {}", "f2").unwrap();
+    assert_ne!(renamed, first);
+    let moved = item_id("pub mod n { pub fn f() {} }\npub fn g() {}", "f").unwrap();
+    assert_ne!(moved, first);
+}
+
+#[test]
+fn crate_hash_and_cfgs() {
+    let hashes = |source: &str, cfgs: &[&str]| {
+        let mut args = args(&["--crate-name=krate", "--crate-type=lib"]);
+        for cfg in cfgs {
+            args.extend(["--cfg".to_owned(), cfg.to_string()]);
+        }
+        with_tyctxt_from_str(source, &args, |tcx| {
+            (crate_hash(tcx), crate_hash_with_cfgs(tcx))
+        })
+        .unwrap()
+    };
+    let source = "#[cfg(foo)]\npub fn f() {}\npub fn g() {}";
+    let (plain, plain_with_cfgs) = hashes(source, &[]);
+    assert_eq!(hashes(source, &[]), (plain, plain_with_cfgs));
+
+    // The SVH only changes if the cfg changes the code.
+    let (enabled, enabled_with_cfgs) = hashes(source, &["foo"]);
+    assert_ne!(enabled, plain);
+    assert_ne!(enabled_with_cfgs, plain_with_cfgs);
+    let (unused, unused_with_cfgs) = hashes(source, &["bar"]);
+    assert_eq!(unused, plain);
+    assert_ne!(unused_with_cfgs, plain_with_cfgs);
+    assert_eq!(
+        hashes(source, &["foo", "bar"]),
+        hashes(source, &["bar", "foo"])
+    );
+    assert_ne!(hashes("pub fn g() {}", &[]).0, plain);
+}
+
+#[test]
+fn crate_hash_of_dependency() {
+    let dir = env::temp_dir().join(format!("rustc-tools-crate-hash-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dep_root = dir.join("dep.rs");
+    fs::write(&dep_root, "pub fn answer() -> u32 { 42 }").unwrap();
+    let dep = compile_dependency(&dep_root, "dep", &dir, &[]).unwrap();
+
+    // The SVH depends on `--emit`, so it's run like `compile_dependency`.
+    let hash = Config::new(&args(&[
+        "--crate-name=dep",
+        "--crate-type=lib",
+        &dep_root.display().to_string(),
+    ]))
+    .emit(EmitKind::Metadata, &dir)
+    .with_tyctxt(crate_hash);
+    let extern_dep = format!("dep={}", dep.display());
+    let dep_hash = with_tyctxt_from_str(
+        "pub use dep::answer;",
+        &args(&[
+            "--crate-type=lib",
+            "--edition=2021",
+            "--extern",
+            &extern_dep,
+        ]),
+        |tcx| {
+            dep_crate_hashes(tcx)
+                .into_iter()
+                .find(|dep| dep.name == "dep")
+                .map(|dep| dep.hash)
+        },
+    );
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(Some(hash.unwrap()), dep_hash.unwrap());
+}
@@ -0,0 +1,43 @@
+#![feature(rustc_private)]
+
+use rustc_tools::Config;
+
+use std::env;
+use std::process::Command;
+
+/// Set when the test runs itself in a child process with a small stack, since a stack overflow
+/// aborts the whole process.
+const CHILD_STACK_SIZE: &str = "RUSTC_TOOLS_TEST_STACK_SIZE";
+
+fn analyze_nested_expression(stack_size: usize) -> bool {
+    let depth = 2000;
+    let source = format!(
+        "pub fn nested() -> u32 {{ {}1{} }}",
+        "(".repeat(depth),
+        ")".repeat(depth)
+    );
+    Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(&source)
+        .allow("unused_parens")
+        .stack_size(stack_size)
+        .with_tyctxt(|_| ())
+        .is_ok()
+}
+
+#[test]
+fn nested_expression_needs_a_bigger_stack() {
+    if let Ok(stack_size) = env::var(CHILD_STACK_SIZE) {
+        analyze_nested_expression(stack_size.parse().unwrap());
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "nested_expression_needs_a_bigger_stack"])
+        .env(CHILD_STACK_SIZE, (1024 * 1024).to_string())
+        .output()
+        .unwrap();
+    // The process is killed before the test harness can print the results.
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("test result"));
+    assert!(analyze_nested_expression(64 * 1024 * 1024));
+}
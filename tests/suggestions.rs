@@ -0,0 +1,71 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::suggestions::{apply_suggestions, ApplyMode};
+use rustc_tools::Config;
+
+use std::env;
+use std::fs;
+use std::process;
+
+const SOURCE: &str = "pub fn area(width: u32, height: u32) -> u32 {
+    let area = (width * height);
+    if (area > 100) {
+        return (100);
+    }
+    area
+}
+";
+
+const FIXED: &str = "pub fn area(width: u32, height: u32) -> u32 {
+    let area = width * height;
+    if area > 100 {
+        return 100;
+    }
+    area
+}
+";
+
+#[test]
+fn unused_parens() {
+    let dir = env::temp_dir().join(format!("rustc-tools-suggestions-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(&root, SOURCE).unwrap();
+    let args = vec!["--crate-type=lib".to_owned(), root.display().to_string()];
+    let check = || {
+        let collector = DiagnosticsCollector::new();
+        let res = Config::new(&args)
+            .collect_diagnostics(collector.clone())
+            .with_tyctxt(|_| ());
+        res.map(|()| collector.take())
+    };
+
+    let diagnostics = check();
+    let dry_run = diagnostics
+        .as_ref()
+        .map(|diagnostics| apply_suggestions(diagnostics, ApplyMode::DryRun));
+    let unchanged = fs::read_to_string(&root).unwrap();
+    let written = diagnostics
+        .as_ref()
+        .map(|diagnostics| apply_suggestions(diagnostics, ApplyMode::Write));
+    let rewritten = fs::read_to_string(&root).unwrap();
+    let diagnostics_after = check();
+    let _ = fs::remove_dir_all(&dir);
+
+    let diagnostics = diagnostics.as_ref().unwrap();
+    // Each parenthesized expression gives a warning, with the summary at the end.
+    assert_eq!(diagnostics.len(), 4, "{diagnostics:?}");
+    let edits = dry_run.unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].file_name, root.display().to_string());
+    assert_eq!(edits[0].contents.as_deref(), Ok(FIXED));
+    assert_eq!(edits[0].applied.len(), 3);
+    assert!(edits[0].conflicts.is_empty());
+    assert_eq!(unchanged, SOURCE);
+
+    assert_eq!(written.unwrap(), edits);
+    assert_eq!(rewritten, FIXED);
+    // The rewritten file compiles without any warning.
+    assert_eq!(diagnostics_after.unwrap(), []);
+}
@@ -0,0 +1,39 @@
+#![feature(rustc_private)]
+
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command};
+
+#[test]
+fn detected_sysroot() {
+    let dir = env::temp_dir().join(format!("rustc-tools-sysroot-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("main.rs");
+    fs::write(
+        &root,
+        "fn main() { let v: Vec<String> = Vec::new(); drop(v); }",
+    )
+    .unwrap();
+    // No `--sysroot`.
+    let res = with_tyctxt(&[root.display().to_string()], |tcx| {
+        let std_loaded = tcx
+            .crates(())
+            .iter()
+            .any(|&krate| tcx.crate_name(krate).as_str() == "std");
+        (tcx.sess.sysroot.clone(), std_loaded)
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let (sysroot, std_loaded) = res.unwrap();
+    assert!(std_loaded);
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let output = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let expected = PathBuf::from(String::from_utf8(output.stdout).unwrap().trim());
+    assert_eq!(sysroot, expected);
+}
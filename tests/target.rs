@@ -0,0 +1,30 @@
+#![feature(rustc_private)]
+
+use rustc_tools::{Config, Error};
+
+#[test]
+fn made_up_target_triple() {
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code("")
+        .target("made-up-unknown-none")
+        .with_tyctxt(|_| ());
+    match res {
+        Err(Error::MissingTargetLibs { triple, hint }) => {
+            assert_eq!(triple, "made-up-unknown-none");
+            assert!(hint.contains("rustc --print target-list"), "{hint}");
+        }
+        res => panic!("{res:?}"),
+    }
+
+    // Same with the `rustc` argument.
+    let res = Config::new(&[
+        "--crate-type=lib".to_owned(),
+        "--target=made-up-unknown-none".to_owned(),
+    ])
+    .source_code("")
+    .with_tyctxt(|_| ());
+    assert!(
+        matches!(res, Err(Error::MissingTargetLibs { ref triple, .. }) if triple == "made-up-unknown-none"),
+        "{res:?}"
+    );
+}
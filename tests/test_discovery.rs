@@ -0,0 +1,28 @@
+#![feature(rustc_private)]
+
+use rustc_tools::test_discovery::test_cases;
+use rustc_tools::with_tyctxt_from_str;
+
+const SOURCE: &str = r#"
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[should_panic(expected = "boom")]
+    fn it_panics() {
+        panic!("boom");
+    }
+}
+"#;
+
+#[test]
+fn test_flag_in_rustc_args() {
+    let args = ["--test".to_owned(), "--crate-name=krate".to_owned()];
+    let tests = with_tyctxt_from_str(SOURCE, &args, test_cases).unwrap();
+    assert_eq!(tests.len(), 1, "{tests:?}");
+    assert_eq!(tests[0].test_name, "tests::it_panics");
+    assert_eq!(tests[0].expected_panic.as_deref(), Some("boom"));
+
+    // Without `--test`, the `#[cfg(test)]` module is removed.
+    let tests = with_tyctxt_from_str(SOURCE, &["--crate-type=lib".to_owned()], test_cases).unwrap();
+    assert!(tests.is_empty(), "{tests:?}");
+}
@@ -0,0 +1,32 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::{DiagnosticsCollector, Level};
+use rustc_tools::{AnalysisLevel, Config, Error};
+
+#[test]
+fn two_threads() {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code("pub fn one() -> u32 { 1 }\npub fn two() -> u32 { one() + 1 }\n")
+        .analysis_level(AnalysisLevel::Full)
+        .collect_diagnostics(collector.clone())
+        .threads(2)
+        .with_tyctxt(|tcx| tcx.hir().body_owners().count());
+    assert_eq!(res.unwrap(), 2);
+    // The compilers of the nightly toolchains don't have the parallel front-end yet.
+    let diagnostics = collector.take();
+    assert!(
+        diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.level == Level::Warning
+                && diagnostic.message.contains("parallel front-end")),
+        "{diagnostics:?}"
+    );
+
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code("")
+        .collect_diagnostics(collector)
+        .threads(0)
+        .with_tyctxt(|_| ());
+    assert!(matches!(res, Err(Error::ArgParse(_))), "{res:?}");
+}
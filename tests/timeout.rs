@@ -0,0 +1,46 @@
+#![feature(rustc_private)]
+
+use rustc_tools::{with_tyctxt_timeout, Error};
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::{Duration, Instant};
+
+#[test]
+fn slow_input_times_out() {
+    let dir = env::temp_dir().join(format!("rustc-tools-timeout-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // Type-checking all these functions takes a few seconds.
+    let slow = dir.join("slow.rs");
+    let source = (0..20_000)
+        .map(|i| {
+            format!("pub fn f{i}(x: u64) -> u64 {{ vec![x; 3].iter().map(|y| y * {i}).sum() }}\n")
+        })
+        .collect::<String>();
+    fs::write(&slow, source).unwrap();
+    let fast = dir.join("fast.rs");
+    fs::write(&fast, "pub fn f() {}").unwrap();
+    let args =
+        |path: &std::path::Path| vec!["--crate-type=lib".to_owned(), path.display().to_string()];
+
+    let start = Instant::now();
+    let res = with_tyctxt_timeout(&args(&slow), Duration::from_millis(200), |tcx| {
+        tcx.hir().par_body_owners(|def_id| {
+            tcx.ensure().typeck(def_id);
+        });
+    });
+    assert!(matches!(res, Err(Error::TimedOut)), "{res:?}");
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "{:?}",
+        start.elapsed()
+    );
+
+    // The next analysis waits for the cancelled one to stop.
+    let res = with_tyctxt_timeout(&args(&fast), Duration::from_secs(60), |tcx| {
+        tcx.hir().items().count()
+    });
+    let _ = fs::remove_dir_all(&dir);
+    assert!(res.is_ok(), "{res:?}");
+}
@@ -0,0 +1,42 @@
+#![cfg(unix)]
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_span::edition::Edition;
+use rustc_tools::{with_ast_parser, with_tyctxt, Error};
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process;
+
+#[test]
+fn ast_parser_checks_the_toolchain() {
+    let dir = env::temp_dir().join(format!("rustc-tools-toolchain-{}", process::id()));
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    let source = dir.join("lib.rs");
+    fs::write(&source, "pub fn f() {}").unwrap();
+    let res = with_ast_parser(&source, Edition::Edition2021, |_, krate| krate.items.len());
+    assert_eq!(res.unwrap(), 1);
+
+    // A sysroot of another toolchain, whose `rustc` is checked like the one `with_ast_parser` runs.
+    let rustc = dir.join("bin/rustc");
+    fs::write(
+        &rustc,
+        "#!/bin/sh\necho 'rustc 1.0.0 (a59de37e9 2015-05-13)'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&rustc, fs::Permissions::from_mode(0o755)).unwrap();
+    let args = [
+        "--sysroot".to_owned(),
+        dir.display().to_string(),
+        source.display().to_string(),
+    ];
+    let res = with_tyctxt(&args, |tcx| tcx.hir().items().count());
+    let _ = fs::remove_dir_all(&dir);
+    match res {
+        Err(Error::ToolchainMismatch { found, .. }) => {
+            assert_eq!(found, "rustc 1.0.0 (a59de37e9 2015-05-13)")
+        }
+        res => panic!("{res:?}"),
+    }
+}
@@ -0,0 +1,108 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_middle::ty::TyCtxt;
+use rustc_tools::traits::{
+    coherence_check, implements_trait, impls_of_trait, trait_def_id_by_path, CoherenceVerdict,
+};
+use rustc_tools::with_tyctxt_from_str;
+
+fn implements(tcx: TyCtxt<'_>, alias: &str, trait_path: &str) -> bool {
+    let item = tcx
+        .hir()
+        .items()
+        .find(|&id| tcx.hir().item(id).ident.as_str() == alias)
+        .unwrap();
+    let ty = tcx.type_of(item.owner_id.to_def_id());
+    let trait_def_id = trait_def_id_by_path(tcx, trait_path).unwrap();
+    implements_trait(tcx, ty, trait_def_id, &[])
+}
+
+#[test]
+fn rc_and_arc_auto_traits() {
+    let source = "pub type Rc = std::rc::Rc<u32>;\npub type Arc = std::sync::Arc<u32>;";
+    let res = with_tyctxt_from_str(source, &["--crate-type=lib".to_owned()], |tcx| {
+        [
+            implements(tcx, "Rc", "std::marker::Send"),
+            implements(tcx, "Rc", "std::marker::Sync"),
+            implements(tcx, "Arc", "std::marker::Send"),
+            implements(tcx, "Arc", "std::marker::Sync"),
+        ]
+    });
+    assert_eq!(res.unwrap(), [false, false, true, true]);
+}
+
+#[test]
+fn orphan_rule() {
+    let source = "pub struct Local;\npub trait LocalTrait {}";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let verdicts = with_tyctxt_from_str(source, &args, |tcx| {
+        [
+            ("std::fmt::Display", "std::vec::Vec<krate::Local>"),
+            ("std::fmt::Display", "std::boxed::Box<krate::Local>"),
+            ("std::fmt::Display", "std::vec::Vec<i32>"),
+            ("krate::LocalTrait", "i32"),
+        ]
+        .map(|(trait_path, self_ty_path)| coherence_check(tcx, trait_path, self_ty_path))
+    })
+    .unwrap();
+    // `Vec` isn't `#[fundamental]`, so the compiler rejects the impl with `E0117` even if its
+    // argument is local.
+    assert!(
+        matches!(verdicts[0], CoherenceVerdict::OrphanViolation { .. }),
+        "{:?}",
+        verdicts[0]
+    );
+    assert_eq!(verdicts[1], CoherenceVerdict::Allowed);
+    assert!(
+        matches!(verdicts[2], CoherenceVerdict::OrphanViolation { .. }),
+        "{:?}",
+        verdicts[2]
+    );
+    assert_eq!(verdicts[3], CoherenceVerdict::Allowed);
+}
+
+#[test]
+fn derived_and_manual_impls() {
+    let source = "#[derive(Clone)]
+pub struct Derived;
+
+pub struct Manual;
+
+impl Clone for Manual {
+    fn clone(&self) -> Self {
+        Manual
+    }
+}
+
+pub trait Named {}
+
+impl<T: Clone> Named for T {}
+";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(source, &args, |tcx| {
+        let impls = |path| {
+            let trait_def_id = trait_def_id_by_path(tcx, path).unwrap();
+            impls_of_trait(tcx, trait_def_id)
+                .into_iter()
+                .map(|info| (info.self_ty, info.is_derived, info.is_blanket))
+                .collect::<Vec<_>>()
+        };
+        assert!(trait_def_id_by_path(tcx, "krate::Derived").is_none());
+        (impls("core::clone::Clone"), impls("krate::Named"))
+    });
+    let (clone, named) = res.unwrap();
+    assert_eq!(
+        clone,
+        [
+            ("Derived".to_owned(), true, false),
+            ("Manual".to_owned(), false, false),
+        ]
+    );
+    assert_eq!(named, [("T".to_owned(), false, true)]);
+}
@@ -0,0 +1,95 @@
+#![feature(rustc_private)]
+
+use rustc_tools::unsafety::{unsafe_inventory, UnsafeCounts, UnsafeKind};
+use rustc_tools::with_tyctxt_from_str;
+
+#[test]
+fn unsafe_code_from_macro_rules() {
+    let source = "macro_rules! read {
+    ($ptr:expr) => {
+        unsafe { *$ptr }
+    };
+}
+
+macro_rules! unsafe_fn {
+    ($name:ident) => {
+        pub unsafe fn $name() {}
+    };
+}
+
+pub mod ptrs {
+    pub fn first(ptr: *const u32) -> u32 {
+        read!(ptr)
+    }
+}
+
+unsafe_fn!(danger);
+
+pub fn written(ptr: *const u32) -> u32 {
+    unsafe { *ptr }
+}
+";
+    let args = [
+        "--crate-type=lib".to_owned(),
+        "--crate-name=krate".to_owned(),
+    ];
+    let res = with_tyctxt_from_str(source, &args, |tcx| {
+        let report = unsafe_inventory(tcx);
+        let items = report
+            .items
+            .iter()
+            .map(|item| {
+                let kind = match item.kind {
+                    UnsafeKind::Fn { .. } => "fn",
+                    UnsafeKind::Block => "block",
+                    _ => "other",
+                };
+                let snippet = tcx.sess.source_map().span_to_snippet(item.span).unwrap();
+                let enclosing_fn = item
+                    .enclosing_fn
+                    .map(|def_id| tcx.item_name(def_id.to_def_id()).to_string());
+                (kind, snippet, item.macro_name.clone(), enclosing_fn)
+            })
+            .collect::<Vec<_>>();
+        (items, report.per_module)
+    });
+    let (mut items, per_module) = res.unwrap();
+    items.sort();
+    let some = |s: &str| Some(s.to_owned());
+    // The spans of the generated code are the ones of the macro calls.
+    assert_eq!(
+        items,
+        [
+            (
+                "block",
+                "read!(ptr)".to_owned(),
+                some("read"),
+                some("first")
+            ),
+            ("block", "unsafe { *ptr }".to_owned(), None, some("written")),
+            (
+                "fn",
+                "unsafe_fn!(danger)".to_owned(),
+                some("unsafe_fn"),
+                None
+            ),
+        ]
+    );
+    assert_eq!(
+        per_module["krate::ptrs"],
+        UnsafeCounts {
+            blocks: 1,
+            from_expansion: 1,
+            ..UnsafeCounts::default()
+        }
+    );
+    assert_eq!(
+        per_module["krate"],
+        UnsafeCounts {
+            fns: 1,
+            blocks: 1,
+            from_expansion: 1,
+            ..UnsafeCounts::default()
+        }
+    );
+}
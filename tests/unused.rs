@@ -0,0 +1,116 @@
+#![feature(rustc_private)]
+
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::unused::{unused_items, UnusedOptions, Usage};
+use rustc_tools::{AnalysisLevel, Config};
+
+const SOURCE: &str = "pub struct Square(pub u32);
+
+pub trait Area {
+    fn area(&self) -> u32;
+}
+
+impl Area for Square {
+    fn area(&self) -> u32 {
+        side(self) * side(self)
+    }
+}
+
+impl Square {
+    pub fn new(side: u32) -> Self {
+        Square(side)
+    }
+
+    pub fn double(&self) -> Self {
+        Square::new(self.0 * 2)
+    }
+}
+
+fn side(square: &Square) -> u32 {
+    square.0
+}
+
+pub fn only_tested() -> u32 {
+    1
+}
+
+pub fn unused() {}
+
+fn private_unused() {}
+
+#[allow(dead_code)]
+fn allowed() {}
+
+#[no_mangle]
+pub extern \"C\" fn exported() {}
+
+#[cfg(test)]
+mod tests {
+    fn helper() -> u32 {
+        super::only_tested()
+    }
+
+    #[test]
+    fn only_tested() {
+        assert_eq!(helper(), 1);
+    }
+}
+";
+
+/// Returns the names of the items with each usage.
+fn usages(test: bool, opts: UnusedOptions) -> [Vec<String>; 3] {
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .source_code(SOURCE)
+        .analysis_level(AnalysisLevel::TypeckBodies)
+        .test(test)
+        // The items for which `dead_code` is allowed aren't reported, so its warnings are only
+        // collected.
+        .collect_diagnostics(DiagnosticsCollector::new())
+        .with_tyctxt(|tcx| {
+            let report = unused_items(tcx, opts);
+            [Usage::UsedInternally, Usage::UsedOnlyInTests, Usage::Unused].map(|usage| {
+                report
+                    .with_usage(usage)
+                    .map(|item| item.name.clone())
+                    .collect::<Vec<_>>()
+            })
+        });
+    res.unwrap()
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .map(|name| format!("rust_out::{name}"))
+        .collect()
+}
+
+#[test]
+fn classification() {
+    let [used, used_in_tests, unused] = usages(true, UnusedOptions::default());
+    // The impl of `Area` uses `Area` but not `Area::area`, which nothing calls.
+    assert_eq!(used, names(&["Square", "Area", "Square::new", "side"]));
+    assert_eq!(used_in_tests, names(&["only_tested"]));
+    // The trait impl methods, `allowed`, `exported` and the test code aren't reported.
+    assert_eq!(
+        unused,
+        names(&["Area::area", "Square::double", "unused", "private_unused"])
+    );
+
+    // Without the tests, what they use is unused.
+    let [_, used_in_tests, unused] = usages(false, UnusedOptions::default());
+    assert!(used_in_tests.is_empty());
+    assert_eq!(
+        unused,
+        names(&[
+            "Area::area",
+            "Square::double",
+            "only_tested",
+            "unused",
+            "private_unused",
+        ])
+    );
+
+    let [_, _, unused] = usages(true, UnusedOptions { public_only: true });
+    assert_eq!(unused, names(&["Area::area", "Square::double", "unused"]));
+}
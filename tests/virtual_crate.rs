@@ -0,0 +1,107 @@
+#![feature(rustc_private)]
+
+use rustc_tools::consts::{eval_const, ConstValueRepr};
+use rustc_tools::diagnostics::DiagnosticsCollector;
+use rustc_tools::rustc_span::def_id::LOCAL_CRATE;
+use rustc_tools::{with_tyctxt_virtual, Config, VirtualCrate};
+
+/// `src/lib.rs` exists on disk (it's the root of this crate), but the virtual one is used.
+fn layered_crate() -> VirtualCrate {
+    VirtualCrate::new()
+        .root(
+            "src/lib.rs",
+            "pub mod shapes;
+pub mod io;
+#[path = \"gen/table.rs\"]
+pub mod table;
+",
+        )
+        .file("src/shapes.rs", "pub struct Square;\n")
+        .file(
+            "src/io/mod.rs",
+            "pub mod read;
+pub const BANNER: &str = include_str!(\"banner.txt\");
+",
+        )
+        .file(
+            "src/io/read.rs",
+            "pub fn read() -> u32 {
+    super::BANNER.len() as u32
+}
+",
+        )
+        .file("src/io/banner.txt", "hello")
+        .file("src/gen/table.rs", "pub static TABLE: [u8; 2] = [1, 2];\n")
+}
+
+#[test]
+fn modules_in_memory() {
+    let res = with_tyctxt_virtual(layered_crate(), &["--crate-type=lib".to_owned()], |tcx| {
+        let source_map = tcx.sess.source_map();
+        let items: Vec<_> = tcx
+            .hir()
+            .items()
+            .map(|id| tcx.hir().item(id))
+            .map(|item| {
+                (
+                    tcx.def_path_str(item.owner_id.to_def_id()),
+                    source_map.span_to_diagnostic_string(item.span),
+                )
+            })
+            .filter(|(_, location)| location.starts_with("src/"))
+            .collect();
+        let banner = tcx
+            .hir()
+            .items()
+            .find(|&id| tcx.hir().item(id).ident.as_str() == "BANNER")
+            .unwrap();
+        (
+            tcx.crate_name(LOCAL_CRATE).to_string(),
+            items,
+            eval_const(tcx, banner.owner_id.to_def_id()).unwrap(),
+        )
+    });
+    let (crate_name, items, banner) = res.unwrap();
+    assert_eq!(crate_name, "lib");
+    let expected = [
+        ("shapes", "src/lib.rs:1:1: 1:16"),
+        ("shapes::Square", "src/shapes.rs:1:1: 1:19"),
+        ("io", "src/lib.rs:2:1: 2:12"),
+        ("io::read", "src/io/mod.rs:1:1: 1:14"),
+        ("io::read::read", "src/io/read.rs:1:1: 3:2"),
+        ("io::BANNER", "src/io/mod.rs:2:1: 2:53"),
+        ("table", "src/lib.rs:4:1: 4:15"),
+        ("table::TABLE", "src/gen/table.rs:1:1: 1:36"),
+    ]
+    .map(|(path, location)| (path.to_owned(), location.to_owned()));
+    assert_eq!(items, expected);
+    // `include_str!` is relative to `src/io/mod.rs`.
+    assert_eq!(banner, ConstValueRepr::Str("hello".to_owned()));
+}
+
+#[test]
+fn missing_module() {
+    let collector = DiagnosticsCollector::new();
+    let res = Config::new(&["--crate-type=lib".to_owned()])
+        .virtual_crate(VirtualCrate::new().root("src/lib.rs", "mod shapes;\nmod missing;\n"))
+        .collect_diagnostics(collector.clone())
+        .with_tyctxt(|_| ());
+    assert!(res.is_err());
+    let diagnostics = collector.take();
+    assert_eq!(
+        diagnostics[0].code.as_deref(),
+        Some("E0583"),
+        "{diagnostics:?}"
+    );
+    assert_eq!(diagnostics[0].message, "file not found for module `shapes`");
+    assert_eq!(
+        diagnostics[1].code.as_deref(),
+        Some("E0583"),
+        "{diagnostics:?}"
+    );
+    let span = diagnostics[1].primary_span().unwrap();
+    assert_eq!(
+        (span.file_name.as_str(), span.line_start),
+        ("src/lib.rs", 2)
+    );
+}
@@ -0,0 +1,82 @@
+#![feature(rustc_private)]
+
+use rustc_tools::rustc_hir::def::DefKind;
+use rustc_tools::visit::for_each_fn;
+use rustc_tools::with_tyctxt;
+
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn functions_of_impls_traits_and_closures() {
+    let dir = env::temp_dir().join(format!("rustc-tools-visit-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(
+        &root,
+        "pub trait Shape {
+    fn sides(&self) -> u32;
+
+    fn is_polygon(&self) -> bool {
+        self.sides() > 2
+    }
+}
+
+pub struct Square;
+
+impl Square {
+    pub fn new() -> Self {
+        Square
+    }
+}
+
+impl Shape for Square {
+    fn sides(&self) -> u32 {
+        4
+    }
+}
+
+pub fn total(shapes: &[&dyn Shape]) -> u32 {
+    fn double(x: u32) -> u32 {
+        x * 2
+    }
+    shapes
+        .iter()
+        .map(|shape| {
+            let add = |x: u32| x + 1;
+            double(add(shape.sides()))
+        })
+        .sum()
+}
+",
+    )
+    .unwrap();
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let res = with_tyctxt(&args, |tcx| {
+        let mut fns = Vec::new();
+        for_each_fn(tcx, |def_id, _, _| {
+            let name = tcx
+                .opt_item_name(def_id.to_def_id())
+                .map(|name| name.to_string());
+            fns.push((tcx.def_kind(def_id), name));
+        });
+        fns.sort_by_key(|(kind, name)| (format!("{kind:?}"), name.clone()));
+        fns
+    });
+    let _ = fs::remove_dir_all(&dir);
+    let name = |name: &str| Some(name.to_owned());
+    // The required trait method `Shape::sides` has no body.
+    assert_eq!(
+        res.unwrap(),
+        [
+            (DefKind::AssocFn, name("is_polygon")),
+            (DefKind::AssocFn, name("new")),
+            (DefKind::AssocFn, name("sides")),
+            (DefKind::Closure, None),
+            (DefKind::Closure, None),
+            (DefKind::Fn, name("double")),
+            (DefKind::Fn, name("total")),
+        ]
+    );
+}
@@ -0,0 +1,42 @@
+#![feature(rustc_private)]
+
+use rustc_tools::watch::{watch_with_options, WatchOptions};
+
+use std::env;
+use std::fs;
+use std::process;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[test]
+fn callback_runs_again_after_a_change() {
+    let dir = env::temp_dir().join(format!("rustc-tools-watch-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("lib.rs");
+    fs::write(&root, "pub fn a() {}").unwrap();
+
+    let options = WatchOptions {
+        poll_interval: Duration::from_millis(20),
+        debounce: Duration::from_millis(20),
+    };
+    let args = ["--crate-type=lib".to_owned(), root.display().to_string()];
+    let (sender, receiver) = mpsc::channel();
+    let handle = watch_with_options(
+        &args,
+        options,
+        |tcx| tcx.hir().items().count(),
+        move |res| sender.send(res.unwrap()).unwrap(),
+    );
+
+    let first = receiver.recv_timeout(TIMEOUT).unwrap();
+    fs::write(&root, "pub fn a() {}\npub fn b() {}").unwrap();
+    let second = receiver.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(second, first + 1);
+
+    handle.stop();
+    let _ = fs::remove_dir_all(&dir);
+    // No run after the watch is stopped.
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+}